@@ -0,0 +1,501 @@
+//! The derive macros of [C3p0](https://docs.rs/c3p0), re-exported by `c3p0` with the
+//! `derive` feature.
+//!
+//! Both of them read the `#[c3p0(...)]` attributes of the type, so that a type can derive
+//! its `DataType` and its versioned `Codec` together.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{quote, quote_spanned};
+use syn::{
+    Data, DeriveInput, Expr, Ident, LitStr, Token, Type,
+    ext::IdentExt,
+    parenthesized,
+    parse::{Parse, ParseStream},
+    parse_macro_input,
+    punctuated::Punctuated,
+};
+
+/// The name of the index over the `UNIQUE_KEY`, reserved by c3p0.
+const UNIQUE_KEY_INDEX: &str = "unique_key";
+
+/// The variants of `c3p0::JsonType`.
+const JSON_TYPES: [&str; 3] = ["Text", "Number", "Bool"];
+
+/// The default tag of the variants of a derived `Codec`.
+const DEFAULT_TAG: &str = "@json_tag";
+
+/// Implements `c3p0::DataType` with the declarations of the `#[c3p0(...)]` attribute:
+///
+/// - `table = "..."`: the `TABLE_NAME`, required. Its syntax is checked at compile time
+///   by `c3p0::TableName::check`.
+/// - `codec = Type`: the `CODEC`, `Self` by default.
+/// - `id = Type`: the id type, wrapping the codec in a `WithId`; `i64` by default.
+/// - `unique("path", ...)`: the JSON paths of the `UNIQUE_KEY`.
+/// - `indexes(name("path": JsonType, ..., unique), name(containment), ...)`: the
+///   `INDEXES`, each one with its name, its paths and the `JsonType` variant of their
+///   values, and optionally `unique`; or a `containment` index.
+/// - `soft_delete`, `history` and `upgrade_on_read`: enable `SOFT_DELETE`, `HISTORY` and
+///   `UPGRADE_ON_READ`.
+/// - `format = Variant`: the `FORMAT`, a variant of `c3p0::Format`.
+/// - `compression = zstd(threshold)` or `compression = gzip(threshold)`: the
+///   `COMPRESSION` of the documents of at least `threshold` bytes.
+///
+/// The `versions(...)` and `tag = "..."` declarations of [`Codec`](derive@Codec) are
+/// ignored.
+///
+/// ```rust,ignore
+/// #[derive(Clone, Serialize, Deserialize, DataType)]
+/// #[c3p0(
+///     table = "billing.invoices",
+///     unique("number"),
+///     indexes(customer("customer.id": Number, "date": Text), paid("paid": Bool)),
+///     soft_delete,
+/// )]
+/// pub struct Invoice {
+///     pub number: String,
+///     pub customer: Customer,
+///     pub date: String,
+///     pub paid: bool,
+/// }
+/// ```
+#[proc_macro_derive(DataType, attributes(c3p0))]
+pub fn derive_data_type(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_data_type(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Generates the versioned `c3p0::Codec` of a struct, named by the `codec = Name`
+/// declaration of the `#[c3p0(...)]` attribute, from the previous versions of the struct
+/// listed in `versions(...)`, from the oldest one:
+///
+/// - the codec is an enum with the variants `V1`, `V2`, ... holding the listed versions,
+///   and a last one holding the struct, serialized with the variant name in the
+///   `tag = "..."` field, `"@json_tag"` by default;
+/// - `encode` stores the struct in the last variant;
+/// - `decode` upgrades the previous versions with their `c3p0::Upgrade` impls, each one to
+///   the next version in the list;
+/// - `outdated` matches the entries whose tag is not the one of the last variant.
+///
+/// The name of the generated enum is shared with the `codec` of
+/// [`DataType`](derive@DataType):
+///
+/// ```rust,ignore
+/// #[derive(Clone, Serialize, Deserialize)]
+/// pub struct UserV1 {
+///     pub username: String,
+/// }
+///
+/// impl Upgrade for UserV1 {
+///     type Next = User;
+///
+///     fn upgrade(self) -> User {
+///         User { username: self.username, age: 18 }
+///     }
+/// }
+///
+/// #[derive(Clone, Serialize, Deserialize, DataType, Codec)]
+/// #[c3p0(table = "USERS", codec = UserCodec, versions(UserV1))]
+/// pub struct User {
+///     pub username: String,
+///     pub age: u32,
+/// }
+/// ```
+#[proc_macro_derive(Codec, attributes(c3p0))]
+pub fn derive_codec(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_codec(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// The declarations of the `#[c3p0(...)]` attributes.
+#[derive(Default)]
+struct Declarations {
+    table: Option<LitStr>,
+    codec: Option<Type>,
+    id: Option<Type>,
+    unique: Vec<LitStr>,
+    indexes: Vec<Index>,
+    soft_delete: bool,
+    history: bool,
+    upgrade_on_read: bool,
+    format: Option<Ident>,
+    compression: Option<(Ident, Expr)>,
+    versions: Option<Vec<Type>>,
+    tag: Option<LitStr>,
+}
+
+/// An index declared in `indexes(...)`.
+struct Index {
+    name: Ident,
+    paths: Vec<(LitStr, Ident)>,
+    unique: bool,
+    containment: bool,
+}
+
+impl Parse for Index {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut index = Index {
+            name: Ident::parse_any(input)?.unraw(),
+            paths: Vec::new(),
+            unique: false,
+            containment: false,
+        };
+        let content;
+        parenthesized!(content in input);
+        while !content.is_empty() {
+            if content.peek(LitStr) {
+                let path: LitStr = content.parse()?;
+                content.parse::<Token![:]>()?;
+                let json_type: Ident = content.parse()?;
+                if !JSON_TYPES.contains(&json_type.to_string().as_str()) {
+                    return Err(syn::Error::new_spanned(
+                        json_type,
+                        "expected a JsonType variant: `Text`, `Number` or `Bool`",
+                    ));
+                }
+                index.paths.push((path, json_type));
+            } else {
+                let flag: Ident = content.parse()?;
+                if flag == "unique" {
+                    index.unique = true;
+                } else if flag == "containment" {
+                    index.containment = true;
+                } else {
+                    return Err(syn::Error::new_spanned(
+                        flag,
+                        "expected `\"path\": JsonType`, `unique` or `containment`",
+                    ));
+                }
+            }
+            if content.is_empty() {
+                break;
+            }
+            content.parse::<Token![,]>()?;
+        }
+        Ok(index)
+    }
+}
+
+fn expand_data_type(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let declarations = parse_declarations(input)?;
+    let Some(table) = &declarations.table else {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            "missing the table name: #[c3p0(table = \"...\")]",
+        ));
+    };
+    for path in &declarations.unique {
+        check_path(path)?;
+    }
+    check_indexes(&declarations.indexes)?;
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let codec = match &declarations.codec {
+        Some(codec) => quote!(#codec),
+        None => quote!(Self),
+    };
+    let codec = match &declarations.id {
+        Some(id) => quote!(::c3p0::WithId<#codec, #id>),
+        None => codec,
+    };
+
+    let mut consts = Vec::new();
+    if !declarations.unique.is_empty() {
+        let paths = &declarations.unique;
+        consts.push(quote!(const UNIQUE_KEY: &'static [&'static str] = &[#(#paths),*];));
+    }
+    if !declarations.indexes.is_empty() {
+        let indexes = declarations.indexes.iter().map(|index| {
+            let name = index.name.to_string();
+            if index.containment {
+                return quote!(::c3p0::JsonIndex::containment(#name));
+            }
+            let paths = index
+                .paths
+                .iter()
+                .map(|(path, json_type)| quote!((#path, ::c3p0::JsonType::#json_type)));
+            let unique = index.unique.then(|| quote!(.unique()));
+            quote!(::c3p0::JsonIndex::new(#name, &[#(#paths),*]) #unique)
+        });
+        consts.push(quote!(const INDEXES: &'static [::c3p0::JsonIndex] = &[#(#indexes),*];));
+    }
+    if declarations.soft_delete {
+        consts.push(quote!(
+            const SOFT_DELETE: bool = true;
+        ));
+    }
+    if declarations.history {
+        consts.push(quote!(
+            const HISTORY: bool = true;
+        ));
+    }
+    if declarations.upgrade_on_read {
+        consts.push(quote!(
+            const UPGRADE_ON_READ: bool = true;
+        ));
+    }
+    if let Some(format) = &declarations.format {
+        consts.push(quote!(const FORMAT: ::c3p0::Format = ::c3p0::Format::#format;));
+    }
+    if let Some((algorithm, threshold)) = &declarations.compression {
+        consts.push(quote!(
+            const COMPRESSION: ::core::option::Option<::c3p0::Compression> =
+                ::core::option::Option::Some(::c3p0::Compression::#algorithm(#threshold));
+        ));
+    }
+    // The table name is checked at compile time by the same function used at runtime
+    let check_table = quote_spanned! {table.span()=>
+        const _: () = if let ::core::result::Result::Err(reason) =
+            ::c3p0::TableName::check(#table)
+        {
+            ::core::panic!("{}", reason);
+        };
+    };
+
+    Ok(quote! {
+        #check_table
+
+        impl #impl_generics ::c3p0::DataType for #name #ty_generics #where_clause {
+            const TABLE_NAME: &'static str = #table;
+            type CODEC = #codec;
+            #(#consts)*
+        }
+    })
+}
+
+fn expand_codec(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let declarations = parse_declarations(input)?;
+    if !matches!(input.data, Data::Struct(_)) || !input.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            "the Codec can only be derived by a struct without generic parameters",
+        ));
+    }
+    let codec = match &declarations.codec {
+        Some(Type::Path(codec)) if codec.qself.is_none() => codec.path.get_ident(),
+        _ => None,
+    };
+    let Some(codec) = codec else {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            "missing the name of the generated enum: #[c3p0(codec = Name)]",
+        ));
+    };
+    let tag = declarations
+        .tag
+        .as_ref()
+        .map(LitStr::value)
+        .unwrap_or_else(|| DEFAULT_TAG.to_owned());
+
+    let name = &input.ident;
+    let vis = &input.vis;
+    let latest: Type = syn::parse_quote!(#name);
+    let versions: Vec<&Type> = declarations
+        .versions
+        .iter()
+        .flatten()
+        .chain([&latest])
+        .collect();
+    let variants: Vec<Ident> = (1..=versions.len())
+        .map(|version| quote::format_ident!("V{version}"))
+        .collect();
+    let latest_variant = &variants[versions.len() - 1];
+    let latest_tag = latest_variant.to_string();
+    let decode = variants.iter().enumerate().map(|(position, variant)| {
+        // Each version is upgraded to the next one, up to the latest
+        let upgrades = versions[position + 1..]
+            .iter()
+            .map(|next| quote!(let data: #next = ::c3p0::Upgrade::upgrade(data);));
+        quote!(#codec::#variant(data) => {
+            #(#upgrades)*
+            data
+        })
+    });
+
+    Ok(quote! {
+        #[derive(::serde::Serialize, ::serde::Deserialize)]
+        #[serde(tag = #tag)]
+        #vis enum #codec {
+            #(#variants(#versions)),*
+        }
+
+        impl ::c3p0::Codec<#name> for #codec {
+            fn encode(data: #name) -> Self {
+                #codec::#latest_variant(data)
+            }
+
+            fn decode(data: Self) -> #name {
+                match data {
+                    #(#decode)*
+                }
+            }
+
+            fn outdated() -> ::core::option::Option<::c3p0::Filter> {
+                ::core::option::Option::Some(::c3p0::Filter::ne(
+                    ::c3p0::Field::json(#tag),
+                    #latest_tag,
+                ))
+            }
+        }
+    })
+}
+
+/// Returns the declarations of all the `#[c3p0(...)]` attributes of the `input`.
+fn parse_declarations(input: &DeriveInput) -> syn::Result<Declarations> {
+    let mut declarations = Declarations::default();
+    for attr in input
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("c3p0"))
+    {
+        attr.parse_nested_meta(|meta| {
+            let duplicate = || meta.error("duplicate declaration");
+            if meta.path.is_ident("table") {
+                if declarations.table.is_some() {
+                    return Err(duplicate());
+                }
+                declarations.table = Some(meta.value()?.parse()?);
+            } else if meta.path.is_ident("codec") {
+                if declarations.codec.is_some() {
+                    return Err(duplicate());
+                }
+                declarations.codec = Some(meta.value()?.parse()?);
+            } else if meta.path.is_ident("id") {
+                if declarations.id.is_some() {
+                    return Err(duplicate());
+                }
+                declarations.id = Some(meta.value()?.parse()?);
+            } else if meta.path.is_ident("format") {
+                if declarations.format.is_some() {
+                    return Err(duplicate());
+                }
+                declarations.format = Some(meta.value()?.parse()?);
+            } else if meta.path.is_ident("compression") {
+                if declarations.compression.is_some() {
+                    return Err(duplicate());
+                }
+                let algorithm: Ident = meta.value()?.parse()?;
+                if algorithm != "zstd" && algorithm != "gzip" {
+                    return Err(syn::Error::new_spanned(
+                        algorithm,
+                        "expected `zstd(threshold)` or `gzip(threshold)`",
+                    ));
+                }
+                let content;
+                parenthesized!(content in meta.input);
+                declarations.compression = Some((algorithm, content.parse()?));
+            } else if meta.path.is_ident("unique") {
+                if !declarations.unique.is_empty() {
+                    return Err(duplicate());
+                }
+                let content;
+                parenthesized!(content in meta.input);
+                declarations.unique = Punctuated::<LitStr, Token![,]>::parse_terminated(&content)?
+                    .into_iter()
+                    .collect();
+                if declarations.unique.is_empty() {
+                    return Err(meta.error("the unique key must have at least one path"));
+                }
+            } else if meta.path.is_ident("indexes") {
+                let content;
+                parenthesized!(content in meta.input);
+                declarations
+                    .indexes
+                    .extend(Punctuated::<Index, Token![,]>::parse_terminated(&content)?);
+            } else if meta.path.is_ident("versions") {
+                if declarations.versions.is_some() {
+                    return Err(duplicate());
+                }
+                let content;
+                parenthesized!(content in meta.input);
+                declarations.versions = Some(
+                    Punctuated::<Type, Token![,]>::parse_terminated(&content)?
+                        .into_iter()
+                        .collect(),
+                );
+            } else if meta.path.is_ident("tag") {
+                if declarations.tag.is_some() {
+                    return Err(duplicate());
+                }
+                declarations.tag = Some(meta.value()?.parse()?);
+            } else if meta.path.is_ident("soft_delete") {
+                declarations.soft_delete = true;
+            } else if meta.path.is_ident("history") {
+                declarations.history = true;
+            } else if meta.path.is_ident("upgrade_on_read") {
+                declarations.upgrade_on_read = true;
+            } else {
+                return Err(meta.error(
+                    "expected `table`, `codec`, `id`, `unique`, `indexes`, `soft_delete`, \
+                     `history`, `upgrade_on_read`, `format`, `compression`, `versions` or `tag`",
+                ));
+            }
+            Ok(())
+        })?;
+    }
+    Ok(declarations)
+}
+
+/// Checks the names and the paths of the `indexes`.
+fn check_indexes(indexes: &[Index]) -> syn::Result<()> {
+    for (position, index) in indexes.iter().enumerate() {
+        let name = index.name.to_string();
+        let invalid = |reason: &str| {
+            syn::Error::new_spanned(&index.name, format!("invalid index [{name}]: {reason}"))
+        };
+        if !is_identifier(&name) {
+            return Err(invalid(
+                "the name must be made of ASCII letters, digits and underscores",
+            ));
+        }
+        if name == UNIQUE_KEY_INDEX {
+            return Err(invalid(
+                "the name is reserved for the index of the unique key",
+            ));
+        }
+        if indexes[..position]
+            .iter()
+            .any(|other| other.name == index.name)
+        {
+            return Err(invalid("the name is used by another index"));
+        }
+        if index.containment {
+            if !index.paths.is_empty() || index.unique {
+                return Err(invalid(
+                    "a containment index has no paths and cannot be unique",
+                ));
+            }
+        } else if index.paths.is_empty() {
+            return Err(invalid("it must index at least one path"));
+        }
+        for (path, _) in &index.paths {
+            check_path(path)?;
+        }
+    }
+    Ok(())
+}
+
+/// Checks that the JSON `path` is made of non-empty keys separated by dots.
+fn check_path(path: &LitStr) -> syn::Result<()> {
+    if path.value().split('.').any(str::is_empty) {
+        return Err(syn::Error::new_spanned(
+            path,
+            "invalid JSON path: it must be made of non-empty keys separated by dots",
+        ));
+    }
+    Ok(())
+}
+
+fn is_identifier(name: &str) -> bool {
+    name.chars()
+        .next()
+        .is_some_and(|first| first.is_ascii_alphabetic() || first == '_')
+        && name
+            .chars()
+            .all(|char| char.is_ascii_alphanumeric() || char == '_')
+}
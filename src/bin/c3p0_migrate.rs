@@ -0,0 +1,77 @@
+//! Writes the sqlx migration of the c3p0 tables described by a JSON file.
+//!
+//! The file contains the array of the [`TableSchema`]s of the tables, as serialized by
+//! `serde_json::to_string(&[TableSchema::of::<UserData>()?, ...])`.
+
+use std::{env, fs, process::ExitCode};
+
+use c3p0::{
+    C3p0Error, TableSchema,
+    migrate::{Backend, MigrationGenerator},
+};
+
+const USAGE: &str = "Usage: c3p0-migrate --backend <mysql|postgres|sqlite> --schemas <FILE> \
+                     [--dir <DIR>] [--name <DESCRIPTION>] [--allow-destructive]";
+
+fn main() -> ExitCode {
+    match run(env::args().skip(1).collect()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("{err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(args: Vec<String>) -> Result<(), C3p0Error> {
+    let mut backend = None;
+    let mut schemas = None;
+    let mut dir = "migrations".to_owned();
+    let mut name = "c3p0".to_owned();
+    let mut allow_destructive = false;
+
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next().ok_or_else(|| C3p0Error::Other {
+                cause: format!("Missing value of [{arg}]\n{USAGE}"),
+            })
+        };
+        match arg.as_str() {
+            "--backend" => backend = Some(value()?.parse::<Backend>()?),
+            "--schemas" => schemas = Some(value()?),
+            "--dir" => dir = value()?,
+            "--name" => name = value()?,
+            "--allow-destructive" => allow_destructive = true,
+            "-h" | "--help" => {
+                println!("{USAGE}");
+                return Ok(());
+            }
+            _ => {
+                return Err(C3p0Error::Other {
+                    cause: format!("Unknown argument [{arg}]\n{USAGE}"),
+                });
+            }
+        }
+    }
+    let (Some(backend), Some(schemas)) = (backend, schemas) else {
+        return Err(C3p0Error::Other {
+            cause: USAGE.to_owned(),
+        });
+    };
+
+    let schemas = fs::read_to_string(&schemas).map_err(|err| C3p0Error::Other {
+        cause: format!("Cannot read [{schemas}]: {err}"),
+    })?;
+    let schemas: Vec<TableSchema> =
+        serde_json::from_str(&schemas).map_err(|err| C3p0Error::Other {
+            cause: format!("Invalid schemas: {err}"),
+        })?;
+
+    let generator = MigrationGenerator::new(backend, dir).allow_destructive(allow_destructive);
+    match generator.generate(&name, &schemas)? {
+        Some(path) => println!("Created {}", path.display()),
+        None => println!("The schemas did not change"),
+    }
+    Ok(())
+}
@@ -1,5 +1,76 @@
+//! Conversion between the in-memory `DATA` and the JSON stored in the `data` column.
+//!
+//! A [`Codec`] is usually an enum with one variant per version of the stored shape,
+//! where each previous version declares its [`Upgrade`] to the next one, and the
+//! [`Codec::outdated`] filter matches the entries stored in a previous version:
+//!
+//! ```rust
+//! use c3p0::{Codec, DataType, Field, Filter, Upgrade};
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Clone, Serialize, Deserialize)]
+//! pub struct UserV1 {
+//!     pub username: String,
+//! }
+//!
+//! #[derive(Clone, Serialize, Deserialize)]
+//! pub struct User {
+//!     pub username: String,
+//!     pub age: u32,
+//! }
+//!
+//! impl Upgrade for UserV1 {
+//!     type Next = User;
+//!
+//!     fn upgrade(self) -> User {
+//!         User { username: self.username, age: 18 }
+//!     }
+//! }
+//!
+//! #[derive(Serialize, Deserialize)]
+//! #[serde(tag = "@json_tag")]
+//! pub enum UserCodec {
+//!     V1(UserV1),
+//!     V2(User),
+//! }
+//!
+//! impl Codec<User> for UserCodec {
+//!     fn encode(data: User) -> Self {
+//!         UserCodec::V2(data)
+//!     }
+//!
+//!     fn decode(data: Self) -> User {
+//!         match data {
+//!             UserCodec::V1(user) => user.upgrade(),
+//!             UserCodec::V2(user) => user,
+//!         }
+//!     }
+//!
+//!     fn outdated() -> Option<Filter> {
+//!         Some(Filter::ne(Field::json("@json_tag"), "V2"))
+//!     }
+//! }
+//!
+//! impl DataType for User {
+//!     const TABLE_NAME: &'static str = "USERS";
+//!     type CODEC = UserCodec;
+//!     // Rewrite the outdated entries when they are read
+//!     const UPGRADE_ON_READ: bool = true;
+//! }
+//! ```
+//!
+//! The outdated entries are decoded into the latest shape when they are read. They are
+//! rewritten in the latest shape by [`Tx::upgrade_all`](crate::Tx::upgrade_all) or, for
+//! the [`DataType::UPGRADE_ON_READ`](crate::DataType::UPGRADE_ON_READ) types, when they
+//! are read.
+//!
+//! With the `derive` feature, the codec enum and its impl are generated by
+//! `#[derive(Codec)]` from the list of the previous versions.
+
 use serde::{Serialize, de::DeserializeOwned};
 
+use crate::filter::Filter;
+
 /// Lossless converter between a [`DataType`](crate::DataType)'s in-memory `DATA` and
 /// the shape that gets serialised into the `data` JSON column.
 pub trait Codec<DATA>: Send + Sync + Serialize + DeserializeOwned {
@@ -8,6 +79,14 @@ pub trait Codec<DATA>: Send + Sync + Serialize + DeserializeOwned {
 
     /// Convert an on-disk value back into the in-memory shape just after reading.
     fn decode(data: Self) -> DATA;
+
+    /// Returns the filter matching the entries stored in a previous shape, which are
+    /// rewritten in the shape returned by [`encode`](Self::encode) by
+    /// [`Tx::upgrade_all`](crate::Tx::upgrade_all). `None`, the default, means that
+    /// there is a single shape.
+    fn outdated() -> Option<Filter> {
+        None
+    }
 }
 
 impl<T: Send + Sync + Serialize + DeserializeOwned> Codec<T> for T {
@@ -21,3 +100,14 @@ impl<T: Send + Sync + Serialize + DeserializeOwned> Codec<T> for T {
         data
     }
 }
+
+/// A previous version of the stored shape of a `DATA`, which is upgraded to the next
+/// version when it is decoded. The versions are chained, e.g.
+/// `user_v1.upgrade().upgrade()` for the third version.
+pub trait Upgrade {
+    /// The next version of the shape.
+    type Next;
+
+    /// Converts the value into the next version of the shape.
+    fn upgrade(self) -> Self::Next;
+}
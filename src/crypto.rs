@@ -0,0 +1,491 @@
+//! Field-level encryption of the `data` column with AES-256-GCM.
+//!
+//! The fields annotated with `#[serde(with = "c3p0::crypto::encrypted")]` are encrypted
+//! with the current key of the [`KeyRing`] supplied to the pool, e.g. with
+//! `PgC3p0Pool::with_key_ring`, and stored as a JSON object with the id of the key and the
+//! nonce and ciphertext in base64. They are decrypted with the key of their id when read,
+//! so the annotated fields can be used in any [`Codec`](crate::Codec) shape:
+//!
+//! ```rust
+//! use c3p0::DataType;
+//! use c3p0::crypto::KeyRing;
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Clone, Serialize, Deserialize)]
+//! pub struct Customer {
+//!     pub name: String,
+//!     #[serde(with = "c3p0::crypto::encrypted")]
+//!     pub tax_code: String,
+//!     #[serde(with = "c3p0::crypto::encrypted")]
+//!     pub phone: Option<String>,
+//! }
+//!
+//! impl DataType for Customer {
+//!     const TABLE_NAME: &'static str = "CUSTOMERS";
+//!     type CODEC = Self;
+//! }
+//!
+//! // The values encrypted with "2024" are still decrypted, the new ones use "2025"
+//! let key_ring = KeyRing::new("2025", [7; 32]).with_key("2024", [3; 32]);
+//! ```
+//!
+//! The keys are rotated by supplying a key ring whose current key is the new one and that
+//! keeps the previous ones, then by rewriting the entries with
+//! [`Tx::reencrypt_all`](crate::Tx::reencrypt_all); the previous keys can be dropped
+//! afterwards.
+//!
+//! Each pool runs its transactions with its own key ring; the connections that do not come
+//! from a pool use the one of an enclosing [`KeyRing::scope`]. The encrypted fields cannot
+//! be queried, indexed nor projected, as their stored values are random.
+//!
+//! The id of the key and the path of the field in the document, e.g. `address.phone`, are
+//! authenticated along with the value, so a stored value copied to another field or
+//! relabeled with another key id cannot be decrypted. The table and the id of the entry are
+//! not, as the ids generated by the database are not known when the values are encrypted:
+//! a value copied to the same field of another entry is still decrypted.
+//!
+//! Outside the c3p0 operations, e.g. when a document is sent in an HTTP response or written
+//! to a cache, the encrypted fields are serialized and deserialized as their plaintext. The
+//! c3p0 operations never store nor read them in plaintext: they fail without a key ring.
+
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    fmt,
+    future::{Future, poll_fn},
+    pin::pin,
+    sync::Arc,
+};
+
+use aes_gcm::{
+    Aes256Gcm, KeyInit, Nonce,
+    aead::{Aead, AeadCore, OsRng, Payload},
+};
+use base64::{Engine, prelude::BASE64_STANDARD};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use serde_json::Value;
+use sqlx::Database;
+
+use crate::{
+    C3p0Error, DataType, Record,
+    filter::Filter,
+    ops::TableOps,
+    patch::{JsonPatch, PatchOp},
+    table::TableName,
+    upgrade::{UpgradeProgress, rewrite_where},
+};
+
+/// The size in bytes of the AES-GCM nonces, stored before the ciphertext.
+const NONCE_SIZE: usize = 12;
+
+/// The key of the JSON object that holds the plaintext of an encrypted field while a
+/// document is encoded, before it is replaced with the [`EncryptedValue`].
+const PLAINTEXT_KEY: &str = "@c3p0_plaintext";
+
+thread_local! {
+    /// The key ring of the [`KeyRing::scope`] being polled on this thread.
+    static KEY_RING: RefCell<Option<Arc<KeyRing>>> = const { RefCell::new(None) };
+
+    /// How the encrypted fields are serialized on this thread.
+    static ENCODING: Cell<Encoding> = const { Cell::new(Encoding::Plaintext) };
+
+    /// The paths of the encrypted values of the documents that [`decrypt_document`]
+    /// deserializes, by their data; `None` outside the c3p0 operations.
+    static PATHS: RefCell<Option<HashMap<String, String>>> = const { RefCell::new(None) };
+}
+
+/// The serialization of the encrypted fields.
+#[derive(Clone, Copy)]
+enum Encoding {
+    /// As their plaintext, outside the c3p0 operations.
+    Plaintext,
+    /// As a [`Plaintext`] marker, replaced by [`encrypt_document`].
+    Marked,
+    /// Not serialized, as the document is stored without a key ring.
+    Rejected,
+}
+
+/// Restores the key ring of the enclosing scope when a scope stops being polled.
+struct ScopeGuard(Option<Arc<KeyRing>>);
+
+impl Drop for ScopeGuard {
+    fn drop(&mut self) {
+        KEY_RING.set(self.0.take());
+    }
+}
+
+/// The AES-256 keys of the encrypted fields, by id: the current one encrypts the values
+/// written, all of them decrypt the values read.
+#[derive(Clone)]
+pub struct KeyRing {
+    current: String,
+    keys: HashMap<String, Aes256Gcm>,
+}
+
+impl KeyRing {
+    /// Returns a key ring whose current key is `key`, with the id `key_id`.
+    pub fn new(key_id: impl Into<String>, key: [u8; 32]) -> Self {
+        let current = key_id.into();
+        let mut keys = HashMap::new();
+        keys.insert(current.clone(), Aes256Gcm::new(&key.into()));
+        KeyRing { current, keys }
+    }
+
+    /// Adds a previous key, only used to decrypt the values encrypted with it.
+    pub fn with_key(mut self, key_id: impl Into<String>, key: [u8; 32]) -> Self {
+        let key_id = key_id.into();
+        if key_id != self.current {
+            self.keys.insert(key_id, Aes256Gcm::new(&key.into()));
+        }
+        self
+    }
+
+    /// Returns the id of the key that encrypts the values written.
+    pub fn current_key_id(&self) -> &str {
+        &self.current
+    }
+
+    /// Runs `future` with this key ring, which encrypts and decrypts the fields serialized
+    /// and deserialized while it is polled. The pools supplied with a key ring run their
+    /// transactions in its scope.
+    pub async fn scope<F: Future>(self: Arc<Self>, future: F) -> F::Output {
+        let mut future = pin!(future);
+        poll_fn(|cx| {
+            let _guard = ScopeGuard(KEY_RING.replace(Some(self.clone())));
+            future.as_mut().poll(cx)
+        })
+        .await
+    }
+
+    /// Returns the key ring of the current scope.
+    fn current() -> Result<Arc<KeyRing>, C3p0Error> {
+        KEY_RING
+            .with_borrow(Option::clone)
+            .ok_or_else(|| C3p0Error::Other {
+                cause: "No key ring was supplied to decrypt and encrypt the data".to_owned(),
+            })
+    }
+
+    fn encrypt(&self, value: &Value, path: &str) -> Result<EncryptedValue, C3p0Error> {
+        let plaintext = serde_json::to_vec(value).map_err(|err| C3p0Error::Other {
+            cause: format!("Cannot serialize the value to encrypt: {err}"),
+        })?;
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let mut bytes = nonce.to_vec();
+        bytes.extend(
+            self.keys[&self.current]
+                .encrypt(
+                    &nonce,
+                    Payload {
+                        msg: &plaintext,
+                        aad: &associated_data(&self.current, path),
+                    },
+                )
+                .map_err(|_| C3p0Error::Other {
+                    cause: format!("Cannot encrypt the value with the key [{}]", self.current),
+                })?,
+        );
+        Ok(EncryptedValue {
+            key_id: self.current.clone(),
+            data: BASE64_STANDARD.encode(bytes),
+        })
+    }
+
+    fn decrypt<T: DeserializeOwned>(
+        &self,
+        value: &EncryptedValue,
+        path: &str,
+    ) -> Result<T, C3p0Error> {
+        let error = |cause: &str| C3p0Error::Other {
+            cause: format!(
+                "Cannot decrypt the value with the key [{}]: {cause}",
+                value.key_id
+            ),
+        };
+        let key = self
+            .keys
+            .get(&value.key_id)
+            .ok_or_else(|| error("unknown key"))?;
+        let bytes = BASE64_STANDARD
+            .decode(&value.data)
+            .map_err(|err| error(&err.to_string()))?;
+        let (nonce, ciphertext) = bytes
+            .split_first_chunk::<NONCE_SIZE>()
+            .ok_or_else(|| error("the data is too short"))?;
+        let plaintext = key
+            .decrypt(
+                &Nonce::from(*nonce),
+                Payload {
+                    msg: ciphertext,
+                    aad: &associated_data(&value.key_id, path),
+                },
+            )
+            .map_err(|_| error("wrong key, corrupted data or moved value"))?;
+        serde_json::from_slice(&plaintext).map_err(|err| error(&err.to_string()))
+    }
+}
+
+impl fmt::Debug for KeyRing {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut key_ids: Vec<_> = self.keys.keys().collect();
+        key_ids.sort();
+        f.debug_struct("KeyRing")
+            .field("current", &self.current)
+            .field("keys", &key_ids)
+            .finish()
+    }
+}
+
+/// Returns the associated data authenticated with a value: the id of its key and its path,
+/// separated by a NUL byte.
+fn associated_data(key_id: &str, path: &str) -> Vec<u8> {
+    [key_id.as_bytes(), &[0], path.as_bytes()].concat()
+}
+
+/// The stored shape of an encrypted value.
+#[derive(Serialize, Deserialize)]
+struct EncryptedValue {
+    key_id: String,
+    data: String,
+}
+
+impl EncryptedValue {
+    /// Returns the value if `value` has its shape.
+    fn of(value: &Value) -> Option<Self> {
+        match value {
+            Value::Object(object) if object.len() == 2 => Some(EncryptedValue {
+                key_id: object.get("key_id")?.as_str()?.to_owned(),
+                data: object.get("data")?.as_str()?.to_owned(),
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// The plaintext of an encrypted field in a document being encoded.
+#[derive(Serialize)]
+struct Plaintext<'a, T> {
+    #[serde(rename = "@c3p0_plaintext")]
+    value: &'a T,
+}
+
+/// Returns the path of the child `key` of the value at `path`.
+fn child_path(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.to_owned()
+    } else {
+        format!("{path}.{key}")
+    }
+}
+
+/// The serde functions of the encrypted fields, used with
+/// `#[serde(with = "c3p0::crypto::encrypted")]`.
+pub mod encrypted {
+
+    use serde::{
+        Deserialize, Deserializer, Serialize, Serializer, de::DeserializeOwned, de::Error as _,
+        ser::Error as _,
+    };
+
+    use super::{ENCODING, Encoding, EncryptedValue, KeyRing, PATHS, Plaintext};
+
+    /// Serializes the `value` encrypted with the current key of the [`KeyRing`] when it is
+    /// stored by c3p0, as its plaintext otherwise.
+    pub fn serialize<T: Serialize, S: Serializer>(
+        value: &T,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match ENCODING.get() {
+            Encoding::Plaintext => value.serialize(serializer),
+            Encoding::Marked => Plaintext { value }.serialize(serializer),
+            Encoding::Rejected => Err(S::Error::custom(
+                "No key ring was supplied to encrypt the data",
+            )),
+        }
+    }
+
+    /// Deserializes a value encrypted with a key of the [`KeyRing`] when it is read by
+    /// c3p0, its plaintext otherwise.
+    pub fn deserialize<'de, T: DeserializeOwned, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<T, D::Error> {
+        if PATHS.with_borrow(Option::is_none) {
+            return T::deserialize(deserializer);
+        }
+        let value = EncryptedValue::deserialize(deserializer)?;
+        let key_ring = KeyRing::current().map_err(D::Error::custom)?;
+        let path = PATHS
+            .with_borrow(|paths| paths.as_ref()?.get(&value.data).cloned())
+            .ok_or_else(|| D::Error::custom("The encrypted value is not in a stored document"))?;
+        key_ring.decrypt(&value, &path).map_err(D::Error::custom)
+    }
+}
+
+/// Returns true if a [`KeyRing`] is in scope, see [`KeyRing::scope`].
+pub(crate) fn key_ring_in_scope() -> bool {
+    KEY_RING.with_borrow(Option::is_some)
+}
+
+/// Returns the JSON of `data` with its encrypted fields, or `None` if it has none or no
+/// [`KeyRing`] is in scope.
+pub(crate) fn encrypt_document<T: Serialize>(data: &T) -> Result<Option<Value>, C3p0Error> {
+    let Some(key_ring) = KEY_RING.with_borrow(Option::clone) else {
+        return Ok(None);
+    };
+    let previous = ENCODING.replace(Encoding::Marked);
+    let document = serde_json::to_value(data);
+    ENCODING.set(previous);
+    let mut document = document.map_err(|err| C3p0Error::Other {
+        cause: format!("Cannot serialize the data: {err}"),
+    })?;
+    Ok(encrypt_values(&key_ring, &mut document, "")?.then_some(document))
+}
+
+/// Replaces the plaintexts in `value`, at `path`, with their [`EncryptedValue`]s. Returns
+/// true if there was any.
+fn encrypt_values(key_ring: &KeyRing, value: &mut Value, path: &str) -> Result<bool, C3p0Error> {
+    match value {
+        Value::Object(object) if object.len() == 1 && object.contains_key(PLAINTEXT_KEY) => {
+            let encrypted = key_ring.encrypt(&object[PLAINTEXT_KEY], path)?;
+            *value = serde_json::to_value(encrypted).map_err(|err| C3p0Error::Other {
+                cause: format!("Cannot serialize the encrypted value: {err}"),
+            })?;
+            Ok(true)
+        }
+        Value::Object(object) => object.iter_mut().try_fold(false, |found, (key, child)| {
+            Ok(encrypt_values(key_ring, child, &child_path(path, key))? || found)
+        }),
+        // The items of an array share the path of the array
+        Value::Array(items) => items.iter_mut().try_fold(false, |found, item| {
+            Ok(encrypt_values(key_ring, item, path)? || found)
+        }),
+        _ => Ok(false),
+    }
+}
+
+/// Runs `encode` on a document stored without encrypting it: its encrypted fields, if
+/// any, fail to serialize instead of being stored in plaintext.
+pub(crate) fn encode_unencrypted<T>(encode: impl FnOnce() -> T) -> T {
+    let previous = ENCODING.replace(Encoding::Rejected);
+    let result = encode();
+    ENCODING.set(previous);
+    result
+}
+
+/// Runs `decode` on a stored document read without decrypting it: its encrypted fields,
+/// if any, fail to deserialize instead of being read as plaintext.
+pub(crate) fn decode_unencrypted<T>(decode: impl FnOnce() -> T) -> T {
+    let previous = PATHS.replace(Some(HashMap::new()));
+    let result = decode();
+    PATHS.set(previous);
+    result
+}
+
+/// Runs `deserialize` on the stored `document`, whose encrypted values are decrypted with
+/// the path where they are found.
+pub(crate) fn decrypt_document<T>(document: &Value, deserialize: impl FnOnce() -> T) -> T {
+    let mut paths = HashMap::new();
+    encrypted_paths(document, "", &mut paths);
+    let previous = PATHS.replace(Some(paths));
+    let result = deserialize();
+    PATHS.set(previous);
+    result
+}
+
+/// Collects the paths of the values of `value`, at `path`, that can be encrypted.
+fn encrypted_paths(value: &Value, path: &str, paths: &mut HashMap<String, String>) {
+    if let Some(encrypted) = EncryptedValue::of(value) {
+        paths.insert(encrypted.data, path.to_owned());
+    }
+    match value {
+        Value::Object(object) => {
+            for (key, child) in object {
+                encrypted_paths(child, &child_path(path, key), paths);
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                encrypted_paths(item, path, paths);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Returns an error if the `patch` sets or removes an encrypted value of the stored
+/// `document` of `table`, or one of its parents or children: the values set would be
+/// stored in plaintext.
+pub(crate) fn check_patch(
+    table: &TableName,
+    document: &Value,
+    patch: &JsonPatch,
+) -> Result<(), C3p0Error> {
+    let mut encrypted = HashMap::new();
+    encrypted_paths(document, "", &mut encrypted);
+    if encrypted.is_empty() {
+        return Ok(());
+    }
+    let mut patched = Vec::new();
+    match patch {
+        JsonPatch::Merge(patch) => merge_patch_paths(patch, "", &mut patched),
+        JsonPatch::Ops(ops) => patched.extend(ops.iter().map(|op| {
+            let (PatchOp::Set(path, _) | PatchOp::Remove(path)) = op;
+            path.keys().join(".")
+        })),
+    }
+    let overlaps = |a: &str, b: &str| {
+        a.is_empty()
+            || b.is_empty()
+            || a == b
+            || a.strip_prefix(b).is_some_and(|rest| rest.starts_with('.'))
+            || b.strip_prefix(a).is_some_and(|rest| rest.starts_with('.'))
+    };
+    match patched
+        .iter()
+        .find(|patched| encrypted.values().any(|path| overlaps(patched, path)))
+    {
+        Some(path) => Err(C3p0Error::Other {
+            cause: format!(
+                "The patch of table [{}] cannot change the encrypted value at [{path}]",
+                table.declared()
+            ),
+        }),
+        None => Ok(()),
+    }
+}
+
+/// Collects the paths of the values replaced by the merge `patch` applied at `path`.
+fn merge_patch_paths(patch: &Value, path: &str, paths: &mut Vec<String>) {
+    match patch {
+        Value::Object(object) => {
+            for (key, child) in object {
+                merge_patch_paths(child, &child_path(path, key), paths);
+            }
+        }
+        _ => paths.push(path.to_owned()),
+    }
+}
+
+/// Runs `future` in the scope of the `key_ring`, if any, see [`KeyRing::scope`].
+pub(crate) async fn in_scope<F: Future>(key_ring: Option<Arc<KeyRing>>, future: F) -> F::Output {
+    match key_ring {
+        Some(key_ring) => key_ring.scope(future).await,
+        None => future.await,
+    }
+}
+
+/// Rewrites all the entries of `DATA`, encrypting their fields with the current key of the
+/// [`KeyRing`], in batches of `batch_size` entries ordered by `id`.
+pub(crate) async fn reencrypt_all<DB: Database, DATA: DataType>(
+    tx: &mut DB::Connection,
+    table: &TableName,
+    batch_size: u64,
+    progress: impl FnMut(&UpgradeProgress),
+) -> Result<UpgradeProgress, C3p0Error>
+where
+    Record<DATA>: TableOps<DB, DATA>,
+{
+    // Fail before reading the entries if there is no key to encrypt them
+    KeyRing::current()?;
+    rewrite_where::<DB, DATA>(tx, table, &Filter::and([]), batch_size, progress).await
+}
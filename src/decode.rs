@@ -0,0 +1,131 @@
+//! The lenient reading of the entries whose `data` cannot be decoded by their [`Codec`].
+//!
+//! A [`DecodeError`] keeps the id and the raw JSON of an entry along with the serde error
+//! and the path of the JSON value where it occurred, e.g. `address.zip` or `tags[2]`.
+
+use std::fmt;
+
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+use sqlx::Database;
+
+use crate::{
+    C3p0Error, Codec, DataType, IdType, Record,
+    filter::{Field, Filter},
+    ops::TableOps,
+    record::CodecOf,
+    table::TableName,
+};
+
+/// An entry whose `data` cannot be decoded.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DecodeError<DATA: DataType> {
+    /// The id of the entry.
+    pub id: DATA::ID,
+    /// The raw JSON of the `data` column.
+    pub data: Value,
+    /// The path of the JSON value that cannot be decoded, empty for the whole document.
+    pub path: String,
+    /// The serde error.
+    pub error: String,
+}
+
+impl<DATA: DataType> fmt::Display for DecodeError<DATA> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.path.is_empty() {
+            write!(f, "Cannot decode the entry [{:?}]: {}", self.id, self.error)
+        } else {
+            write!(
+                f,
+                "Cannot decode the entry [{:?}] at [{}]: {}",
+                self.id, self.path, self.error
+            )
+        }
+    }
+}
+
+/// An entry read leniently: the decoded record, or the [`DecodeError`] of its `data`.
+pub type LenientRecord<DATA> = Result<Record<DATA>, DecodeError<DATA>>;
+
+/// A row whose `data` is read as raw JSON.
+pub(crate) struct RawRecord<DATA: DataType> {
+    pub(crate) id: DATA::ID,
+    pub(crate) version: i64,
+    pub(crate) create_time: DateTime<Utc>,
+    pub(crate) update_time: DateTime<Utc>,
+    pub(crate) data: Value,
+}
+
+impl<DATA: DataType> RawRecord<DATA> {
+    /// Decodes the `data` with the [`DataType::CODEC`].
+    pub(crate) fn decode(self) -> LenientRecord<DATA> {
+        #[cfg(feature = "encryption")]
+        return crate::crypto::decrypt_document(&self.data.clone(), || self.decode_document());
+        #[cfg(not(feature = "encryption"))]
+        self.decode_document()
+    }
+
+    fn decode_document(self) -> LenientRecord<DATA> {
+        match serde_path_to_error::deserialize::<_, CodecOf<DATA>>(&self.data) {
+            Ok(data) => Ok(Record {
+                id: self.id,
+                version: self.version,
+                data: CodecOf::<DATA>::decode(data),
+                create_time: self.create_time,
+                update_time: self.update_time,
+            }),
+            Err(err) => Err(DecodeError {
+                // An empty path, for the whole document, is displayed as `.`
+                path: match err.path().iter().next() {
+                    Some(_) => err.path().to_string(),
+                    None => String::new(),
+                },
+                error: err.into_inner().to_string(),
+                id: self.id,
+                data: self.data,
+            }),
+        }
+    }
+}
+
+/// Returns the entries that cannot be decoded, reading the table in chunks of `chunk_size`
+/// entries ordered by `id`.
+pub(crate) async fn scan_for_decode_errors<DB: Database, DATA: DataType>(
+    tx: &mut DB::Connection,
+    table: &TableName,
+    chunk_size: u64,
+) -> Result<Vec<DecodeError<DATA>>, C3p0Error>
+where
+    Record<DATA>: TableOps<DB, DATA>,
+{
+    let chunk_size = chunk_size.max(1);
+    let mut errors = Vec::new();
+    let mut last_id: Option<DATA::ID> = None;
+    loop {
+        let filter = last_id
+            .as_ref()
+            .map(|id| Filter::gt(Field::Id, id.to_sql()));
+        let chunk = <Record<DATA> as TableOps<DB, DATA>>::fetch_lenient(
+            tx,
+            table,
+            filter.as_ref(),
+            0,
+            Some(chunk_size),
+        )
+        .await?;
+        let complete = (chunk.len() as u64) < chunk_size;
+        for entry in chunk {
+            match entry {
+                Ok(record) => last_id = Some(record.id),
+                Err(err) => {
+                    last_id = Some(err.id.clone());
+                    errors.push(err);
+                }
+            }
+        }
+        if complete {
+            break;
+        }
+    }
+    Ok(errors)
+}
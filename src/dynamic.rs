@@ -0,0 +1,923 @@
+//! Access to the c3p0 tables without a compiled [`DataType`] for each of them, e.g. for
+//! admin tools.
+//!
+//! A [`DynamicTable`] reads and writes the entries of a table whose name and options are
+//! known at runtime as raw JSON, including the optimistic locking of
+//! [`DynamicTable::update`]:
+//!
+//! ```rust
+//! #[cfg(feature = "postgres")]
+//! pub mod with_postgres {
+//!
+//!     use c3p0::{C3p0Error, DynamicRecord, DynamicTable};
+//!     use serde_json::Value;
+//!
+//!     pub async fn rename_user(
+//!         conn: &mut sqlx::PgConnection,
+//!         table_name: &str,
+//!         id: i64,
+//!         username: &str,
+//!     ) -> Result<DynamicRecord, C3p0Error> {
+//!         let table = DynamicTable::new(table_name)?.with_soft_delete(true);
+//!         let mut record = table.fetch_one_by_id(conn, id).await?;
+//!         record.data["username"] = Value::from(username);
+//!         table.update(conn, record).await
+//!     }
+//! }
+//! ```
+//!
+//! The options must be the ones of the [`DataType`] of the table, see
+//! [`DynamicTable::of`]: the soft deleted entries are not read and are marked as deleted,
+//! the history table keeps the previous versions and the data is read and written in the
+//! [`Format`]. The compressed documents are decompressed on read, while the documents
+//! written through a [`DynamicTable`] are stored uncompressed, see
+//! [`DynamicTable::with_compression`].
+
+use std::marker::PhantomData;
+
+use chrono::{DateTime, Utc};
+use futures_core::Stream;
+use futures_util::{StreamExt, TryStreamExt, future, stream};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    C3p0Error, DataType, IdType, Record, Tx, WithId,
+    filter::Filter,
+    format::{Compression, CompressionAlgorithm, Format},
+    ops::DynamicOps,
+    page::{Cursor, Page, PageOrder},
+    patch::JsonPatch,
+    sql::SortKey,
+    table::TableName,
+};
+
+/// Runs `$body` with `$entry` the [`Entry`] type of the options of the `$table`.
+macro_rules! with_entry {
+    ($table:expr, $entry:ident => $body:expr) => {
+        match ($table.ids, $table.soft_delete, $table.history) {
+            (IdKind::AutoIncrement, false, false) => {
+                with_entry!(@format $table, i64, false, false, $entry => $body)
+            }
+            (IdKind::AutoIncrement, false, true) => {
+                with_entry!(@format $table, i64, false, true, $entry => $body)
+            }
+            (IdKind::AutoIncrement, true, false) => {
+                with_entry!(@format $table, i64, true, false, $entry => $body)
+            }
+            (IdKind::AutoIncrement, true, true) => {
+                with_entry!(@format $table, i64, true, true, $entry => $body)
+            }
+            (IdKind::Text, false, false) => {
+                with_entry!(@format $table, String, false, false, $entry => $body)
+            }
+            (IdKind::Text, false, true) => {
+                with_entry!(@format $table, String, false, true, $entry => $body)
+            }
+            (IdKind::Text, true, false) => {
+                with_entry!(@format $table, String, true, false, $entry => $body)
+            }
+            (IdKind::Text, true, true) => {
+                with_entry!(@format $table, String, true, true, $entry => $body)
+            }
+        }
+    };
+    (@format $table:expr, $id:ty, $soft_delete:literal, $history:literal, $entry:ident => $body:expr) => {
+        match $table.format {
+            Format::Json => {
+                with_entry!(@compression $table, $id, $soft_delete, $history, JSON, $entry => $body)
+            }
+            #[cfg(feature = "msgpack")]
+            Format::MessagePack => {
+                with_entry!(
+                    @compression $table, $id, $soft_delete, $history, MESSAGE_PACK, $entry => $body
+                )
+            }
+            #[cfg(feature = "cbor")]
+            Format::Cbor => {
+                with_entry!(@compression $table, $id, $soft_delete, $history, CBOR, $entry => $body)
+            }
+        }
+    };
+    (
+        @compression $table:expr, $id:ty, $soft_delete:literal, $history:literal, $format:ident,
+        $entry:ident => $body:expr
+    ) => {
+        match compression_code($table.compression) {
+            #[cfg(feature = "zstd")]
+            ZSTD => {
+                type $entry = Entry<$id, $soft_delete, $history, $format, ZSTD>;
+                $body
+            }
+            #[cfg(feature = "gzip")]
+            GZIP => {
+                type $entry = Entry<$id, $soft_delete, $history, $format, GZIP>;
+                $body
+            }
+            UNCOMPRESSED => {
+                type $entry = Entry<$id, $soft_delete, $history, $format, UNCOMPRESSED>;
+                $body
+            }
+            code => unreachable!("Unknown compression code {code}"),
+        }
+    };
+}
+
+/// The kind of the ids of a [`DynamicTable`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum IdKind {
+    /// `i64` ids generated by the database.
+    #[default]
+    AutoIncrement,
+    /// Text ids, like the UUIDs and the ULIDs, which must be provided to
+    /// [`DynamicTable::save_with_id`].
+    Text,
+}
+
+impl IdKind {
+    /// Returns the kind of the ids of type `ID`.
+    pub fn of<ID: IdType>() -> Self {
+        if ID::AUTO_INCREMENT {
+            IdKind::AutoIncrement
+        } else {
+            IdKind::Text
+        }
+    }
+}
+
+/// The id of an entry of a [`DynamicTable`].
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum DynamicId {
+    /// An id of a table of [`IdKind::AutoIncrement`] ids.
+    Int(i64),
+    /// An id of a table of [`IdKind::Text`] ids.
+    Text(String),
+}
+
+impl From<i64> for DynamicId {
+    fn from(id: i64) -> Self {
+        DynamicId::Int(id)
+    }
+}
+
+impl From<String> for DynamicId {
+    fn from(id: String) -> Self {
+        DynamicId::Text(id)
+    }
+}
+
+impl From<&str> for DynamicId {
+    fn from(id: &str) -> Self {
+        DynamicId::Text(id.to_owned())
+    }
+}
+
+/// An entry of a [`DynamicTable`], whose data is raw JSON.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DynamicRecord {
+    /// The unique identifier of the entry.
+    pub id: DynamicId,
+    /// The version of the entry used for optimistic locking.
+    pub version: i64,
+    /// UTC timestamp when the entry was created (DB-side clock).
+    pub create_time: DateTime<Utc>,
+    /// UTC timestamp when the entry was last updated (DB-side clock).
+    pub update_time: DateTime<Utc>,
+    /// The data of the entry.
+    pub data: Value,
+}
+
+/// An entry of a [`DynamicTable`] along with its deletion time, see
+/// [`SoftDeleteRecord`](crate::SoftDeleteRecord).
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DynamicSoftDeleteRecord {
+    /// The record.
+    pub record: DynamicRecord,
+    /// UTC timestamp when the record was soft deleted (DB-side clock); `None` if it is
+    /// not deleted.
+    pub delete_time: Option<DateTime<Utc>>,
+}
+
+/// The result of [`DynamicTable::update_all`], see
+/// [`UpdateAllResult`](crate::UpdateAllResult).
+#[derive(Clone, Debug, PartialEq)]
+pub struct DynamicUpdateAllResult {
+    /// The updated records, with their new `version` and `update_time`.
+    pub updated: Vec<DynamicRecord>,
+    /// The records that were not updated because of a version conflict, unchanged.
+    pub conflicts: Vec<DynamicRecord>,
+}
+
+/// A page of the entries of a [`DynamicTable`], see [`Page`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct DynamicPage {
+    /// The entries of the page.
+    pub records: Vec<DynamicRecord>,
+    /// The cursor to fetch the next page; `None` when this is the last page.
+    pub next: Option<Cursor>,
+}
+
+/// A table whose name and options are chosen at runtime, and whose entries are read and
+/// written as raw JSON.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DynamicTable {
+    name: String,
+    ids: IdKind,
+    soft_delete: bool,
+    history: bool,
+    format: Format,
+    compression: Option<CompressionAlgorithm>,
+}
+
+impl DynamicTable {
+    /// Returns the table with the given name, with [`IdKind::AutoIncrement`] ids, neither
+    /// soft delete nor history and uncompressed JSON data, or an error if the name is not
+    /// valid. The name has the syntax of [`DataType::TABLE_NAME`] and is quoted with the
+    /// same rules.
+    pub fn new(name: impl Into<String>) -> Result<Self, C3p0Error> {
+        let name = name.into();
+        TableName::validate(&name)?;
+        Ok(DynamicTable {
+            name,
+            ids: IdKind::AutoIncrement,
+            soft_delete: false,
+            history: false,
+            format: Format::Json,
+            compression: None,
+        })
+    }
+
+    /// Returns the [`DataType::TABLE_NAME`] table of `DATA`, with its options.
+    pub fn of<DATA: DataType>() -> Result<Self, C3p0Error> {
+        Ok(DynamicTable::new(DATA::TABLE_NAME)?
+            .with_ids(IdKind::of::<DATA::ID>())
+            .with_soft_delete(DATA::SOFT_DELETE)
+            .with_history(DATA::HISTORY)
+            .with_format(DATA::FORMAT)
+            .with_compression(DATA::COMPRESSION.map(|compression| compression.algorithm)))
+    }
+
+    /// Sets the kind of the ids, see [`DataType::CODEC`].
+    pub fn with_ids(mut self, ids: IdKind) -> Self {
+        self.ids = ids;
+        self
+    }
+
+    /// Sets whether the entries are soft deleted, see [`DataType::SOFT_DELETE`].
+    pub fn with_soft_delete(mut self, soft_delete: bool) -> Self {
+        self.soft_delete = soft_delete;
+        self
+    }
+
+    /// Sets whether the table has a history table, see [`DataType::HISTORY`].
+    pub fn with_history(mut self, history: bool) -> Self {
+        self.history = history;
+        self
+    }
+
+    /// Sets the format of the data, see [`DataType::FORMAT`].
+    pub fn with_format(mut self, format: Format) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Sets the algorithm of the compressed documents, see [`DataType::COMPRESSION`].
+    ///
+    /// The compressed documents are decompressed on read and, as for the [`DataType`], are
+    /// not matched by the JSON filters. The documents written through the table are stored
+    /// uncompressed whatever their size, until they are updated through the [`DataType`].
+    pub fn with_compression(mut self, compression: Option<CompressionAlgorithm>) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// Returns the name of the table, as it was given.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the identifiers of the table quoted for the backend of `C`.
+    pub fn table_name<C: Tx>(&self) -> Result<TableName, C3p0Error> {
+        C::parse_table_name(&self.name)
+    }
+
+    /// See [`Tx::count_all`].
+    pub async fn count_all<C: Tx>(&self, tx: &mut C) -> Result<u64, C3p0Error>
+    where
+        C::DB: DynamicOps,
+    {
+        self.count_where(tx, &Filter::and([])).await
+    }
+
+    /// See [`Tx::count_where`].
+    pub async fn count_where<C: Tx>(&self, tx: &mut C, filter: &Filter) -> Result<u64, C3p0Error>
+    where
+        C::DB: DynamicOps,
+    {
+        let table = self.table_name::<C>()?;
+        with_entry!(self, E => C::DB::count_where::<E>(tx, &table, filter).await)
+    }
+
+    /// See [`Tx::exists_by_id`].
+    pub async fn exists_by_id<C: Tx>(
+        &self,
+        tx: &mut C,
+        id: impl Into<DynamicId>,
+    ) -> Result<bool, C3p0Error>
+    where
+        C::DB: DynamicOps,
+    {
+        let table = self.table_name::<C>()?;
+        let id = id.into();
+        with_entry!(self, E => C::DB::exists_by_id::<E>(tx, &table, E::id(id)?).await)
+    }
+
+    /// See [`Tx::fetch_all`].
+    pub async fn fetch_all<C: Tx>(
+        &self,
+        tx: &mut C,
+        offset: u64,
+        limit: Option<u64>,
+    ) -> Result<Vec<DynamicRecord>, C3p0Error>
+    where
+        C::DB: DynamicOps,
+    {
+        self.fetch_where(tx, &Filter::and([]), offset, limit).await
+    }
+
+    /// See [`Tx::fetch_all_sorted`].
+    pub async fn fetch_all_sorted<C: Tx>(
+        &self,
+        tx: &mut C,
+        sort: &[SortKey],
+        offset: u64,
+        limit: Option<u64>,
+    ) -> Result<Vec<DynamicRecord>, C3p0Error>
+    where
+        C::DB: DynamicOps,
+    {
+        self.fetch_where_sorted(tx, &Filter::and([]), sort, offset, limit)
+            .await
+    }
+
+    /// See [`Tx::fetch_all_stream`].
+    pub fn fetch_all_stream<'a, C: Tx>(
+        &self,
+        tx: &'a mut C,
+    ) -> impl Stream<Item = Result<DynamicRecord, C3p0Error>> + use<'a, C>
+    where
+        C::DB: DynamicOps,
+    {
+        self.fetch_where_stream(tx, &Filter::and([]))
+    }
+
+    /// See [`Tx::fetch_where`].
+    pub async fn fetch_where<C: Tx>(
+        &self,
+        tx: &mut C,
+        filter: &Filter,
+        offset: u64,
+        limit: Option<u64>,
+    ) -> Result<Vec<DynamicRecord>, C3p0Error>
+    where
+        C::DB: DynamicOps,
+    {
+        let table = self.table_name::<C>()?;
+        with_entry!(self, E => {
+            let records = C::DB::fetch_where::<E>(tx, &table, filter, offset, limit).await?;
+            Ok(records.into_iter().map(E::dynamic).collect())
+        })
+    }
+
+    /// See [`Tx::fetch_where_sorted`].
+    pub async fn fetch_where_sorted<C: Tx>(
+        &self,
+        tx: &mut C,
+        filter: &Filter,
+        sort: &[SortKey],
+        offset: u64,
+        limit: Option<u64>,
+    ) -> Result<Vec<DynamicRecord>, C3p0Error>
+    where
+        C::DB: DynamicOps,
+    {
+        let table = self.table_name::<C>()?;
+        with_entry!(self, E => {
+            let records =
+                C::DB::fetch_where_sorted::<E>(tx, &table, filter, sort, offset, limit).await?;
+            Ok(records.into_iter().map(E::dynamic).collect())
+        })
+    }
+
+    /// See [`Tx::fetch_where_stream`].
+    pub fn fetch_where_stream<'a, C: Tx>(
+        &self,
+        tx: &'a mut C,
+        filter: &Filter,
+    ) -> impl Stream<Item = Result<DynamicRecord, C3p0Error>> + use<'a, C>
+    where
+        C::DB: DynamicOps,
+    {
+        let table = match self.table_name::<C>() {
+            Ok(table) => table,
+            Err(err) => return stream::once(future::ready(Err(err))).right_stream(),
+        };
+        with_entry!(self, E => {
+            C::DB::fetch_where_stream::<E>(tx, &table, filter)
+                .map_ok(E::dynamic)
+                .boxed_local()
+        })
+        .left_stream()
+    }
+
+    /// See [`Tx::fetch_page`].
+    pub async fn fetch_page<C: Tx>(
+        &self,
+        tx: &mut C,
+        order: PageOrder,
+        cursor: Option<&Cursor>,
+        limit: u64,
+    ) -> Result<DynamicPage, C3p0Error>
+    where
+        C::DB: DynamicOps,
+    {
+        self.page(tx, None, order, cursor, limit).await
+    }
+
+    /// See [`Tx::fetch_page_where`].
+    pub async fn fetch_page_where<C: Tx>(
+        &self,
+        tx: &mut C,
+        filter: &Filter,
+        order: PageOrder,
+        cursor: Option<&Cursor>,
+        limit: u64,
+    ) -> Result<DynamicPage, C3p0Error>
+    where
+        C::DB: DynamicOps,
+    {
+        self.page(tx, Some(filter), order, cursor, limit).await
+    }
+
+    async fn page<C: Tx>(
+        &self,
+        tx: &mut C,
+        filter: Option<&Filter>,
+        order: PageOrder,
+        cursor: Option<&Cursor>,
+        limit: u64,
+    ) -> Result<DynamicPage, C3p0Error>
+    where
+        C::DB: DynamicOps,
+    {
+        let table = self.table_name::<C>()?;
+        with_entry!(self, E => {
+            let page = C::DB::fetch_page::<E>(tx, &table, filter, order, cursor, limit).await?;
+            Ok(E::page(page))
+        })
+    }
+
+    /// See [`Tx::fetch_one_optional_by_id`].
+    pub async fn fetch_one_optional_by_id<C: Tx>(
+        &self,
+        tx: &mut C,
+        id: impl Into<DynamicId>,
+    ) -> Result<Option<DynamicRecord>, C3p0Error>
+    where
+        C::DB: DynamicOps,
+    {
+        let table = self.table_name::<C>()?;
+        let id = id.into();
+        with_entry!(self, E => {
+            let record = C::DB::fetch_one_optional_by_id::<E>(tx, &table, E::id(id)?).await?;
+            Ok(record.map(E::dynamic))
+        })
+    }
+
+    /// See [`Tx::fetch_one_by_id`].
+    pub async fn fetch_one_by_id<C: Tx>(
+        &self,
+        tx: &mut C,
+        id: impl Into<DynamicId>,
+    ) -> Result<DynamicRecord, C3p0Error>
+    where
+        C::DB: DynamicOps,
+    {
+        self.fetch_one_optional_by_id(tx, id)
+            .await?
+            .ok_or_else(|| sqlx::Error::RowNotFound.into())
+    }
+
+    /// Creates an entry with an id generated by the database, see [`Tx::save`].
+    pub async fn save<C: Tx>(&self, tx: &mut C, data: Value) -> Result<DynamicRecord, C3p0Error>
+    where
+        C::DB: DynamicOps,
+    {
+        let table = self.table_name::<C>()?;
+        with_entry!(self, E => {
+            let record = C::DB::save::<E>(tx, &table, None, E::new(data)).await?;
+            Ok(E::dynamic(record))
+        })
+    }
+
+    /// Creates an entry with the provided text id, see [`Tx::save`].
+    pub async fn save_with_id<C: Tx>(
+        &self,
+        tx: &mut C,
+        id: impl Into<DynamicId>,
+        data: Value,
+    ) -> Result<DynamicRecord, C3p0Error>
+    where
+        C::DB: DynamicOps,
+    {
+        let table = self.table_name::<C>()?;
+        let id = id.into();
+        with_entry!(self, E => {
+            let record = C::DB::save::<E>(tx, &table, Some(E::id(id)?), E::new(data)).await?;
+            Ok(E::dynamic(record))
+        })
+    }
+
+    /// See [`Tx::update`].
+    pub async fn update<C: Tx>(
+        &self,
+        tx: &mut C,
+        record: DynamicRecord,
+    ) -> Result<DynamicRecord, C3p0Error>
+    where
+        C::DB: DynamicOps,
+    {
+        let table = self.table_name::<C>()?;
+        with_entry!(self, E => {
+            let record = C::DB::update::<E>(tx, &table, E::record(record)?).await?;
+            Ok(E::dynamic(record))
+        })
+    }
+
+    /// See [`Tx::update_all`].
+    pub async fn update_all<C: Tx>(
+        &self,
+        tx: &mut C,
+        records: Vec<DynamicRecord>,
+    ) -> Result<DynamicUpdateAllResult, C3p0Error>
+    where
+        C::DB: DynamicOps,
+    {
+        let table = self.table_name::<C>()?;
+        with_entry!(self, E => {
+            let records = records
+                .into_iter()
+                .map(E::record)
+                .collect::<Result<Vec<_>, C3p0Error>>()?;
+            let result = C::DB::update_all::<E>(tx, &table, records).await?;
+            Ok(DynamicUpdateAllResult {
+                updated: result.updated.into_iter().map(E::dynamic).collect(),
+                conflicts: result.conflicts.into_iter().map(E::dynamic).collect(),
+            })
+        })
+    }
+
+    /// See [`Tx::patch`].
+    pub async fn patch<C: Tx>(
+        &self,
+        tx: &mut C,
+        id: impl Into<DynamicId>,
+        version: Option<i64>,
+        patch: &JsonPatch,
+    ) -> Result<DynamicRecord, C3p0Error>
+    where
+        C::DB: DynamicOps,
+    {
+        let table = self.table_name::<C>()?;
+        let id = id.into();
+        with_entry!(self, E => {
+            let record = C::DB::patch::<E>(tx, &table, E::id(id)?, version, patch).await?;
+            Ok(E::dynamic(record))
+        })
+    }
+
+    /// See [`Tx::delete`].
+    pub async fn delete<C: Tx>(
+        &self,
+        tx: &mut C,
+        record: DynamicRecord,
+    ) -> Result<DynamicRecord, C3p0Error>
+    where
+        C::DB: DynamicOps,
+    {
+        let table = self.table_name::<C>()?;
+        with_entry!(self, E => {
+            let record = C::DB::delete::<E>(tx, &table, E::record(record)?).await?;
+            Ok(E::dynamic(record))
+        })
+    }
+
+    /// See [`Tx::delete_all`].
+    pub async fn delete_all<C: Tx>(&self, tx: &mut C) -> Result<u64, C3p0Error>
+    where
+        C::DB: DynamicOps,
+    {
+        let table = self.table_name::<C>()?;
+        with_entry!(self, E => C::DB::delete_all::<E>(tx, &table).await)
+    }
+
+    /// See [`Tx::delete_by_id`].
+    pub async fn delete_by_id<C: Tx>(
+        &self,
+        tx: &mut C,
+        id: impl Into<DynamicId>,
+    ) -> Result<u64, C3p0Error>
+    where
+        C::DB: DynamicOps,
+    {
+        let table = self.table_name::<C>()?;
+        let id = id.into();
+        with_entry!(self, E => C::DB::delete_by_id::<E>(tx, &table, E::id(id)?).await)
+    }
+
+    /// See [`Tx::delete_where`].
+    pub async fn delete_where<C: Tx>(&self, tx: &mut C, filter: &Filter) -> Result<u64, C3p0Error>
+    where
+        C::DB: DynamicOps,
+    {
+        let table = self.table_name::<C>()?;
+        with_entry!(self, E => C::DB::delete_where::<E>(tx, &table, filter).await)
+    }
+
+    /// See [`Tx::fetch_including_deleted`].
+    pub async fn fetch_including_deleted<C: Tx>(
+        &self,
+        tx: &mut C,
+        offset: u64,
+        limit: Option<u64>,
+    ) -> Result<Vec<DynamicSoftDeleteRecord>, C3p0Error>
+    where
+        C::DB: DynamicOps,
+    {
+        let table = self.table_name::<C>()?;
+        with_entry!(self, E => {
+            let records = C::DB::fetch_including_deleted::<E>(tx, &table, offset, limit).await?;
+            Ok(records
+                .into_iter()
+                .map(|record| DynamicSoftDeleteRecord {
+                    record: E::dynamic(record.record),
+                    delete_time: record.delete_time,
+                })
+                .collect())
+        })
+    }
+
+    /// See [`Tx::restore`].
+    pub async fn restore<C: Tx>(
+        &self,
+        tx: &mut C,
+        id: impl Into<DynamicId>,
+    ) -> Result<DynamicRecord, C3p0Error>
+    where
+        C::DB: DynamicOps,
+    {
+        let table = self.table_name::<C>()?;
+        let id = id.into();
+        with_entry!(self, E => {
+            let record = C::DB::restore::<E>(tx, &table, E::id(id)?).await?;
+            Ok(E::dynamic(record))
+        })
+    }
+
+    /// See [`Tx::purge`].
+    pub async fn purge<C: Tx>(
+        &self,
+        tx: &mut C,
+        deleted_before: Option<DateTime<Utc>>,
+    ) -> Result<u64, C3p0Error>
+    where
+        C::DB: DynamicOps,
+    {
+        let table = self.table_name::<C>()?;
+        with_entry!(self, E => C::DB::purge::<E>(tx, &table, deleted_before).await)
+    }
+
+    /// See [`Tx::fetch_history`].
+    pub async fn fetch_history<C: Tx>(
+        &self,
+        tx: &mut C,
+        id: impl Into<DynamicId>,
+    ) -> Result<Vec<DynamicRecord>, C3p0Error>
+    where
+        C::DB: DynamicOps,
+    {
+        let table = self.table_name::<C>()?;
+        let id = id.into();
+        with_entry!(self, E => {
+            let records = C::DB::fetch_history::<E>(tx, &table, E::id(id)?).await?;
+            Ok(records.into_iter().map(E::dynamic).collect())
+        })
+    }
+
+    /// See [`Tx::fetch_version`].
+    pub async fn fetch_version<C: Tx>(
+        &self,
+        tx: &mut C,
+        id: impl Into<DynamicId>,
+        version: i64,
+    ) -> Result<Option<DynamicRecord>, C3p0Error>
+    where
+        C::DB: DynamicOps,
+    {
+        let table = self.table_name::<C>()?;
+        let id = id.into();
+        with_entry!(self, E => {
+            let record = C::DB::fetch_version::<E>(tx, &table, E::id(id)?, version).await?;
+            Ok(record.map(E::dynamic))
+        })
+    }
+
+    /// See [`Tx::fetch_as_of`].
+    pub async fn fetch_as_of<C: Tx>(
+        &self,
+        tx: &mut C,
+        id: impl Into<DynamicId>,
+        timestamp: DateTime<Utc>,
+    ) -> Result<Option<DynamicRecord>, C3p0Error>
+    where
+        C::DB: DynamicOps,
+    {
+        let table = self.table_name::<C>()?;
+        let id = id.into();
+        with_entry!(self, E => {
+            let record = C::DB::fetch_as_of::<E>(tx, &table, E::id(id)?, timestamp).await?;
+            Ok(record.map(E::dynamic))
+        })
+    }
+}
+
+/// The codes of the [`Format`]s in the type of an [`Entry`].
+const JSON: u8 = 0;
+#[cfg(feature = "msgpack")]
+const MESSAGE_PACK: u8 = 1;
+#[cfg(feature = "cbor")]
+const CBOR: u8 = 2;
+
+const fn format(code: u8) -> Format {
+    match code {
+        #[cfg(feature = "msgpack")]
+        MESSAGE_PACK => Format::MessagePack,
+        #[cfg(feature = "cbor")]
+        CBOR => Format::Cbor,
+        _ => Format::Json,
+    }
+}
+
+/// The codes of the [`CompressionAlgorithm`]s in the type of an [`Entry`].
+const UNCOMPRESSED: u8 = 0;
+#[cfg(feature = "zstd")]
+const ZSTD: u8 = 1;
+#[cfg(feature = "gzip")]
+const GZIP: u8 = 2;
+
+fn compression_code(compression: Option<CompressionAlgorithm>) -> u8 {
+    match compression {
+        None => UNCOMPRESSED,
+        #[cfg(feature = "zstd")]
+        Some(CompressionAlgorithm::Zstd) => ZSTD,
+        #[cfg(feature = "gzip")]
+        Some(CompressionAlgorithm::Gzip) => GZIP,
+    }
+}
+
+/// Returns the compression of the [`Entry`]s, whose documents are written uncompressed.
+// Without a compression feature there is no algorithm.
+#[cfg_attr(
+    not(any(feature = "zstd", feature = "gzip")),
+    allow(unreachable_code, unused_variables)
+)]
+const fn compression(code: u8) -> Option<Compression> {
+    let algorithm = match code {
+        #[cfg(feature = "zstd")]
+        ZSTD => CompressionAlgorithm::Zstd,
+        #[cfg(feature = "gzip")]
+        GZIP => CompressionAlgorithm::Gzip,
+        _ => return None,
+    };
+    Some(Compression {
+        algorithm,
+        threshold: usize::MAX,
+    })
+}
+
+/// The ids of the [`Entry`]s.
+trait EntryId: IdType {
+    fn from_dynamic(id: DynamicId) -> Option<Self>;
+
+    fn into_dynamic(self) -> DynamicId;
+}
+
+impl EntryId for i64 {
+    fn from_dynamic(id: DynamicId) -> Option<Self> {
+        match id {
+            DynamicId::Int(id) => Some(id),
+            DynamicId::Text(_) => None,
+        }
+    }
+
+    fn into_dynamic(self) -> DynamicId {
+        DynamicId::Int(self)
+    }
+}
+
+impl EntryId for String {
+    fn from_dynamic(id: DynamicId) -> Option<Self> {
+        match id {
+            DynamicId::Text(id) => Some(id),
+            DynamicId::Int(_) => None,
+        }
+    }
+
+    fn into_dynamic(self) -> DynamicId {
+        DynamicId::Text(self)
+    }
+}
+
+/// The data of the entries of a [`DynamicTable`] with the given options, each of them a
+/// distinct [`DataType`] run by the operations of the backends.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(transparent)]
+struct Entry<
+    ID,
+    const SOFT_DELETE: bool,
+    const HISTORY: bool,
+    const FORMAT: u8,
+    const COMPRESSION: u8,
+> {
+    data: Value,
+    #[serde(skip)]
+    id: PhantomData<fn() -> ID>,
+}
+
+impl<
+    ID: EntryId,
+    const SOFT_DELETE: bool,
+    const HISTORY: bool,
+    const FORMAT: u8,
+    const COMPRESSION: u8,
+> DataType for Entry<ID, SOFT_DELETE, HISTORY, FORMAT, COMPRESSION>
+{
+    /// Not used: the entries are only accessed through a [`DynamicTable`], whose name is
+    /// the one of the errors.
+    const TABLE_NAME: &'static str = "";
+    type CODEC = WithId<Self, ID>;
+    const SOFT_DELETE: bool = SOFT_DELETE;
+    const HISTORY: bool = HISTORY;
+    const FORMAT: Format = format(FORMAT);
+    const COMPRESSION: Option<Compression> = compression(COMPRESSION);
+}
+
+impl<
+    ID: EntryId,
+    const SOFT_DELETE: bool,
+    const HISTORY: bool,
+    const FORMAT: u8,
+    const COMPRESSION: u8,
+> Entry<ID, SOFT_DELETE, HISTORY, FORMAT, COMPRESSION>
+{
+    fn new(data: Value) -> Self {
+        Entry {
+            data,
+            id: PhantomData,
+        }
+    }
+
+    fn id(id: DynamicId) -> Result<ID, C3p0Error> {
+        ID::from_dynamic(id.clone()).ok_or_else(|| C3p0Error::Other {
+            cause: format!("The id [{id:?}] is not of the kind of the ids of the table"),
+        })
+    }
+
+    fn record(record: DynamicRecord) -> Result<Record<Self>, C3p0Error> {
+        Ok(Record {
+            id: Self::id(record.id)?,
+            version: record.version,
+            create_time: record.create_time,
+            update_time: record.update_time,
+            data: Self::new(record.data),
+        })
+    }
+
+    fn dynamic(record: Record<Self>) -> DynamicRecord {
+        DynamicRecord {
+            id: record.id.into_dynamic(),
+            version: record.version,
+            create_time: record.create_time,
+            update_time: record.update_time,
+            data: record.data.data,
+        }
+    }
+
+    fn page(page: Page<Self>) -> DynamicPage {
+        DynamicPage {
+            records: page.records.into_iter().map(Self::dynamic).collect(),
+            next: page.next,
+        }
+    }
+}
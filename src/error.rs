@@ -14,6 +14,11 @@ use thiserror::Error;
 /// - [`SqlxError`](Self::SqlxError) wraps `sqlx::Error` and bubbles up anything the
 ///   driver reports — connectivity, schema mismatches, missing rows
 ///   ([`sqlx::Error::RowNotFound`] from `fetch_one*`), constraint violations, etc.
+/// - [`InvalidCursor`](Self::InvalidCursor) is returned when a pagination
+///   [`Cursor`](crate::page::Cursor) token cannot be decoded or does not match the query
+///   it is used with. Since tokens usually come from clients, this is a client error. It
+///   is also returned when the id of an entry cannot be stored in a cursor, which is a bug
+///   of its [`IdType`](crate::IdType).
 /// - [`Other`](Self::Other) is the catch-all for c3p0-internal errors that don't fit
 ///   any of the above. It carries a free-form `cause` string and is the variant to
 ///   construct when surfacing your own validation failures from inside a
@@ -37,6 +42,10 @@ pub enum C3p0Error {
     /// `fetch_one*` calls when the requested id does not exist.
     #[error("SqlxError: {0:?}")]
     SqlxError(#[from] sqlx::Error),
+    /// Returned when a pagination [`Cursor`](crate::page::Cursor) is malformed, was
+    /// created by a query with a different order or cannot be created for an id.
+    #[error("InvalidCursor: {cause}")]
+    InvalidCursor { cause: String },
 }
 
 #[cfg(test)]
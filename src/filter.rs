@@ -0,0 +1,294 @@
+//! A backend agnostic filter DSL.
+//!
+//! A [`Filter`] is rendered to the SQL dialect of the backend it is executed on,
+//! so the same filter works unchanged on Postgres, MySQL and SQLite:
+//!
+//! ```rust
+//! use c3p0::filter::{Field, Filter};
+//!
+//! let filter = Filter::and([
+//!     Filter::eq(Field::json("address.city"), "Rome"),
+//!     Filter::or([
+//!         Filter::gte(Field::json("age"), 18),
+//!         Filter::is_null(Field::json("age")),
+//!     ]),
+//!     !Filter::in_list(Field::Id, [1, 2, 3]),
+//! ]);
+//! ```
+
+use crate::{
+    DataType,
+    error::C3p0Error,
+    format::{has_compressed_column, json_queryable},
+    sql::{JsonPath, JsonType, SortKey, SqlBuilder, SqlDialect, SqlValue},
+    table::TableName,
+};
+
+/// A field of a c3p0 table that can be used in a [`Filter`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum Field {
+    /// The `id` column.
+    Id,
+    /// The `version` column.
+    Version,
+    /// The `create_time` column.
+    CreateTime,
+    /// The `update_time` column.
+    UpdateTime,
+    /// A value nested inside the `data` JSON column.
+    Json(JsonPath),
+}
+
+impl Field {
+    /// Returns a field addressing the value at `path` inside the `data` JSON column.
+    /// See [`JsonPath`] for the path syntax.
+    pub fn json(path: impl Into<JsonPath>) -> Self {
+        Field::Json(path.into())
+    }
+
+    fn to_sql<D: SqlDialect>(&self, value: Option<&SqlValue>) -> String {
+        match self {
+            Field::Id => "id".to_owned(),
+            Field::Version => "version".to_owned(),
+            Field::CreateTime => "create_time".to_owned(),
+            Field::UpdateTime => "update_time".to_owned(),
+            Field::Json(path) => D::json_field(
+                path,
+                value.map(SqlValue::json_type).unwrap_or(JsonType::Text),
+            ),
+        }
+    }
+
+    /// JSON paths are compared against the textual representation of timestamps.
+    fn adapt_value(&self, value: SqlValue) -> SqlValue {
+        match (self, value) {
+            (Field::Json(_), SqlValue::Timestamp(value)) => {
+                SqlValue::Text(value.to_rfc3339_opts(chrono::SecondsFormat::AutoSi, true))
+            }
+            (_, value) => value,
+        }
+    }
+}
+
+/// A comparison operator.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Operator {
+    Eq,
+    Ne,
+    Lt,
+    Lte,
+    Gt,
+    Gte,
+}
+
+impl Operator {
+    fn as_sql(&self) -> &'static str {
+        match self {
+            Operator::Eq => "=",
+            Operator::Ne => "<>",
+            Operator::Lt => "<",
+            Operator::Lte => "<=",
+            Operator::Gt => ">",
+            Operator::Gte => ">=",
+        }
+    }
+}
+
+/// A condition on the rows of a c3p0 table.
+///
+/// Following the SQL semantics, comparisons with a missing JSON key or a JSON `null`
+/// never match; use [`Filter::is_null`] to select them.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Filter {
+    /// Compares a field with a value.
+    Compare(Field, Operator, SqlValue),
+    /// Matches when the field is equal to any of the values. An empty list matches nothing.
+    In(Field, Vec<SqlValue>),
+    /// Matches when the field is `NULL`; for JSON paths, when the key is missing or `null`.
+    IsNull(Field),
+    /// Matches when the `data` column contains the JSON value, see [`Filter::contains`].
+    Contains(serde_json::Value),
+    /// Matches when all the filters match. An empty list matches everything.
+    And(Vec<Filter>),
+    /// Matches when at least one of the filters matches. An empty list matches nothing.
+    Or(Vec<Filter>),
+    /// Matches when the filter does not match.
+    Not(Box<Filter>),
+}
+
+impl Filter {
+    /// `field = value`
+    pub fn eq(field: Field, value: impl Into<SqlValue>) -> Self {
+        Filter::Compare(field, Operator::Eq, value.into())
+    }
+
+    /// `field <> value`
+    pub fn ne(field: Field, value: impl Into<SqlValue>) -> Self {
+        Filter::Compare(field, Operator::Ne, value.into())
+    }
+
+    /// `field < value`
+    pub fn lt(field: Field, value: impl Into<SqlValue>) -> Self {
+        Filter::Compare(field, Operator::Lt, value.into())
+    }
+
+    /// `field <= value`
+    pub fn lte(field: Field, value: impl Into<SqlValue>) -> Self {
+        Filter::Compare(field, Operator::Lte, value.into())
+    }
+
+    /// `field > value`
+    pub fn gt(field: Field, value: impl Into<SqlValue>) -> Self {
+        Filter::Compare(field, Operator::Gt, value.into())
+    }
+
+    /// `field >= value`
+    pub fn gte(field: Field, value: impl Into<SqlValue>) -> Self {
+        Filter::Compare(field, Operator::Gte, value.into())
+    }
+
+    /// `field IN (values...)`
+    pub fn in_list<V: Into<SqlValue>>(field: Field, values: impl IntoIterator<Item = V>) -> Self {
+        Filter::In(field, values.into_iter().map(Into::into).collect())
+    }
+
+    /// `field IS NULL`
+    pub fn is_null(field: Field) -> Self {
+        Filter::IsNull(field)
+    }
+
+    /// `data @> value`: matches when the `data` column contains the JSON `value`, with the
+    /// semantics of the PostgreSQL `@>` operator: an object contains the keys of `value`
+    /// with contained values, an array contains each element of `value` in any position,
+    /// and a scalar is equal to `value`. On PostgreSQL the queries can use the
+    /// [`JsonIndex::containment`](crate::JsonIndex::containment) indexes; MySQL uses
+    /// `JSON_CONTAINS`, which also finds a scalar `value` in the arrays.
+    pub fn contains(value: serde_json::Value) -> Self {
+        Filter::Contains(value)
+    }
+
+    /// `filter_1 AND filter_2 AND ...`
+    pub fn and(filters: impl IntoIterator<Item = Filter>) -> Self {
+        Filter::And(filters.into_iter().collect())
+    }
+
+    /// `filter_1 OR filter_2 OR ...`
+    pub fn or(filters: impl IntoIterator<Item = Filter>) -> Self {
+        Filter::Or(filters.into_iter().collect())
+    }
+
+    /// Returns true if the filter, or one of its nested filters, reads a [`Field::Json`].
+    fn has_json_field(&self) -> bool {
+        match self {
+            Filter::Compare(field, _, _) | Filter::In(field, _) | Filter::IsNull(field) => {
+                matches!(field, Field::Json(_))
+            }
+            Filter::Contains(_) => true,
+            Filter::And(filters) | Filter::Or(filters) => {
+                filters.iter().any(Filter::has_json_field)
+            }
+            Filter::Not(filter) => filter.has_json_field(),
+        }
+    }
+
+    /// Appends the SQL condition to the builder, binding the values in order.
+    pub(crate) fn to_sql<D: SqlDialect>(&self, sql: &mut SqlBuilder<D>) {
+        match self {
+            Filter::Compare(field, operator, value) => {
+                sql.push("(")
+                    .push(&field.to_sql::<D>(Some(value)))
+                    .push(" ")
+                    .push(operator.as_sql())
+                    .push(" ")
+                    .push_value(field.adapt_value(value.clone()))
+                    .push(")");
+            }
+            Filter::In(_, values) if values.is_empty() => {
+                sql.push("(1 = 0)");
+            }
+            Filter::In(field, values) => {
+                sql.push("(")
+                    .push(&field.to_sql::<D>(values.first()))
+                    .push(" IN (");
+                for (index, value) in values.iter().enumerate() {
+                    if index > 0 {
+                        sql.push(", ");
+                    }
+                    sql.push_value(field.adapt_value(value.clone()));
+                }
+                sql.push("))");
+            }
+            Filter::IsNull(field) => {
+                sql.push("(")
+                    .push(&field.to_sql::<D>(None))
+                    .push(" IS NULL)");
+            }
+            Filter::Contains(value) => D::json_contains(sql, value),
+            Filter::And(filters) => Self::join_to_sql(sql, filters, " AND ", "(1 = 1)"),
+            Filter::Or(filters) => Self::join_to_sql(sql, filters, " OR ", "(1 = 0)"),
+            Filter::Not(filter) => {
+                sql.push("(NOT ");
+                filter.to_sql(sql);
+                sql.push(")");
+            }
+        }
+    }
+
+    fn join_to_sql<D: SqlDialect>(
+        sql: &mut SqlBuilder<D>,
+        filters: &[Filter],
+        separator: &str,
+        when_empty: &str,
+    ) {
+        if filters.is_empty() {
+            sql.push(when_empty);
+            return;
+        }
+        sql.push("(");
+        for (index, filter) in filters.iter().enumerate() {
+            if index > 0 {
+                sql.push(separator);
+            }
+            filter.to_sql(sql);
+        }
+        sql.push(")");
+    }
+}
+
+/// Returns the condition restricting a query on `table` to the entries stored as plain
+/// JSON when the `filter` or the `sort` keys read the `data` column of a compressed type,
+/// whose compressed entries hold no JSON to match; an error when they read the `data`
+/// column of a binary type, whose entries would be silently skipped or misplaced.
+pub(crate) fn plain_json_condition<DATA: DataType>(
+    table: &TableName,
+    filter: Option<&Filter>,
+    sort: &[SortKey],
+) -> Result<Option<&'static str>, C3p0Error> {
+    let json_filter = filter.is_some_and(Filter::has_json_field);
+    let json_sort = sort.iter().any(|key| matches!(key, SortKey::Json(..)));
+    if !json_filter && !json_sort {
+        return Ok(None);
+    }
+    if !json_queryable::<DATA>() {
+        return Err(C3p0Error::Other {
+            cause: format!(
+                "The JSON filters and sort keys are not supported by table [{}]: its data is \
+                 binary",
+                table
+            ),
+        });
+    }
+    Ok(has_compressed_column::<DATA>().then_some(PLAIN_JSON))
+}
+
+/// The condition matching the entries of a compressed type stored as plain JSON.
+pub(crate) const PLAIN_JSON: &str = "data_compressed IS NULL";
+
+/// `NOT filter`
+impl std::ops::Not for Filter {
+    type Output = Filter;
+
+    fn not(self) -> Self::Output {
+        Filter::Not(Box::new(self))
+    }
+}
@@ -0,0 +1,495 @@
+//! The serialization of the `data` column.
+//!
+//! The entries are stored as JSON by default, which is required by the JSON queries:
+//! the [`Filter`](crate::Filter)s and [`SortKey`](crate::SortKey)s over JSON paths, the
+//! [`JsonPatch`](crate::JsonPatch)es, the [`DataType::UNIQUE_KEY`] and the
+//! [`DataType::INDEXES`]. The types that never need them can select a binary [`Format`],
+//! which is more compact, stored in a `BYTEA` (PostgreSQL), `LONGBLOB` (MySQL) or `BLOB`
+//! (SQLite) column:
+//!
+//! ```rust
+//! #[cfg(feature = "msgpack")]
+//! pub mod with_msgpack {
+//!
+//!     use c3p0::{DataType, Format};
+//!     use serde::{Deserialize, Serialize};
+//!
+//!     #[derive(Clone, Serialize, Deserialize)]
+//!     pub struct Sample {
+//!         pub sensor: String,
+//!         pub values: Vec<f64>,
+//!     }
+//!
+//!     impl DataType for Sample {
+//!         const TABLE_NAME: &'static str = "SAMPLES";
+//!         type CODEC = Self;
+//!         const FORMAT: Format = Format::MessagePack;
+//!     }
+//! }
+//! ```
+//!
+//! The [`Codec`](crate::Codec) works the same with every format, so the previous shapes
+//! are decoded into the latest one when they are read. As the [`Codec::outdated`]
+//! filter cannot be evaluated on binary data, [`Tx::upgrade_all`](crate::Tx::upgrade_all)
+//! rewrites all the entries of a binary table and they are not upgraded on read.
+//!
+//! The large documents can also be compressed, see [`Compression`]. The compressed
+//! documents of a JSON table are stored in the nullable `data_compressed` binary column,
+//! next to a JSON `null` in the `data` column, while the smaller documents stay plain JSON.
+//! The JSON queries only match the plain entries: the filters and the sort keys over
+//! JSON paths skip the compressed entries, the patches fail on them, and the `UNIQUE_KEY`
+//! and the unique indexes are rejected:
+//!
+//! ```rust
+//! #[cfg(feature = "zstd")]
+//! pub mod with_zstd {
+//!
+//!     use c3p0::{Compression, DataType};
+//!     use serde::{Deserialize, Serialize};
+//!
+//!     #[derive(Clone, Serialize, Deserialize)]
+//!     pub struct Report {
+//!         pub title: String,
+//!         pub body: String,
+//!     }
+//!
+//!     impl DataType for Report {
+//!         const TABLE_NAME: &'static str = "REPORTS";
+//!         type CODEC = Self;
+//!         // Compress the documents of 16 KiB or more
+//!         const COMPRESSION: Option<Compression> = Some(Compression::zstd(16 * 1024));
+//!     }
+//! }
+//! ```
+//!
+//! The `data_compressed` column is created along with the table, is checked by
+//! [`Tx::verify_table`](crate::Tx::verify_table) and is added by the migrations of the
+//! `migrate` module when the compression is turned on. The binary formats store the
+//! compressed bytes in the `data` column, told apart from the uncompressed documents by
+//! their first bytes.
+//!
+//! [`Codec::outdated`]: crate::Codec::outdated
+
+use std::marker::PhantomData;
+
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use serde_json::{Value, value::RawValue};
+use sqlx::{
+    ColumnIndex, Database, Decode, Encode, Row, Type, encode::IsNull, error::BoxDynError,
+    types::Json,
+};
+
+use crate::{C3p0Error, DataType, record::CodecOf, sql::SqlDialect};
+
+/// The serialization of the `data` column of a [`DataType`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Format {
+    /// JSON, in a `JSONB` (PostgreSQL) or `JSON` (MySQL, SQLite) column.
+    #[default]
+    Json,
+    /// [MessagePack](https://msgpack.org), with the names of the struct fields.
+    #[cfg(feature = "msgpack")]
+    MessagePack,
+    /// [CBOR](https://cbor.io).
+    #[cfg(feature = "cbor")]
+    Cbor,
+}
+
+impl Format {
+    /// Returns true if the data is stored in a binary column.
+    pub fn is_binary(self) -> bool {
+        self != Format::Json
+    }
+
+    /// Serializes the value in the format.
+    pub fn to_bytes<T: Serialize>(self, value: &T) -> Result<Vec<u8>, C3p0Error> {
+        let error = |cause: String| C3p0Error::Other {
+            cause: format!("Cannot serialize the data as {self:?}: {cause}"),
+        };
+        match self {
+            Format::Json => serde_json::to_vec(value).map_err(|err| error(err.to_string())),
+            #[cfg(feature = "msgpack")]
+            Format::MessagePack => {
+                rmp_serde::to_vec_named(value).map_err(|err| error(err.to_string()))
+            }
+            #[cfg(feature = "cbor")]
+            Format::Cbor => {
+                let mut bytes = Vec::new();
+                ciborium::into_writer(value, &mut bytes).map_err(|err| error(err.to_string()))?;
+                Ok(bytes)
+            }
+        }
+    }
+
+    /// Deserializes a value serialized in the format.
+    pub fn from_bytes<T: DeserializeOwned>(self, bytes: &[u8]) -> Result<T, C3p0Error> {
+        let error = |cause: String| C3p0Error::Other {
+            cause: format!("Cannot deserialize the data as {self:?}: {cause}"),
+        };
+        match self {
+            Format::Json => serde_json::from_slice(bytes).map_err(|err| error(err.to_string())),
+            #[cfg(feature = "msgpack")]
+            Format::MessagePack => {
+                rmp_serde::from_slice(bytes).map_err(|err| error(err.to_string()))
+            }
+            #[cfg(feature = "cbor")]
+            Format::Cbor => ciborium::from_reader(bytes).map_err(|err| error(err.to_string())),
+        }
+    }
+}
+
+/// The compression of the documents of a [`DataType`], see
+/// [`DataType::COMPRESSION`].
+///
+/// The documents whose serialization is smaller than the `threshold` are stored
+/// uncompressed. The [`Tx`](crate::Tx) methods that read or modify the JSON in the
+/// database only see the uncompressed entries: the JSON filters and sort keys skip the
+/// compressed ones, [`Tx::patch`](crate::Tx::patch) fails on them,
+/// [`Tx::upgrade_all`](crate::Tx::upgrade_all) rewrites all the entries, and the
+/// [`DataType::UNIQUE_KEY`] and the unique [`DataType::INDEXES`] are not supported.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Compression {
+    /// The compression algorithm.
+    pub algorithm: CompressionAlgorithm,
+    /// The minimum size, in bytes, of the serialized documents that are compressed.
+    pub threshold: usize,
+}
+
+impl Compression {
+    /// The [zstd](https://facebook.github.io/zstd/) compression of the documents of at
+    /// least `threshold` bytes.
+    #[cfg(feature = "zstd")]
+    pub const fn zstd(threshold: usize) -> Self {
+        Compression {
+            algorithm: CompressionAlgorithm::Zstd,
+            threshold,
+        }
+    }
+
+    /// The gzip compression of the documents of at least `threshold` bytes.
+    #[cfg(feature = "gzip")]
+    pub const fn gzip(threshold: usize) -> Self {
+        Compression {
+            algorithm: CompressionAlgorithm::Gzip,
+            threshold,
+        }
+    }
+
+    /// Returns the `bytes` compressed if they reach the threshold, `None` otherwise.
+    fn compress(self, bytes: &[u8]) -> Result<Option<Vec<u8>>, C3p0Error> {
+        if bytes.len() < self.threshold {
+            Ok(None)
+        } else {
+            self.algorithm.compress(bytes).map(Some)
+        }
+    }
+}
+
+/// The algorithms of a [`Compression`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CompressionAlgorithm {
+    #[cfg(feature = "zstd")]
+    Zstd,
+    #[cfg(feature = "gzip")]
+    Gzip,
+}
+
+// Without a compression feature the enum has no variants.
+#[cfg_attr(
+    not(any(feature = "zstd", feature = "gzip")),
+    allow(unreachable_code, unused_variables)
+)]
+impl CompressionAlgorithm {
+    /// Returns the name of the algorithm.
+    pub fn name(self) -> &'static str {
+        match self {
+            #[cfg(feature = "zstd")]
+            CompressionAlgorithm::Zstd => "zstd",
+            #[cfg(feature = "gzip")]
+            CompressionAlgorithm::Gzip => "gzip",
+        }
+    }
+
+    /// The first bytes of the compressed data, which tell them from the uncompressed
+    /// documents of the binary formats.
+    fn magic(self) -> &'static [u8] {
+        match self {
+            #[cfg(feature = "zstd")]
+            CompressionAlgorithm::Zstd => &[0x28, 0xb5, 0x2f, 0xfd],
+            #[cfg(feature = "gzip")]
+            CompressionAlgorithm::Gzip => &[0x1f, 0x8b],
+        }
+    }
+
+    fn compress(self, bytes: &[u8]) -> Result<Vec<u8>, C3p0Error> {
+        let result: std::io::Result<Vec<u8>> = match self {
+            #[cfg(feature = "zstd")]
+            CompressionAlgorithm::Zstd => zstd::encode_all(bytes, 0),
+            #[cfg(feature = "gzip")]
+            CompressionAlgorithm::Gzip => {
+                use std::io::Write;
+                let mut encoder =
+                    flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+                encoder.write_all(bytes).and_then(|_| encoder.finish())
+            }
+        };
+        result.map_err(|err| C3p0Error::Other {
+            cause: format!("Cannot compress the data with {}: {err}", self.name()),
+        })
+    }
+
+    fn decompress(self, bytes: &[u8]) -> Result<Vec<u8>, C3p0Error> {
+        let result: std::io::Result<Vec<u8>> = match self {
+            #[cfg(feature = "zstd")]
+            CompressionAlgorithm::Zstd => zstd::decode_all(bytes),
+            #[cfg(feature = "gzip")]
+            CompressionAlgorithm::Gzip => {
+                use std::io::Read;
+                let mut decompressed = Vec::new();
+                flate2::read::GzDecoder::new(bytes)
+                    .read_to_end(&mut decompressed)
+                    .map(|_| decompressed)
+            }
+        };
+        result.map_err(|err| C3p0Error::Other {
+            cause: format!("Cannot decompress the data with {}: {err}", self.name()),
+        })
+    }
+}
+
+/// Returns true if the JSON queries, the [`Filter`](crate::Filter)s and
+/// [`SortKey`](crate::SortKey)s over JSON paths, the [`JsonPatch`](crate::JsonPatch)es
+/// and the [`Projection`](crate::Projection)s, are supported by `DATA`.
+pub(crate) fn json_queryable<DATA: DataType>() -> bool {
+    !DATA::FORMAT.is_binary()
+}
+
+/// Returns true if the compressed documents of `DATA` are stored in the `data_compressed`
+/// column, that is if `DATA` is a compressed JSON type.
+pub(crate) fn has_compressed_column<DATA: DataType>() -> bool {
+    !DATA::FORMAT.is_binary() && DATA::COMPRESSION.is_some()
+}
+
+/// Returns the `data_compressed` column, preceded by a comma, for the compressed JSON
+/// types, to be appended to the lists of columns; an empty string otherwise.
+pub(crate) fn compressed_column<DATA: DataType>() -> &'static str {
+    if has_compressed_column::<DATA>() {
+        ", data_compressed"
+    } else {
+        ""
+    }
+}
+
+/// Returns the parameter at `position` bound to the `data_compressed` column, preceded
+/// by a comma, for the compressed JSON types; an empty string otherwise.
+pub(crate) fn compressed_value<DATA: DataType, D: SqlDialect>(position: usize) -> String {
+    if has_compressed_column::<DATA>() {
+        format!(", {}", D::placeholder(position))
+    } else {
+        String::new()
+    }
+}
+
+/// Returns the assignment of the parameter at `position` to the `data_compressed` column,
+/// preceded by a comma, for the compressed JSON types; an empty string otherwise.
+pub(crate) fn set_compressed<DATA: DataType, D: SqlDialect>(position: usize) -> String {
+    if has_compressed_column::<DATA>() {
+        format!(", data_compressed = {}", D::placeholder(position))
+    } else {
+        String::new()
+    }
+}
+
+/// Returns the document stored in the binary `bytes`, decompressed if required.
+fn from_stored_bytes<DATA: DataType, T: DeserializeOwned>(bytes: &[u8]) -> Result<T, C3p0Error> {
+    match DATA::COMPRESSION {
+        Some(compression) if bytes.starts_with(compression.algorithm.magic()) => {
+            DATA::FORMAT.from_bytes(&compression.algorithm.decompress(bytes)?)
+        }
+        _ => DATA::FORMAT.from_bytes(bytes),
+    }
+}
+
+/// The `data` of a `DATA` encoded in the [`DataType::FORMAT`], compressed if required:
+/// the value bound to the `data` column and, for the compressed JSON documents, the
+/// bytes bound to the `data_compressed` column.
+pub(crate) struct Stored<DATA: DataType> {
+    data: StoredData,
+    compressed: Option<Vec<u8>>,
+    data_type: PhantomData<fn() -> DATA>,
+}
+
+/// The value of the `data` column of a [`Stored`].
+enum StoredData {
+    Json(Box<RawValue>),
+    Bytes(Vec<u8>),
+}
+
+impl<DATA: DataType> Stored<DATA> {
+    /// Encodes the `data`, whose fields are encrypted with the key ring in scope.
+    pub(crate) fn new(data: &CodecOf<DATA>) -> Result<Self, C3p0Error> {
+        #[cfg(feature = "encryption")]
+        {
+            if let Some(document) = crate::crypto::encrypt_document(data)? {
+                return Self::encode(&document);
+            }
+            crate::crypto::encode_unencrypted(|| Self::encode(data))
+        }
+        #[cfg(not(feature = "encryption"))]
+        Self::encode(data)
+    }
+
+    fn encode<T: Serialize>(data: &T) -> Result<Self, C3p0Error> {
+        let (data, compressed) = if DATA::FORMAT.is_binary() {
+            let bytes = DATA::FORMAT.to_bytes(data)?;
+            let bytes = match DATA::COMPRESSION {
+                Some(compression) => compression.compress(&bytes)?.unwrap_or(bytes),
+                None => bytes,
+            };
+            (StoredData::Bytes(bytes), None)
+        } else {
+            let json = serde_json::value::to_raw_value(data).map_err(|err| C3p0Error::Other {
+                cause: format!("Cannot serialize the data as {:?}: {err}", DATA::FORMAT),
+            })?;
+            let compressed = match DATA::COMPRESSION {
+                Some(compression) => compression.compress(json.get().as_bytes())?,
+                None => None,
+            };
+            match compressed {
+                Some(compressed) => (StoredData::Json(null_json()), Some(compressed)),
+                None => (StoredData::Json(json), None),
+            }
+        };
+        Ok(Stored {
+            data,
+            compressed,
+            data_type: PhantomData,
+        })
+    }
+
+    /// Returns the value of the `data_compressed` column, `None` if the document is not
+    /// compressed.
+    pub(crate) fn compressed(&self) -> Option<&[u8]> {
+        self.compressed.as_deref()
+    }
+
+    /// Returns the number of bytes bound to the query by the stored document.
+    #[cfg_attr(not(feature = "mysql"), allow(dead_code))]
+    pub(crate) fn size(&self) -> usize {
+        let data = match &self.data {
+            StoredData::Json(json) => json.get().len(),
+            StoredData::Bytes(bytes) => bytes.len(),
+        };
+        data + self.compressed.as_ref().map_or(0, Vec::len)
+    }
+}
+
+/// Returns the JSON `null` stored in the `data` column of the compressed documents.
+fn null_json() -> Box<RawValue> {
+    RawValue::from_string("null".to_owned()).expect("null is valid JSON")
+}
+
+impl<DB: Database, DATA: DataType> Type<DB> for Stored<DATA>
+where
+    Vec<u8>: Type<DB>,
+    Json<Value>: Type<DB>,
+{
+    fn type_info() -> DB::TypeInfo {
+        if DATA::FORMAT.is_binary() {
+            <Vec<u8> as Type<DB>>::type_info()
+        } else {
+            <Json<Value> as Type<DB>>::type_info()
+        }
+    }
+
+    fn compatible(ty: &DB::TypeInfo) -> bool {
+        if DATA::FORMAT.is_binary() {
+            <Vec<u8> as Type<DB>>::compatible(ty)
+        } else {
+            <Json<Value> as Type<DB>>::compatible(ty)
+        }
+    }
+}
+
+impl<'q, DB: Database, DATA: DataType> Encode<'q, DB> for Stored<DATA>
+where
+    Vec<u8>: Encode<'q, DB>,
+    for<'a> Json<&'a RawValue>: Encode<'q, DB>,
+{
+    fn encode_by_ref(&self, buf: &mut DB::ArgumentBuffer) -> Result<IsNull, BoxDynError> {
+        match &self.data {
+            StoredData::Json(json) => Json(json.as_ref()).encode_by_ref(buf),
+            StoredData::Bytes(bytes) => bytes.encode_by_ref(buf),
+        }
+    }
+}
+
+/// Returns the `data` column of a row, at `index`, decoded from the [`DataType::FORMAT`]
+/// of `DATA`, or the `data_compressed` column if it holds the document.
+pub(crate) fn data_from_row<'r, R: Row, DATA: DataType, T: DeserializeOwned>(
+    row: &'r R,
+    index: usize,
+) -> Result<T, sqlx::Error>
+where
+    usize: ColumnIndex<R>,
+    &'static str: ColumnIndex<R>,
+    Vec<u8>: Decode<'r, R::Database> + Type<R::Database>,
+    Json<T>: Decode<'r, R::Database> + Type<R::Database>,
+{
+    let data = if DATA::FORMAT.is_binary() {
+        let bytes: Vec<u8> = row.try_get(index)?;
+        from_stored_bytes::<DATA, T>(&bytes)
+    } else if let Some(compression) = DATA::COMPRESSION
+        && let Some(bytes) = row.try_get::<Option<Vec<u8>>, _>("data_compressed")?
+    {
+        compression
+            .algorithm
+            .decompress(&bytes)
+            .and_then(|bytes| Format::Json.from_bytes(&bytes))
+    } else {
+        let Json(data) = row.try_get(index)?;
+        return Ok(data);
+    };
+    data.map_err(|err| sqlx::Error::ColumnDecode {
+        index: index.to_string(),
+        source: Box::new(err),
+    })
+}
+
+/// Returns the `data` column of a row, at `index`, as the [`DataType::CODEC`] of `DATA`,
+/// whose encrypted fields are decrypted.
+pub(crate) fn codec_from_row<'r, R: Row, DATA: DataType>(
+    row: &'r R,
+    index: usize,
+) -> Result<CodecOf<DATA>, sqlx::Error>
+where
+    usize: ColumnIndex<R>,
+    &'static str: ColumnIndex<R>,
+    Vec<u8>: Decode<'r, R::Database> + Type<R::Database>,
+    Json<CodecOf<DATA>>: Decode<'r, R::Database> + Type<R::Database>,
+    Json<Value>: Decode<'r, R::Database> + Type<R::Database>,
+{
+    // The documents without encrypted fields can hold values that have no JSON equivalent,
+    // like the byte arrays of the binary formats, and are decoded directly
+    #[cfg(feature = "encryption")]
+    if crate::crypto::key_ring_in_scope()
+        && let Ok(document) = data_from_row::<R, DATA, Value>(row, index)
+    {
+        return crate::crypto::decrypt_document(&document, || {
+            CodecOf::<DATA>::deserialize(&document)
+        })
+        .map_err(|err| sqlx::Error::ColumnDecode {
+            index: index.to_string(),
+            source: Box::new(C3p0Error::Other {
+                cause: format!("Cannot deserialize the data: {err}"),
+            }),
+        });
+    }
+    #[cfg(feature = "encryption")]
+    return crate::crypto::decode_unencrypted(|| {
+        data_from_row::<R, DATA, CodecOf<DATA>>(row, index)
+    });
+    #[cfg(not(feature = "encryption"))]
+    data_from_row::<R, DATA, CodecOf<DATA>>(row, index)
+}
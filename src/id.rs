@@ -0,0 +1,247 @@
+//! The types of the `id` of the entries.
+//!
+//! The ids are `i64` values generated by the database with an auto-increment column,
+//! unless the [`DataType::CODEC`](crate::DataType::CODEC) is wrapped in a [`WithId`] with
+//! another id type:
+//!
+//! - `uuid::Uuid` (feature `uuid`): a random UUID v4 generated by c3p0 before the insert;
+//! - [`UuidV7`] (feature `uuid`): a time ordered UUID v7 generated by c3p0 before the insert;
+//! - `ulid::Ulid` (feature `ulid`): a ULID generated by c3p0 before the insert;
+//! - `String`: an id provided by the caller with [`NewRecord::with_id`](crate::NewRecord::with_id).
+//!
+//! ```rust
+//! use c3p0::{DataType, WithId};
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Clone, Serialize, Deserialize)]
+//! pub struct Country {
+//!     pub name: String,
+//! }
+//!
+//! impl DataType for Country {
+//!     const TABLE_NAME: &'static str = "COUNTRIES";
+//!     // The ISO code of the country, provided by the caller
+//!     type CODEC = WithId<Self, String>;
+//! }
+//! ```
+//!
+//! All the ids except `i64` are stored in a text column.
+
+use std::fmt::Debug;
+use std::hash::Hash;
+use std::marker::PhantomData;
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use sqlx::encode::IsNull;
+use sqlx::error::BoxDynError;
+use sqlx::{Database, Decode, Encode, Type};
+
+use crate::{codec::Codec, error::C3p0Error, sql::SqlValue};
+
+/// The type of the `id` of the entries of a table.
+pub trait IdType:
+    Clone + Debug + Eq + Ord + Hash + Send + Sync + Unpin + Serialize + DeserializeOwned + 'static
+{
+    /// Whether the ids are generated by the database with an auto-increment integer
+    /// column. The other ids are stored in a text column.
+    const AUTO_INCREMENT: bool = false;
+
+    /// Returns the id of a new entry, or `None` if it is generated by the database or must
+    /// be provided by the caller.
+    fn generate() -> Option<Self> {
+        None
+    }
+
+    /// Returns the value of the `id` column: an `Int` for the [`AUTO_INCREMENT`](Self::AUTO_INCREMENT)
+    /// ids, a `Text` otherwise.
+    fn to_sql(&self) -> SqlValue;
+
+    /// Parses the value of the `id` column.
+    fn from_sql(value: SqlValue) -> Result<Self, C3p0Error>;
+}
+
+/// The [`Codec`] of a [`DataType`](crate::DataType) along with the type of the ids of its
+/// entries: `i64` for every codec, the one chosen with [`WithId`] otherwise.
+pub trait IdCodec<DATA> {
+    /// The codec of the `data` column.
+    type CODEC: Codec<DATA>;
+    /// The type of the ids.
+    type ID: IdType;
+}
+
+impl<DATA, CODEC: Codec<DATA>> IdCodec<DATA> for CODEC {
+    type CODEC = CODEC;
+    type ID = i64;
+}
+
+/// Chooses the id type `ID` for the entries of a [`DataType`](crate::DataType) whose
+/// codec is `CODEC`, e.g. `type CODEC = WithId<Self, uuid::Uuid>;`.
+pub struct WithId<CODEC, ID>(PhantomData<fn() -> (CODEC, ID)>);
+
+impl<DATA, CODEC: Codec<DATA>, ID: IdType> IdCodec<DATA> for WithId<CODEC, ID> {
+    type CODEC = CODEC;
+    type ID = ID;
+}
+
+fn invalid_id<ID>(value: SqlValue) -> C3p0Error {
+    C3p0Error::Other {
+        cause: format!(
+            "The value [{value:?}] is not a valid id of type [{}]",
+            std::any::type_name::<ID>()
+        ),
+    }
+}
+
+impl IdType for i64 {
+    const AUTO_INCREMENT: bool = true;
+
+    fn to_sql(&self) -> SqlValue {
+        SqlValue::Int(*self)
+    }
+
+    fn from_sql(value: SqlValue) -> Result<Self, C3p0Error> {
+        match value {
+            SqlValue::Int(value) => Ok(value),
+            value => Err(invalid_id::<Self>(value)),
+        }
+    }
+}
+
+impl IdType for String {
+    fn to_sql(&self) -> SqlValue {
+        SqlValue::Text(self.clone())
+    }
+
+    fn from_sql(value: SqlValue) -> Result<Self, C3p0Error> {
+        match value {
+            SqlValue::Text(value) => Ok(value),
+            value => Err(invalid_id::<Self>(value)),
+        }
+    }
+}
+
+#[cfg(feature = "uuid")]
+impl IdType for uuid::Uuid {
+    fn generate() -> Option<Self> {
+        Some(uuid::Uuid::new_v4())
+    }
+
+    fn to_sql(&self) -> SqlValue {
+        SqlValue::Text(self.to_string())
+    }
+
+    fn from_sql(value: SqlValue) -> Result<Self, C3p0Error> {
+        match value {
+            SqlValue::Text(text) => text
+                .parse()
+                .map_err(|_| invalid_id::<Self>(SqlValue::Text(text))),
+            value => Err(invalid_id::<Self>(value)),
+        }
+    }
+}
+
+/// A UUID v7, whose ids generated later sort after the ones generated earlier, which keeps
+/// the inserts at the end of the primary key index.
+#[cfg(feature = "uuid")]
+#[derive(
+    Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, serde::Deserialize,
+)]
+#[serde(transparent)]
+pub struct UuidV7(pub uuid::Uuid);
+
+#[cfg(feature = "uuid")]
+impl std::fmt::Display for UuidV7 {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Display::fmt(&self.0, f)
+    }
+}
+
+#[cfg(feature = "uuid")]
+impl IdType for UuidV7 {
+    fn generate() -> Option<Self> {
+        Some(UuidV7(uuid::Uuid::now_v7()))
+    }
+
+    fn to_sql(&self) -> SqlValue {
+        IdType::to_sql(&self.0)
+    }
+
+    fn from_sql(value: SqlValue) -> Result<Self, C3p0Error> {
+        <uuid::Uuid as IdType>::from_sql(value).map(UuidV7)
+    }
+}
+
+#[cfg(feature = "ulid")]
+impl IdType for ulid::Ulid {
+    fn generate() -> Option<Self> {
+        Some(ulid::Ulid::new())
+    }
+
+    fn to_sql(&self) -> SqlValue {
+        SqlValue::Text(self.to_string())
+    }
+
+    fn from_sql(value: SqlValue) -> Result<Self, C3p0Error> {
+        match value {
+            SqlValue::Text(text) => text
+                .parse()
+                .map_err(|_| invalid_id::<Self>(SqlValue::Text(text))),
+            value => Err(invalid_id::<Self>(value)),
+        }
+    }
+}
+
+/// Binds and decodes an [`IdType`] as the `BIGINT` or text value of the `id` column.
+pub(crate) struct DbId<ID>(pub(crate) ID);
+
+impl<DB: Database, ID: IdType> Type<DB> for DbId<ID>
+where
+    i64: Type<DB>,
+    String: Type<DB>,
+{
+    fn type_info() -> DB::TypeInfo {
+        if ID::AUTO_INCREMENT {
+            <i64 as Type<DB>>::type_info()
+        } else {
+            <String as Type<DB>>::type_info()
+        }
+    }
+
+    fn compatible(ty: &DB::TypeInfo) -> bool {
+        if ID::AUTO_INCREMENT {
+            <i64 as Type<DB>>::compatible(ty)
+        } else {
+            <String as Type<DB>>::compatible(ty)
+        }
+    }
+}
+
+impl<'q, DB: Database, ID: IdType> Encode<'q, DB> for DbId<ID>
+where
+    i64: Encode<'q, DB>,
+    String: Encode<'q, DB>,
+{
+    fn encode_by_ref(&self, buf: &mut DB::ArgumentBuffer) -> Result<IsNull, BoxDynError> {
+        match self.0.to_sql() {
+            SqlValue::Int(value) => value.encode(buf),
+            SqlValue::Text(value) => value.encode(buf),
+            value => Err(Box::new(invalid_id::<ID>(value))),
+        }
+    }
+}
+
+impl<'r, DB: Database, ID: IdType> Decode<'r, DB> for DbId<ID>
+where
+    i64: Decode<'r, DB>,
+    String: Decode<'r, DB>,
+{
+    fn decode(value: DB::ValueRef<'r>) -> Result<Self, BoxDynError> {
+        let value = if ID::AUTO_INCREMENT {
+            SqlValue::Int(<i64 as Decode<DB>>::decode(value)?)
+        } else {
+            SqlValue::Text(<String as Decode<DB>>::decode(value)?)
+        };
+        Ok(DbId(ID::from_sql(value)?))
+    }
+}
@@ -0,0 +1,112 @@
+//! The indexes over the `data` column declared by a [`DataType`](crate::DataType).
+//!
+//! The indexes listed in [`DataType::INDEXES`](crate::DataType::INDEXES) are created by
+//! [`Tx::create_table_if_not_exists`](crate::Tx::create_table_if_not_exists) with the
+//! expressions rendered by the [`Filter`](crate::Filter) and [`SortKey`](crate::SortKey)
+//! of the same paths and types, so that the queries can use them:
+//!
+//! ```rust
+//! use c3p0::{DataType, JsonIndex, JsonType};
+//!
+//! #[derive(Clone, serde::Serialize, serde::Deserialize, Debug, PartialEq)]
+//! pub struct UserData {
+//!     pub username: String,
+//!     pub age: u32,
+//! }
+//!
+//! impl DataType for UserData {
+//!     const TABLE_NAME: &'static str = "USER_DATA";
+//!     type CODEC = Self;
+//!     const INDEXES: &'static [JsonIndex] = &[
+//!         JsonIndex::new("username", &[("username", JsonType::Text)]).unique(),
+//!         JsonIndex::new("age", &[("age", JsonType::Number)]),
+//!         JsonIndex::containment("data"),
+//!     ];
+//! }
+//! ```
+//!
+//! Each index is named `<TABLE_NAME>_<name>`. On PostgreSQL and SQLite the indexes are
+//! created with `CREATE INDEX IF NOT EXISTS`, so they are added to the existing tables
+//! too. MariaDB does not support functional indexes, so on MySQL / MariaDB / TiDB the
+//! indexed values are stored in generated columns named `c3p0_<name>_<position>`, which
+//! are added with `ALTER TABLE` to the existing tables that have no key with the name of
+//! the index. There the `Text` values are indexed by their first 255 characters, so the
+//! unique indexes only compare those.
+
+use crate::sql::JsonType;
+
+/// An index over the `data` column, see the [module documentation](self).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct JsonIndex {
+    name: &'static str,
+    kind: IndexKind,
+}
+
+/// What a [`JsonIndex`] indexes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum IndexKind {
+    /// The values at the paths, extracted as the given types.
+    Values {
+        paths: &'static [(&'static str, JsonType)],
+        unique: bool,
+    },
+    /// The whole document, for the [`Filter::contains`](crate::Filter::contains) queries.
+    Containment,
+}
+
+impl JsonIndex {
+    /// Returns an index over the values at the JSON `paths`, extracted as the given
+    /// types; see [`JsonPath`](crate::JsonPath) for the path syntax.
+    pub const fn new(name: &'static str, paths: &'static [(&'static str, JsonType)]) -> Self {
+        JsonIndex {
+            name,
+            kind: IndexKind::Values {
+                paths,
+                unique: false,
+            },
+        }
+    }
+
+    /// Returns a GIN index over the whole `data` column supporting the
+    /// [`Filter::contains`](crate::Filter::contains) queries on PostgreSQL. The other
+    /// backends ignore it.
+    pub const fn containment(name: &'static str) -> Self {
+        JsonIndex {
+            name,
+            kind: IndexKind::Containment,
+        }
+    }
+
+    /// Makes the index unique. The entries where one of the paths is missing or `null`
+    /// never conflict.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the index is a [`containment`](Self::containment) one.
+    pub const fn unique(self) -> Self {
+        match self.kind {
+            IndexKind::Values { paths, .. } => JsonIndex {
+                name: self.name,
+                kind: IndexKind::Values {
+                    paths,
+                    unique: true,
+                },
+            },
+            IndexKind::Containment => panic!("A containment index cannot be unique"),
+        }
+    }
+
+    /// Returns the name of the index, without the table name.
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Returns true if the index is unique.
+    pub fn is_unique(&self) -> bool {
+        matches!(self.kind, IndexKind::Values { unique: true, .. })
+    }
+
+    pub(crate) fn kind(&self) -> IndexKind {
+        self.kind
+    }
+}
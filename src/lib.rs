@@ -1,11 +1,33 @@
 #![doc = include_str!("../README.md")]
+// The SQL rendering internals are only used by the backend modules.
+#![cfg_attr(
+    not(any(feature = "mysql", feature = "postgres", feature = "sqlite")),
+    allow(dead_code)
+)]
 
 pub mod codec;
+#[cfg(feature = "encryption")]
+pub mod crypto;
+pub mod decode;
+pub mod dynamic;
 pub mod error;
+pub mod filter;
+pub mod format;
+pub mod id;
+pub mod index;
+#[cfg(feature = "migrate")]
+pub mod migrate;
+mod ops;
+pub mod page;
+pub mod patch;
 pub mod pool;
+pub mod projection;
 pub mod record;
+pub mod schema;
 pub mod sql;
+pub mod table;
 pub mod tx;
+pub mod upgrade;
 
 #[cfg(feature = "mysql")]
 pub mod mysql;
@@ -17,11 +39,28 @@ pub mod sqlite;
 pub mod sqlx {
     pub use sqlx::*;
 }
-pub use codec::Codec;
+#[cfg(feature = "derive")]
+pub use c3p0_derive::{Codec, DataType};
+pub use codec::{Codec, Upgrade};
+pub use decode::{DecodeError, LenientRecord};
+pub use dynamic::{
+    DynamicId, DynamicPage, DynamicRecord, DynamicSoftDeleteRecord, DynamicTable,
+    DynamicUpdateAllResult, IdKind,
+};
 pub use error::C3p0Error;
+pub use filter::{Field, Filter};
+pub use format::{Compression, CompressionAlgorithm, Format};
+pub use id::{IdType, WithId};
+pub use index::JsonIndex;
+pub use page::{Cursor, Page, PageOrder};
+pub use patch::{JsonPatch, PatchOp};
 pub use pool::C3p0Pool;
 pub use record::*;
+pub use schema::{TableIssue, TableReport, TableSchema};
+pub use sql::{JsonPath, JsonType, OrderBy, SortKey};
+pub use table::{Table, TableName};
 pub use tx::Tx;
+pub use upgrade::UpgradeProgress;
 
 #[cfg(feature = "mysql")]
 pub use crate::mysql::MySqlC3p0Pool;
@@ -0,0 +1,339 @@
+//! The generator of the [sqlx migrations](https://docs.rs/sqlx/latest/sqlx/macro.migrate.html)
+//! of the c3p0 tables.
+//!
+//! A [`MigrationGenerator`] renders the [`TableSchema`]s of the [`DataType`](crate::DataType)s
+//! into the DDL of a [`Backend`] and writes it into a timestamped `<VERSION>_<DESCRIPTION>.sql`
+//! file of a migrations directory. The generated schemas are stored in the
+//! [`SNAPSHOT_FILE`] of the same directory, so the following migrations only contain the
+//! changes of the declarations: added, removed or changed tables, indexes, soft delete and
+//! history.
+//!
+//! ```rust,no_run
+//! use c3p0::{C3p0Error, DataType, TableSchema};
+//! use c3p0::migrate::{Backend, MigrationGenerator};
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Clone, Serialize, Deserialize)]
+//! struct UserData {
+//!     username: String,
+//! }
+//!
+//! impl DataType for UserData {
+//!     const TABLE_NAME: &'static str = "USER_DATA";
+//!     type CODEC = Self;
+//!     const UNIQUE_KEY: &'static [&'static str] = &["username"];
+//! }
+//!
+//! fn main() -> Result<(), C3p0Error> {
+//!     let backend: Backend = "postgres".parse()?;
+//!     let generator = MigrationGenerator::new(backend, "./migrations");
+//!     if let Some(path) = generator.generate("users", &[TableSchema::of::<UserData>()?])? {
+//!         println!("Created {}", path.display());
+//!     }
+//!     Ok(())
+//! }
+//! ```
+//!
+//! The same is done by the `c3p0-migrate` binary, which reads the schemas from a JSON file:
+//!
+//! ```text
+//! c3p0-migrate --backend postgres --schemas schemas.json --dir ./migrations --name users
+//! ```
+//!
+//! The identifier type of a table cannot be changed by a migration and renaming a
+//! [`DataType::TABLE_NAME`](crate::DataType::TABLE_NAME) drops the table and creates a
+//! new one.
+//!
+//! The changes that lose data, i.e. dropping a table or a history table and removing the
+//! soft delete, which deletes the soft deleted entries, are only generated if they are
+//! allowed with [`MigrationGenerator::allow_destructive`] or the `--allow-destructive`
+//! switch of the binary; otherwise the generation fails listing them.
+
+use std::{
+    fmt, fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use chrono::Utc;
+
+use crate::{
+    error::C3p0Error,
+    schema::{Ddl, TableSchema, alter_table, destructive_changes},
+    table::TableName,
+};
+
+/// The name of the file holding the schemas of the last generated migration.
+pub const SNAPSHOT_FILE: &str = "c3p0_schema.json";
+
+/// The database backends whose migrations can be generated.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Backend {
+    #[cfg(feature = "mysql")]
+    MySql,
+    #[cfg(feature = "postgres")]
+    Postgres,
+    #[cfg(feature = "sqlite")]
+    Sqlite,
+}
+
+impl FromStr for Backend {
+    type Err = C3p0Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_ascii_lowercase().as_str() {
+            #[cfg(feature = "mysql")]
+            "mysql" | "mariadb" => Ok(Backend::MySql),
+            #[cfg(feature = "postgres")]
+            "postgres" | "postgresql" | "pg" => Ok(Backend::Postgres),
+            #[cfg(feature = "sqlite")]
+            "sqlite" => Ok(Backend::Sqlite),
+            _ => Err(C3p0Error::Other {
+                cause: format!("Unknown or not enabled backend [{value}]"),
+            }),
+        }
+    }
+}
+
+impl fmt::Display for Backend {
+    // Without a backend feature the enum has no variants.
+    #[cfg_attr(
+        not(any(feature = "mysql", feature = "postgres", feature = "sqlite")),
+        allow(unreachable_code, unused_variables)
+    )]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match *self {
+            #[cfg(feature = "mysql")]
+            Backend::MySql => "mysql",
+            #[cfg(feature = "postgres")]
+            Backend::Postgres => "postgres",
+            #[cfg(feature = "sqlite")]
+            Backend::Sqlite => "sqlite",
+        })
+    }
+}
+
+/// Returns the statements changing the tables of the `previous` schemas into the ones of
+/// the `current` schemas: the new tables are created, the changed ones are altered and
+/// the removed ones are dropped. Fails listing the changes that lose data unless
+/// `allow_destructive` is true.
+#[cfg_attr(
+    not(any(feature = "mysql", feature = "postgres", feature = "sqlite")),
+    allow(unreachable_code, unused_variables)
+)]
+pub fn migration_statements(
+    backend: Backend,
+    previous: &[TableSchema],
+    current: &[TableSchema],
+    allow_destructive: bool,
+) -> Result<Vec<String>, C3p0Error> {
+    match backend {
+        #[cfg(feature = "mysql")]
+        Backend::MySql => statements::<sqlx::MySql>(previous, current, allow_destructive),
+        #[cfg(feature = "postgres")]
+        Backend::Postgres => statements::<sqlx::Postgres>(previous, current, allow_destructive),
+        #[cfg(feature = "sqlite")]
+        Backend::Sqlite => statements::<sqlx::Sqlite>(previous, current, allow_destructive),
+    }
+}
+
+fn statements<D: Ddl>(
+    previous: &[TableSchema],
+    current: &[TableSchema],
+    allow_destructive: bool,
+) -> Result<Vec<String>, C3p0Error> {
+    let find = |schemas: &'_ [TableSchema], table_name: &str| {
+        schemas
+            .iter()
+            .find(|schema| schema.table_name == table_name)
+            .cloned()
+    };
+
+    let mut statements = Vec::new();
+    let mut destructive = Vec::new();
+    for (position, schema) in current.iter().enumerate() {
+        if find(&current[..position], &schema.table_name).is_some() {
+            return Err(C3p0Error::Other {
+                cause: format!("The table [{}] is declared twice", schema.table_name),
+            });
+        }
+        match find(previous, &schema.table_name) {
+            Some(previous) if previous == *schema => {}
+            Some(previous) => {
+                destructive.extend(destructive_changes(&previous, schema));
+                statements.extend(alter_table::<D>(&previous, schema)?);
+            }
+            None => {
+                schema.check()?;
+                let table = TableName::parse::<D>(&schema.table_name)?;
+                statements.extend(D::create_table(schema, &table));
+            }
+        }
+    }
+    for schema in previous {
+        if find(current, &schema.table_name).is_none() {
+            let table = TableName::parse::<D>(&schema.table_name)?;
+            destructive.push(format!("drop the table [{}]", schema.table_name));
+            statements.extend(D::drop_table(schema, &table, false));
+        }
+    }
+    if !allow_destructive && !destructive.is_empty() {
+        return Err(C3p0Error::Other {
+            cause: format!(
+                "The migration would {}; the changes that lose data must be allowed",
+                destructive.join(", ")
+            ),
+        });
+    }
+    Ok(statements)
+}
+
+/// Writes the migrations of a backend into a sqlx migrations directory.
+#[derive(Clone, Debug)]
+pub struct MigrationGenerator {
+    backend: Backend,
+    dir: PathBuf,
+    allow_destructive: bool,
+}
+
+impl MigrationGenerator {
+    /// Creates a generator of the migrations of `backend` in the `dir` directory.
+    pub fn new(backend: Backend, dir: impl Into<PathBuf>) -> Self {
+        Self {
+            backend,
+            dir: dir.into(),
+            allow_destructive: false,
+        }
+    }
+
+    /// Allows the migrations to drop tables and history tables and to delete the soft
+    /// deleted entries; without it the generation of such a migration fails.
+    pub fn allow_destructive(mut self, allow_destructive: bool) -> Self {
+        self.allow_destructive = allow_destructive;
+        self
+    }
+
+    /// Writes the migration from the schemas of the last generated migration to the
+    /// `schemas` and returns its path, or `None` if the schemas did not change.
+    ///
+    /// The version of the migration is the current UTC time as `YYYYMMDDHHMMSS`, or the
+    /// successor of the last version of the directory if that is not lower.
+    pub fn generate(
+        &self,
+        description: &str,
+        schemas: &[TableSchema],
+    ) -> Result<Option<PathBuf>, C3p0Error> {
+        let snapshot_path = self.dir.join(SNAPSHOT_FILE);
+        let previous: Vec<TableSchema> = if snapshot_path.exists() {
+            serde_json::from_str(&read(&snapshot_path)?)
+                .map_err(|err| json_error(&snapshot_path, err))?
+        } else {
+            vec![]
+        };
+
+        let statements =
+            migration_statements(self.backend, &previous, schemas, self.allow_destructive)?;
+        if statements.is_empty() {
+            return Ok(None);
+        }
+
+        fs::create_dir_all(&self.dir).map_err(|err| io_error(&self.dir, err))?;
+        let version = Utc::now()
+            .format("%Y%m%d%H%M%S")
+            .to_string()
+            .parse::<i64>()
+            .unwrap_or_default()
+            .max(self.last_version()? + 1);
+        let path = self
+            .dir
+            .join(format!("{version}_{}.sql", file_description(description)));
+
+        let mut sql = format!("-- Generated by c3p0 for {}\n", self.backend);
+        for statement in &statements {
+            sql.push('\n');
+            sql.push_str(&format_statement(statement));
+            sql.push_str(";\n");
+        }
+        write(&path, &sql)?;
+        let snapshot =
+            serde_json::to_string_pretty(schemas).map_err(|err| json_error(&snapshot_path, err))?;
+        write(&snapshot_path, &snapshot)?;
+        Ok(Some(path))
+    }
+
+    /// Returns the greatest version of the migrations of the directory, or 0.
+    fn last_version(&self) -> Result<i64, C3p0Error> {
+        if !self.dir.exists() {
+            return Ok(0);
+        }
+        let mut last_version = 0;
+        for entry in fs::read_dir(&self.dir).map_err(|err| io_error(&self.dir, err))? {
+            let entry = entry.map_err(|err| io_error(&self.dir, err))?;
+            let file_name = entry.file_name();
+            let Some(file_name) = file_name.to_str() else {
+                continue;
+            };
+            if let Some((version, _)) = file_name.split_once('_')
+                && let Ok(version) = version.parse::<i64>()
+            {
+                last_version = last_version.max(version);
+            }
+        }
+        Ok(last_version)
+    }
+}
+
+/// Returns the description as lower case words separated by underscores.
+fn file_description(description: &str) -> String {
+    let words: Vec<String> = description
+        .split(|char: char| !char.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_ascii_lowercase)
+        .collect();
+    if words.is_empty() {
+        "c3p0".to_owned()
+    } else {
+        words.join("_")
+    }
+}
+
+/// Returns the statement without the surrounding blank lines and the common indentation.
+fn format_statement(statement: &str) -> String {
+    let lines: Vec<&str> = statement
+        .trim_end()
+        .trim_start_matches('\n')
+        .lines()
+        .map(str::trim_end)
+        .collect();
+    let indentation = lines
+        .iter()
+        .filter(|line| !line.is_empty())
+        .map(|line| line.len() - line.trim_start().len())
+        .min()
+        .unwrap_or_default();
+    lines
+        .iter()
+        .map(|line| line.get(indentation..).unwrap_or_default())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn read(path: &Path) -> Result<String, C3p0Error> {
+    fs::read_to_string(path).map_err(|err| io_error(path, err))
+}
+
+fn write(path: &Path, content: &str) -> Result<(), C3p0Error> {
+    fs::write(path, content).map_err(|err| io_error(path, err))
+}
+
+fn io_error(path: &Path, err: std::io::Error) -> C3p0Error {
+    C3p0Error::Other {
+        cause: format!("Cannot access [{}]: {err}", path.display()),
+    }
+}
+
+fn json_error(path: &Path, err: serde_json::Error) -> C3p0Error {
+    C3p0Error::Other {
+        cause: format!("Invalid schemas in [{}]: {err}", path.display()),
+    }
+}
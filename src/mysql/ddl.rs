@@ -0,0 +1,285 @@
+use sqlx::{MySql, MySqlConnection, Row};
+
+use crate::{
+    error::C3p0Error,
+    schema::{ColumnInfo, ColumnKind, Ddl, IndexSchema, TableInfo, TableSchema},
+    sql::{JsonPath, JsonType, SqlDialect},
+    table::TableName,
+};
+
+/// Returns the type and the constraints of the `data` column, followed by the
+/// `data_compressed` column of the compressed JSON types.
+fn data_column(schema: &TableSchema) -> String {
+    if schema.format.is_binary() {
+        "LONGBLOB NOT NULL".to_owned()
+    } else if schema.compressed {
+        format!(
+            "JSON NOT NULL, data_compressed {}",
+            <MySql as Ddl>::COMPRESSED_TYPE
+        )
+    } else {
+        "JSON NOT NULL".to_owned()
+    }
+}
+
+impl Ddl for MySql {
+    const DELETE_TIME_TYPE: &'static str = "TIMESTAMP(3) NULL DEFAULT NULL";
+    const COMPRESSED_TYPE: &'static str = "LONGBLOB";
+
+    fn create_table(schema: &TableSchema, table: &TableName) -> Vec<String> {
+        let data = data_column(schema);
+        let delete_time_column = if schema.soft_delete {
+            ", delete_time TIMESTAMP(3) NULL DEFAULT NULL"
+        } else {
+            ""
+        };
+        let index_columns: String = schema
+            .indexes
+            .iter()
+            .flat_map(|index| index_definitions(index, table))
+            .map(|definition| format!(", {definition}"))
+            .collect();
+
+        let mut statements = vec![format!(
+            r#"
+                CREATE TABLE IF NOT EXISTS {} (
+                    id {} primary key NOT NULL{},
+                    version BIGINT not null,
+                    create_time TIMESTAMP(3) NOT NULL,
+                    update_time TIMESTAMP(3) NOT NULL,
+                    data {data}{delete_time_column}{index_columns}
+                )
+                "#,
+            table,
+            if schema.auto_increment {
+                "BIGINT"
+            } else {
+                "VARCHAR(255)"
+            },
+            if schema.auto_increment {
+                " AUTO_INCREMENT"
+            } else {
+                ""
+            },
+        )];
+        if schema.history {
+            statements.push(Self::create_history_table(schema, table));
+        }
+        statements
+    }
+
+    fn create_history_table(schema: &TableSchema, table: &TableName) -> String {
+        let data = data_column(schema);
+        format!(
+            r#"
+                CREATE TABLE IF NOT EXISTS {} (
+                    id {} NOT NULL,
+                    version BIGINT not null,
+                    create_time TIMESTAMP(3) NOT NULL,
+                    update_time TIMESTAMP(3) NOT NULL,
+                    data {data},{}
+                    history_time TIMESTAMP(3) NOT NULL,
+                    primary key (id, version)
+                )
+                "#,
+            table.history(),
+            if schema.auto_increment {
+                "BIGINT"
+            } else {
+                "VARCHAR(255)"
+            },
+            if schema.soft_delete {
+                "\n                    delete_time TIMESTAMP(3) NULL DEFAULT NULL,"
+            } else {
+                ""
+            },
+        )
+    }
+
+    fn create_index(index: &IndexSchema, table: &TableName) -> Vec<String> {
+        let definitions = index_definitions(index, table);
+        if definitions.is_empty() {
+            return vec![];
+        }
+        let changes: Vec<String> = definitions
+            .iter()
+            .map(|definition| format!("ADD {definition}"))
+            .collect();
+        vec![format!("ALTER TABLE {table} {}", changes.join(", "))]
+    }
+
+    fn column_types(kind: ColumnKind, auto_increment: bool) -> &'static [&'static str] {
+        match kind {
+            ColumnKind::Id if auto_increment => &["bigint"],
+            ColumnKind::Id => &["varchar", "char"],
+            ColumnKind::Version => &["bigint"],
+            ColumnKind::Time => &["timestamp", "datetime"],
+            // MariaDB stores the JSON columns as LONGTEXT
+            ColumnKind::Data => &["json", "longtext"],
+            ColumnKind::Bytes => &["longblob", "mediumblob", "blob"],
+        }
+    }
+
+    fn requires_json_check(data_type: &str) -> bool {
+        data_type != "json"
+    }
+
+    fn drop_index(index: &IndexSchema, table: &TableName) -> Vec<String> {
+        let IndexSchema::Values { paths, .. } = index else {
+            return vec![];
+        };
+        let mut changes = vec![format!("DROP INDEX {}", table.index(index.name()))];
+        for position in 0..paths.len() {
+            changes.push(format!(
+                "DROP COLUMN {}",
+                index_column(index.name(), position)
+            ));
+        }
+        vec![format!("ALTER TABLE {table} {}", changes.join(", "))]
+    }
+}
+
+/// Returns the name of the generated column holding the value of the path at `position`
+/// of the index `name`.
+fn index_column(name: &str, position: usize) -> String {
+    format!("c3p0_{name}_{position}")
+}
+
+/// The number of leading characters of the `Text` values stored in the indexes.
+const TEXT_PREFIX: usize = 255;
+
+/// Returns the definitions of the generated columns and of the key of an index.
+/// MariaDB does not support functional indexes, so the indexed values are stored in
+/// generated columns. `JSON_VALUE` returns up to 512 characters on MySQL and a
+/// `LONGTEXT` on MariaDB, so the `Text` values are stored in `LONGTEXT` columns, which
+/// are indexed by their first [`TEXT_PREFIX`] characters. The containment queries have
+/// no index on the other backends, so the containment indexes are ignored.
+fn index_definitions(index: &IndexSchema, table: &TableName) -> Vec<String> {
+    let IndexSchema::Values {
+        name,
+        paths,
+        unique,
+    } = index
+    else {
+        return vec![];
+    };
+    let mut definitions = Vec::with_capacity(paths.len() + 1);
+    let mut columns = Vec::with_capacity(paths.len());
+    for (position, (path, json_type)) in paths.iter().enumerate() {
+        let column = index_column(name, position);
+        // Stored, as the virtual TEXT columns cannot be indexed on every server
+        let (column_type, storage, key_part) = match json_type {
+            JsonType::Text => ("LONGTEXT", "STORED", format!("{column}({TEXT_PREFIX})")),
+            JsonType::Number => ("DECIMAL(65,30)", "VIRTUAL", column.clone()),
+            JsonType::Bool => ("BOOLEAN", "VIRTUAL", column.clone()),
+        };
+        definitions.push(format!(
+            "{column} {column_type} AS ({}) {storage}",
+            MySql::json_field(&JsonPath::from(path.as_str()), *json_type)
+        ));
+        columns.push(key_part);
+    }
+    definitions.push(format!(
+        "{} {} ({})",
+        if *unique { "UNIQUE KEY" } else { "KEY" },
+        table.index(name),
+        columns.join(", ")
+    ));
+    definitions
+}
+
+/// Adds to an existing table the indexes of the `schema` it does not have yet, as
+/// `CREATE TABLE IF NOT EXISTS` leaves the existing tables unchanged.
+pub(super) async fn create_missing_indexes(
+    tx: &mut MySqlConnection,
+    schema: &TableSchema,
+    table: &TableName,
+) -> Result<(), C3p0Error> {
+    let name = table.unquoted_name(false);
+    let keys: Vec<String> = sqlx::query_scalar(
+        r#"
+            SELECT DISTINCT CAST(INDEX_NAME AS CHAR) FROM information_schema.STATISTICS
+            WHERE TABLE_SCHEMA = COALESCE(?, DATABASE()) AND TABLE_NAME = ?
+            "#,
+    )
+    .bind(table.unquoted_schema())
+    .bind(&name)
+    .fetch_all(&mut *tx)
+    .await?;
+    for index in &schema.indexes {
+        let key = format!("{name}_{}", index.name());
+        if keys
+            .iter()
+            .any(|existing| existing.eq_ignore_ascii_case(&key))
+        {
+            continue;
+        }
+        for query in <MySql as Ddl>::create_index(index, table) {
+            sqlx::query(sqlx::AssertSqlSafe(query))
+                .execute(&mut *tx)
+                .await?;
+        }
+    }
+    Ok(())
+}
+
+/// Returns the structure of the table, or of its history table, or `None` if it does
+/// not exist.
+pub(super) async fn table_info(
+    tx: &mut MySqlConnection,
+    table: &TableName,
+    history: bool,
+) -> Result<Option<TableInfo>, C3p0Error> {
+    let name = table.unquoted_name(history);
+    let schema = table.unquoted_schema();
+    let columns: Vec<ColumnInfo> = sqlx::query(
+        r#"
+            SELECT CAST(COLUMN_NAME AS CHAR), CAST(COLUMN_TYPE AS CHAR), CAST(COLUMN_KEY AS CHAR)
+            FROM information_schema.COLUMNS
+            WHERE TABLE_SCHEMA = COALESCE(?, DATABASE()) AND TABLE_NAME = ?
+            "#,
+    )
+    .bind(schema)
+    .bind(&name)
+    .fetch_all(&mut *tx)
+    .await?
+    .iter()
+    .map(|row| {
+        Ok(ColumnInfo {
+            name: row.try_get(0)?,
+            data_type: row.try_get(1)?,
+            primary_key: row.try_get::<String, _>(2)? == "PRI",
+        })
+    })
+    .collect::<Result<_, sqlx::Error>>()?;
+    if columns.is_empty() {
+        return Ok(None);
+    }
+
+    let text_data = columns.iter().any(|column| {
+        column.name.eq_ignore_ascii_case("data")
+            && column.data_type.eq_ignore_ascii_case("longtext")
+    });
+    // Only MariaDB, which stores the JSON columns as text, reports the table of the checks
+    let json_check = if text_data {
+        let checks: Vec<String> = sqlx::query_scalar(
+            r#"
+                SELECT CAST(CHECK_CLAUSE AS CHAR) FROM information_schema.CHECK_CONSTRAINTS
+                WHERE CONSTRAINT_SCHEMA = COALESCE(?, DATABASE()) AND TABLE_NAME = ?
+                "#,
+        )
+        .bind(schema)
+        .bind(&name)
+        .fetch_all(&mut *tx)
+        .await?;
+        checks
+            .iter()
+            .any(|check| check.to_lowercase().contains("json_valid("))
+    } else {
+        true
+    };
+    Ok(Some(TableInfo {
+        columns,
+        json_check,
+    }))
+}
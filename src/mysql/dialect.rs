@@ -0,0 +1,102 @@
+use sqlx::MySql;
+
+use serde_json::Value;
+
+use crate::{
+    patch::{JsonPatch, PatchOp},
+    sql::{JsonPath, JsonType, OrderBy, SqlBuilder, SqlDialect, SqlValue},
+};
+
+impl SqlDialect for MySql {
+    /// MySQL requires LIMIT to use OFFSET; u64::MAX is the documented sentinel for "no limit".
+    const NO_LIMIT: &'static str = "LIMIT 18446744073709551615";
+
+    /// The binary protocol encodes the number of parameters as a 16-bit integer.
+    const MAX_BIND_PARAMS: usize = 65535;
+
+    fn placeholder(_index: usize) -> String {
+        "?".to_owned()
+    }
+
+    /// Backslashes are escape characters in MySQL string literals.
+    fn string_literal(value: &str) -> String {
+        format!("'{}'", value.replace('\\', "\\\\").replace('\'', "''"))
+    }
+
+    /// Backticks quote the identifiers unless the `ANSI_QUOTES` mode is enabled.
+    fn quote_identifier(identifier: &str) -> String {
+        format!("`{}`", identifier.replace('`', "``"))
+    }
+
+    /// Renders `JSON_VALUE(data, '$.key1.key2')`, which is supported by both MySQL and
+    /// MariaDB. Note that on MySQL `JSON_VALUE` returns at most 512 characters.
+    fn json_field(path: &JsonPath, json_type: JsonType) -> String {
+        let field = format!(
+            "JSON_VALUE(data, {})",
+            Self::string_literal(&path.to_dollar_path())
+        );
+        match json_type {
+            JsonType::Text => field,
+            JsonType::Number => format!("CAST({field} AS DECIMAL(65,30))"),
+            JsonType::Bool => format!("({field} = 'true')"),
+        }
+    }
+
+    /// Renders `JSON_CONTAINS(data, ?)`, which is supported by both MySQL and MariaDB.
+    fn json_contains(sql: &mut SqlBuilder<Self>, value: &Value) {
+        sql.push("JSON_CONTAINS(data, ")
+            .push_value(SqlValue::Text(value.to_string()))
+            .push(")");
+    }
+
+    /// Renders `JSON_OBJECT('name', JSON_EXTRACT(data, '$.key1.key2'), ...)`.
+    fn json_object(fields: &[(String, JsonPath)]) -> String {
+        let arguments: Vec<String> = fields
+            .iter()
+            .map(|(name, path)| {
+                format!(
+                    "{}, JSON_EXTRACT(data, {})",
+                    Self::string_literal(name),
+                    Self::string_literal(&path.to_dollar_path())
+                )
+            })
+            .collect();
+        format!("JSON_OBJECT({})", arguments.join(", "))
+    }
+
+    /// MySQL does not support `NULLS LAST`, `expr IS NULL` sorts the non `NULL` values first.
+    fn order_by_nulls_last(expr: &str, order: &OrderBy) -> String {
+        format!("{expr} IS NULL, {expr} {order}")
+    }
+
+    /// Renders nested `JSON_MERGE_PATCH`, `JSON_SET` and `JSON_REMOVE` calls. `JSON_SET`
+    /// does not create the missing parents, so they are first added with a merge patch.
+    fn json_patch(patch: &JsonPatch, values: &mut Vec<Value>) -> String {
+        match patch {
+            JsonPatch::Merge(patch) => {
+                values.push(patch.clone());
+                "JSON_MERGE_PATCH(data, ?)".to_owned()
+            }
+            JsonPatch::Ops(ops) => ops.iter().fold("data".to_owned(), |data, op| match op {
+                PatchOp::Set(path, value) => {
+                    let data = match PatchOp::parents_patch(path) {
+                        Some(parents) => {
+                            values.push(parents);
+                            format!("JSON_MERGE_PATCH({data}, ?)")
+                        }
+                        None => data,
+                    };
+                    values.push(value.clone());
+                    format!(
+                        "JSON_SET({data}, {}, JSON_EXTRACT(?, '$'))",
+                        Self::string_literal(&path.to_dollar_path())
+                    )
+                }
+                PatchOp::Remove(path) => format!(
+                    "JSON_REMOVE({data}, {})",
+                    Self::string_literal(&path.to_dollar_path())
+                ),
+            }),
+        }
+    }
+}
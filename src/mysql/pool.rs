@@ -6,18 +6,32 @@ use sqlx::{MySql, MySqlConnection, Pool};
 #[derive(Clone)]
 pub struct MySqlC3p0Pool {
     pool: Pool<MySql>,
+    #[cfg(feature = "encryption")]
+    key_ring: Option<std::sync::Arc<crate::crypto::KeyRing>>,
 }
 
 impl MySqlC3p0Pool {
     /// Creates a new SqlxMySqlC3p0Pool from a Sqlx Pool
     pub fn new(pool: Pool<MySql>) -> Self {
-        MySqlC3p0Pool { pool }
+        MySqlC3p0Pool {
+            pool,
+            #[cfg(feature = "encryption")]
+            key_ring: None,
+        }
     }
 
     /// Returns the underlying Sqlx Pool
     pub fn pool(&self) -> &Pool<MySql> {
         &self.pool
     }
+
+    /// Sets the [`KeyRing`](crate::crypto::KeyRing) of the encrypted fields, used by the
+    /// transactions of this pool.
+    #[cfg(feature = "encryption")]
+    pub fn with_key_ring(mut self, key_ring: crate::crypto::KeyRing) -> Self {
+        self.key_ring = Some(std::sync::Arc::new(key_ring));
+        self
+    }
 }
 
 impl From<Pool<MySql>> for MySqlC3p0Pool {
@@ -39,7 +53,10 @@ impl C3p0Pool for MySqlC3p0Pool {
     ) -> Result<T, E> {
         let mut transaction = self.pool.begin().await.map_err(C3p0Error::from)?;
 
-        let result = (tx)(&mut transaction).await?;
+        let result = (tx)(&mut transaction);
+        #[cfg(feature = "encryption")]
+        let result = crate::crypto::in_scope(self.key_ring.clone(), result);
+        let result = result.await?;
 
         transaction.commit().await.map_err(C3p0Error::from)?;
 
@@ -1,16 +1,43 @@
+use std::collections::HashMap;
+use std::ops::Range;
+
 use chrono::{DateTime, Utc};
+use futures_core::Stream;
+use futures_util::{StreamExt, TryStreamExt, future, stream};
 
+use super::ddl;
 use crate::codec::Codec;
+use crate::decode::{LenientRecord, RawRecord};
+use crate::filter::{Field, Filter, PLAIN_JSON, plain_json_condition};
+use crate::format::{
+    Stored, codec_from_row, compressed_column, compressed_value, data_from_row,
+    has_compressed_column, set_compressed,
+};
+use crate::id::{DbId, IdType};
+use crate::index::JsonIndex;
+use crate::page::{Cursor, Page, PageOrder, page_query};
+use crate::patch::JsonPatch;
+use crate::projection::{Projected, Projection, projection_query};
+use crate::schema::{Ddl, TableReport, TableSchema, verify_table};
+use crate::sql::{OrderBy, SortKey, SqlBuilder, SqlDialect, SqlValue, to_arguments};
+use crate::table::TableName;
 use crate::{
     error::C3p0Error,
-    record::{DataType, DbOps, DbSave, NewRecord, Record},
+    ops::{TableOps, TableSave},
+    record::{
+        CodecOf, DataType, EncodedRecord, NewData, NewRecord, Record, SoftDeleteRecord,
+        UpdateAllResult, and_not_deleted, as_of_query, check_history, check_soft_delete,
+        history_columns, new_id, returned_in_order, select_query, unique_by_id, unique_key,
+        unique_key_filter, where_not_deleted,
+    },
 };
+use serde::de::DeserializeOwned;
 use sqlx::Database;
 use sqlx::FromRow;
 use sqlx::MySql;
 use sqlx::MySqlConnection;
 use sqlx::Row;
-use sqlx::mysql::MySqlRow;
+use sqlx::mysql::{MySqlQueryResult, MySqlRow};
 use sqlx::query::QueryAs;
 
 /// SQL expression returning the current `TIMESTAMP(3)` (millisecond precision) computed by
@@ -24,36 +51,125 @@ const NOW_EXPR: &str = "CURRENT_TIMESTAMP(3)";
 
 impl<DATA: DataType> FromRow<'_, MySqlRow> for Record<DATA> {
     fn from_row(row: &MySqlRow) -> Result<Self, sqlx::Error> {
-        let id: i64 = row.try_get(0)?;
+        let DbId(id): DbId<DATA::ID> = row.try_get(0)?;
         let version: i64 = row.try_get(1)?;
         let create_time: DateTime<Utc> = row.try_get(2)?;
         let update_time: DateTime<Utc> = row.try_get(3)?;
-        let sqlx::types::Json(data): sqlx::types::Json<DATA::CODEC> = row.try_get(4)?;
+        let data = codec_from_row::<_, DATA>(row, 4)?;
 
         Ok(Record {
             id,
             version,
-            data: DATA::CODEC::decode(data),
+            data: CodecOf::<DATA>::decode(data),
             create_time,
             update_time,
         })
     }
 }
 
-impl<DATA: DataType> DbOps<MySql, DATA> for Record<DATA> {
-    fn query_with_tail(
-        tail: &str,
-    ) -> QueryAs<'_, MySql, Record<DATA>, <MySql as Database>::Arguments> {
-        let query = format!(
-            "{} {}",
-            <Self as DbOps<MySql, DATA>>::select_query_base(),
-            tail
-        );
+impl<DATA: DataType> FromRow<'_, MySqlRow> for SoftDeleteRecord<DATA> {
+    fn from_row(row: &MySqlRow) -> Result<Self, sqlx::Error> {
+        Ok(SoftDeleteRecord {
+            record: Record::from_row(row)?,
+            delete_time: row.try_get(5)?,
+        })
+    }
+}
+
+impl<DATA: DataType> FromRow<'_, MySqlRow> for RawRecord<DATA> {
+    fn from_row(row: &MySqlRow) -> Result<Self, sqlx::Error> {
+        let DbId(id): DbId<DATA::ID> = row.try_get(0)?;
+        let data: serde_json::Value = data_from_row::<_, DATA, _>(row, 4)?;
+        Ok(RawRecord {
+            id,
+            version: row.try_get(1)?,
+            create_time: row.try_get(2)?,
+            update_time: row.try_get(3)?,
+            data,
+        })
+    }
+}
+
+impl<DATA: DataType, P: DeserializeOwned> FromRow<'_, MySqlRow> for Projected<DATA, P> {
+    fn from_row(row: &MySqlRow) -> Result<Self, sqlx::Error> {
+        let DbId(id): DbId<DATA::ID> = row.try_get(0)?;
+        let sqlx::types::Json(data) = row.try_get(2)?;
+        Ok(Projected {
+            id,
+            version: row.try_get(1)?,
+            data,
+        })
+    }
+}
+
+/// Returns a [`QueryAs`] for `Record<DATA>` selecting from `table`, followed by `tail`.
+fn select_with_tail<'q, DATA: DataType>(
+    table: &TableName,
+    tail: &str,
+) -> QueryAs<'q, MySql, Record<DATA>, <MySql as Database>::Arguments> {
+    sqlx::query_as(sqlx::AssertSqlSafe(format!(
+        "{} {}",
+        select_query::<DATA>(table),
+        tail
+    )))
+}
+
+impl<DATA: DataType> TableOps<MySql, DATA> for Record<DATA> {
+    fn query_with_tail<'q>(
+        table: &TableName,
+        tail: &'q str,
+    ) -> QueryAs<'q, MySql, Record<DATA>, <MySql as Database>::Arguments> {
+        let query = format!("{} {}", select_query::<DATA>(table), tail);
         sqlx::query_as(sqlx::AssertSqlSafe(query))
     }
 
-    async fn count_all(tx: &mut MySqlConnection) -> Result<u64, C3p0Error> {
-        let query = format!("SELECT COUNT(*) FROM {}", DATA::TABLE_NAME,);
+    async fn create_table_if_not_exists(
+        tx: &mut MySqlConnection,
+        table: &TableName,
+    ) -> Result<(), C3p0Error> {
+        let schema = TableSchema::of::<DATA>()?;
+        for query in <MySql as Ddl>::create_table(&schema, table) {
+            sqlx::query(sqlx::AssertSqlSafe(query))
+                .execute(&mut *tx)
+                .await?;
+        }
+        ddl::create_missing_indexes(tx, &schema, table).await
+    }
+
+    async fn drop_table_if_exists(
+        tx: &mut MySqlConnection,
+        table: &TableName,
+        cascade: bool,
+    ) -> Result<(), C3p0Error> {
+        let schema = TableSchema::of::<DATA>()?;
+        for query in <MySql as Ddl>::drop_table(&schema, table, cascade) {
+            sqlx::query(sqlx::AssertSqlSafe(query))
+                .execute(&mut *tx)
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn verify_table(
+        tx: &mut MySqlConnection,
+        table: &TableName,
+    ) -> Result<TableReport, C3p0Error> {
+        let schema = TableSchema::of::<DATA>()?;
+        let main = ddl::table_info(tx, table, false).await?;
+        let history = if schema.history {
+            ddl::table_info(tx, table, true).await?
+        } else {
+            None
+        };
+        Ok(verify_table::<MySql>(&schema, table, main, history))
+    }
+
+    async fn count_all(tx: &mut MySqlConnection, table: &TableName) -> Result<u64, C3p0Error> {
+        let query = format!(
+            "SELECT COUNT(*) FROM {}{}",
+            table,
+            where_not_deleted::<DATA>()
+        );
 
         Ok(sqlx::query(sqlx::AssertSqlSafe(query))
             .fetch_one(tx)
@@ -62,14 +178,19 @@ impl<DATA: DataType> DbOps<MySql, DATA> for Record<DATA> {
             .map(|val: i64| val as u64)?)
     }
 
-    async fn exists_by_id(tx: &mut MySqlConnection, id: i64) -> Result<bool, C3p0Error> {
+    async fn exists_by_id(
+        tx: &mut MySqlConnection,
+        table: &TableName,
+        id: DATA::ID,
+    ) -> Result<bool, C3p0Error> {
         let query = format!(
-            "SELECT EXISTS (SELECT 1 FROM {} WHERE id = ?)",
-            DATA::TABLE_NAME,
+            "SELECT EXISTS (SELECT 1 FROM {} WHERE id = ?{})",
+            table,
+            and_not_deleted::<DATA>(),
         );
 
         Ok(sqlx::query(sqlx::AssertSqlSafe(query))
-            .bind(id)
+            .bind(DbId(id))
             .fetch_one(tx)
             .await
             .and_then(|row| row.try_get(0))?)
@@ -77,47 +198,231 @@ impl<DATA: DataType> DbOps<MySql, DATA> for Record<DATA> {
 
     async fn fetch_all(
         tx: &mut MySqlConnection,
+        table: &TableName,
         offset: u64,
         limit: Option<u64>,
     ) -> Result<Vec<Record<DATA>>, C3p0Error> {
         let query = match limit {
-            Some(limit) => Self::query_with_tail("ORDER BY id ASC LIMIT ? OFFSET ?")
+            Some(limit) => select_with_tail::<DATA>(table, "ORDER BY id ASC LIMIT ? OFFSET ?")
                 .bind(limit)
                 .bind(offset),
             // MySQL requires LIMIT to use OFFSET; u64::MAX is the documented sentinel for "no limit".
-            None => Self::query_with_tail("ORDER BY id ASC LIMIT 18446744073709551615 OFFSET ?")
-                .bind(offset),
+            None => select_with_tail::<DATA>(
+                table,
+                "ORDER BY id ASC LIMIT 18446744073709551615 OFFSET ?",
+            )
+            .bind(offset),
         };
         Ok(query.fetch_all(tx).await?)
     }
 
+    async fn fetch_all_sorted(
+        tx: &mut MySqlConnection,
+        table: &TableName,
+        sort: &[SortKey],
+        offset: u64,
+        limit: Option<u64>,
+    ) -> Result<Vec<Record<DATA>>, C3p0Error> {
+        fetch_sorted::<DATA, _>(tx, table, None, sort, offset, limit).await
+    }
+
+    fn fetch_all_stream<'a>(
+        tx: &'a mut MySqlConnection,
+        table: &TableName,
+    ) -> impl Stream<Item = Result<Record<DATA>, C3p0Error>> + use<'a, DATA>
+    where
+        DATA: 'a,
+    {
+        select_with_tail::<DATA>(table, "ORDER BY id ASC")
+            .fetch(tx)
+            .map_err(C3p0Error::from)
+    }
+
+    async fn fetch_where(
+        tx: &mut MySqlConnection,
+        table: &TableName,
+        filter: &Filter,
+        offset: u64,
+        limit: Option<u64>,
+    ) -> Result<Vec<Record<DATA>>, C3p0Error> {
+        fetch_sorted::<DATA, _>(
+            tx,
+            table,
+            Some(filter),
+            &[SortKey::Id(OrderBy::Asc)],
+            offset,
+            limit,
+        )
+        .await
+    }
+
+    async fn fetch_lenient(
+        tx: &mut MySqlConnection,
+        table: &TableName,
+        filter: Option<&Filter>,
+        offset: u64,
+        limit: Option<u64>,
+    ) -> Result<Vec<LenientRecord<DATA>>, C3p0Error> {
+        let records: Vec<RawRecord<DATA>> = fetch_sorted::<DATA, _>(
+            tx,
+            table,
+            filter,
+            &[SortKey::Id(OrderBy::Asc)],
+            offset,
+            limit,
+        )
+        .await?;
+        Ok(records.into_iter().map(RawRecord::decode).collect())
+    }
+
+    async fn fetch_projected<P: DeserializeOwned + Send + Unpin>(
+        tx: &mut MySqlConnection,
+        table: &TableName,
+        projection: &Projection,
+        filter: &Filter,
+        sort: &[SortKey],
+        offset: u64,
+        limit: Option<u64>,
+    ) -> Result<Vec<Projected<DATA, P>>, C3p0Error> {
+        projection.check::<DATA>(table)?;
+        fetch_select(
+            tx,
+            projection_query::<DATA, MySql>(table, projection),
+            Some(filter),
+            has_compressed_column::<DATA>().then_some(PLAIN_JSON),
+            sort,
+            offset,
+            limit,
+        )
+        .await
+    }
+
+    async fn fetch_where_sorted(
+        tx: &mut MySqlConnection,
+        table: &TableName,
+        filter: &Filter,
+        sort: &[SortKey],
+        offset: u64,
+        limit: Option<u64>,
+    ) -> Result<Vec<Record<DATA>>, C3p0Error> {
+        fetch_sorted::<DATA, _>(tx, table, Some(filter), sort, offset, limit).await
+    }
+
+    fn fetch_where_stream<'a>(
+        tx: &'a mut MySqlConnection,
+        table: &TableName,
+        filter: &Filter,
+    ) -> impl Stream<Item = Result<Record<DATA>, C3p0Error>> + use<'a, DATA>
+    where
+        DATA: 'a,
+    {
+        let condition = match plain_json_condition::<DATA>(table, Some(filter), &[]) {
+            Ok(condition) => condition,
+            Err(err) => return stream::once(future::ready(Err(err))).right_stream(),
+        };
+        let mut sql = SqlBuilder::<MySql>::new(select_query::<DATA>(table));
+        sql.push("WHERE ");
+        filter.to_sql(&mut sql);
+        if let Some(condition) = condition {
+            sql.push(" AND ").push(condition);
+        }
+        sql.push_order_by(&[SortKey::Id(OrderBy::Asc)]);
+
+        let (query, values) = sql.build();
+        match to_arguments::<MySql>(values) {
+            Ok(arguments) => sqlx::query_as_with(sqlx::AssertSqlSafe(query), arguments)
+                .fetch(tx)
+                .map_err(C3p0Error::from)
+                .left_stream(),
+            Err(err) => stream::once(future::ready(Err(err))).right_stream(),
+        }
+    }
+
+    async fn count_where(
+        tx: &mut MySqlConnection,
+        table: &TableName,
+        filter: &Filter,
+    ) -> Result<u64, C3p0Error> {
+        let condition = plain_json_condition::<DATA>(table, Some(filter), &[])?;
+        let mut sql = SqlBuilder::<MySql>::new(format!("SELECT COUNT(*) FROM {} WHERE ", table));
+        filter.to_sql(&mut sql);
+        if let Some(condition) = condition {
+            sql.push(" AND ").push(condition);
+        }
+        sql.push(and_not_deleted::<DATA>());
+
+        let (query, values) = sql.build();
+        Ok(
+            sqlx::query_with(sqlx::AssertSqlSafe(query), to_arguments::<MySql>(values)?)
+                .fetch_one(tx)
+                .await
+                .and_then(|row| row.try_get(0))
+                .map(|val: i64| val as u64)?,
+        )
+    }
+
+    async fn fetch_page(
+        tx: &mut MySqlConnection,
+        table: &TableName,
+        filter: Option<&Filter>,
+        order: PageOrder,
+        cursor: Option<&Cursor>,
+        limit: u64,
+    ) -> Result<Page<DATA>, C3p0Error> {
+        let (filter, sort) = page_query(filter, order, cursor, limit)?;
+        let records =
+            fetch_sorted::<DATA, _>(tx, table, filter.as_ref(), &sort, 0, Some(limit + 1)).await?;
+        Page::new(records, order, limit)
+    }
+
     async fn fetch_one_optional_by_id(
         tx: &mut MySqlConnection,
-        id: i64,
+        table: &TableName,
+        id: DATA::ID,
     ) -> Result<Option<Record<DATA>>, C3p0Error> {
-        Ok(Self::query_with_tail("WHERE id = ? LIMIT 1")
-            .bind(id)
+        Ok(select_with_tail::<DATA>(table, "WHERE id = ? LIMIT 1")
+            .bind(DbId(id))
             .fetch_optional(tx)
             .await?)
     }
 
-    async fn fetch_one_by_id(tx: &mut MySqlConnection, id: i64) -> Result<Record<DATA>, C3p0Error> {
-        Ok(Self::query_with_tail("WHERE id = ? LIMIT 1")
-            .bind(id)
+    async fn fetch_one_by_id(
+        tx: &mut MySqlConnection,
+        table: &TableName,
+        id: DATA::ID,
+    ) -> Result<Record<DATA>, C3p0Error> {
+        Ok(select_with_tail::<DATA>(table, "WHERE id = ? LIMIT 1")
+            .bind(DbId(id))
             .fetch_one(tx)
             .await?)
     }
 
-    async fn delete(self, tx: &mut MySqlConnection) -> Result<Record<DATA>, C3p0Error> {
+    async fn delete(
+        mut self,
+        tx: &mut MySqlConnection,
+        table: &TableName,
+    ) -> Result<Record<DATA>, C3p0Error> {
+        copy_to_history::<DATA>(
+            tx,
+            table,
+            &Filter::and([
+                Filter::eq(Field::Id, self.id.to_sql()),
+                Filter::eq(Field::Version, self.version),
+            ]),
+            and_not_deleted::<DATA>(),
+        )
+        .await?;
+
         let query = format!(
-            "DELETE FROM {} WHERE id = ? AND version = ?",
-            DATA::TABLE_NAME,
+            "{} WHERE id = ? AND version = ?{}",
+            delete_query::<DATA>(table),
+            and_not_deleted::<DATA>(),
         );
 
         let result = sqlx::query(sqlx::AssertSqlSafe(query))
-            .bind(self.id)
+            .bind(DbId(self.id.clone()))
             .bind(self.version)
-            .execute(tx)
+            .execute(&mut *tx)
             .await?
             .rows_affected();
 
@@ -125,18 +430,33 @@ impl<DATA: DataType> DbOps<MySql, DATA> for Record<DATA> {
             return Err(C3p0Error::OptimisticLockError {
                 cause: format!(
                     "Cannot delete data in table [{}] with id [{:?}], version [{}]: data was changed!",
-                    DATA::TABLE_NAME,
+                    table.declared(),
                     self.id,
                     self.version
                 ),
             });
         }
 
+        if DATA::SOFT_DELETE {
+            let select_ts = format!("SELECT update_time FROM {} WHERE id = ?", table);
+            self.version += 1;
+            self.update_time = sqlx::query(sqlx::AssertSqlSafe(select_ts))
+                .bind(DbId(self.id.clone()))
+                .fetch_one(tx)
+                .await
+                .and_then(|row| row.try_get(0))?;
+        }
         Ok(self)
     }
 
-    async fn delete_all(tx: &mut MySqlConnection) -> Result<u64, C3p0Error> {
-        let query = format!("DELETE FROM {}", DATA::TABLE_NAME);
+    async fn delete_all(tx: &mut MySqlConnection, table: &TableName) -> Result<u64, C3p0Error> {
+        copy_to_history::<DATA>(tx, table, &Filter::and([]), and_not_deleted::<DATA>()).await?;
+
+        let query = format!(
+            "{}{}",
+            delete_query::<DATA>(table),
+            where_not_deleted::<DATA>()
+        );
 
         Ok(sqlx::query(sqlx::AssertSqlSafe(query))
             .execute(tx)
@@ -144,32 +464,175 @@ impl<DATA: DataType> DbOps<MySql, DATA> for Record<DATA> {
             .map(|done| done.rows_affected())?)
     }
 
-    async fn delete_by_id(tx: &mut MySqlConnection, id: i64) -> Result<u64, C3p0Error> {
-        let query = format!("DELETE FROM {} WHERE id = ?", DATA::TABLE_NAME);
+    async fn delete_by_id(
+        tx: &mut MySqlConnection,
+        table: &TableName,
+        id: DATA::ID,
+    ) -> Result<u64, C3p0Error> {
+        copy_to_history::<DATA>(
+            tx,
+            table,
+            &Filter::eq(Field::Id, id.to_sql()),
+            and_not_deleted::<DATA>(),
+        )
+        .await?;
+
+        let query = format!(
+            "{} WHERE id = ?{}",
+            delete_query::<DATA>(table),
+            and_not_deleted::<DATA>()
+        );
 
         Ok(sqlx::query(sqlx::AssertSqlSafe(query))
-            .bind(id)
+            .bind(DbId(id))
             .execute(tx)
             .await
             .map(|done| done.rows_affected())?)
     }
 
-    async fn update(mut self, tx: &mut MySqlConnection) -> Result<Record<DATA>, C3p0Error> {
+    async fn delete_where(
+        tx: &mut MySqlConnection,
+        table: &TableName,
+        filter: &Filter,
+    ) -> Result<u64, C3p0Error> {
+        let condition = match plain_json_condition::<DATA>(table, Some(filter), &[])? {
+            Some(condition) => format!(" AND {condition}{}", and_not_deleted::<DATA>()),
+            None => and_not_deleted::<DATA>().to_owned(),
+        };
+        copy_to_history::<DATA>(tx, table, filter, &condition).await?;
+
+        let mut sql = SqlBuilder::<MySql>::new(format!("{} WHERE ", delete_query::<DATA>(table)));
+        filter.to_sql(&mut sql);
+        sql.push(&condition);
+
+        let (query, values) = sql.build();
+        Ok(
+            sqlx::query_with(sqlx::AssertSqlSafe(query), to_arguments::<MySql>(values)?)
+                .execute(tx)
+                .await
+                .map(|done| done.rows_affected())?,
+        )
+    }
+
+    async fn fetch_including_deleted(
+        tx: &mut MySqlConnection,
+        table: &TableName,
+        offset: u64,
+        limit: Option<u64>,
+    ) -> Result<Vec<SoftDeleteRecord<DATA>>, C3p0Error> {
+        check_soft_delete::<DATA>(table)?;
+
+        let mut sql = SqlBuilder::<MySql>::new(format!(
+            "SELECT id, version, create_time, update_time, data, delete_time{} FROM {}",
+            compressed_column::<DATA>(),
+            table
+        ));
+        sql.push_order_by(&[SortKey::Id(OrderBy::Asc)])
+            .push_limit_offset(offset, limit);
+
+        let (query, values) = sql.build();
+        Ok(
+            sqlx::query_as_with(sqlx::AssertSqlSafe(query), to_arguments::<MySql>(values)?)
+                .fetch_all(tx)
+                .await?,
+        )
+    }
+
+    async fn restore(
+        tx: &mut MySqlConnection,
+        table: &TableName,
+        id: DATA::ID,
+    ) -> Result<Record<DATA>, C3p0Error> {
+        check_soft_delete::<DATA>(table)?;
+        copy_to_history::<DATA>(
+            tx,
+            table,
+            &Filter::eq(Field::Id, id.to_sql()),
+            " AND delete_time IS NOT NULL",
+        )
+        .await?;
+
         let query = format!(
-            "UPDATE {} SET version = ?, update_time = {NOW_EXPR}, data = ? \
-             WHERE id = ? AND version = ?",
-            DATA::TABLE_NAME
+            "UPDATE {} SET version = version + 1, update_time = {NOW_EXPR}, delete_time = NULL \
+             WHERE id = ? AND delete_time IS NOT NULL",
+            table,
         );
-        let select_ts = format!("SELECT update_time FROM {} WHERE id = ?", DATA::TABLE_NAME,);
 
-        let data_encoded = DATA::CODEC::encode(self.data);
+        let result = sqlx::query(sqlx::AssertSqlSafe(query))
+            .bind(DbId(id.clone()))
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+        if result == 0 {
+            return Err(sqlx::Error::RowNotFound.into());
+        }
+
+        <Self as TableOps<MySql, DATA>>::fetch_one_by_id(tx, table, id).await
+    }
+
+    async fn purge(
+        tx: &mut MySqlConnection,
+        table: &TableName,
+        deleted_before: Option<DateTime<Utc>>,
+    ) -> Result<u64, C3p0Error> {
+        check_soft_delete::<DATA>(table)?;
+
+        let mut sql = SqlBuilder::<MySql>::new(format!(
+            "DELETE FROM {} WHERE delete_time IS NOT NULL",
+            table
+        ));
+        if let Some(deleted_before) = deleted_before {
+            sql.push(" AND delete_time < ")
+                .push_value(SqlValue::Timestamp(deleted_before));
+        }
+
+        let (query, values) = sql.build();
+        Ok(
+            sqlx::query_with(sqlx::AssertSqlSafe(query), to_arguments::<MySql>(values)?)
+                .execute(tx)
+                .await
+                .map(|done| done.rows_affected())?,
+        )
+    }
+
+    async fn update(
+        mut self,
+        tx: &mut MySqlConnection,
+        table: &TableName,
+    ) -> Result<Record<DATA>, C3p0Error> {
+        copy_to_history::<DATA>(
+            tx,
+            table,
+            &Filter::and([
+                Filter::eq(Field::Id, self.id.to_sql()),
+                Filter::eq(Field::Version, self.version),
+            ]),
+            and_not_deleted::<DATA>(),
+        )
+        .await?;
+
+        let query = format!(
+            "UPDATE {} SET version = ?, update_time = {NOW_EXPR}, data = ?{} \
+             WHERE id = ? AND version = ?{}",
+            table,
+            set_compressed::<DATA, MySql>(3),
+            and_not_deleted::<DATA>()
+        );
+        let select_ts = format!("SELECT update_time FROM {} WHERE id = ?", table,);
+
+        let data_encoded = CodecOf::<DATA>::encode(self.data);
+        let stored = Stored::<DATA>::new(&data_encoded)?;
         let previous_version = self.version;
         let new_version = previous_version + 1;
 
-        let result = sqlx::query(sqlx::AssertSqlSafe(query))
+        let mut update = sqlx::query(sqlx::AssertSqlSafe(query))
             .bind(new_version)
-            .bind(sqlx::types::Json(&data_encoded))
-            .bind(self.id)
+            .bind(&stored);
+        if has_compressed_column::<DATA>() {
+            update = update.bind(stored.compressed());
+        }
+        let result = update
+            .bind(DbId(self.id.clone()))
             .bind(previous_version)
             .execute(&mut *tx)
             .await
@@ -179,48 +642,290 @@ impl<DATA: DataType> DbOps<MySql, DATA> for Record<DATA> {
             return Err(C3p0Error::OptimisticLockError {
                 cause: format!(
                     "Cannot update data in table [{}] with id [{:?}], version [{}]: data was changed!",
-                    DATA::TABLE_NAME,
+                    table.declared(),
                     self.id,
                     &previous_version
                 ),
             });
         }
 
-        self.data = DATA::CODEC::decode(data_encoded);
+        self.data = CodecOf::<DATA>::decode(data_encoded);
         self.version = new_version;
         self.update_time = sqlx::query(sqlx::AssertSqlSafe(select_ts))
-            .bind(self.id)
+            .bind(DbId(self.id.clone()))
             .fetch_one(tx)
             .await
             .and_then(|row| row.try_get(0))?;
 
         Ok(self)
     }
+
+    async fn patch(
+        tx: &mut MySqlConnection,
+        table: &TableName,
+        id: DATA::ID,
+        version: Option<i64>,
+        patch: &JsonPatch,
+    ) -> Result<Record<DATA>, C3p0Error> {
+        patch.check::<DATA>(table)?;
+        check_not_compressed::<DATA>(tx, table, &id).await?;
+        #[cfg(feature = "encryption")]
+        if let Some(document) = stored_data::<DATA>(tx, table, &id).await? {
+            crate::crypto::check_patch(table, &document, patch)?;
+        }
+        let mut current = vec![Filter::eq(Field::Id, id.to_sql())];
+        current.extend(version.map(|version| Filter::eq(Field::Version, version)));
+        copy_to_history::<DATA>(tx, table, &Filter::and(current), and_not_deleted::<DATA>())
+            .await?;
+
+        let mut values = Vec::new();
+        let data = <MySql as SqlDialect>::json_patch(patch, &mut values);
+        let mut query = format!(
+            "UPDATE {} SET version = version + 1, update_time = {NOW_EXPR}, data = {data} \
+             WHERE id = ?",
+            table,
+        );
+        query.push_str(and_not_deleted::<DATA>());
+        if has_compressed_column::<DATA>() {
+            query.push_str(&format!(" AND {PLAIN_JSON}"));
+        }
+        if version.is_some() {
+            query.push_str(" AND version = ?");
+        }
+
+        let mut update = sqlx::query(sqlx::AssertSqlSafe(query));
+        for value in &values {
+            update = update.bind(sqlx::types::Json(value));
+        }
+        update = update.bind(DbId(id.clone()));
+        if let Some(version) = version {
+            update = update.bind(version);
+        }
+        let result = update
+            .execute(&mut *tx)
+            .await
+            .map(|done| done.rows_affected())?;
+
+        match (result, version) {
+            (0, Some(version)) => Err(C3p0Error::OptimisticLockError {
+                cause: format!(
+                    "Cannot patch data in table [{}] with id [{:?}], version [{}]: data was changed!",
+                    table.declared(),
+                    id,
+                    version
+                ),
+            }),
+            (0, None) => Err(sqlx::Error::RowNotFound.into()),
+            _ => <Self as TableOps<MySql, DATA>>::fetch_one_by_id(tx, table, id).await,
+        }
+    }
+
+    async fn update_all(
+        records: Vec<Record<DATA>>,
+        tx: &mut MySqlConnection,
+        table: &TableName,
+    ) -> Result<UpdateAllResult<DATA>, C3p0Error> {
+        let records: Vec<EncodedRecord<DATA>> =
+            records.into_iter().map(EncodedRecord::encode).collect();
+
+        let params = 3 + usize::from(has_compressed_column::<DATA>());
+        let mut updated = HashMap::new();
+        for chunk in unique_by_id(&records).chunks(<MySql as SqlDialect>::MAX_BIND_PARAMS / params)
+        {
+            let ids = vec!["?"; chunk.len()].join(", ");
+
+            // Without `RETURNING`, the current versions are read and the rows locked
+            // first, so that the entries updated afterwards are known exactly.
+            let select_versions = format!(
+                "SELECT id, version FROM {} WHERE id IN ({ids}){} FOR UPDATE",
+                table,
+                and_not_deleted::<DATA>()
+            );
+            let mut select = sqlx::query(sqlx::AssertSqlSafe(select_versions));
+            for record in chunk {
+                select = select.bind(DbId(record.id.clone()));
+            }
+            let mut versions = HashMap::new();
+            for row in select.fetch_all(&mut *tx).await? {
+                let DbId(id): DbId<DATA::ID> = row.try_get(0)?;
+                versions.insert(id, row.try_get::<i64, _>(1)?);
+            }
+            let matching: Vec<_> = chunk
+                .iter()
+                .filter(|record| versions.get(&record.id) == Some(&record.version))
+                .collect();
+            if matching.is_empty() {
+                continue;
+            }
+            copy_to_history::<DATA>(
+                tx,
+                table,
+                &Filter::in_list(Field::Id, matching.iter().map(|record| record.id.to_sql())),
+                "",
+            )
+            .await?;
+
+            let (compressed_value, set_compressed) = if has_compressed_column::<DATA>() {
+                (
+                    ", ? AS data_compressed",
+                    format!(", {table}.data_compressed = v.data_compressed"),
+                )
+            } else {
+                ("", String::new())
+            };
+            let values = vec![
+                format!("SELECT ? AS id, ? AS version, ? AS data{compressed_value}");
+                matching.len()
+            ]
+            .join(" UNION ALL ");
+            let query = format!(
+                "UPDATE {table} JOIN ({values}) AS v \
+                 ON {table}.id = v.id AND {table}.version = v.version \
+                 SET {table}.version = {table}.version + 1, {table}.update_time = {NOW_EXPR}, {table}.data = v.data{set_compressed}",
+            );
+            let stored = matching
+                .iter()
+                .map(|record| Stored::<DATA>::new(&record.data))
+                .collect::<Result<Vec<_>, C3p0Error>>()?;
+            let mut update = sqlx::query(sqlx::AssertSqlSafe(query));
+            for (record, stored) in matching.iter().zip(&stored) {
+                update = update
+                    .bind(DbId(record.id.clone()))
+                    .bind(record.version)
+                    .bind(stored);
+                if has_compressed_column::<DATA>() {
+                    update = update.bind(stored.compressed());
+                }
+            }
+            update.execute(&mut *tx).await?;
+
+            let select_ts = format!(
+                "SELECT id, update_time FROM {} WHERE id IN ({})",
+                table,
+                vec!["?"; matching.len()].join(", ")
+            );
+            let mut select = sqlx::query(sqlx::AssertSqlSafe(select_ts));
+            for record in &matching {
+                select = select.bind(DbId(record.id.clone()));
+            }
+            for row in select.fetch_all(&mut *tx).await? {
+                let DbId(id): DbId<DATA::ID> = row.try_get(0)?;
+                updated.insert(id, row.try_get(1)?);
+            }
+        }
+
+        Ok(UpdateAllResult::new(records, updated))
+    }
+
+    async fn fetch_history(
+        tx: &mut MySqlConnection,
+        table: &TableName,
+        id: DATA::ID,
+    ) -> Result<Vec<Record<DATA>>, C3p0Error> {
+        check_history::<DATA>(table)?;
+
+        let query = format!(
+            "SELECT id, version, create_time, update_time, data{} FROM {} \
+             WHERE id = ? ORDER BY version ASC",
+            compressed_column::<DATA>(),
+            table.history(),
+        );
+
+        Ok(sqlx::query_as(sqlx::AssertSqlSafe(query))
+            .bind(DbId(id))
+            .fetch_all(tx)
+            .await?)
+    }
+
+    async fn fetch_version(
+        tx: &mut MySqlConnection,
+        table: &TableName,
+        id: DATA::ID,
+        version: i64,
+    ) -> Result<Option<Record<DATA>>, C3p0Error> {
+        check_history::<DATA>(table)?;
+
+        let compressed = compressed_column::<DATA>();
+        let query = format!(
+            "SELECT id, version, create_time, update_time, data{compressed} FROM {} \
+             WHERE id = ? AND version = ? \
+             UNION ALL \
+             SELECT id, version, create_time, update_time, data{compressed} FROM {} \
+             WHERE id = ? AND version = ? LIMIT 1",
+            table,
+            table.history(),
+        );
+
+        Ok(sqlx::query_as(sqlx::AssertSqlSafe(query))
+            .bind(DbId(id.clone()))
+            .bind(version)
+            .bind(DbId(id))
+            .bind(version)
+            .fetch_optional(tx)
+            .await?)
+    }
+
+    async fn fetch_as_of(
+        tx: &mut MySqlConnection,
+        table: &TableName,
+        id: DATA::ID,
+        timestamp: DateTime<Utc>,
+    ) -> Result<Option<Record<DATA>>, C3p0Error> {
+        check_history::<DATA>(table)?;
+
+        let (query, values) = as_of_query::<MySql, DATA>(table, &id, timestamp).build();
+        let record: Option<SoftDeleteRecord<DATA>> =
+            sqlx::query_as_with(sqlx::AssertSqlSafe(query), to_arguments::<MySql>(values)?)
+                .fetch_optional(tx)
+                .await?;
+        Ok(record
+            .filter(|record| record.delete_time.is_none_or(|time| time > timestamp))
+            .map(|record| record.record))
+    }
 }
 
-impl<DATA: DataType> DbSave<MySql, DATA> for NewRecord<DATA> {
-    async fn save(self, tx: &mut MySqlConnection) -> Result<Record<DATA>, C3p0Error> {
+impl<DATA: DataType, NEW: NewData<DATA = DATA>> TableSave<MySql, DATA> for NewRecord<NEW> {
+    async fn save(
+        self,
+        tx: &mut MySqlConnection,
+        table: &TableName,
+    ) -> Result<Record<DATA>, C3p0Error> {
+        let (id, data) = self.data.into_parts();
+        let id = new_id::<DATA>(table, id)?;
+        let (id_column, id_value) = if id.is_some() {
+            ("id, ", "?, ")
+        } else {
+            ("", "")
+        };
         let query = format!(
-            "INSERT INTO {} (version, create_time, update_time, data) \
-             VALUES (?, {NOW_EXPR}, {NOW_EXPR}, ?)",
-            DATA::TABLE_NAME,
+            "INSERT INTO {} ({id_column}version, create_time, update_time, data{}) \
+             VALUES ({id_value}?, {NOW_EXPR}, {NOW_EXPR}, ?{})",
+            table,
+            compressed_column::<DATA>(),
+            compressed_value::<DATA, MySql>(if id.is_some() { 4 } else { 3 }),
         );
-        let select_ts = format!("SELECT create_time FROM {} WHERE id = ?", DATA::TABLE_NAME,);
+        let select_ts = format!("SELECT create_time FROM {} WHERE id = ?", table,);
 
-        let data_encoded = DATA::CODEC::encode(self.data);
+        let data_encoded = CodecOf::<DATA>::encode(data);
+        let stored = Stored::<DATA>::new(&data_encoded)?;
 
-        // sqlx-mysql's `last_insert_id` is u64; the column is signed BIGINT, and
-        // AUTO_INCREMENT values are always positive, so the conversion is safe.
-        let id: i64 = sqlx::query(sqlx::AssertSqlSafe(query))
-            .bind(0_i64)
-            .bind(sqlx::types::Json(&data_encoded))
-            .execute(&mut *tx)
-            .await
-            .map(|done| done.last_insert_id() as i64)?;
-        let data = DATA::CODEC::decode(data_encoded);
+        let mut insert = sqlx::query(sqlx::AssertSqlSafe(query));
+        if let Some(id) = &id {
+            insert = insert.bind(DbId(id.clone()));
+        }
+        insert = insert.bind(0_i64).bind(&stored);
+        if has_compressed_column::<DATA>() {
+            insert = insert.bind(stored.compressed());
+        }
+        let done = insert.execute(&mut *tx).await?;
+        let id = match id {
+            Some(id) => id,
+            None => last_insert_id::<DATA>(&done)?,
+        };
+        let data = CodecOf::<DATA>::decode(data_encoded);
 
         let create_time: DateTime<Utc> = sqlx::query(sqlx::AssertSqlSafe(select_ts))
-            .bind(id)
+            .bind(DbId(id.clone()))
             .fetch_one(tx)
             .await
             .and_then(|row| row.try_get(0))?;
@@ -233,4 +938,439 @@ impl<DATA: DataType> DbSave<MySql, DATA> for NewRecord<DATA> {
             update_time: create_time,
         })
     }
+
+    async fn upsert_by_key(
+        self,
+        tx: &mut MySqlConnection,
+        table: &TableName,
+    ) -> Result<Record<DATA>, C3p0Error> {
+        unique_key::<DATA>()?;
+        let (id, data) = self.data.into_parts();
+        // `ON DUPLICATE KEY UPDATE` fires on a conflict over any unique index, so the key
+        // must be the only unique value of the entry other than a generated id
+        if id.is_some() || DATA::INDEXES.iter().any(JsonIndex::is_unique) {
+            return Err(C3p0Error::Other {
+                cause: format!(
+                    "The upsert by key on MySQL is not supported for table [{}]: the entry \
+                     has a provided id or the type declares other unique indexes",
+                    table.declared()
+                ),
+            });
+        }
+        let id = new_id::<DATA>(table, None)?;
+        let (id_column, id_value) = if id.is_some() {
+            ("id, ", "?, ")
+        } else {
+            ("", "")
+        };
+        // The conflict is detected by the unique index over the generated columns of the
+        // key; `LAST_INSERT_ID(id)` makes the id of the updated row available as the
+        // last insert id.
+        let query = format!(
+            "INSERT INTO {} ({id_column}version, create_time, update_time, data{}) \
+             VALUES ({id_value}0, {NOW_EXPR}, {NOW_EXPR}, ?{}) \
+             ON DUPLICATE KEY UPDATE id = LAST_INSERT_ID(id), version = version + 1, \
+             update_time = {NOW_EXPR}, data = VALUES(data){}{}",
+            table,
+            compressed_column::<DATA>(),
+            compressed_value::<DATA, MySql>(2),
+            if has_compressed_column::<DATA>() {
+                ", data_compressed = VALUES(data_compressed)"
+            } else {
+                ""
+            },
+            if DATA::SOFT_DELETE {
+                ", delete_time = NULL"
+            } else {
+                ""
+            },
+        );
+
+        let data_encoded = CodecOf::<DATA>::encode(data);
+        let key_filter = unique_key_filter::<DATA>(&data_encoded)?;
+        // The entry with the same key, including the soft deleted ones, that is expected
+        // to be updated
+        let existing = match &key_filter {
+            Some(filter) => {
+                copy_to_history::<DATA>(tx, table, filter, "").await?;
+                let mut sql =
+                    SqlBuilder::<MySql>::new(format!("SELECT id, version FROM {} WHERE ", table));
+                filter.to_sql(&mut sql);
+                sql.push(" FOR UPDATE");
+                let (query, values) = sql.build();
+                sqlx::query_with(sqlx::AssertSqlSafe(query), to_arguments::<MySql>(values)?)
+                    .fetch_optional(&mut *tx)
+                    .await?
+                    .map(|row| {
+                        Ok::<_, sqlx::Error>((
+                            row.try_get::<DbId<DATA::ID>, _>(0)?.0,
+                            row.try_get::<i64, _>(1)?,
+                        ))
+                    })
+                    .transpose()?
+            }
+            None => None,
+        };
+
+        let stored = Stored::<DATA>::new(&data_encoded)?;
+        let mut upsert = sqlx::query(sqlx::AssertSqlSafe(query));
+        if let Some(id) = &id {
+            upsert = upsert.bind(DbId(id.clone()));
+        }
+        upsert = upsert.bind(&stored);
+        if has_compressed_column::<DATA>() {
+            upsert = upsert.bind(stored.compressed());
+        }
+        let done = upsert.execute(&mut *tx).await?;
+
+        // A conflict over another unique index, e.g. one created by hand, updates a row
+        // other than the one with the same key: 1 row is affected by an insert, 2 by an
+        // update
+        let record = match existing {
+            None if done.rows_affected() == 1 => {
+                let id = match id {
+                    Some(id) => id,
+                    None => last_insert_id::<DATA>(&done)?,
+                };
+                Some(<Record<DATA> as TableOps<MySql, DATA>>::fetch_one_by_id(tx, table, id).await?)
+            }
+            Some((id, version)) if done.rows_affected() == 2 => {
+                <Record<DATA> as TableOps<MySql, DATA>>::fetch_one_optional_by_id(tx, table, id)
+                    .await?
+                    .filter(|record| record.version == version + 1)
+            }
+            _ => None,
+        };
+        record.ok_or_else(|| C3p0Error::Other {
+            cause: format!(
+                "The upsert by key of table [{}] conflicted on a unique index other than the \
+                 UNIQUE_KEY",
+                table.declared()
+            ),
+        })
+    }
+
+    async fn save_all(
+        records: Vec<Self>,
+        tx: &mut MySqlConnection,
+        table: &TableName,
+    ) -> Result<Vec<Record<DATA>>, C3p0Error> {
+        let records = records
+            .into_iter()
+            .map(|record| {
+                let (id, data) = record.data.into_parts();
+                Ok((new_id::<DATA>(table, id)?, CodecOf::<DATA>::encode(data)))
+            })
+            .collect::<Result<Vec<_>, C3p0Error>>()?;
+        let stored = records
+            .iter()
+            .map(|(_, data)| Stored::<DATA>::new(data))
+            .collect::<Result<Vec<_>, C3p0Error>>()?;
+
+        let (id_column, id_value) = if DATA::ID::AUTO_INCREMENT {
+            ("", "")
+        } else {
+            ("id, ", "?, ")
+        };
+        let mut saved: Vec<(DATA::ID, DateTime<Utc>)> = Vec::with_capacity(records.len());
+        for chunk in insert_chunks(&stored) {
+            let (records, stored) = (&records[chunk.clone()], &stored[chunk]);
+            let values = vec![
+                format!(
+                    "({id_value}0, {NOW_EXPR}, {NOW_EXPR}, ?{})",
+                    compressed_value::<DATA, MySql>(3)
+                );
+                records.len()
+            ]
+            .join(", ");
+            let query = format!(
+                "INSERT INTO {} ({id_column}version, create_time, update_time, data{}) \
+                 VALUES {values}",
+                table,
+                compressed_column::<DATA>(),
+            );
+
+            let mut insert = sqlx::query(sqlx::AssertSqlSafe(query));
+            for ((id, _), stored) in records.iter().zip(stored) {
+                if let Some(id) = id {
+                    insert = insert.bind(DbId(id.clone()));
+                }
+                insert = insert.bind(stored);
+                if has_compressed_column::<DATA>() {
+                    insert = insert.bind(stored.compressed());
+                }
+            }
+            let done = insert.execute(&mut *tx).await?;
+
+            let rows = if DATA::ID::AUTO_INCREMENT {
+                generated_rows::<DATA>(tx, table, &done).await?
+            } else {
+                let select_ids = format!(
+                    "SELECT id, create_time FROM {} WHERE id IN ({})",
+                    table,
+                    vec!["?"; records.len()].join(", ")
+                );
+                let mut select = sqlx::query(sqlx::AssertSqlSafe(select_ids));
+                for id in records.iter().flat_map(|(id, _)| id) {
+                    select = select.bind(DbId(id.clone()));
+                }
+                select
+                    .fetch_all(&mut *tx)
+                    .await?
+                    .into_iter()
+                    .map(|row| Ok((row.try_get::<DbId<DATA::ID>, _>(0)?.0, row.try_get(1)?)))
+                    .collect::<Result<Vec<(DATA::ID, DateTime<Utc>)>, sqlx::Error>>()?
+            };
+            saved.extend(returned_in_order::<DATA, _>(records, rows)?);
+        }
+
+        Ok(records
+            .into_iter()
+            .map(|(_, data)| data)
+            .zip(saved)
+            .map(|(data, (id, create_time))| Record {
+                id,
+                version: 0,
+                data: CodecOf::<DATA>::decode(data),
+                create_time,
+                update_time: create_time,
+            })
+            .collect())
+    }
+}
+
+/// The maximum size of the documents bound to a multi-row insert, well below the default
+/// `max_allowed_packet` of MySQL (64 MiB) and MariaDB (16 MiB).
+const MAX_INSERT_BYTES: usize = 4 * 1024 * 1024;
+
+/// Returns the ranges of the `stored` documents of a multi-row insert split in chunks that
+/// fit both the bind parameters limit and [`MAX_INSERT_BYTES`]; a larger document is
+/// inserted alone.
+fn insert_chunks<DATA: DataType>(stored: &[Stored<DATA>]) -> Vec<Range<usize>> {
+    let params =
+        1 + usize::from(!DATA::ID::AUTO_INCREMENT) + usize::from(has_compressed_column::<DATA>());
+    let max_rows = <MySql as SqlDialect>::MAX_BIND_PARAMS / params;
+    let mut chunks = Vec::new();
+    let (mut start, mut bytes) = (0, 0);
+    for (index, stored) in stored.iter().enumerate() {
+        let size = stored.size();
+        if index > start && (index - start == max_rows || bytes + size > MAX_INSERT_BYTES) {
+            chunks.push(start..index);
+            (start, bytes) = (index, 0);
+        }
+        bytes += size;
+    }
+    if start < stored.len() {
+        chunks.push(start..stored.len());
+    }
+    chunks
+}
+
+/// Returns the `(id, create_time)` rows generated by the multi-row insert of an
+/// AUTO_INCREMENT table.
+///
+/// The ids of the rows of a "simple insert" are drawn from `LAST_INSERT_ID()`, the id of
+/// the first row, in steps of `@@auto_increment_increment`; the rows are read back to
+/// check it, as the ids could be interleaved with the ones of other statements, e.g. on
+/// TiDB.
+async fn generated_rows<DATA: DataType>(
+    tx: &mut MySqlConnection,
+    table: &TableName,
+    done: &MySqlQueryResult,
+) -> Result<Vec<(DATA::ID, DateTime<Utc>)>, C3p0Error> {
+    let count = done.rows_affected();
+    // sqlx-mysql's `last_insert_id` is u64; AUTO_INCREMENT values are always positive
+    let first = done.last_insert_id() as i64;
+    let query = format!(
+        "SELECT id, create_time FROM {} \
+         WHERE id BETWEEN ? AND ? + (? - 1) * @@auto_increment_increment ORDER BY id",
+        table
+    );
+    let rows = sqlx::query(sqlx::AssertSqlSafe(query))
+        .bind(first)
+        .bind(first)
+        .bind(count as i64)
+        .fetch_all(tx)
+        .await?
+        .into_iter()
+        .map(|row| Ok((row.try_get::<DbId<DATA::ID>, _>(0)?.0, row.try_get(1)?)))
+        .collect::<Result<Vec<(DATA::ID, DateTime<Utc>)>, sqlx::Error>>()?;
+    if rows.len() as u64 != count {
+        return Err(C3p0Error::Other {
+            cause: format!(
+                "The ids generated for the {count} entries inserted in table [{}] are not \
+                 consecutive",
+                table.declared()
+            ),
+        });
+    }
+    Ok(rows)
+}
+
+/// Returns the id generated by the database for the row inserted by the query.
+fn last_insert_id<DATA: DataType>(done: &MySqlQueryResult) -> Result<DATA::ID, C3p0Error> {
+    // sqlx-mysql's `last_insert_id` is u64; the column is signed BIGINT, and
+    // AUTO_INCREMENT values are always positive, so the conversion is safe.
+    DATA::ID::from_sql(SqlValue::Int(done.last_insert_id() as i64))
+}
+
+/// Returns the `data` of the entry with the given id as it is stored, with its encrypted
+/// values.
+#[cfg(feature = "encryption")]
+async fn stored_data<DATA: DataType>(
+    tx: &mut MySqlConnection,
+    table: &TableName,
+    id: &DATA::ID,
+) -> Result<Option<serde_json::Value>, C3p0Error> {
+    let query = format!(
+        "SELECT data{} FROM {} WHERE id = ?",
+        compressed_column::<DATA>(),
+        table
+    );
+    Ok(sqlx::query(sqlx::AssertSqlSafe(query))
+        .bind(DbId(id.clone()))
+        .fetch_optional(tx)
+        .await?
+        .map(|row| data_from_row::<_, DATA, serde_json::Value>(&row, 0))
+        .transpose()?)
+}
+
+/// Returns an error if the document of the entry with the given id is compressed, as it
+/// cannot be patched.
+async fn check_not_compressed<DATA: DataType>(
+    tx: &mut MySqlConnection,
+    table: &TableName,
+    id: &DATA::ID,
+) -> Result<(), C3p0Error> {
+    if !has_compressed_column::<DATA>() {
+        return Ok(());
+    }
+    let query = format!(
+        "SELECT EXISTS (SELECT 1 FROM {} WHERE id = ? AND data_compressed IS NOT NULL)",
+        table
+    );
+    let compressed: bool = sqlx::query_scalar(sqlx::AssertSqlSafe(query))
+        .bind(DbId(id.clone()))
+        .fetch_one(tx)
+        .await?;
+    if compressed {
+        return Err(C3p0Error::Other {
+            cause: format!(
+                "Cannot patch data in table [{}] with id [{:?}]: the document is compressed",
+                table.declared(),
+                id
+            ),
+        });
+    }
+    Ok(())
+}
+
+/// Fetches the entries matching the optional `filter`, sorted by the `sort` keys.
+async fn fetch_sorted<DATA: DataType, ROW>(
+    tx: &mut MySqlConnection,
+    table: &TableName,
+    filter: Option<&Filter>,
+    sort: &[SortKey],
+    offset: u64,
+    limit: Option<u64>,
+) -> Result<Vec<ROW>, C3p0Error>
+where
+    ROW: for<'r> FromRow<'r, MySqlRow> + Send + Unpin,
+{
+    let condition = plain_json_condition::<DATA>(table, filter, sort)?;
+    fetch_select(
+        tx,
+        select_query::<DATA>(table),
+        filter,
+        condition,
+        sort,
+        offset,
+        limit,
+    )
+    .await
+}
+
+/// Returns the rows of the `select` query matching the `filter` and the additional
+/// `condition`, ordered by the `sort` keys, skipping the first `offset` rows and returning
+/// at most `limit` rows.
+async fn fetch_select<ROW>(
+    tx: &mut MySqlConnection,
+    select: String,
+    filter: Option<&Filter>,
+    condition: Option<&str>,
+    sort: &[SortKey],
+    offset: u64,
+    limit: Option<u64>,
+) -> Result<Vec<ROW>, C3p0Error>
+where
+    ROW: for<'r> FromRow<'r, MySqlRow> + Send + Unpin,
+{
+    let mut sql = SqlBuilder::<MySql>::new(select);
+    match (filter, condition) {
+        (Some(filter), Some(condition)) => {
+            sql.push("WHERE ");
+            filter.to_sql(&mut sql);
+            sql.push(" AND ").push(condition);
+        }
+        (Some(filter), None) => {
+            sql.push("WHERE ");
+            filter.to_sql(&mut sql);
+        }
+        (None, Some(condition)) => {
+            sql.push("WHERE ").push(condition);
+        }
+        (None, None) => {}
+    }
+    sql.push_order_by(sort).push_limit_offset(offset, limit);
+
+    let (query, values) = sql.build();
+    Ok(
+        sqlx::query_as_with(sqlx::AssertSqlSafe(query), to_arguments::<MySql>(values)?)
+            .fetch_all(tx)
+            .await?,
+    )
+}
+
+/// Copies the current state of the entries matching the `filter` and the additional
+/// `condition` into the history table, for the [`DataType::HISTORY`] types. The entries
+/// whose version is already in the history are skipped.
+async fn copy_to_history<DATA: DataType>(
+    tx: &mut MySqlConnection,
+    table: &TableName,
+    filter: &Filter,
+    condition: &str,
+) -> Result<(), C3p0Error> {
+    if !DATA::HISTORY {
+        return Ok(());
+    }
+
+    let columns = history_columns::<DATA>();
+    let mut sql = SqlBuilder::<MySql>::new(format!(
+        "INSERT IGNORE INTO {} ({columns}, history_time) \
+         SELECT {columns}, {NOW_EXPR} FROM {} WHERE ",
+        table.history(),
+        table,
+    ));
+    filter.to_sql(&mut sql);
+    sql.push(condition);
+
+    let (query, values) = sql.build();
+    sqlx::query_with(sqlx::AssertSqlSafe(query), to_arguments::<MySql>(values)?)
+        .execute(tx)
+        .await?;
+    Ok(())
+}
+
+/// Returns the beginning of the statement deleting entries, to be followed by a `WHERE`
+/// clause: a `DELETE` or, for the [`DataType::SOFT_DELETE`] types, an `UPDATE` marking
+/// them as deleted.
+fn delete_query<DATA: DataType>(table: &TableName) -> String {
+    if DATA::SOFT_DELETE {
+        format!(
+            "UPDATE {} SET version = version + 1, update_time = {NOW_EXPR}, delete_time = {NOW_EXPR}",
+            table
+        )
+    } else {
+        format!("DELETE FROM {}", table)
+    }
 }
@@ -1,28 +1,37 @@
+use chrono::{DateTime, Utc};
+use futures_core::Stream;
+use futures_util::{StreamExt, future, stream};
+use serde::de::DeserializeOwned;
 use sqlx::{MySql, MySqlConnection};
 
-use crate::{C3p0Error, DataType, DbOps, DbSave, NewRecord, Record, Tx, WithData};
+use crate::{
+    C3p0Error, DataId, DataType, NewData, NewRecord, Record, SoftDeleteRecord, Tx, UpdateAllResult,
+    WithData,
+    decode::{DecodeError, LenientRecord, scan_for_decode_errors},
+    filter::Filter,
+    ops::{TableOps, TableSave},
+    page::{Cursor, Page, PageOrder},
+    patch::JsonPatch,
+    projection::{Projected, Projection},
+    schema::TableReport,
+    sql::SortKey,
+    table::TableName,
+    upgrade::{UpgradeProgress, upgrade_all, upgrade_on_read},
+};
 
 impl Tx for MySqlConnection {
     type DB = MySql;
 
+    fn parse_table_name(table_name: &str) -> Result<TableName, C3p0Error> {
+        TableName::parse::<MySql>(table_name)
+    }
+
     async fn create_table_if_not_exists<DATA: WithData>(&mut self) -> Result<(), C3p0Error> {
-        let query = format!(
-            r#"
-                CREATE TABLE IF NOT EXISTS {} (
-                    id BIGINT primary key NOT NULL AUTO_INCREMENT,
-                    version BIGINT not null,
-                    create_time TIMESTAMP(3) NOT NULL,
-                    update_time TIMESTAMP(3) NOT NULL,
-                    data JSON NOT NULL
-                )
-                "#,
-            <DATA::DATA as DataType>::TABLE_NAME,
-        );
-
-        Ok(sqlx::query(sqlx::AssertSqlSafe(query))
-            .execute(self)
-            .await
-            .map(|_| ())?)
+        <Record<DATA::DATA> as TableOps<MySql, DATA::DATA>>::create_table_if_not_exists(
+            self,
+            &TableName::of::<DATA::DATA, MySql>()?,
+        )
+        .await
     }
 
     /// Note: MySQL parses `CASCADE` on `DROP TABLE` for compatibility but does not
@@ -32,30 +41,40 @@ impl Tx for MySqlConnection {
         &mut self,
         cascade: bool,
     ) -> Result<(), C3p0Error> {
-        let query = if cascade {
-            format!(
-                "DROP TABLE IF EXISTS {} CASCADE",
-                <DATA::DATA as DataType>::TABLE_NAME
-            )
-        } else {
-            format!(
-                "DROP TABLE IF EXISTS {}",
-                <DATA::DATA as DataType>::TABLE_NAME
-            )
-        };
+        <Record<DATA::DATA> as TableOps<MySql, DATA::DATA>>::drop_table_if_exists(
+            self,
+            &TableName::of::<DATA::DATA, MySql>()?,
+            cascade,
+        )
+        .await
+    }
 
-        Ok(sqlx::query(sqlx::AssertSqlSafe(query))
-            .execute(self)
-            .await
-            .map(|_| ())?)
+    async fn verify_table<DATA: WithData>(&mut self) -> Result<TableReport, C3p0Error> {
+        <Record<DATA::DATA> as TableOps<MySql, DATA::DATA>>::verify_table(
+            self,
+            &TableName::of::<DATA::DATA, MySql>()?,
+        )
+        .await
     }
 
     async fn count_all<DATA: WithData>(&mut self) -> Result<u64, C3p0Error> {
-        <Record<DATA::DATA> as DbOps<MySql, DATA::DATA>>::count_all(self).await
+        <Record<DATA::DATA> as TableOps<MySql, DATA::DATA>>::count_all(
+            self,
+            &TableName::of::<DATA::DATA, MySql>()?,
+        )
+        .await
     }
 
-    async fn exists_by_id<DATA: WithData>(&mut self, id: i64) -> Result<bool, C3p0Error> {
-        <Record<DATA::DATA> as DbOps<MySql, DATA::DATA>>::exists_by_id(self, id).await
+    async fn exists_by_id<DATA: WithData>(
+        &mut self,
+        id: <DATA::DATA as DataId>::ID,
+    ) -> Result<bool, C3p0Error> {
+        <Record<DATA::DATA> as TableOps<MySql, DATA::DATA>>::exists_by_id(
+            self,
+            &TableName::of::<DATA::DATA, MySql>()?,
+            id,
+        )
+        .await
     }
 
     async fn fetch_all<DATA: WithData>(
@@ -63,49 +82,432 @@ impl Tx for MySqlConnection {
         offset: u64,
         limit: Option<u64>,
     ) -> Result<Vec<Record<DATA::DATA>>, C3p0Error> {
-        <Record<DATA::DATA> as DbOps<MySql, DATA::DATA>>::fetch_all(self, offset, limit).await
+        let table = TableName::of::<DATA::DATA, MySql>()?;
+        let records = <Record<DATA::DATA> as TableOps<MySql, DATA::DATA>>::fetch_all(
+            self, &table, offset, limit,
+        )
+        .await?;
+        upgrade_on_read::<MySql, DATA::DATA>(self, &table, records).await
+    }
+
+    async fn fetch_all_sorted<DATA: WithData>(
+        &mut self,
+        sort: &[SortKey],
+        offset: u64,
+        limit: Option<u64>,
+    ) -> Result<Vec<Record<DATA::DATA>>, C3p0Error> {
+        let table = TableName::of::<DATA::DATA, MySql>()?;
+        let records = <Record<DATA::DATA> as TableOps<MySql, DATA::DATA>>::fetch_all_sorted(
+            self, &table, sort, offset, limit,
+        )
+        .await?;
+        upgrade_on_read::<MySql, DATA::DATA>(self, &table, records).await
+    }
+
+    fn fetch_all_stream<'a, DATA: WithData>(
+        &'a mut self,
+    ) -> impl Stream<Item = Result<Record<DATA::DATA>, C3p0Error>> + 'a
+    where
+        DATA::DATA: 'a,
+    {
+        match TableName::of::<DATA::DATA, MySql>() {
+            Ok(table) => {
+                <Record<DATA::DATA> as TableOps<MySql, DATA::DATA>>::fetch_all_stream(self, &table)
+                    .left_stream()
+            }
+            Err(err) => stream::once(future::ready(Err(err))).right_stream(),
+        }
+    }
+
+    async fn fetch_where<DATA: WithData>(
+        &mut self,
+        filter: &Filter,
+        offset: u64,
+        limit: Option<u64>,
+    ) -> Result<Vec<Record<DATA::DATA>>, C3p0Error> {
+        let table = TableName::of::<DATA::DATA, MySql>()?;
+        let records = <Record<DATA::DATA> as TableOps<MySql, DATA::DATA>>::fetch_where(
+            self, &table, filter, offset, limit,
+        )
+        .await?;
+        upgrade_on_read::<MySql, DATA::DATA>(self, &table, records).await
+    }
+
+    async fn fetch_where_sorted<DATA: WithData>(
+        &mut self,
+        filter: &Filter,
+        sort: &[SortKey],
+        offset: u64,
+        limit: Option<u64>,
+    ) -> Result<Vec<Record<DATA::DATA>>, C3p0Error> {
+        let table = TableName::of::<DATA::DATA, MySql>()?;
+        let records = <Record<DATA::DATA> as TableOps<MySql, DATA::DATA>>::fetch_where_sorted(
+            self, &table, filter, sort, offset, limit,
+        )
+        .await?;
+        upgrade_on_read::<MySql, DATA::DATA>(self, &table, records).await
+    }
+
+    async fn fetch_projected<DATA: WithData, P: DeserializeOwned + Send + Unpin>(
+        &mut self,
+        projection: &Projection,
+        filter: &Filter,
+        sort: &[SortKey],
+        offset: u64,
+        limit: Option<u64>,
+    ) -> Result<Vec<Projected<DATA::DATA, P>>, C3p0Error> {
+        <Record<DATA::DATA> as TableOps<MySql, DATA::DATA>>::fetch_projected(
+            self,
+            &TableName::of::<DATA::DATA, MySql>()?,
+            projection,
+            filter,
+            sort,
+            offset,
+            limit,
+        )
+        .await
+    }
+
+    async fn fetch_all_lenient<DATA: WithData>(
+        &mut self,
+        offset: u64,
+        limit: Option<u64>,
+    ) -> Result<Vec<LenientRecord<DATA::DATA>>, C3p0Error> {
+        <Record<DATA::DATA> as TableOps<MySql, DATA::DATA>>::fetch_lenient(
+            self,
+            &TableName::of::<DATA::DATA, MySql>()?,
+            None,
+            offset,
+            limit,
+        )
+        .await
+    }
+
+    fn fetch_where_stream<'a, DATA: WithData>(
+        &'a mut self,
+        filter: &Filter,
+    ) -> impl Stream<Item = Result<Record<DATA::DATA>, C3p0Error>> + 'a
+    where
+        DATA::DATA: 'a,
+    {
+        match TableName::of::<DATA::DATA, MySql>() {
+            Ok(table) => <Record<DATA::DATA> as TableOps<MySql, DATA::DATA>>::fetch_where_stream(
+                self, &table, filter,
+            )
+            .left_stream(),
+            Err(err) => stream::once(future::ready(Err(err))).right_stream(),
+        }
+    }
+
+    async fn count_where<DATA: WithData>(&mut self, filter: &Filter) -> Result<u64, C3p0Error> {
+        <Record<DATA::DATA> as TableOps<MySql, DATA::DATA>>::count_where(
+            self,
+            &TableName::of::<DATA::DATA, MySql>()?,
+            filter,
+        )
+        .await
+    }
+
+    async fn fetch_page<DATA: WithData>(
+        &mut self,
+        order: PageOrder,
+        cursor: Option<&Cursor>,
+        limit: u64,
+    ) -> Result<Page<DATA::DATA>, C3p0Error> {
+        let table = TableName::of::<DATA::DATA, MySql>()?;
+        let mut page = <Record<DATA::DATA> as TableOps<MySql, DATA::DATA>>::fetch_page(
+            self, &table, None, order, cursor, limit,
+        )
+        .await?;
+        page.records = upgrade_on_read::<MySql, DATA::DATA>(self, &table, page.records).await?;
+        Ok(page)
+    }
+
+    async fn fetch_page_where<DATA: WithData>(
+        &mut self,
+        filter: &Filter,
+        order: PageOrder,
+        cursor: Option<&Cursor>,
+        limit: u64,
+    ) -> Result<Page<DATA::DATA>, C3p0Error> {
+        let table = TableName::of::<DATA::DATA, MySql>()?;
+        let mut page = <Record<DATA::DATA> as TableOps<MySql, DATA::DATA>>::fetch_page(
+            self,
+            &table,
+            Some(filter),
+            order,
+            cursor,
+            limit,
+        )
+        .await?;
+        page.records = upgrade_on_read::<MySql, DATA::DATA>(self, &table, page.records).await?;
+        Ok(page)
     }
 
     async fn fetch_one_optional_by_id<DATA: WithData>(
         &mut self,
-        id: i64,
+        id: <DATA::DATA as DataId>::ID,
     ) -> Result<Option<Record<DATA::DATA>>, C3p0Error> {
-        <Record<DATA::DATA> as DbOps<MySql, DATA::DATA>>::fetch_one_optional_by_id(self, id).await
+        let table = TableName::of::<DATA::DATA, MySql>()?;
+        let record = <Record<DATA::DATA> as TableOps<MySql, DATA::DATA>>::fetch_one_optional_by_id(
+            self, &table, id,
+        )
+        .await?;
+        Ok(
+            upgrade_on_read::<MySql, DATA::DATA>(self, &table, record.into_iter().collect())
+                .await?
+                .pop(),
+        )
     }
 
     async fn fetch_one_by_id<DATA: WithData>(
         &mut self,
-        id: i64,
+        id: <DATA::DATA as DataId>::ID,
     ) -> Result<Record<DATA::DATA>, C3p0Error> {
-        <Record<DATA::DATA> as DbOps<MySql, DATA::DATA>>::fetch_one_by_id(self, id).await
+        let table = TableName::of::<DATA::DATA, MySql>()?;
+        let record =
+            <Record<DATA::DATA> as TableOps<MySql, DATA::DATA>>::fetch_one_by_id(self, &table, id)
+                .await?;
+        Ok(
+            upgrade_on_read::<MySql, DATA::DATA>(self, &table, vec![record])
+                .await?
+                .remove(0),
+        )
     }
 
     async fn delete<DATA: DataType>(
         &mut self,
         record: Record<DATA>,
     ) -> Result<Record<DATA>, C3p0Error> {
-        <Record<DATA> as DbOps<MySql, DATA>>::delete(record, self).await
+        <Record<DATA> as TableOps<MySql, DATA>>::delete(
+            record,
+            self,
+            &TableName::of::<DATA, MySql>()?,
+        )
+        .await
     }
 
     async fn delete_all<DATA: WithData>(&mut self) -> Result<u64, C3p0Error> {
-        <Record<DATA::DATA> as DbOps<MySql, DATA::DATA>>::delete_all(self).await
+        <Record<DATA::DATA> as TableOps<MySql, DATA::DATA>>::delete_all(
+            self,
+            &TableName::of::<DATA::DATA, MySql>()?,
+        )
+        .await
+    }
+
+    async fn delete_by_id<DATA: WithData>(
+        &mut self,
+        id: <DATA::DATA as DataId>::ID,
+    ) -> Result<u64, C3p0Error> {
+        <Record<DATA::DATA> as TableOps<MySql, DATA::DATA>>::delete_by_id(
+            self,
+            &TableName::of::<DATA::DATA, MySql>()?,
+            id,
+        )
+        .await
     }
 
-    async fn delete_by_id<DATA: WithData>(&mut self, id: i64) -> Result<u64, C3p0Error> {
-        <Record<DATA::DATA> as DbOps<MySql, DATA::DATA>>::delete_by_id(self, id).await
+    async fn delete_where<DATA: WithData>(&mut self, filter: &Filter) -> Result<u64, C3p0Error> {
+        <Record<DATA::DATA> as TableOps<MySql, DATA::DATA>>::delete_where(
+            self,
+            &TableName::of::<DATA::DATA, MySql>()?,
+            filter,
+        )
+        .await
+    }
+
+    async fn fetch_including_deleted<DATA: WithData>(
+        &mut self,
+        offset: u64,
+        limit: Option<u64>,
+    ) -> Result<Vec<SoftDeleteRecord<DATA::DATA>>, C3p0Error> {
+        <Record<DATA::DATA> as TableOps<MySql, DATA::DATA>>::fetch_including_deleted(
+            self,
+            &TableName::of::<DATA::DATA, MySql>()?,
+            offset,
+            limit,
+        )
+        .await
+    }
+
+    async fn restore<DATA: WithData>(
+        &mut self,
+        id: <DATA::DATA as DataId>::ID,
+    ) -> Result<Record<DATA::DATA>, C3p0Error> {
+        <Record<DATA::DATA> as TableOps<MySql, DATA::DATA>>::restore(
+            self,
+            &TableName::of::<DATA::DATA, MySql>()?,
+            id,
+        )
+        .await
+    }
+
+    async fn purge<DATA: WithData>(
+        &mut self,
+        deleted_before: Option<DateTime<Utc>>,
+    ) -> Result<u64, C3p0Error> {
+        <Record<DATA::DATA> as TableOps<MySql, DATA::DATA>>::purge(
+            self,
+            &TableName::of::<DATA::DATA, MySql>()?,
+            deleted_before,
+        )
+        .await
     }
 
     async fn update<DATA: DataType>(
         &mut self,
         record: Record<DATA>,
     ) -> Result<Record<DATA>, C3p0Error> {
-        <Record<DATA> as DbOps<MySql, DATA>>::update(record, self).await
+        <Record<DATA> as TableOps<MySql, DATA>>::update(
+            record,
+            self,
+            &TableName::of::<DATA, MySql>()?,
+        )
+        .await
+    }
+
+    async fn patch<DATA: WithData>(
+        &mut self,
+        id: <DATA::DATA as DataId>::ID,
+        version: Option<i64>,
+        patch: &JsonPatch,
+    ) -> Result<Record<DATA::DATA>, C3p0Error> {
+        <Record<DATA::DATA> as TableOps<MySql, DATA::DATA>>::patch(
+            self,
+            &TableName::of::<DATA::DATA, MySql>()?,
+            id,
+            version,
+            patch,
+        )
+        .await
+    }
+
+    async fn update_all<DATA: DataType>(
+        &mut self,
+        records: Vec<Record<DATA>>,
+    ) -> Result<UpdateAllResult<DATA>, C3p0Error> {
+        <Record<DATA> as TableOps<MySql, DATA>>::update_all(
+            records,
+            self,
+            &TableName::of::<DATA, MySql>()?,
+        )
+        .await
+    }
+
+    async fn upgrade_all<DATA: WithData>(
+        &mut self,
+        batch_size: u64,
+        progress: impl FnMut(&UpgradeProgress),
+    ) -> Result<UpgradeProgress, C3p0Error> {
+        upgrade_all::<MySql, DATA::DATA>(
+            self,
+            &TableName::of::<DATA::DATA, MySql>()?,
+            batch_size,
+            progress,
+        )
+        .await
+    }
+
+    #[cfg(feature = "encryption")]
+    async fn reencrypt_all<DATA: WithData>(
+        &mut self,
+        batch_size: u64,
+        progress: impl FnMut(&UpgradeProgress),
+    ) -> Result<UpgradeProgress, C3p0Error> {
+        crate::crypto::reencrypt_all::<MySql, DATA::DATA>(
+            self,
+            &TableName::of::<DATA::DATA, MySql>()?,
+            batch_size,
+            progress,
+        )
+        .await
+    }
+
+    async fn scan_for_decode_errors<DATA: WithData>(
+        &mut self,
+        chunk_size: u64,
+    ) -> Result<Vec<DecodeError<DATA::DATA>>, C3p0Error> {
+        scan_for_decode_errors::<MySql, DATA::DATA>(
+            self,
+            &TableName::of::<DATA::DATA, MySql>()?,
+            chunk_size,
+        )
+        .await
     }
 
-    async fn save<DATA: DataType>(
+    async fn fetch_history<DATA: WithData>(
+        &mut self,
+        id: <DATA::DATA as DataId>::ID,
+    ) -> Result<Vec<Record<DATA::DATA>>, C3p0Error> {
+        <Record<DATA::DATA> as TableOps<MySql, DATA::DATA>>::fetch_history(
+            self,
+            &TableName::of::<DATA::DATA, MySql>()?,
+            id,
+        )
+        .await
+    }
+
+    async fn fetch_version<DATA: WithData>(
+        &mut self,
+        id: <DATA::DATA as DataId>::ID,
+        version: i64,
+    ) -> Result<Option<Record<DATA::DATA>>, C3p0Error> {
+        <Record<DATA::DATA> as TableOps<MySql, DATA::DATA>>::fetch_version(
+            self,
+            &TableName::of::<DATA::DATA, MySql>()?,
+            id,
+            version,
+        )
+        .await
+    }
+
+    async fn fetch_as_of<DATA: WithData>(
+        &mut self,
+        id: <DATA::DATA as DataId>::ID,
+        timestamp: DateTime<Utc>,
+    ) -> Result<Option<Record<DATA::DATA>>, C3p0Error> {
+        <Record<DATA::DATA> as TableOps<MySql, DATA::DATA>>::fetch_as_of(
+            self,
+            &TableName::of::<DATA::DATA, MySql>()?,
+            id,
+            timestamp,
+        )
+        .await
+    }
+
+    async fn save<DATA: NewData>(
         &mut self,
         record: NewRecord<DATA>,
-    ) -> Result<Record<DATA>, C3p0Error> {
-        <NewRecord<DATA> as DbSave<MySql, DATA>>::save(record, self).await
+    ) -> Result<Record<DATA::DATA>, C3p0Error> {
+        <NewRecord<DATA> as TableSave<MySql, DATA::DATA>>::save(
+            record,
+            self,
+            &TableName::of::<DATA::DATA, MySql>()?,
+        )
+        .await
+    }
+
+    async fn upsert_by_key<DATA: NewData>(
+        &mut self,
+        record: NewRecord<DATA>,
+    ) -> Result<Record<DATA::DATA>, C3p0Error> {
+        <NewRecord<DATA> as TableSave<MySql, DATA::DATA>>::upsert_by_key(
+            record,
+            self,
+            &TableName::of::<DATA::DATA, MySql>()?,
+        )
+        .await
+    }
+
+    async fn save_all<DATA: NewData>(
+        &mut self,
+        records: Vec<NewRecord<DATA>>,
+    ) -> Result<Vec<Record<DATA::DATA>>, C3p0Error> {
+        <NewRecord<DATA> as TableSave<MySql, DATA::DATA>>::save_all(
+            records,
+            self,
+            &TableName::of::<DATA::DATA, MySql>()?,
+        )
+        .await
     }
 }
@@ -0,0 +1,621 @@
+//! The operations of [`DbOps`](crate::DbOps) and [`DbSave`](crate::DbSave) on a given
+//! table, implemented for [`Record`] by each backend.
+//!
+//! The public traits run them on the [`DataType::TABLE_NAME`](crate::DataType::TABLE_NAME)
+//! table, a [`Table`](crate::Table) on its own one.
+
+use chrono::{DateTime, Utc};
+use futures_core::Stream;
+use serde::de::DeserializeOwned;
+use sqlx::{Database, query::QueryAs};
+
+use crate::{
+    C3p0Error, DataId, DataType, Record, SoftDeleteRecord, UpdateAllResult, WithData,
+    decode::LenientRecord,
+    filter::Filter,
+    page::{Cursor, Page, PageOrder},
+    patch::JsonPatch,
+    projection::{Projected, Projection},
+    schema::TableReport,
+    sql::SortKey,
+    table::TableName,
+};
+
+/// See [`DbOps`](crate::DbOps).
+pub trait TableOps<DB: Database, WITH: WithData> {
+    fn query_with_tail<'q>(
+        table: &TableName,
+        tail: &'q str,
+    ) -> QueryAs<'q, DB, Record<WITH::DATA>, <DB as Database>::Arguments>;
+
+    fn create_table_if_not_exists(
+        tx: &mut DB::Connection,
+        table: &TableName,
+    ) -> impl Future<Output = Result<(), C3p0Error>>;
+
+    fn drop_table_if_exists(
+        tx: &mut DB::Connection,
+        table: &TableName,
+        cascade: bool,
+    ) -> impl Future<Output = Result<(), C3p0Error>>;
+
+    fn verify_table(
+        tx: &mut DB::Connection,
+        table: &TableName,
+    ) -> impl Future<Output = Result<TableReport, C3p0Error>>;
+
+    fn count_all(
+        tx: &mut DB::Connection,
+        table: &TableName,
+    ) -> impl Future<Output = Result<u64, C3p0Error>>;
+
+    fn exists_by_id(
+        tx: &mut DB::Connection,
+        table: &TableName,
+        id: <WITH::DATA as DataId>::ID,
+    ) -> impl Future<Output = Result<bool, C3p0Error>>;
+
+    fn fetch_all(
+        tx: &mut DB::Connection,
+        table: &TableName,
+        offset: u64,
+        limit: Option<u64>,
+    ) -> impl Future<Output = Result<Vec<Record<WITH::DATA>>, C3p0Error>>;
+
+    fn fetch_all_sorted(
+        tx: &mut DB::Connection,
+        table: &TableName,
+        sort: &[SortKey],
+        offset: u64,
+        limit: Option<u64>,
+    ) -> impl Future<Output = Result<Vec<Record<WITH::DATA>>, C3p0Error>>;
+
+    fn fetch_all_stream<'a>(
+        tx: &'a mut DB::Connection,
+        table: &TableName,
+    ) -> impl Stream<Item = Result<Record<WITH::DATA>, C3p0Error>> + use<'a, DB, WITH, Self>
+    where
+        WITH::DATA: 'a;
+
+    fn fetch_where(
+        tx: &mut DB::Connection,
+        table: &TableName,
+        filter: &Filter,
+        offset: u64,
+        limit: Option<u64>,
+    ) -> impl Future<Output = Result<Vec<Record<WITH::DATA>>, C3p0Error>>;
+
+    fn fetch_lenient(
+        tx: &mut DB::Connection,
+        table: &TableName,
+        filter: Option<&Filter>,
+        offset: u64,
+        limit: Option<u64>,
+    ) -> impl Future<Output = Result<Vec<LenientRecord<WITH::DATA>>, C3p0Error>>;
+
+    fn fetch_projected<P: DeserializeOwned + Send + Unpin>(
+        tx: &mut DB::Connection,
+        table: &TableName,
+        projection: &Projection,
+        filter: &Filter,
+        sort: &[SortKey],
+        offset: u64,
+        limit: Option<u64>,
+    ) -> impl Future<Output = Result<Vec<Projected<WITH::DATA, P>>, C3p0Error>>;
+
+    fn fetch_where_sorted(
+        tx: &mut DB::Connection,
+        table: &TableName,
+        filter: &Filter,
+        sort: &[SortKey],
+        offset: u64,
+        limit: Option<u64>,
+    ) -> impl Future<Output = Result<Vec<Record<WITH::DATA>>, C3p0Error>>;
+
+    fn fetch_where_stream<'a>(
+        tx: &'a mut DB::Connection,
+        table: &TableName,
+        filter: &Filter,
+    ) -> impl Stream<Item = Result<Record<WITH::DATA>, C3p0Error>> + use<'a, DB, WITH, Self>
+    where
+        WITH::DATA: 'a;
+
+    fn count_where(
+        tx: &mut DB::Connection,
+        table: &TableName,
+        filter: &Filter,
+    ) -> impl Future<Output = Result<u64, C3p0Error>>;
+
+    fn fetch_page(
+        tx: &mut DB::Connection,
+        table: &TableName,
+        filter: Option<&Filter>,
+        order: PageOrder,
+        cursor: Option<&Cursor>,
+        limit: u64,
+    ) -> impl Future<Output = Result<Page<WITH::DATA>, C3p0Error>>;
+
+    fn fetch_one_optional_by_id(
+        tx: &mut DB::Connection,
+        table: &TableName,
+        id: <WITH::DATA as DataId>::ID,
+    ) -> impl Future<Output = Result<Option<Record<WITH::DATA>>, C3p0Error>>;
+
+    fn fetch_one_by_id(
+        tx: &mut DB::Connection,
+        table: &TableName,
+        id: <WITH::DATA as DataId>::ID,
+    ) -> impl Future<Output = Result<Record<WITH::DATA>, C3p0Error>>;
+
+    fn delete(
+        self,
+        tx: &mut DB::Connection,
+        table: &TableName,
+    ) -> impl Future<Output = Result<Record<WITH::DATA>, C3p0Error>>;
+
+    fn delete_all(
+        tx: &mut DB::Connection,
+        table: &TableName,
+    ) -> impl Future<Output = Result<u64, C3p0Error>>;
+
+    fn delete_by_id(
+        tx: &mut DB::Connection,
+        table: &TableName,
+        id: <WITH::DATA as DataId>::ID,
+    ) -> impl Future<Output = Result<u64, C3p0Error>>;
+
+    fn delete_where(
+        tx: &mut DB::Connection,
+        table: &TableName,
+        filter: &Filter,
+    ) -> impl Future<Output = Result<u64, C3p0Error>>;
+
+    fn fetch_including_deleted(
+        tx: &mut DB::Connection,
+        table: &TableName,
+        offset: u64,
+        limit: Option<u64>,
+    ) -> impl Future<Output = Result<Vec<SoftDeleteRecord<WITH::DATA>>, C3p0Error>>;
+
+    fn restore(
+        tx: &mut DB::Connection,
+        table: &TableName,
+        id: <WITH::DATA as DataId>::ID,
+    ) -> impl Future<Output = Result<Record<WITH::DATA>, C3p0Error>>;
+
+    fn purge(
+        tx: &mut DB::Connection,
+        table: &TableName,
+        deleted_before: Option<DateTime<Utc>>,
+    ) -> impl Future<Output = Result<u64, C3p0Error>>;
+
+    fn update(
+        self,
+        tx: &mut DB::Connection,
+        table: &TableName,
+    ) -> impl Future<Output = Result<Record<WITH::DATA>, C3p0Error>>;
+
+    fn patch(
+        tx: &mut DB::Connection,
+        table: &TableName,
+        id: <WITH::DATA as DataId>::ID,
+        version: Option<i64>,
+        patch: &JsonPatch,
+    ) -> impl Future<Output = Result<Record<WITH::DATA>, C3p0Error>>;
+
+    fn update_all(
+        records: Vec<Record<WITH::DATA>>,
+        tx: &mut DB::Connection,
+        table: &TableName,
+    ) -> impl Future<Output = Result<UpdateAllResult<WITH::DATA>, C3p0Error>>;
+
+    fn fetch_history(
+        tx: &mut DB::Connection,
+        table: &TableName,
+        id: <WITH::DATA as DataId>::ID,
+    ) -> impl Future<Output = Result<Vec<Record<WITH::DATA>>, C3p0Error>>;
+
+    fn fetch_version(
+        tx: &mut DB::Connection,
+        table: &TableName,
+        id: <WITH::DATA as DataId>::ID,
+        version: i64,
+    ) -> impl Future<Output = Result<Option<Record<WITH::DATA>>, C3p0Error>>;
+
+    fn fetch_as_of(
+        tx: &mut DB::Connection,
+        table: &TableName,
+        id: <WITH::DATA as DataId>::ID,
+        timestamp: DateTime<Utc>,
+    ) -> impl Future<Output = Result<Option<Record<WITH::DATA>>, C3p0Error>>;
+}
+
+/// See [`DbSave`](crate::DbSave).
+pub trait TableSave<DB: Database, WITH: WithData> {
+    fn save(
+        self,
+        tx: &mut DB::Connection,
+        table: &TableName,
+    ) -> impl Future<Output = Result<Record<WITH::DATA>, C3p0Error>>;
+
+    fn upsert_by_key(
+        self,
+        tx: &mut DB::Connection,
+        table: &TableName,
+    ) -> impl Future<Output = Result<Record<WITH::DATA>, C3p0Error>>;
+
+    fn save_all(
+        records: Vec<Self>,
+        tx: &mut DB::Connection,
+        table: &TableName,
+    ) -> impl Future<Output = Result<Vec<Record<WITH::DATA>>, C3p0Error>>
+    where
+        Self: Sized;
+}
+
+/// The [`TableOps`] used by a [`DynamicTable`](crate::DynamicTable), which are implemented
+/// for the [`Record`]s of every [`DataType`] by each backend.
+pub trait DynamicOps: Database {
+    fn exists_by_id<DATA: DataType>(
+        tx: &mut Self::Connection,
+        table: &TableName,
+        id: DATA::ID,
+    ) -> impl Future<Output = Result<bool, C3p0Error>>;
+
+    fn count_where<DATA: DataType>(
+        tx: &mut Self::Connection,
+        table: &TableName,
+        filter: &Filter,
+    ) -> impl Future<Output = Result<u64, C3p0Error>>;
+
+    fn fetch_where<DATA: DataType>(
+        tx: &mut Self::Connection,
+        table: &TableName,
+        filter: &Filter,
+        offset: u64,
+        limit: Option<u64>,
+    ) -> impl Future<Output = Result<Vec<Record<DATA>>, C3p0Error>>;
+
+    fn fetch_where_sorted<DATA: DataType>(
+        tx: &mut Self::Connection,
+        table: &TableName,
+        filter: &Filter,
+        sort: &[SortKey],
+        offset: u64,
+        limit: Option<u64>,
+    ) -> impl Future<Output = Result<Vec<Record<DATA>>, C3p0Error>>;
+
+    fn fetch_where_stream<'a, DATA: DataType + 'a>(
+        tx: &'a mut Self::Connection,
+        table: &TableName,
+        filter: &Filter,
+    ) -> impl Stream<Item = Result<Record<DATA>, C3p0Error>> + use<'a, DATA, Self>;
+
+    fn fetch_page<DATA: DataType>(
+        tx: &mut Self::Connection,
+        table: &TableName,
+        filter: Option<&Filter>,
+        order: PageOrder,
+        cursor: Option<&Cursor>,
+        limit: u64,
+    ) -> impl Future<Output = Result<Page<DATA>, C3p0Error>>;
+
+    fn fetch_one_optional_by_id<DATA: DataType>(
+        tx: &mut Self::Connection,
+        table: &TableName,
+        id: DATA::ID,
+    ) -> impl Future<Output = Result<Option<Record<DATA>>, C3p0Error>>;
+
+    fn save<DATA: DataType>(
+        tx: &mut Self::Connection,
+        table: &TableName,
+        id: Option<DATA::ID>,
+        data: DATA,
+    ) -> impl Future<Output = Result<Record<DATA>, C3p0Error>>;
+
+    fn update<DATA: DataType>(
+        tx: &mut Self::Connection,
+        table: &TableName,
+        record: Record<DATA>,
+    ) -> impl Future<Output = Result<Record<DATA>, C3p0Error>>;
+
+    fn update_all<DATA: DataType>(
+        tx: &mut Self::Connection,
+        table: &TableName,
+        records: Vec<Record<DATA>>,
+    ) -> impl Future<Output = Result<UpdateAllResult<DATA>, C3p0Error>>;
+
+    fn patch<DATA: DataType>(
+        tx: &mut Self::Connection,
+        table: &TableName,
+        id: DATA::ID,
+        version: Option<i64>,
+        patch: &JsonPatch,
+    ) -> impl Future<Output = Result<Record<DATA>, C3p0Error>>;
+
+    fn delete<DATA: DataType>(
+        tx: &mut Self::Connection,
+        table: &TableName,
+        record: Record<DATA>,
+    ) -> impl Future<Output = Result<Record<DATA>, C3p0Error>>;
+
+    fn delete_all<DATA: DataType>(
+        tx: &mut Self::Connection,
+        table: &TableName,
+    ) -> impl Future<Output = Result<u64, C3p0Error>>;
+
+    fn delete_by_id<DATA: DataType>(
+        tx: &mut Self::Connection,
+        table: &TableName,
+        id: DATA::ID,
+    ) -> impl Future<Output = Result<u64, C3p0Error>>;
+
+    fn delete_where<DATA: DataType>(
+        tx: &mut Self::Connection,
+        table: &TableName,
+        filter: &Filter,
+    ) -> impl Future<Output = Result<u64, C3p0Error>>;
+
+    fn fetch_including_deleted<DATA: DataType>(
+        tx: &mut Self::Connection,
+        table: &TableName,
+        offset: u64,
+        limit: Option<u64>,
+    ) -> impl Future<Output = Result<Vec<SoftDeleteRecord<DATA>>, C3p0Error>>;
+
+    fn restore<DATA: DataType>(
+        tx: &mut Self::Connection,
+        table: &TableName,
+        id: DATA::ID,
+    ) -> impl Future<Output = Result<Record<DATA>, C3p0Error>>;
+
+    fn purge<DATA: DataType>(
+        tx: &mut Self::Connection,
+        table: &TableName,
+        deleted_before: Option<DateTime<Utc>>,
+    ) -> impl Future<Output = Result<u64, C3p0Error>>;
+
+    fn fetch_history<DATA: DataType>(
+        tx: &mut Self::Connection,
+        table: &TableName,
+        id: DATA::ID,
+    ) -> impl Future<Output = Result<Vec<Record<DATA>>, C3p0Error>>;
+
+    fn fetch_version<DATA: DataType>(
+        tx: &mut Self::Connection,
+        table: &TableName,
+        id: DATA::ID,
+        version: i64,
+    ) -> impl Future<Output = Result<Option<Record<DATA>>, C3p0Error>>;
+
+    fn fetch_as_of<DATA: DataType>(
+        tx: &mut Self::Connection,
+        table: &TableName,
+        id: DATA::ID,
+        timestamp: DateTime<Utc>,
+    ) -> impl Future<Output = Result<Option<Record<DATA>>, C3p0Error>>;
+}
+
+/// Implements the [`DynamicOps`] of a backend with its [`TableOps`] and [`TableSave`].
+#[cfg_attr(
+    not(any(feature = "mysql", feature = "postgres", feature = "sqlite")),
+    allow(unused_macros)
+)]
+macro_rules! impl_dynamic_ops {
+    ($db:ty) => {
+        impl DynamicOps for $db {
+            async fn exists_by_id<DATA: DataType>(
+                tx: &mut Self::Connection,
+                table: &TableName,
+                id: DATA::ID,
+            ) -> Result<bool, C3p0Error> {
+                <Record<DATA> as TableOps<Self, DATA>>::exists_by_id(tx, table, id).await
+            }
+
+            async fn count_where<DATA: DataType>(
+                tx: &mut Self::Connection,
+                table: &TableName,
+                filter: &Filter,
+            ) -> Result<u64, C3p0Error> {
+                <Record<DATA> as TableOps<Self, DATA>>::count_where(tx, table, filter).await
+            }
+
+            async fn fetch_where<DATA: DataType>(
+                tx: &mut Self::Connection,
+                table: &TableName,
+                filter: &Filter,
+                offset: u64,
+                limit: Option<u64>,
+            ) -> Result<Vec<Record<DATA>>, C3p0Error> {
+                <Record<DATA> as TableOps<Self, DATA>>::fetch_where(
+                    tx, table, filter, offset, limit,
+                )
+                .await
+            }
+
+            async fn fetch_where_sorted<DATA: DataType>(
+                tx: &mut Self::Connection,
+                table: &TableName,
+                filter: &Filter,
+                sort: &[SortKey],
+                offset: u64,
+                limit: Option<u64>,
+            ) -> Result<Vec<Record<DATA>>, C3p0Error> {
+                <Record<DATA> as TableOps<Self, DATA>>::fetch_where_sorted(
+                    tx, table, filter, sort, offset, limit,
+                )
+                .await
+            }
+
+            fn fetch_where_stream<'a, DATA: DataType + 'a>(
+                tx: &'a mut Self::Connection,
+                table: &TableName,
+                filter: &Filter,
+            ) -> impl Stream<Item = Result<Record<DATA>, C3p0Error>> + use<'a, DATA> {
+                <Record<DATA> as TableOps<Self, DATA>>::fetch_where_stream(tx, table, filter)
+            }
+
+            async fn fetch_page<DATA: DataType>(
+                tx: &mut Self::Connection,
+                table: &TableName,
+                filter: Option<&Filter>,
+                order: PageOrder,
+                cursor: Option<&Cursor>,
+                limit: u64,
+            ) -> Result<Page<DATA>, C3p0Error> {
+                <Record<DATA> as TableOps<Self, DATA>>::fetch_page(
+                    tx, table, filter, order, cursor, limit,
+                )
+                .await
+            }
+
+            async fn fetch_one_optional_by_id<DATA: DataType>(
+                tx: &mut Self::Connection,
+                table: &TableName,
+                id: DATA::ID,
+            ) -> Result<Option<Record<DATA>>, C3p0Error> {
+                <Record<DATA> as TableOps<Self, DATA>>::fetch_one_optional_by_id(tx, table, id)
+                    .await
+            }
+
+            async fn save<DATA: DataType>(
+                tx: &mut Self::Connection,
+                table: &TableName,
+                id: Option<DATA::ID>,
+                data: DATA,
+            ) -> Result<Record<DATA>, C3p0Error> {
+                match id {
+                    Some(id) => {
+                        TableSave::<Self, DATA>::save(
+                            crate::NewRecord::with_id(id, data),
+                            tx,
+                            table,
+                        )
+                        .await
+                    }
+                    None => {
+                        TableSave::<Self, DATA>::save(crate::NewRecord::new(data), tx, table).await
+                    }
+                }
+            }
+
+            async fn update<DATA: DataType>(
+                tx: &mut Self::Connection,
+                table: &TableName,
+                record: Record<DATA>,
+            ) -> Result<Record<DATA>, C3p0Error> {
+                <Record<DATA> as TableOps<Self, DATA>>::update(record, tx, table).await
+            }
+
+            async fn update_all<DATA: DataType>(
+                tx: &mut Self::Connection,
+                table: &TableName,
+                records: Vec<Record<DATA>>,
+            ) -> Result<UpdateAllResult<DATA>, C3p0Error> {
+                <Record<DATA> as TableOps<Self, DATA>>::update_all(records, tx, table).await
+            }
+
+            async fn patch<DATA: DataType>(
+                tx: &mut Self::Connection,
+                table: &TableName,
+                id: DATA::ID,
+                version: Option<i64>,
+                patch: &JsonPatch,
+            ) -> Result<Record<DATA>, C3p0Error> {
+                <Record<DATA> as TableOps<Self, DATA>>::patch(tx, table, id, version, patch).await
+            }
+
+            async fn delete<DATA: DataType>(
+                tx: &mut Self::Connection,
+                table: &TableName,
+                record: Record<DATA>,
+            ) -> Result<Record<DATA>, C3p0Error> {
+                <Record<DATA> as TableOps<Self, DATA>>::delete(record, tx, table).await
+            }
+
+            async fn delete_all<DATA: DataType>(
+                tx: &mut Self::Connection,
+                table: &TableName,
+            ) -> Result<u64, C3p0Error> {
+                <Record<DATA> as TableOps<Self, DATA>>::delete_all(tx, table).await
+            }
+
+            async fn delete_by_id<DATA: DataType>(
+                tx: &mut Self::Connection,
+                table: &TableName,
+                id: DATA::ID,
+            ) -> Result<u64, C3p0Error> {
+                <Record<DATA> as TableOps<Self, DATA>>::delete_by_id(tx, table, id).await
+            }
+
+            async fn delete_where<DATA: DataType>(
+                tx: &mut Self::Connection,
+                table: &TableName,
+                filter: &Filter,
+            ) -> Result<u64, C3p0Error> {
+                <Record<DATA> as TableOps<Self, DATA>>::delete_where(tx, table, filter).await
+            }
+
+            async fn fetch_including_deleted<DATA: DataType>(
+                tx: &mut Self::Connection,
+                table: &TableName,
+                offset: u64,
+                limit: Option<u64>,
+            ) -> Result<Vec<SoftDeleteRecord<DATA>>, C3p0Error> {
+                <Record<DATA> as TableOps<Self, DATA>>::fetch_including_deleted(
+                    tx, table, offset, limit,
+                )
+                .await
+            }
+
+            async fn restore<DATA: DataType>(
+                tx: &mut Self::Connection,
+                table: &TableName,
+                id: DATA::ID,
+            ) -> Result<Record<DATA>, C3p0Error> {
+                <Record<DATA> as TableOps<Self, DATA>>::restore(tx, table, id).await
+            }
+
+            async fn purge<DATA: DataType>(
+                tx: &mut Self::Connection,
+                table: &TableName,
+                deleted_before: Option<DateTime<Utc>>,
+            ) -> Result<u64, C3p0Error> {
+                <Record<DATA> as TableOps<Self, DATA>>::purge(tx, table, deleted_before).await
+            }
+
+            async fn fetch_history<DATA: DataType>(
+                tx: &mut Self::Connection,
+                table: &TableName,
+                id: DATA::ID,
+            ) -> Result<Vec<Record<DATA>>, C3p0Error> {
+                <Record<DATA> as TableOps<Self, DATA>>::fetch_history(tx, table, id).await
+            }
+
+            async fn fetch_version<DATA: DataType>(
+                tx: &mut Self::Connection,
+                table: &TableName,
+                id: DATA::ID,
+                version: i64,
+            ) -> Result<Option<Record<DATA>>, C3p0Error> {
+                <Record<DATA> as TableOps<Self, DATA>>::fetch_version(tx, table, id, version).await
+            }
+
+            async fn fetch_as_of<DATA: DataType>(
+                tx: &mut Self::Connection,
+                table: &TableName,
+                id: DATA::ID,
+                timestamp: DateTime<Utc>,
+            ) -> Result<Option<Record<DATA>>, C3p0Error> {
+                <Record<DATA> as TableOps<Self, DATA>>::fetch_as_of(tx, table, id, timestamp).await
+            }
+        }
+    };
+}
+
+#[cfg(feature = "mysql")]
+impl_dynamic_ops!(sqlx::MySql);
+#[cfg(feature = "postgres")]
+impl_dynamic_ops!(sqlx::Postgres);
+#[cfg(feature = "sqlite")]
+impl_dynamic_ops!(sqlx::Sqlite);
@@ -0,0 +1,232 @@
+//! Keyset (cursor based) pagination.
+//!
+//! Unlike `OFFSET` based pagination, each page starts right after the sort key of the
+//! last entry of the previous page, so fetching a page costs the same regardless of its
+//! position and concurrent inserts never cause duplicated or skipped entries.
+
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    DataType, Record,
+    error::C3p0Error,
+    filter::{Field, Filter},
+    id::IdType,
+    sql::{OrderBy, SortKey, SqlValue},
+};
+
+/// The order of the entries of a paginated query.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PageOrder {
+    /// Ordered by `id` ASC.
+    IdAsc,
+    /// Ordered by `id` DESC.
+    IdDesc,
+    /// Ordered by `(update_time, id)` ASC.
+    UpdateTimeAsc,
+    /// Ordered by `(update_time, id)` DESC.
+    UpdateTimeDesc,
+}
+
+impl PageOrder {
+    fn direction(&self) -> OrderBy {
+        match self {
+            PageOrder::IdAsc | PageOrder::UpdateTimeAsc => OrderBy::Asc,
+            PageOrder::IdDesc | PageOrder::UpdateTimeDesc => OrderBy::Desc,
+        }
+    }
+
+    /// Returns the sort keys of the query.
+    pub(crate) fn sort_keys(&self) -> Vec<SortKey> {
+        match self {
+            PageOrder::IdAsc | PageOrder::IdDesc => vec![SortKey::Id(self.direction())],
+            PageOrder::UpdateTimeAsc | PageOrder::UpdateTimeDesc => vec![
+                SortKey::UpdateTime(self.direction()),
+                SortKey::Id(self.direction()),
+            ],
+        }
+    }
+}
+
+/// The position after which the next page starts.
+///
+/// A cursor can be sent to HTTP clients as an opaque string with [`Cursor::to_token`] and
+/// parsed back with [`Cursor::from_token`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Cursor {
+    order: PageOrder,
+    id: CursorId,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    update_time: Option<DateTime<Utc>>,
+}
+
+impl Cursor {
+    fn after<DATA: DataType>(order: PageOrder, record: &Record<DATA>) -> Result<Self, C3p0Error> {
+        let update_time = match order {
+            PageOrder::IdAsc | PageOrder::IdDesc => None,
+            PageOrder::UpdateTimeAsc | PageOrder::UpdateTimeDesc => Some(record.update_time),
+        };
+        Ok(Cursor {
+            order,
+            id: CursorId::of::<DATA::ID>(&record.id)?,
+            update_time,
+        })
+    }
+
+    /// Returns the order of the query that produced this cursor.
+    pub fn order(&self) -> PageOrder {
+        self.order
+    }
+
+    /// Encodes the cursor as an URL safe string.
+    pub fn to_token(&self) -> String {
+        // Serialising a struct of plain values cannot fail
+        let json = serde_json::to_vec(self).expect("a Cursor should always be serializable");
+        URL_SAFE_NO_PAD.encode(json)
+    }
+
+    /// Decodes a cursor previously encoded with [`Cursor::to_token`].
+    pub fn from_token(token: &str) -> Result<Self, C3p0Error> {
+        let json = URL_SAFE_NO_PAD
+            .decode(token)
+            .map_err(|err| C3p0Error::InvalidCursor {
+                cause: format!("Cannot decode the cursor token: {err}"),
+            })?;
+        let cursor: Cursor =
+            serde_json::from_slice(&json).map_err(|err| C3p0Error::InvalidCursor {
+                cause: format!("Cannot parse the cursor token: {err}"),
+            })?;
+        cursor.check()?;
+        Ok(cursor)
+    }
+
+    fn check(&self) -> Result<(), C3p0Error> {
+        let has_update_time = matches!(
+            self.order,
+            PageOrder::UpdateTimeAsc | PageOrder::UpdateTimeDesc
+        );
+        if has_update_time != self.update_time.is_some() {
+            return Err(C3p0Error::InvalidCursor {
+                cause: format!("The cursor is not valid for the order [{:?}]", self.order),
+            });
+        }
+        Ok(())
+    }
+
+    /// Returns the filter matching the entries that come after the cursor.
+    fn to_filter(&self) -> Filter {
+        let after = |field: Field, value: SqlValue| match self.order.direction() {
+            OrderBy::Desc => Filter::lt(field, value),
+            _ => Filter::gt(field, value),
+        };
+        match self.update_time {
+            None => after(Field::Id, self.id.to_sql()),
+            Some(update_time) => Filter::or([
+                after(Field::UpdateTime, update_time.into()),
+                Filter::and([
+                    Filter::eq(Field::UpdateTime, update_time),
+                    after(Field::Id, self.id.to_sql()),
+                ]),
+            ]),
+        }
+    }
+}
+
+/// The id of the last entry of a page: an integer or the text of the ids that are not
+/// generated by the database.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+enum CursorId {
+    Int(i64),
+    Text(String),
+}
+
+impl CursorId {
+    /// Returns the cursor value of the `id`, which is an `Int` for the
+    /// [`AUTO_INCREMENT`](IdType::AUTO_INCREMENT) ids and a `Text` otherwise, as stored in
+    /// the `id` column.
+    fn of<ID: IdType>(id: &ID) -> Result<Self, C3p0Error> {
+        match (ID::AUTO_INCREMENT, id.to_sql()) {
+            (true, SqlValue::Int(id)) => Ok(CursorId::Int(id)),
+            (false, SqlValue::Text(id)) => Ok(CursorId::Text(id)),
+            (_, value) => Err(C3p0Error::InvalidCursor {
+                cause: format!(
+                    "The id [{value:?}] of type [{}] cannot be stored in a cursor",
+                    std::any::type_name::<ID>()
+                ),
+            }),
+        }
+    }
+
+    fn to_sql(&self) -> SqlValue {
+        match self {
+            CursorId::Int(id) => SqlValue::Int(*id),
+            CursorId::Text(id) => SqlValue::Text(id.clone()),
+        }
+    }
+}
+
+/// A page of entries returned by a paginated query.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Page<DATA: DataType> {
+    /// The entries of the page.
+    pub records: Vec<Record<DATA>>,
+    /// The cursor to fetch the next page; `None` when this is the last page.
+    pub next: Option<Cursor>,
+}
+
+/// Returns the filter and sort keys of the query fetching the page after `cursor`.
+///
+/// The cursor must have been produced by a query with the same `order`, and the `limit`
+/// must be positive and leave room for the extra entry fetched to detect the next page.
+pub(crate) fn page_query(
+    filter: Option<&Filter>,
+    order: PageOrder,
+    cursor: Option<&Cursor>,
+    limit: u64,
+) -> Result<(Option<Filter>, Vec<SortKey>), C3p0Error> {
+    if limit == 0 || limit >= i64::MAX as u64 {
+        return Err(C3p0Error::Other {
+            cause: format!("The page limit must be between 1 and {}", i64::MAX - 1),
+        });
+    }
+    let filter = match cursor {
+        Some(cursor) if cursor.order != order => {
+            return Err(C3p0Error::InvalidCursor {
+                cause: format!(
+                    "The cursor was created for the order [{:?}] but was used with [{:?}]",
+                    cursor.order, order
+                ),
+            });
+        }
+        Some(cursor) => {
+            cursor.check()?;
+            Some(match filter {
+                Some(filter) => Filter::and([filter.clone(), cursor.to_filter()]),
+                None => cursor.to_filter(),
+            })
+        }
+        None => filter.cloned(),
+    };
+    Ok((filter, order.sort_keys()))
+}
+
+impl<DATA: DataType> Page<DATA> {
+    /// Builds the page from the result of a query fetching up to `limit + 1` entries; the
+    /// extra entry is only used to know whether a next page exists.
+    pub(crate) fn new(
+        mut records: Vec<Record<DATA>>,
+        order: PageOrder,
+        limit: u64,
+    ) -> Result<Self, C3p0Error> {
+        let has_next = records.len() as u64 > limit;
+        records.truncate(limit as usize);
+        let next = match records.last() {
+            Some(last) if has_next => Some(Cursor::after(order, last)?),
+            _ => None,
+        };
+        Ok(Page { records, next })
+    }
+}
@@ -0,0 +1,120 @@
+//! Partial updates of the `data` column applied by the database.
+//!
+//! A [`JsonPatch`] is executed with [`Tx::patch`](crate::Tx::patch) in a single `UPDATE`
+//! statement, so it never overwrites the fields changed by concurrent writers that it
+//! does not touch:
+//!
+//! ```rust
+//! use c3p0::patch::{JsonPatch, PatchOp};
+//! use serde_json::json;
+//!
+//! // RFC 7396 merge patch: `null` removes a key, objects are merged recursively
+//! let merge = JsonPatch::Merge(json!({ "address": { "city": "Rome", "zip": null } }));
+//!
+//! // Operations applied in order
+//! let ops = JsonPatch::Ops(vec![
+//!     PatchOp::set("address.city", "Rome"),
+//!     PatchOp::remove("address.zip"),
+//! ]);
+//! ```
+//!
+//! The patch is applied to the JSON produced by the [`Codec`](crate::Codec) of the
+//! [`DataType`], and the result must still be decodable by it.
+//!
+//! The patches return an error on the entries whose document is compressed, see
+//! [`Compression`](crate::Compression).
+//!
+//! With the `encryption` feature, the patches that change an encrypted field of the
+//! stored document, or one of its parents, return an error, as the new value would be
+//! stored in plaintext.
+
+use serde_json::{Map, Value};
+
+use crate::{DataType, error::C3p0Error, format::json_queryable, sql::JsonPath, table::TableName};
+
+/// A partial update of the `data` column.
+#[derive(Clone, Debug, PartialEq)]
+pub enum JsonPatch {
+    /// An [RFC 7396](https://www.rfc-editor.org/rfc/rfc7396) merge patch.
+    Merge(Value),
+    /// A list of operations applied in order.
+    Ops(Vec<PatchOp>),
+}
+
+/// An operation of a [`JsonPatch::Ops`] patch.
+#[derive(Clone, Debug, PartialEq)]
+pub enum PatchOp {
+    /// Sets the value at the path. The missing parent objects are created, and the
+    /// parents that are not objects are replaced by objects.
+    Set(JsonPath, Value),
+    /// Removes the value at the path, if present.
+    Remove(JsonPath),
+}
+
+impl PatchOp {
+    /// Returns an operation setting `value` at `path`.
+    pub fn set(path: impl Into<JsonPath>, value: impl Into<Value>) -> Self {
+        PatchOp::Set(path.into(), value.into())
+    }
+
+    /// Returns an operation removing the value at `path`.
+    pub fn remove(path: impl Into<JsonPath>) -> Self {
+        PatchOp::Remove(path.into())
+    }
+
+    /// Returns the merge patch creating the parent objects of the path of a `Set`
+    /// operation, e.g. `{"a": {"b": {}}}` for `a.b.c`; `None` if the path has no parents.
+    pub(crate) fn parents_patch(path: &JsonPath) -> Option<Value> {
+        let (_, parents) = path.keys().split_last()?;
+        parents.iter().rev().fold(None, |patch, key| {
+            let mut object = Map::new();
+            object.insert(
+                key.clone(),
+                patch.unwrap_or_else(|| Value::Object(Map::new())),
+            );
+            Some(Value::Object(object))
+        })
+    }
+}
+
+impl JsonPatch {
+    /// Returns an error if an operation has an empty path, or if the `data` of `DATA` is
+    /// binary.
+    pub(crate) fn check<DATA: DataType>(&self, table: &TableName) -> Result<(), C3p0Error> {
+        if !json_queryable::<DATA>() {
+            return Err(C3p0Error::Other {
+                cause: format!(
+                    "The patches are not supported by table [{}]: its data is binary",
+                    table.declared()
+                ),
+            });
+        }
+        if let JsonPatch::Ops(ops) = self {
+            for op in ops {
+                let (PatchOp::Set(path, _) | PatchOp::Remove(path)) = op;
+                if path.keys().is_empty() {
+                    return Err(C3p0Error::Other {
+                        cause: "The path of a patch operation cannot be empty".to_owned(),
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Returns the result of applying the merge `patch` to a value that is not an object,
+/// that is the patch without its `null` object members.
+#[cfg_attr(not(feature = "postgres"), allow(dead_code))]
+pub(crate) fn strip_nulls(patch: &Value) -> Value {
+    match patch {
+        Value::Object(object) => Value::Object(
+            object
+                .iter()
+                .filter(|(_, value)| !value.is_null())
+                .map(|(key, value)| (key.clone(), strip_nulls(value)))
+                .collect(),
+        ),
+        value => value.clone(),
+    }
+}
@@ -0,0 +1,177 @@
+use sqlx::{PgConnection, Postgres, Row};
+
+use crate::{
+    error::C3p0Error,
+    schema::{ColumnInfo, ColumnKind, Ddl, IndexSchema, TableInfo, TableSchema},
+    sql::{JsonPath, SqlDialect},
+    table::TableName,
+};
+
+/// Returns the type and the constraints of the `data` column, followed by the
+/// `data_compressed` column of the compressed JSON types.
+fn data_column(schema: &TableSchema) -> String {
+    if schema.format.is_binary() {
+        "BYTEA NOT NULL".to_owned()
+    } else if schema.compressed {
+        format!(
+            "JSONB NOT NULL, data_compressed {}",
+            <Postgres as Ddl>::COMPRESSED_TYPE
+        )
+    } else {
+        "JSONB NOT NULL".to_owned()
+    }
+}
+
+impl Ddl for Postgres {
+    const DELETE_TIME_TYPE: &'static str = "TIMESTAMPTZ";
+    const COMPRESSED_TYPE: &'static str = "BYTEA";
+
+    fn create_table(schema: &TableSchema, table: &TableName) -> Vec<String> {
+        let data = data_column(schema);
+        let mut statements = vec![format!(
+            r#"
+                CREATE TABLE IF NOT EXISTS {} (
+                    id {} primary key,
+                    version bigint not null,
+                    create_time TIMESTAMPTZ NOT NULL,
+                    update_time TIMESTAMPTZ NOT NULL,
+                    data {data}{}
+                )
+                "#,
+            table,
+            if schema.auto_increment {
+                "bigserial"
+            } else {
+                "TEXT"
+            },
+            if schema.soft_delete {
+                ", delete_time TIMESTAMPTZ"
+            } else {
+                ""
+            },
+        )];
+        if schema.history {
+            statements.push(Self::create_history_table(schema, table));
+        }
+        for index in &schema.indexes {
+            statements.extend(Self::create_index(index, table));
+        }
+        statements
+    }
+
+    fn create_history_table(schema: &TableSchema, table: &TableName) -> String {
+        let data = data_column(schema);
+        format!(
+            r#"
+                CREATE TABLE IF NOT EXISTS {} (
+                    id {} not null,
+                    version bigint not null,
+                    create_time TIMESTAMPTZ NOT NULL,
+                    update_time TIMESTAMPTZ NOT NULL,
+                    data {data},{}
+                    history_time TIMESTAMPTZ NOT NULL,
+                    primary key (id, version)
+                )
+                "#,
+            table.history(),
+            if schema.auto_increment {
+                "bigint"
+            } else {
+                "TEXT"
+            },
+            if schema.soft_delete {
+                "\n                    delete_time TIMESTAMPTZ,"
+            } else {
+                ""
+            },
+        )
+    }
+
+    fn create_index(index: &IndexSchema, table: &TableName) -> Vec<String> {
+        let name = table.index(index.name());
+        vec![match index {
+            IndexSchema::Values { paths, unique, .. } => {
+                let values: Vec<String> = paths
+                    .iter()
+                    .map(|(path, json_type)| {
+                        let path = JsonPath::from(path.as_str());
+                        format!("({})", Self::json_field(&path, *json_type))
+                    })
+                    .collect();
+                format!(
+                    "CREATE {}INDEX IF NOT EXISTS {name} ON {table} ({})",
+                    if *unique { "UNIQUE " } else { "" },
+                    values.join(", "),
+                )
+            }
+            IndexSchema::Containment { .. } => format!(
+                "CREATE INDEX IF NOT EXISTS {name} ON {table} USING GIN (data jsonb_path_ops)"
+            ),
+        }]
+    }
+
+    fn column_types(kind: ColumnKind, auto_increment: bool) -> &'static [&'static str] {
+        match kind {
+            ColumnKind::Id if auto_increment => &["bigint"],
+            ColumnKind::Id => &["text", "character varying"],
+            ColumnKind::Version => &["bigint"],
+            ColumnKind::Time => &["timestamp with time zone"],
+            ColumnKind::Data => &["jsonb"],
+            ColumnKind::Bytes => &["bytea"],
+        }
+    }
+
+    /// The `jsonb` type only holds valid JSON.
+    fn requires_json_check(_data_type: &str) -> bool {
+        false
+    }
+
+    /// The indexes are in the schema of their table.
+    fn drop_index(index: &IndexSchema, table: &TableName) -> Vec<String> {
+        let name = table.index(index.name());
+        vec![match table.schema() {
+            Some(schema) => format!("DROP INDEX IF EXISTS {schema}.{name}"),
+            None => format!("DROP INDEX IF EXISTS {name}"),
+        }]
+    }
+}
+
+/// Returns the structure of the table with the quoted identifier `table`, or `None` if
+/// it does not exist.
+pub(super) async fn table_info(
+    tx: &mut PgConnection,
+    table: &str,
+) -> Result<Option<TableInfo>, C3p0Error> {
+    let exists: bool = sqlx::query_scalar("SELECT to_regclass($1) IS NOT NULL")
+        .bind(table)
+        .fetch_one(&mut *tx)
+        .await?;
+    if !exists {
+        return Ok(None);
+    }
+    let columns = sqlx::query(
+        r#"
+            SELECT a.attname::text, format_type(a.atttypid, a.atttypmod),
+                COALESCE(a.attnum = ANY(i.indkey), false)
+            FROM pg_attribute a
+            LEFT JOIN pg_index i ON i.indrelid = a.attrelid AND i.indisprimary
+            WHERE a.attrelid = to_regclass($1) AND a.attnum > 0 AND NOT a.attisdropped
+            "#,
+    )
+    .bind(table)
+    .fetch_all(&mut *tx)
+    .await?
+    .iter()
+    .map(|row| {
+        Ok(ColumnInfo {
+            name: row.try_get(0)?,
+            data_type: row.try_get(1)?,
+            primary_key: row.try_get(2)?,
+        })
+    })
+    .collect::<Result<_, sqlx::Error>>()?;
+    Ok(Some(TableInfo {
+        columns,
+        json_check: true,
+    }))
+}
@@ -0,0 +1,170 @@
+use sqlx::Postgres;
+
+use serde_json::{Map, Value};
+
+use crate::{
+    patch::{JsonPatch, PatchOp, strip_nulls},
+    sql::{JsonPath, JsonType, SqlBuilder, SqlDialect, SqlValue},
+};
+
+impl SqlDialect for Postgres {
+    const NO_LIMIT: &'static str = "";
+
+    /// The extended query protocol encodes the number of parameters as a 16-bit integer.
+    const MAX_BIND_PARAMS: usize = 65535;
+
+    fn placeholder(index: usize) -> String {
+        format!("${index}")
+    }
+
+    /// PostgreSQL folds the unquoted identifiers to lower case.
+    fn fold_identifier(identifier: &str) -> String {
+        identifier.to_ascii_lowercase()
+    }
+
+    /// Renders `data -> 'key1' ->> 'key2'`, cast to `numeric` or `boolean` when needed.
+    fn json_field(path: &JsonPath, json_type: JsonType) -> String {
+        let mut field = String::from("data");
+        let keys = path.keys();
+        for (index, key) in keys.iter().enumerate() {
+            let operator = if index + 1 == keys.len() { "->>" } else { "->" };
+            field.push_str(&format!(" {operator} {}", Self::string_literal(key)));
+        }
+        match json_type {
+            JsonType::Text => format!("({field})"),
+            JsonType::Number => format!("({field})::numeric"),
+            JsonType::Bool => format!("({field})::boolean"),
+        }
+    }
+
+    /// Renders `data @> CAST($1 AS jsonb)`, which can use the GIN indexes of the column.
+    fn json_contains(sql: &mut SqlBuilder<Self>, value: &Value) {
+        sql.push("(data @> CAST(")
+            .push_value(SqlValue::Text(value.to_string()))
+            .push(" AS jsonb))");
+    }
+
+    /// Renders `jsonb_build_object('name', data -> 'key1' -> 'key2', ...)`, concatenating
+    /// the objects of at most 50 fields, as the functions accept at most 100 arguments.
+    fn json_object(fields: &[(String, JsonPath)]) -> String {
+        if fields.is_empty() {
+            return "jsonb_build_object()".to_owned();
+        }
+        let objects: Vec<String> = fields
+            .chunks(50)
+            .map(|chunk| {
+                let arguments: Vec<String> = chunk
+                    .iter()
+                    .map(|(name, path)| {
+                        let mut value = String::from("data");
+                        for key in path.keys() {
+                            value.push_str(&format!(" -> {}", Self::string_literal(key)));
+                        }
+                        format!("{}, {value}", Self::string_literal(name))
+                    })
+                    .collect();
+                format!("jsonb_build_object({})", arguments.join(", "))
+            })
+            .collect();
+        objects.join(" || ")
+    }
+
+    /// Renders the operations as a chain of `LATERAL` subqueries, each one referencing
+    /// the result of the previous one, so that the expression does not grow
+    /// exponentially with the number of operations.
+    fn json_patch(patch: &JsonPatch, values: &mut Vec<Value>) -> String {
+        match patch {
+            JsonPatch::Merge(patch) => merge_patch("data", patch, values),
+            JsonPatch::Ops(ops) => {
+                let mut chain = String::new();
+                for (index, op) in ops.iter().enumerate() {
+                    let data = format!("p{index}.v");
+                    let patched = match op {
+                        PatchOp::Set(path, value) => {
+                            let data = match PatchOp::parents_patch(path) {
+                                Some(parents) => merge_patch(&data, &parents, values),
+                                None => data,
+                            };
+                            format!(
+                                "jsonb_set({data}, {}, {})",
+                                text_array(path),
+                                bind(value.clone(), values)
+                            )
+                        }
+                        PatchOp::Remove(path) => format!("({data} #- {})", text_array(path)),
+                    };
+                    chain.push_str(&format!(
+                        " CROSS JOIN LATERAL (SELECT {patched} AS v) AS p{}",
+                        index + 1
+                    ));
+                }
+                format!(
+                    "(SELECT p{}.v FROM (SELECT data AS v) AS p0{chain})",
+                    ops.len()
+                )
+            }
+        }
+    }
+}
+
+/// Binds a JSON value and returns its placeholder.
+fn bind(value: Value, values: &mut Vec<Value>) -> String {
+    values.push(value);
+    Postgres::placeholder(values.len())
+}
+
+/// Renders `ARRAY['key1', 'key2']::text[]`.
+fn text_array(path: &JsonPath) -> String {
+    let keys: Vec<String> = path
+        .keys()
+        .iter()
+        .map(|key| Postgres::string_literal(key))
+        .collect();
+    format!("ARRAY[{}]::text[]", keys.join(", "))
+}
+
+/// Renders the RFC 7396 merge of `patch` into the `target` expression, which Postgres does
+/// not provide natively: the members of an object patch are removed (`null`), replaced or
+/// merged recursively (objects) when the target is an object, while the target is
+/// replaced otherwise.
+fn merge_patch(target: &str, patch: &Value, values: &mut Vec<Value>) -> String {
+    let Value::Object(object) = patch else {
+        return bind(patch.clone(), values);
+    };
+
+    let mut merged = target.to_owned();
+    let removed: Vec<String> = object
+        .iter()
+        .filter(|(_, value)| value.is_null())
+        .map(|(key, _)| Postgres::string_literal(key))
+        .collect();
+    if !removed.is_empty() {
+        merged = format!("({merged} - ARRAY[{}]::text[])", removed.join(", "));
+    }
+    let replaced: Map<String, Value> = object
+        .iter()
+        .filter(|(_, value)| !value.is_null() && !value.is_object())
+        .map(|(key, value)| (key.clone(), value.clone()))
+        .collect();
+    if !replaced.is_empty() {
+        merged = format!("({merged} || {})", bind(Value::Object(replaced), values));
+    }
+    let nested: Vec<String> = object
+        .iter()
+        .filter(|(_, value)| value.is_object())
+        .map(|(key, value)| {
+            let key = Postgres::string_literal(key);
+            let value = merge_patch(&format!("({target} -> {key})"), value, values);
+            format!("{key}, {value}")
+        })
+        .collect();
+    // `jsonb_build_object` accepts at most 100 arguments
+    for chunk in nested.chunks(50) {
+        merged = format!("({merged} || jsonb_build_object({}))", chunk.join(", "));
+    }
+
+    format!(
+        "CASE WHEN jsonb_typeof({target}) = 'object' THEN {merged} ELSE {} END",
+        bind(strip_nulls(patch), values)
+    )
+}
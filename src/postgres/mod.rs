@@ -1,3 +1,5 @@
+mod ddl;
+mod dialect;
 mod pool;
 mod record;
 mod tx;
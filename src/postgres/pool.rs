@@ -5,18 +5,32 @@ use sqlx::{PgConnection, Pool, Postgres};
 #[derive(Clone)]
 pub struct PgC3p0Pool {
     pool: Pool<Postgres>,
+    #[cfg(feature = "encryption")]
+    key_ring: Option<std::sync::Arc<crate::crypto::KeyRing>>,
 }
 
 impl PgC3p0Pool {
     /// Creates a new SqlxPgC3p0Pool from a Sqlx Pool
     pub fn new(pool: Pool<Postgres>) -> Self {
-        PgC3p0Pool { pool }
+        PgC3p0Pool {
+            pool,
+            #[cfg(feature = "encryption")]
+            key_ring: None,
+        }
     }
 
     /// Returns the underlying Sqlx Pool
     pub fn pool(&self) -> &Pool<Postgres> {
         &self.pool
     }
+
+    /// Sets the [`KeyRing`](crate::crypto::KeyRing) of the encrypted fields, used by the
+    /// transactions of this pool.
+    #[cfg(feature = "encryption")]
+    pub fn with_key_ring(mut self, key_ring: crate::crypto::KeyRing) -> Self {
+        self.key_ring = Some(std::sync::Arc::new(key_ring));
+        self
+    }
 }
 
 impl From<Pool<Postgres>> for PgC3p0Pool {
@@ -38,7 +52,10 @@ impl C3p0Pool for PgC3p0Pool {
     ) -> Result<T, E> {
         let mut transaction = self.pool.begin().await.map_err(C3p0Error::from)?;
 
-        let result = (tx)(&mut transaction).await?;
+        let result = (tx)(&mut transaction);
+        #[cfg(feature = "encryption")]
+        let result = crate::crypto::in_scope(self.key_ring.clone(), result);
+        let result = result.await?;
 
         transaction.commit().await.map_err(C3p0Error::from)?;
         Ok(result)
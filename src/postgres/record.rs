@@ -1,11 +1,36 @@
+use std::collections::HashMap;
+
 use chrono::{DateTime, Utc};
+use futures_core::Stream;
+use futures_util::{StreamExt, TryStreamExt, future, stream};
 
+use super::ddl;
 use crate::codec::Codec;
+use crate::decode::{LenientRecord, RawRecord};
+use crate::filter::{Field, Filter, PLAIN_JSON, plain_json_condition};
+use crate::format::{
+    Stored, codec_from_row, compressed_column, compressed_value, data_from_row,
+    has_compressed_column, set_compressed,
+};
+use crate::id::{DbId, IdType};
+use crate::page::{Cursor, Page, PageOrder, page_query};
+use crate::patch::JsonPatch;
+use crate::projection::{Projected, Projection, projection_query};
+use crate::schema::{Ddl, TableReport, TableSchema, verify_table};
+use crate::sql::{JsonType, OrderBy, SortKey, SqlBuilder, SqlDialect, SqlValue, to_arguments};
+use crate::table::TableName;
 use crate::{
     error::C3p0Error,
-    record::{DataType, DbOps, DbSave, NewRecord, Record},
+    ops::{TableOps, TableSave},
+    record::{
+        CodecOf, DataType, EncodedRecord, NewData, NewRecord, Record, SoftDeleteRecord,
+        UpdateAllResult, and_not_deleted, as_of_query, check_history, check_soft_delete,
+        history_columns, new_id, returned_in_order, select_query, unique_by_id, unique_key,
+        unique_key_filter, where_not_deleted,
+    },
 };
 
+use serde::de::DeserializeOwned;
 use sqlx::Database;
 use sqlx::FromRow;
 use sqlx::PgConnection;
@@ -25,36 +50,125 @@ const NOW_EXPR: &str = "CURRENT_TIMESTAMP";
 
 impl<DATA: DataType> FromRow<'_, PgRow> for Record<DATA> {
     fn from_row(row: &PgRow) -> Result<Self, sqlx::Error> {
-        let id: i64 = row.try_get(0)?;
+        let DbId(id): DbId<DATA::ID> = row.try_get(0)?;
         let version: i64 = row.try_get(1)?;
         let create_time: DateTime<Utc> = row.try_get(2)?;
         let update_time: DateTime<Utc> = row.try_get(3)?;
-        let sqlx::types::Json(data): sqlx::types::Json<DATA::CODEC> = row.try_get(4)?;
+        let data = codec_from_row::<_, DATA>(row, 4)?;
 
         Ok(Record {
             id,
             version,
-            data: DATA::CODEC::decode(data),
+            data: CodecOf::<DATA>::decode(data),
             create_time,
             update_time,
         })
     }
 }
 
-impl<DATA: DataType> DbOps<Postgres, DATA> for Record<DATA> {
-    fn query_with_tail(
-        tail: &str,
-    ) -> QueryAs<'_, Postgres, Record<DATA>, <Postgres as Database>::Arguments> {
-        let query = format!(
-            "{} {}",
-            <Self as DbOps<Postgres, DATA>>::select_query_base(),
-            tail
-        );
+impl<DATA: DataType> FromRow<'_, PgRow> for SoftDeleteRecord<DATA> {
+    fn from_row(row: &PgRow) -> Result<Self, sqlx::Error> {
+        Ok(SoftDeleteRecord {
+            record: Record::from_row(row)?,
+            delete_time: row.try_get(5)?,
+        })
+    }
+}
+
+impl<DATA: DataType> FromRow<'_, PgRow> for RawRecord<DATA> {
+    fn from_row(row: &PgRow) -> Result<Self, sqlx::Error> {
+        let DbId(id): DbId<DATA::ID> = row.try_get(0)?;
+        let data: serde_json::Value = data_from_row::<_, DATA, _>(row, 4)?;
+        Ok(RawRecord {
+            id,
+            version: row.try_get(1)?,
+            create_time: row.try_get(2)?,
+            update_time: row.try_get(3)?,
+            data,
+        })
+    }
+}
+
+impl<DATA: DataType, P: DeserializeOwned> FromRow<'_, PgRow> for Projected<DATA, P> {
+    fn from_row(row: &PgRow) -> Result<Self, sqlx::Error> {
+        let DbId(id): DbId<DATA::ID> = row.try_get(0)?;
+        let sqlx::types::Json(data) = row.try_get(2)?;
+        Ok(Projected {
+            id,
+            version: row.try_get(1)?,
+            data,
+        })
+    }
+}
+
+/// Returns a [`QueryAs`] for `Record<DATA>` selecting from `table`, followed by `tail`.
+fn select_with_tail<'q, DATA: DataType>(
+    table: &TableName,
+    tail: &str,
+) -> QueryAs<'q, Postgres, Record<DATA>, <Postgres as Database>::Arguments> {
+    sqlx::query_as(sqlx::AssertSqlSafe(format!(
+        "{} {}",
+        select_query::<DATA>(table),
+        tail
+    )))
+}
+
+impl<DATA: DataType> TableOps<Postgres, DATA> for Record<DATA> {
+    fn query_with_tail<'q>(
+        table: &TableName,
+        tail: &'q str,
+    ) -> QueryAs<'q, Postgres, Record<DATA>, <Postgres as Database>::Arguments> {
+        let query = format!("{} {}", select_query::<DATA>(table), tail);
         sqlx::query_as(sqlx::AssertSqlSafe(query))
     }
 
-    async fn count_all(tx: &mut PgConnection) -> Result<u64, C3p0Error> {
-        let query = format!("SELECT COUNT(*) FROM {}", DATA::TABLE_NAME,);
+    async fn create_table_if_not_exists(
+        tx: &mut PgConnection,
+        table: &TableName,
+    ) -> Result<(), C3p0Error> {
+        let schema = TableSchema::of::<DATA>()?;
+        for query in <Postgres as Ddl>::create_table(&schema, table) {
+            sqlx::query(sqlx::AssertSqlSafe(query))
+                .execute(&mut *tx)
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn drop_table_if_exists(
+        tx: &mut PgConnection,
+        table: &TableName,
+        cascade: bool,
+    ) -> Result<(), C3p0Error> {
+        let schema = TableSchema::of::<DATA>()?;
+        for query in <Postgres as Ddl>::drop_table(&schema, table, cascade) {
+            sqlx::query(sqlx::AssertSqlSafe(query))
+                .execute(&mut *tx)
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn verify_table(
+        tx: &mut PgConnection,
+        table: &TableName,
+    ) -> Result<TableReport, C3p0Error> {
+        let schema = TableSchema::of::<DATA>()?;
+        let main = ddl::table_info(tx, table.name()).await?;
+        let history = if schema.history {
+            ddl::table_info(tx, table.history()).await?
+        } else {
+            None
+        };
+        Ok(verify_table::<Postgres>(&schema, table, main, history))
+    }
+
+    async fn count_all(tx: &mut PgConnection, table: &TableName) -> Result<u64, C3p0Error> {
+        let query = format!(
+            "SELECT COUNT(*) FROM {}{}",
+            table,
+            where_not_deleted::<DATA>()
+        );
 
         Ok(sqlx::query(sqlx::AssertSqlSafe(query))
             .fetch_one(tx)
@@ -63,14 +177,19 @@ impl<DATA: DataType> DbOps<Postgres, DATA> for Record<DATA> {
             .map(|val: i64| val as u64)?)
     }
 
-    async fn exists_by_id(tx: &mut PgConnection, id: i64) -> Result<bool, C3p0Error> {
+    async fn exists_by_id(
+        tx: &mut PgConnection,
+        table: &TableName,
+        id: DATA::ID,
+    ) -> Result<bool, C3p0Error> {
         let query = format!(
-            "SELECT EXISTS (SELECT 1 FROM {} WHERE id = $1)",
-            DATA::TABLE_NAME,
+            "SELECT EXISTS (SELECT 1 FROM {} WHERE id = $1{})",
+            table,
+            and_not_deleted::<DATA>(),
         );
 
         Ok(sqlx::query(sqlx::AssertSqlSafe(query))
-            .bind(id)
+            .bind(DbId(id))
             .fetch_one(tx)
             .await
             .and_then(|row| row.try_get(0))?)
@@ -78,64 +197,257 @@ impl<DATA: DataType> DbOps<Postgres, DATA> for Record<DATA> {
 
     async fn fetch_all(
         tx: &mut PgConnection,
+        table: &TableName,
         offset: u64,
         limit: Option<u64>,
     ) -> Result<Vec<Record<DATA>>, C3p0Error> {
         let query = match limit {
-            Some(limit) => Self::query_with_tail("ORDER BY id ASC LIMIT $1 OFFSET $2")
+            Some(limit) => select_with_tail::<DATA>(table, "ORDER BY id ASC LIMIT $1 OFFSET $2")
                 .bind(limit as i64)
                 .bind(offset as i64),
-            None => Self::query_with_tail("ORDER BY id ASC OFFSET $1").bind(offset as i64),
+            None => {
+                select_with_tail::<DATA>(table, "ORDER BY id ASC OFFSET $1").bind(offset as i64)
+            }
         };
         Ok(query.fetch_all(tx).await?)
     }
 
+    async fn fetch_all_sorted(
+        tx: &mut PgConnection,
+        table: &TableName,
+        sort: &[SortKey],
+        offset: u64,
+        limit: Option<u64>,
+    ) -> Result<Vec<Record<DATA>>, C3p0Error> {
+        fetch_sorted::<DATA, _>(tx, table, None, sort, offset, limit).await
+    }
+
+    fn fetch_all_stream<'a>(
+        tx: &'a mut PgConnection,
+        table: &TableName,
+    ) -> impl Stream<Item = Result<Record<DATA>, C3p0Error>> + use<'a, DATA>
+    where
+        DATA: 'a,
+    {
+        select_with_tail::<DATA>(table, "ORDER BY id ASC")
+            .fetch(tx)
+            .map_err(C3p0Error::from)
+    }
+
+    async fn fetch_where(
+        tx: &mut PgConnection,
+        table: &TableName,
+        filter: &Filter,
+        offset: u64,
+        limit: Option<u64>,
+    ) -> Result<Vec<Record<DATA>>, C3p0Error> {
+        fetch_sorted::<DATA, _>(
+            tx,
+            table,
+            Some(filter),
+            &[SortKey::Id(OrderBy::Asc)],
+            offset,
+            limit,
+        )
+        .await
+    }
+
+    async fn fetch_lenient(
+        tx: &mut PgConnection,
+        table: &TableName,
+        filter: Option<&Filter>,
+        offset: u64,
+        limit: Option<u64>,
+    ) -> Result<Vec<LenientRecord<DATA>>, C3p0Error> {
+        let records: Vec<RawRecord<DATA>> = fetch_sorted::<DATA, _>(
+            tx,
+            table,
+            filter,
+            &[SortKey::Id(OrderBy::Asc)],
+            offset,
+            limit,
+        )
+        .await?;
+        Ok(records.into_iter().map(RawRecord::decode).collect())
+    }
+
+    async fn fetch_projected<P: DeserializeOwned + Send + Unpin>(
+        tx: &mut PgConnection,
+        table: &TableName,
+        projection: &Projection,
+        filter: &Filter,
+        sort: &[SortKey],
+        offset: u64,
+        limit: Option<u64>,
+    ) -> Result<Vec<Projected<DATA, P>>, C3p0Error> {
+        projection.check::<DATA>(table)?;
+        fetch_select(
+            tx,
+            projection_query::<DATA, Postgres>(table, projection),
+            Some(filter),
+            has_compressed_column::<DATA>().then_some(PLAIN_JSON),
+            sort,
+            offset,
+            limit,
+        )
+        .await
+    }
+
+    async fn fetch_where_sorted(
+        tx: &mut PgConnection,
+        table: &TableName,
+        filter: &Filter,
+        sort: &[SortKey],
+        offset: u64,
+        limit: Option<u64>,
+    ) -> Result<Vec<Record<DATA>>, C3p0Error> {
+        fetch_sorted::<DATA, _>(tx, table, Some(filter), sort, offset, limit).await
+    }
+
+    fn fetch_where_stream<'a>(
+        tx: &'a mut PgConnection,
+        table: &TableName,
+        filter: &Filter,
+    ) -> impl Stream<Item = Result<Record<DATA>, C3p0Error>> + use<'a, DATA>
+    where
+        DATA: 'a,
+    {
+        let condition = match plain_json_condition::<DATA>(table, Some(filter), &[]) {
+            Ok(condition) => condition,
+            Err(err) => return stream::once(future::ready(Err(err))).right_stream(),
+        };
+        let mut sql = SqlBuilder::<Postgres>::new(select_query::<DATA>(table));
+        sql.push("WHERE ");
+        filter.to_sql(&mut sql);
+        if let Some(condition) = condition {
+            sql.push(" AND ").push(condition);
+        }
+        sql.push_order_by(&[SortKey::Id(OrderBy::Asc)]);
+
+        let (query, values) = sql.build();
+        match to_arguments::<Postgres>(values) {
+            Ok(arguments) => sqlx::query_as_with(sqlx::AssertSqlSafe(query), arguments)
+                .fetch(tx)
+                .map_err(C3p0Error::from)
+                .left_stream(),
+            Err(err) => stream::once(future::ready(Err(err))).right_stream(),
+        }
+    }
+
+    async fn count_where(
+        tx: &mut PgConnection,
+        table: &TableName,
+        filter: &Filter,
+    ) -> Result<u64, C3p0Error> {
+        let condition = plain_json_condition::<DATA>(table, Some(filter), &[])?;
+        let mut sql = SqlBuilder::<Postgres>::new(format!("SELECT COUNT(*) FROM {} WHERE ", table));
+        filter.to_sql(&mut sql);
+        if let Some(condition) = condition {
+            sql.push(" AND ").push(condition);
+        }
+        sql.push(and_not_deleted::<DATA>());
+
+        let (query, values) = sql.build();
+        Ok(sqlx::query_with(
+            sqlx::AssertSqlSafe(query),
+            to_arguments::<Postgres>(values)?,
+        )
+        .fetch_one(tx)
+        .await
+        .and_then(|row| row.try_get(0))
+        .map(|val: i64| val as u64)?)
+    }
+
+    async fn fetch_page(
+        tx: &mut PgConnection,
+        table: &TableName,
+        filter: Option<&Filter>,
+        order: PageOrder,
+        cursor: Option<&Cursor>,
+        limit: u64,
+    ) -> Result<Page<DATA>, C3p0Error> {
+        let (filter, sort) = page_query(filter, order, cursor, limit)?;
+        let records =
+            fetch_sorted::<DATA, _>(tx, table, filter.as_ref(), &sort, 0, Some(limit + 1)).await?;
+        Page::new(records, order, limit)
+    }
+
     async fn fetch_one_optional_by_id(
         tx: &mut PgConnection,
-        id: i64,
+        table: &TableName,
+        id: DATA::ID,
     ) -> Result<Option<Record<DATA>>, C3p0Error> {
-        Ok(Self::query_with_tail("WHERE id = $1 LIMIT 1")
-            .bind(id)
+        Ok(select_with_tail::<DATA>(table, "WHERE id = $1 LIMIT 1")
+            .bind(DbId(id))
             .fetch_optional(tx)
             .await?)
     }
 
-    async fn fetch_one_by_id(tx: &mut PgConnection, id: i64) -> Result<Record<DATA>, C3p0Error> {
-        Ok(Self::query_with_tail("WHERE id = $1 LIMIT 1")
-            .bind(id)
+    async fn fetch_one_by_id(
+        tx: &mut PgConnection,
+        table: &TableName,
+        id: DATA::ID,
+    ) -> Result<Record<DATA>, C3p0Error> {
+        Ok(select_with_tail::<DATA>(table, "WHERE id = $1 LIMIT 1")
+            .bind(DbId(id))
             .fetch_one(tx)
             .await?)
     }
 
-    async fn delete(self, tx: &mut PgConnection) -> Result<Record<DATA>, C3p0Error> {
+    async fn delete(
+        mut self,
+        tx: &mut PgConnection,
+        table: &TableName,
+    ) -> Result<Record<DATA>, C3p0Error> {
+        copy_to_history::<DATA>(
+            tx,
+            table,
+            &Filter::and([
+                Filter::eq(Field::Id, self.id.to_sql()),
+                Filter::eq(Field::Version, self.version),
+            ]),
+            and_not_deleted::<DATA>(),
+        )
+        .await?;
+
         let query = format!(
-            "DELETE FROM {} WHERE id = $1 AND version = $2",
-            DATA::TABLE_NAME,
+            "{} WHERE id = $1 AND version = $2{} RETURNING update_time",
+            delete_query::<DATA>(table),
+            and_not_deleted::<DATA>(),
         );
 
-        let result = sqlx::query(sqlx::AssertSqlSafe(query))
-            .bind(self.id)
+        let row = sqlx::query(sqlx::AssertSqlSafe(query))
+            .bind(DbId(self.id.clone()))
             .bind(self.version)
-            .execute(tx)
-            .await?
-            .rows_affected();
+            .fetch_optional(tx)
+            .await?;
 
-        if result == 0 {
+        let Some(row) = row else {
             return Err(C3p0Error::OptimisticLockError {
                 cause: format!(
                     "Cannot delete data in table [{}] with id [{:?}], version [{}]: data was changed!",
-                    DATA::TABLE_NAME,
+                    table.declared(),
                     self.id,
                     self.version
                 ),
             });
-        }
+        };
 
+        if DATA::SOFT_DELETE {
+            self.version += 1;
+            self.update_time = row.try_get(0)?;
+        }
         Ok(self)
     }
 
-    async fn delete_all(tx: &mut PgConnection) -> Result<u64, C3p0Error> {
-        let query = format!("DELETE FROM {}", DATA::TABLE_NAME);
+    async fn delete_all(tx: &mut PgConnection, table: &TableName) -> Result<u64, C3p0Error> {
+        copy_to_history::<DATA>(tx, table, &Filter::and([]), and_not_deleted::<DATA>()).await?;
+
+        let query = format!(
+            "{}{}",
+            delete_query::<DATA>(table),
+            where_not_deleted::<DATA>()
+        );
 
         Ok(sqlx::query(sqlx::AssertSqlSafe(query))
             .execute(tx)
@@ -143,73 +455,438 @@ impl<DATA: DataType> DbOps<Postgres, DATA> for Record<DATA> {
             .map(|done| done.rows_affected())?)
     }
 
-    async fn delete_by_id(tx: &mut PgConnection, id: i64) -> Result<u64, C3p0Error> {
-        let query = format!("DELETE FROM {} WHERE id = $1", DATA::TABLE_NAME);
+    async fn delete_by_id(
+        tx: &mut PgConnection,
+        table: &TableName,
+        id: DATA::ID,
+    ) -> Result<u64, C3p0Error> {
+        copy_to_history::<DATA>(
+            tx,
+            table,
+            &Filter::eq(Field::Id, id.to_sql()),
+            and_not_deleted::<DATA>(),
+        )
+        .await?;
+
+        let query = format!(
+            "{} WHERE id = $1{}",
+            delete_query::<DATA>(table),
+            and_not_deleted::<DATA>()
+        );
 
         Ok(sqlx::query(sqlx::AssertSqlSafe(query))
-            .bind(id)
+            .bind(DbId(id))
             .execute(tx)
             .await
             .map(|done| done.rows_affected())?)
     }
 
-    async fn update(mut self, tx: &mut PgConnection) -> Result<Record<DATA>, C3p0Error> {
+    async fn delete_where(
+        tx: &mut PgConnection,
+        table: &TableName,
+        filter: &Filter,
+    ) -> Result<u64, C3p0Error> {
+        let condition = match plain_json_condition::<DATA>(table, Some(filter), &[])? {
+            Some(condition) => format!(" AND {condition}{}", and_not_deleted::<DATA>()),
+            None => and_not_deleted::<DATA>().to_owned(),
+        };
+        copy_to_history::<DATA>(tx, table, filter, &condition).await?;
+
+        let mut sql =
+            SqlBuilder::<Postgres>::new(format!("{} WHERE ", delete_query::<DATA>(table)));
+        filter.to_sql(&mut sql);
+        sql.push(&condition);
+
+        let (query, values) = sql.build();
+        Ok(sqlx::query_with(
+            sqlx::AssertSqlSafe(query),
+            to_arguments::<Postgres>(values)?,
+        )
+        .execute(tx)
+        .await
+        .map(|done| done.rows_affected())?)
+    }
+
+    async fn fetch_including_deleted(
+        tx: &mut PgConnection,
+        table: &TableName,
+        offset: u64,
+        limit: Option<u64>,
+    ) -> Result<Vec<SoftDeleteRecord<DATA>>, C3p0Error> {
+        check_soft_delete::<DATA>(table)?;
+
+        let mut sql = SqlBuilder::<Postgres>::new(format!(
+            "SELECT id, version, create_time, update_time, data, delete_time{} FROM {}",
+            compressed_column::<DATA>(),
+            table
+        ));
+        sql.push_order_by(&[SortKey::Id(OrderBy::Asc)])
+            .push_limit_offset(offset, limit);
+
+        let (query, values) = sql.build();
+        Ok(sqlx::query_as_with(
+            sqlx::AssertSqlSafe(query),
+            to_arguments::<Postgres>(values)?,
+        )
+        .fetch_all(tx)
+        .await?)
+    }
+
+    async fn restore(
+        tx: &mut PgConnection,
+        table: &TableName,
+        id: DATA::ID,
+    ) -> Result<Record<DATA>, C3p0Error> {
+        check_soft_delete::<DATA>(table)?;
+        copy_to_history::<DATA>(
+            tx,
+            table,
+            &Filter::eq(Field::Id, id.to_sql()),
+            " AND delete_time IS NOT NULL",
+        )
+        .await?;
+
+        let query = format!(
+            "UPDATE {} SET version = version + 1, update_time = {NOW_EXPR}, delete_time = NULL \
+             WHERE id = $1 AND delete_time IS NOT NULL \
+             RETURNING id, version, create_time, update_time, data{}",
+            table,
+            compressed_column::<DATA>(),
+        );
+
+        Ok(sqlx::query_as(sqlx::AssertSqlSafe(query))
+            .bind(DbId(id))
+            .fetch_one(tx)
+            .await?)
+    }
+
+    async fn purge(
+        tx: &mut PgConnection,
+        table: &TableName,
+        deleted_before: Option<DateTime<Utc>>,
+    ) -> Result<u64, C3p0Error> {
+        check_soft_delete::<DATA>(table)?;
+
+        let mut sql = SqlBuilder::<Postgres>::new(format!(
+            "DELETE FROM {} WHERE delete_time IS NOT NULL",
+            table
+        ));
+        if let Some(deleted_before) = deleted_before {
+            sql.push(" AND delete_time < ")
+                .push_value(SqlValue::Timestamp(deleted_before));
+        }
+
+        let (query, values) = sql.build();
+        Ok(sqlx::query_with(
+            sqlx::AssertSqlSafe(query),
+            to_arguments::<Postgres>(values)?,
+        )
+        .execute(tx)
+        .await
+        .map(|done| done.rows_affected())?)
+    }
+
+    async fn update(
+        mut self,
+        tx: &mut PgConnection,
+        table: &TableName,
+    ) -> Result<Record<DATA>, C3p0Error> {
+        copy_to_history::<DATA>(
+            tx,
+            table,
+            &Filter::and([
+                Filter::eq(Field::Id, self.id.to_sql()),
+                Filter::eq(Field::Version, self.version),
+            ]),
+            and_not_deleted::<DATA>(),
+        )
+        .await?;
+
         let query = format!(
-            "UPDATE {} SET version = $1, update_time = {NOW_EXPR}, data = $2 \
-             WHERE id = $3 AND version = $4 RETURNING update_time",
-            DATA::TABLE_NAME
+            "UPDATE {} SET version = $1, update_time = {NOW_EXPR}, data = $2{} \
+             WHERE id = $3 AND version = $4{} RETURNING update_time",
+            table,
+            set_compressed::<DATA, Postgres>(5),
+            and_not_deleted::<DATA>()
         );
 
-        let data_encoded = DATA::CODEC::encode(self.data);
+        let data_encoded = CodecOf::<DATA>::encode(self.data);
+        let stored = Stored::<DATA>::new(&data_encoded)?;
         let previous_version = self.version;
         let new_version = previous_version + 1;
 
-        let row = sqlx::query(sqlx::AssertSqlSafe(query))
+        let mut update = sqlx::query(sqlx::AssertSqlSafe(query))
             .bind(new_version)
-            .bind(sqlx::types::Json(&data_encoded))
-            .bind(self.id)
-            .bind(previous_version)
-            .fetch_optional(tx)
-            .await?;
+            .bind(&stored)
+            .bind(DbId(self.id.clone()))
+            .bind(previous_version);
+        if has_compressed_column::<DATA>() {
+            update = update.bind(stored.compressed());
+        }
+        let row = update.fetch_optional(tx).await?;
 
         let Some(row) = row else {
             return Err(C3p0Error::OptimisticLockError {
                 cause: format!(
                     "Cannot update data in table [{}] with id [{:?}], version [{}]: data was changed!",
-                    DATA::TABLE_NAME,
+                    table.declared(),
                     self.id,
                     &previous_version
                 ),
             });
         };
 
-        self.data = DATA::CODEC::decode(data_encoded);
+        self.data = CodecOf::<DATA>::decode(data_encoded);
         self.version = new_version;
         self.update_time = row.try_get(0)?;
         Ok(self)
     }
+
+    async fn patch(
+        tx: &mut PgConnection,
+        table: &TableName,
+        id: DATA::ID,
+        version: Option<i64>,
+        patch: &JsonPatch,
+    ) -> Result<Record<DATA>, C3p0Error> {
+        patch.check::<DATA>(table)?;
+        check_not_compressed::<DATA>(tx, table, &id).await?;
+        #[cfg(feature = "encryption")]
+        if let Some(document) = stored_data::<DATA>(tx, table, &id).await? {
+            crate::crypto::check_patch(table, &document, patch)?;
+        }
+        let mut current = vec![Filter::eq(Field::Id, id.to_sql())];
+        current.extend(version.map(|version| Filter::eq(Field::Version, version)));
+        copy_to_history::<DATA>(tx, table, &Filter::and(current), and_not_deleted::<DATA>())
+            .await?;
+
+        let mut values = Vec::new();
+        let data = <Postgres as SqlDialect>::json_patch(patch, &mut values);
+        let mut query = format!(
+            "UPDATE {} SET version = version + 1, update_time = {NOW_EXPR}, data = {data} \
+             WHERE id = {}",
+            table,
+            <Postgres as SqlDialect>::placeholder(values.len() + 1),
+        );
+        query.push_str(and_not_deleted::<DATA>());
+        if has_compressed_column::<DATA>() {
+            query.push_str(&format!(" AND {PLAIN_JSON}"));
+        }
+        if version.is_some() {
+            query.push_str(&format!(
+                " AND version = {}",
+                <Postgres as SqlDialect>::placeholder(values.len() + 2)
+            ));
+        }
+        query.push_str(&format!(
+            " RETURNING id, version, create_time, update_time, data{}",
+            compressed_column::<DATA>()
+        ));
+
+        let mut update = sqlx::query_as(sqlx::AssertSqlSafe(query));
+        for value in &values {
+            update = update.bind(sqlx::types::Json(value));
+        }
+        update = update.bind(DbId(id.clone()));
+        if let Some(version) = version {
+            update = update.bind(version);
+        }
+
+        match (update.fetch_optional(tx).await?, version) {
+            (Some(record), _) => Ok(record),
+            (None, Some(version)) => Err(C3p0Error::OptimisticLockError {
+                cause: format!(
+                    "Cannot patch data in table [{}] with id [{:?}], version [{}]: data was changed!",
+                    table.declared(),
+                    id,
+                    version
+                ),
+            }),
+            (None, None) => Err(sqlx::Error::RowNotFound.into()),
+        }
+    }
+
+    async fn update_all(
+        records: Vec<Record<DATA>>,
+        tx: &mut PgConnection,
+        table: &TableName,
+    ) -> Result<UpdateAllResult<DATA>, C3p0Error> {
+        let records: Vec<EncodedRecord<DATA>> =
+            records.into_iter().map(EncodedRecord::encode).collect();
+
+        let params = 3 + usize::from(has_compressed_column::<DATA>());
+        let mut updated = HashMap::new();
+        for chunk in
+            unique_by_id(&records).chunks(<Postgres as SqlDialect>::MAX_BIND_PARAMS / params)
+        {
+            copy_to_history::<DATA>(
+                tx,
+                table,
+                &Filter::or(chunk.iter().map(|record| {
+                    Filter::and([
+                        Filter::eq(Field::Id, record.id.to_sql()),
+                        Filter::eq(Field::Version, record.version),
+                    ])
+                })),
+                and_not_deleted::<DATA>(),
+            )
+            .await?;
+
+            let values = (0..chunk.len())
+                .map(|index| {
+                    format!(
+                        "(${}, ${}, ${}{})",
+                        params * index + 1,
+                        params * index + 2,
+                        params * index + 3,
+                        compressed_value::<DATA, Postgres>(params * index + 4)
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join(", ");
+            let compressed = compressed_column::<DATA>();
+            let query = format!(
+                "UPDATE {table} SET version = {table}.version + 1, update_time = {NOW_EXPR}, data = v.data{} \
+                 FROM (VALUES {values}) AS v (id, version, data{compressed}) \
+                 WHERE {table}.id = v.id AND {table}.version = v.version{} \
+                 RETURNING {table}.id, {table}.update_time",
+                if has_compressed_column::<DATA>() {
+                    ", data_compressed = v.data_compressed"
+                } else {
+                    ""
+                },
+                and_not_deleted::<DATA>(),
+            );
+
+            let stored = chunk
+                .iter()
+                .map(|record| Stored::<DATA>::new(&record.data))
+                .collect::<Result<Vec<_>, C3p0Error>>()?;
+            let mut update = sqlx::query(sqlx::AssertSqlSafe(query));
+            for (record, stored) in chunk.iter().zip(&stored) {
+                update = update
+                    .bind(DbId(record.id.clone()))
+                    .bind(record.version)
+                    .bind(stored);
+                if has_compressed_column::<DATA>() {
+                    update = update.bind(stored.compressed());
+                }
+            }
+            for row in update.fetch_all(&mut *tx).await? {
+                let DbId(id) = row.try_get(0)?;
+                updated.insert(id, row.try_get(1)?);
+            }
+        }
+
+        Ok(UpdateAllResult::new(records, updated))
+    }
+
+    async fn fetch_history(
+        tx: &mut PgConnection,
+        table: &TableName,
+        id: DATA::ID,
+    ) -> Result<Vec<Record<DATA>>, C3p0Error> {
+        check_history::<DATA>(table)?;
+
+        let query = format!(
+            "SELECT id, version, create_time, update_time, data{} FROM {} \
+             WHERE id = $1 ORDER BY version ASC",
+            compressed_column::<DATA>(),
+            table.history(),
+        );
+
+        Ok(sqlx::query_as(sqlx::AssertSqlSafe(query))
+            .bind(DbId(id))
+            .fetch_all(tx)
+            .await?)
+    }
+
+    async fn fetch_version(
+        tx: &mut PgConnection,
+        table: &TableName,
+        id: DATA::ID,
+        version: i64,
+    ) -> Result<Option<Record<DATA>>, C3p0Error> {
+        check_history::<DATA>(table)?;
+
+        let compressed = compressed_column::<DATA>();
+        let query = format!(
+            "SELECT id, version, create_time, update_time, data{compressed} FROM {} \
+             WHERE id = $1 AND version = $2 \
+             UNION ALL \
+             SELECT id, version, create_time, update_time, data{compressed} FROM {} \
+             WHERE id = $1 AND version = $2 LIMIT 1",
+            table,
+            table.history(),
+        );
+
+        Ok(sqlx::query_as(sqlx::AssertSqlSafe(query))
+            .bind(DbId(id))
+            .bind(version)
+            .fetch_optional(tx)
+            .await?)
+    }
+
+    async fn fetch_as_of(
+        tx: &mut PgConnection,
+        table: &TableName,
+        id: DATA::ID,
+        timestamp: DateTime<Utc>,
+    ) -> Result<Option<Record<DATA>>, C3p0Error> {
+        check_history::<DATA>(table)?;
+
+        let (query, values) = as_of_query::<Postgres, DATA>(table, &id, timestamp).build();
+        let record: Option<SoftDeleteRecord<DATA>> = sqlx::query_as_with(
+            sqlx::AssertSqlSafe(query),
+            to_arguments::<Postgres>(values)?,
+        )
+        .fetch_optional(tx)
+        .await?;
+        Ok(record
+            .filter(|record| record.delete_time.is_none_or(|time| time > timestamp))
+            .map(|record| record.record))
+    }
 }
 
-impl<DATA: DataType> DbSave<Postgres, DATA> for NewRecord<DATA> {
-    async fn save(self, tx: &mut PgConnection) -> Result<Record<DATA>, C3p0Error> {
+impl<DATA: DataType, NEW: NewData<DATA = DATA>> TableSave<Postgres, DATA> for NewRecord<NEW> {
+    async fn save(
+        self,
+        tx: &mut PgConnection,
+        table: &TableName,
+    ) -> Result<Record<DATA>, C3p0Error> {
+        let (id, data) = self.data.into_parts();
+        let id = new_id::<DATA>(table, id)?;
+        let (id_column, id_value) = if id.is_some() {
+            ("id, ", "$3, ")
+        } else {
+            ("", "")
+        };
         let query = format!(
             "WITH ts AS (SELECT {NOW_EXPR} AS v) \
-             INSERT INTO {} (version, create_time, update_time, data) \
-             SELECT $1, ts.v, ts.v, $2 FROM ts \
+             INSERT INTO {} ({id_column}version, create_time, update_time, data{}) \
+             SELECT {id_value}$1, ts.v, ts.v, $2{} FROM ts \
              RETURNING id, create_time",
-            DATA::TABLE_NAME,
+            table,
+            compressed_column::<DATA>(),
+            compressed_value::<DATA, Postgres>(if id.is_some() { 4 } else { 3 }),
         );
 
-        let data_encoded = DATA::CODEC::encode(self.data);
+        let data_encoded = CodecOf::<DATA>::encode(data);
+        let stored = Stored::<DATA>::new(&data_encoded)?;
 
-        let row = sqlx::query(sqlx::AssertSqlSafe(query))
+        let mut insert = sqlx::query(sqlx::AssertSqlSafe(query))
             .bind(0_i64)
-            .bind(sqlx::types::Json(&data_encoded))
-            .fetch_one(tx)
-            .await?;
-        let id: i64 = row.try_get(0)?;
+            .bind(&stored);
+        if let Some(id) = id {
+            insert = insert.bind(DbId(id));
+        }
+        if has_compressed_column::<DATA>() {
+            insert = insert.bind(stored.compressed());
+        }
+        let row = insert.fetch_one(tx).await?;
+        let DbId(id): DbId<DATA::ID> = row.try_get(0)?;
         let create_time: DateTime<Utc> = row.try_get(1)?;
-        let data = DATA::CODEC::decode(data_encoded);
+        let data = CodecOf::<DATA>::decode(data_encoded);
 
         Ok(Record {
             id,
@@ -219,4 +896,306 @@ impl<DATA: DataType> DbSave<Postgres, DATA> for NewRecord<DATA> {
             update_time: create_time,
         })
     }
+
+    async fn upsert_by_key(
+        self,
+        tx: &mut PgConnection,
+        table: &TableName,
+    ) -> Result<Record<DATA>, C3p0Error> {
+        let key: Vec<String> = unique_key::<DATA>()?
+            .iter()
+            .map(|path| <Postgres as SqlDialect>::json_field(path, JsonType::Text))
+            .collect();
+        let (id, data) = self.data.into_parts();
+        let id = new_id::<DATA>(table, id)?;
+        let (id_column, id_value) = if id.is_some() {
+            ("id, ", "$2, ")
+        } else {
+            ("", "")
+        };
+        let compressed = compressed_column::<DATA>();
+        let query = format!(
+            "INSERT INTO {table} ({id_column}version, create_time, update_time, data{compressed}) \
+             VALUES ({id_value}0, {NOW_EXPR}, {NOW_EXPR}, {}{}) \
+             ON CONFLICT ({}) DO UPDATE SET version = {table}.version + 1, \
+             update_time = {NOW_EXPR}, data = EXCLUDED.data{}{} \
+             RETURNING id, version, create_time, update_time, data{compressed}",
+            <Postgres as SqlDialect>::placeholder(1),
+            compressed_value::<DATA, Postgres>(if id.is_some() { 3 } else { 2 }),
+            key.join(", "),
+            if has_compressed_column::<DATA>() {
+                ", data_compressed = EXCLUDED.data_compressed"
+            } else {
+                ""
+            },
+            if DATA::SOFT_DELETE {
+                ", delete_time = NULL"
+            } else {
+                ""
+            },
+        );
+
+        let data_encoded = CodecOf::<DATA>::encode(data);
+        if DATA::HISTORY
+            && let Some(filter) = unique_key_filter::<DATA>(&data_encoded)?
+        {
+            copy_to_history::<DATA>(tx, table, &filter, "").await?;
+        }
+
+        let stored = Stored::<DATA>::new(&data_encoded)?;
+        let mut upsert = sqlx::query_as(sqlx::AssertSqlSafe(query)).bind(&stored);
+        if let Some(id) = id {
+            upsert = upsert.bind(DbId(id));
+        }
+        if has_compressed_column::<DATA>() {
+            upsert = upsert.bind(stored.compressed());
+        }
+        Ok(upsert.fetch_one(tx).await?)
+    }
+
+    async fn save_all(
+        records: Vec<Self>,
+        tx: &mut PgConnection,
+        table: &TableName,
+    ) -> Result<Vec<Record<DATA>>, C3p0Error> {
+        let records = records
+            .into_iter()
+            .map(|record| {
+                let (id, data) = record.data.into_parts();
+                Ok((new_id::<DATA>(table, id)?, CodecOf::<DATA>::encode(data)))
+            })
+            .collect::<Result<Vec<_>, C3p0Error>>()?;
+        let id_column = if DATA::ID::AUTO_INCREMENT { "" } else { "id, " };
+        let params = 1
+            + usize::from(!DATA::ID::AUTO_INCREMENT)
+            + usize::from(has_compressed_column::<DATA>());
+
+        let mut saved: Vec<(DATA::ID, DateTime<Utc>)> = Vec::with_capacity(records.len());
+        for chunk in records.chunks(<Postgres as SqlDialect>::MAX_BIND_PARAMS / params) {
+            let values = (0..chunk.len())
+                .map(|index| {
+                    let id_value = if DATA::ID::AUTO_INCREMENT {
+                        String::new()
+                    } else {
+                        format!(
+                            "{}, ",
+                            <Postgres as SqlDialect>::placeholder(params * index + 2)
+                        )
+                    };
+                    format!(
+                        "({id_value}0, {NOW_EXPR}, {NOW_EXPR}, {}{})",
+                        <Postgres as SqlDialect>::placeholder(params * index + 1),
+                        compressed_value::<DATA, Postgres>(params * index + params)
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join(", ");
+            let query = format!(
+                "INSERT INTO {} ({id_column}version, create_time, update_time, data{}) \
+                 VALUES {values} RETURNING id, create_time",
+                table,
+                compressed_column::<DATA>(),
+            );
+
+            let stored = chunk
+                .iter()
+                .map(|(_, data)| Stored::<DATA>::new(data))
+                .collect::<Result<Vec<_>, C3p0Error>>()?;
+            let mut insert = sqlx::query(sqlx::AssertSqlSafe(query));
+            for ((id, _), stored) in chunk.iter().zip(&stored) {
+                insert = insert.bind(stored);
+                if let Some(id) = id {
+                    insert = insert.bind(DbId(id.clone()));
+                }
+                if has_compressed_column::<DATA>() {
+                    insert = insert.bind(stored.compressed());
+                }
+            }
+            let rows = insert
+                .fetch_all(&mut *tx)
+                .await?
+                .into_iter()
+                .map(|row| Ok((row.try_get::<DbId<DATA::ID>, _>(0)?.0, row.try_get(1)?)))
+                .collect::<Result<Vec<(DATA::ID, DateTime<Utc>)>, sqlx::Error>>()?;
+            saved.extend(returned_in_order::<DATA, _>(chunk, rows)?);
+        }
+
+        Ok(records
+            .into_iter()
+            .map(|(_, data)| data)
+            .zip(saved)
+            .map(|(data, (id, create_time))| Record {
+                id,
+                version: 0,
+                data: CodecOf::<DATA>::decode(data),
+                create_time,
+                update_time: create_time,
+            })
+            .collect())
+    }
+}
+
+/// Returns the `data` of the entry with the given id as it is stored, with its encrypted
+/// values.
+#[cfg(feature = "encryption")]
+async fn stored_data<DATA: DataType>(
+    tx: &mut PgConnection,
+    table: &TableName,
+    id: &DATA::ID,
+) -> Result<Option<serde_json::Value>, C3p0Error> {
+    let query = format!(
+        "SELECT data{} FROM {} WHERE id = $1",
+        compressed_column::<DATA>(),
+        table
+    );
+    Ok(sqlx::query(sqlx::AssertSqlSafe(query))
+        .bind(DbId(id.clone()))
+        .fetch_optional(tx)
+        .await?
+        .map(|row| data_from_row::<_, DATA, serde_json::Value>(&row, 0))
+        .transpose()?)
+}
+
+/// Returns an error if the document of the entry with the given id is compressed, as it
+/// cannot be patched.
+async fn check_not_compressed<DATA: DataType>(
+    tx: &mut PgConnection,
+    table: &TableName,
+    id: &DATA::ID,
+) -> Result<(), C3p0Error> {
+    if !has_compressed_column::<DATA>() {
+        return Ok(());
+    }
+    let query = format!(
+        "SELECT EXISTS (SELECT 1 FROM {} WHERE id = $1 AND data_compressed IS NOT NULL)",
+        table
+    );
+    let compressed: bool = sqlx::query_scalar(sqlx::AssertSqlSafe(query))
+        .bind(DbId(id.clone()))
+        .fetch_one(tx)
+        .await?;
+    if compressed {
+        return Err(C3p0Error::Other {
+            cause: format!(
+                "Cannot patch data in table [{}] with id [{:?}]: the document is compressed",
+                table.declared(),
+                id
+            ),
+        });
+    }
+    Ok(())
+}
+
+/// Fetches the entries matching the optional `filter`, sorted by the `sort` keys.
+async fn fetch_sorted<DATA: DataType, ROW>(
+    tx: &mut PgConnection,
+    table: &TableName,
+    filter: Option<&Filter>,
+    sort: &[SortKey],
+    offset: u64,
+    limit: Option<u64>,
+) -> Result<Vec<ROW>, C3p0Error>
+where
+    ROW: for<'r> FromRow<'r, PgRow> + Send + Unpin,
+{
+    let condition = plain_json_condition::<DATA>(table, filter, sort)?;
+    fetch_select(
+        tx,
+        select_query::<DATA>(table),
+        filter,
+        condition,
+        sort,
+        offset,
+        limit,
+    )
+    .await
+}
+
+/// Returns the rows of the `select` query matching the `filter` and the additional
+/// `condition`, ordered by the `sort` keys, skipping the first `offset` rows and returning
+/// at most `limit` rows.
+async fn fetch_select<ROW>(
+    tx: &mut PgConnection,
+    select: String,
+    filter: Option<&Filter>,
+    condition: Option<&str>,
+    sort: &[SortKey],
+    offset: u64,
+    limit: Option<u64>,
+) -> Result<Vec<ROW>, C3p0Error>
+where
+    ROW: for<'r> FromRow<'r, PgRow> + Send + Unpin,
+{
+    let mut sql = SqlBuilder::<Postgres>::new(select);
+    match (filter, condition) {
+        (Some(filter), Some(condition)) => {
+            sql.push("WHERE ");
+            filter.to_sql(&mut sql);
+            sql.push(" AND ").push(condition);
+        }
+        (Some(filter), None) => {
+            sql.push("WHERE ");
+            filter.to_sql(&mut sql);
+        }
+        (None, Some(condition)) => {
+            sql.push("WHERE ").push(condition);
+        }
+        (None, None) => {}
+    }
+    sql.push_order_by(sort).push_limit_offset(offset, limit);
+
+    let (query, values) = sql.build();
+    Ok(sqlx::query_as_with(
+        sqlx::AssertSqlSafe(query),
+        to_arguments::<Postgres>(values)?,
+    )
+    .fetch_all(tx)
+    .await?)
+}
+
+/// Copies the current state of the entries matching the `filter` and the additional
+/// `condition` into the history table, for the [`DataType::HISTORY`] types. The entries
+/// whose version is already in the history are skipped.
+async fn copy_to_history<DATA: DataType>(
+    tx: &mut PgConnection,
+    table: &TableName,
+    filter: &Filter,
+    condition: &str,
+) -> Result<(), C3p0Error> {
+    if !DATA::HISTORY {
+        return Ok(());
+    }
+
+    let columns = history_columns::<DATA>();
+    let mut sql = SqlBuilder::<Postgres>::new(format!(
+        "INSERT INTO {} ({columns}, history_time) \
+         SELECT {columns}, {NOW_EXPR} FROM {} WHERE ",
+        table.history(),
+        table,
+    ));
+    filter.to_sql(&mut sql);
+    sql.push(condition).push(" ON CONFLICT DO NOTHING");
+
+    let (query, values) = sql.build();
+    sqlx::query_with(
+        sqlx::AssertSqlSafe(query),
+        to_arguments::<Postgres>(values)?,
+    )
+    .execute(tx)
+    .await?;
+    Ok(())
+}
+
+/// Returns the beginning of the statement deleting entries, to be followed by a `WHERE`
+/// clause: a `DELETE` or, for the [`DataType::SOFT_DELETE`] types, an `UPDATE` marking
+/// them as deleted.
+fn delete_query<DATA: DataType>(table: &TableName) -> String {
+    if DATA::SOFT_DELETE {
+        format!(
+            "UPDATE {} SET version = version + 1, update_time = {NOW_EXPR}, delete_time = {NOW_EXPR}",
+            table
+        )
+    } else {
+        format!("DELETE FROM {}", table)
+    }
 }
@@ -0,0 +1,142 @@
+//! The queries returning only some fields of the `data` column.
+//!
+//! A [`Projection`] names the JSON paths to extract: the database builds a JSON object
+//! with a key per field, so that only the selected values are transferred, and
+//! [`Tx::fetch_projected`](crate::Tx::fetch_projected) deserializes it into the type
+//! chosen by the caller, along with the `id` and `version` of the entries:
+//!
+//! ```rust
+//! #[cfg(feature = "postgres")]
+//! pub mod with_postgres {
+//!
+//!     use c3p0::{C3p0Error, DataType, Filter, OrderBy, SortKey, Tx};
+//!     use c3p0::projection::{Projected, Projection};
+//!     use serde::{Deserialize, Serialize};
+//!
+//!     #[derive(Clone, Serialize, Deserialize)]
+//!     pub struct Order {
+//!         pub number: String,
+//!         pub customer: Customer,
+//!         pub lines: Vec<String>,
+//!     }
+//!
+//!     #[derive(Clone, Serialize, Deserialize)]
+//!     pub struct Customer {
+//!         pub name: String,
+//!     }
+//!
+//!     impl DataType for Order {
+//!         const TABLE_NAME: &'static str = "ORDERS";
+//!         type CODEC = Self;
+//!     }
+//!
+//!     #[derive(Deserialize)]
+//!     pub struct OrderRow {
+//!         pub number: String,
+//!         pub customer: String,
+//!     }
+//!
+//!     pub async fn order_rows(
+//!         conn: &mut sqlx::PgConnection,
+//!     ) -> Result<Vec<Projected<Order, OrderRow>>, C3p0Error> {
+//!         let projection = Projection::new()
+//!             .field("number", "number")
+//!             .field("customer", "customer.name");
+//!         conn.fetch_projected::<Order, _>(
+//!             &projection,
+//!             &Filter::and([]),
+//!             &[SortKey::Id(OrderBy::Desc)],
+//!             0,
+//!             Some(50),
+//!         )
+//!         .await
+//!     }
+//! }
+//! ```
+//!
+//! The missing values are `null`. The projections are not supported by the binary
+//! [`Format`](crate::Format)s, only return the uncompressed entries of the types with a
+//! [`Compression`](crate::Compression), and the entries are not decoded by the
+//! [`Codec`](crate::Codec) nor upgraded on read.
+
+use crate::{
+    C3p0Error, DataType,
+    format::json_queryable,
+    record::select_columns_query,
+    sql::{JsonPath, SqlDialect},
+    table::TableName,
+};
+
+/// The fields of the `data` column returned by
+/// [`Tx::fetch_projected`](crate::Tx::fetch_projected), each one with its name and the
+/// JSON path of its value.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Projection {
+    fields: Vec<(String, JsonPath)>,
+}
+
+impl Projection {
+    /// Returns a projection without fields.
+    pub fn new() -> Self {
+        Projection::default()
+    }
+
+    /// Adds the field `name` with the value at the JSON `path`; see [`JsonPath`] for the
+    /// path syntax.
+    pub fn field(mut self, name: impl Into<String>, path: impl Into<JsonPath>) -> Self {
+        self.fields.push((name.into(), path.into()));
+        self
+    }
+
+    /// Returns the fields, in order.
+    pub fn fields(&self) -> &[(String, JsonPath)] {
+        &self.fields
+    }
+
+    /// Returns an error if the `data` of `DATA` is binary or if two fields have the same
+    /// name.
+    pub(crate) fn check<DATA: DataType>(&self, table: &TableName) -> Result<(), C3p0Error> {
+        if !json_queryable::<DATA>() {
+            return Err(C3p0Error::Other {
+                cause: format!(
+                    "The projections are not supported by table [{}]: its data is binary",
+                    table.declared()
+                ),
+            });
+        }
+        for (position, (name, _)) in self.fields.iter().enumerate() {
+            if self.fields[..position]
+                .iter()
+                .any(|(other, _)| other == name)
+            {
+                return Err(C3p0Error::Other {
+                    cause: format!("The projection has two fields named [{name}]"),
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// An entry with the fields of a [`Projection`], deserialized as `P`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Projected<DATA: DataType, P> {
+    /// The id of the entry.
+    pub id: DATA::ID,
+    /// The version of the entry.
+    pub version: i64,
+    /// The projected fields.
+    pub data: P,
+}
+
+/// Returns the query selecting the `id`, the `version` and the JSON object of the
+/// `projection` of the entries of `table`.
+pub(crate) fn projection_query<DATA: DataType, D: SqlDialect>(
+    table: &TableName,
+    projection: &Projection,
+) -> String {
+    select_columns_query::<DATA>(
+        table,
+        &format!("id, version, {}", D::json_object(projection.fields())),
+    )
+}
@@ -1,19 +1,316 @@
+use std::collections::{HashMap, HashSet};
+
 use chrono::{DateTime, Utc};
-use serde::{Deserialize, Serialize};
+use futures_core::Stream;
+use futures_util::{StreamExt, future, stream};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use serde_json::Value;
 use sqlx::{Database, query::QueryAs};
 
-use crate::{codec::Codec, error::C3p0Error};
+use crate::{
+    codec::Codec,
+    decode::LenientRecord,
+    error::C3p0Error,
+    filter::{Field, Filter},
+    format::{Compression, Format, compressed_column},
+    id::{IdCodec, IdType},
+    index::JsonIndex,
+    ops::{TableOps, TableSave},
+    page::{Cursor, Page, PageOrder},
+    patch::JsonPatch,
+    projection::{Projected, Projection},
+    schema::TableReport,
+    sql::{JsonPath, SortKey, SqlBuilder, SqlDialect, SqlValue},
+    table::TableName,
+};
 
-pub trait DataType: Sized + Send + Sync + Unpin {
+pub trait DataType: Sized + Send + Sync + Unpin + DataId {
     /// The name of the database table backing this type.
     ///
-    /// # SQL injection
+    /// The name can be qualified by a schema (a database on MySQL), e.g.
+    /// `"billing.invoices"`. Each part is either made of ASCII letters, digits and
+    /// underscores, starting with a letter or an underscore, or enclosed in double quotes
+    /// or backticks; the parts are quoted with the rules of the backend when they are
+    /// interpolated in the statements. The unquoted parts keep the meaning they have in
+    /// plain SQL, e.g. they are case insensitive on PostgreSQL, while the quoted ones are
+    /// case sensitive.
     ///
-    /// This value is interpolated **verbatim** into SQL strings (via
-    /// `format!`), implementors **must** therefore restrict `TABLE_NAME`
-    /// to a valid SQL identifier.
+    /// The operations fail with a [`C3p0Error::Other`] if the name is not valid.
     const TABLE_NAME: &'static str;
-    type CODEC: Codec<Self>;
+
+    /// The [`Codec`] of the `data` column, e.g. `Self`. The entries have `i64` ids
+    /// generated by the database, unless the codec is wrapped in a [`WithId`](crate::WithId) choosing
+    /// another id type; see the [`id`](crate::id) module.
+    type CODEC: IdCodec<Self>;
+
+    /// The JSON paths of the natural key of the type, e.g. `&["email"]` or
+    /// `&["tenant", "address.city"]`; see [`JsonPath`] for the path syntax.
+    ///
+    /// When not empty, [`Tx::create_table_if_not_exists`](crate::Tx::create_table_if_not_exists)
+    /// creates a unique index over these paths, which is required by
+    /// [`Tx::upsert_by_key`](crate::Tx::upsert_by_key). Entries where one of the paths is
+    /// missing or `null` never conflict.
+    const UNIQUE_KEY: &'static [&'static str] = &[];
+
+    /// The indexes over the `data` column created by
+    /// [`Tx::create_table_if_not_exists`](crate::Tx::create_table_if_not_exists); see the
+    /// [`index`](crate::index) module.
+    const INDEXES: &'static [JsonIndex] = &[];
+
+    /// Whether the entries of the table are soft deleted.
+    ///
+    /// When `true`, the table has a nullable `delete_time` column, the `delete*` methods of
+    /// [`Tx`](crate::Tx) set it instead of removing the rows, and all the other methods
+    /// ignore the rows where it is set. The deleted entries can be fetched with
+    /// [`Tx::fetch_including_deleted`](crate::Tx::fetch_including_deleted), restored with
+    /// [`Tx::restore`](crate::Tx::restore) and physically removed with
+    /// [`Tx::purge`](crate::Tx::purge).
+    const SOFT_DELETE: bool = false;
+
+    /// Whether the prior versions of the entries are kept in a history table.
+    ///
+    /// When `true`, every statement of [`Tx`](crate::Tx) that modifies or deletes entries
+    /// first copies their current state into the `<TABLE_NAME>_HISTORY` table, in the
+    /// same transaction. The snapshots can be read with
+    /// [`Tx::fetch_history`](crate::Tx::fetch_history),
+    /// [`Tx::fetch_version`](crate::Tx::fetch_version) and
+    /// [`Tx::fetch_as_of`](crate::Tx::fetch_as_of).
+    const HISTORY: bool = false;
+
+    /// Whether the entries stored in a previous shape of the [`CODEC`](Self::CODEC), as
+    /// matched by [`Codec::outdated`], are rewritten in the latest shape when they are read.
+    ///
+    /// When `true`, the `fetch_*` methods of [`Tx`](crate::Tx) that return the entries
+    /// at once update the outdated ones, incrementing their `version` as
+    /// [`Tx::update_all`](crate::Tx::update_all) does, and return them updated. The
+    /// streams, the history, the binary [`FORMAT`](Self::FORMAT)s and the compressed
+    /// documents are not rewritten; see [`Tx::upgrade_all`](crate::Tx::upgrade_all) to
+    /// rewrite the whole table.
+    const UPGRADE_ON_READ: bool = false;
+
+    /// The serialization of the `data` column, JSON by default; see the
+    /// [`format`](crate::format) module.
+    const FORMAT: Format = Format::Json;
+
+    /// The compression of the large documents, `None` by default; see [`Compression`].
+    const COMPRESSION: Option<Compression> = None;
+}
+
+/// The type of the `id` of the entries of a [`DataType`], chosen by its
+/// [`DataType::CODEC`].
+pub trait DataId {
+    /// The type of the ids; `i64` for the ids generated by the database.
+    type ID: IdType;
+}
+
+impl<DATA: DataType> DataId for DATA {
+    type ID = <DATA::CODEC as IdCodec<DATA>>::ID;
+}
+
+/// The [`Codec`] of the `data` column of `DATA`.
+pub(crate) type CodecOf<DATA> = <<DATA as DataType>::CODEC as IdCodec<DATA>>::CODEC;
+
+/// Returns the columns copied into the history table of a [`DataType::HISTORY`] type.
+pub(crate) fn history_columns<DATA: DataType>() -> String {
+    format!(
+        "id, version, create_time, update_time, data{}{}",
+        compressed_column::<DATA>(),
+        if DATA::SOFT_DELETE {
+            ", delete_time"
+        } else {
+            ""
+        }
+    )
+}
+
+/// Returns the query selecting the latest version of the entry with the given id whose
+/// `update_time` is not after `timestamp`, followed by the time it was deleted, if any.
+///
+/// A version in the history was deleted when it was soft deleted, or, if it is the last
+/// version of an entry that no longer exists, when it was copied into the history.
+pub(crate) fn as_of_query<D: SqlDialect, DATA: DataType>(
+    table: &TableName,
+    id: &DATA::ID,
+    timestamp: DateTime<Utc>,
+) -> SqlBuilder<D> {
+    let delete_time = if DATA::SOFT_DELETE {
+        "delete_time"
+    } else {
+        "NULL"
+    };
+    let history = table.history();
+    let compressed = compressed_column::<DATA>();
+    let mut sql = SqlBuilder::new(format!(
+        "SELECT id, version, create_time, update_time, data, end_time{compressed} FROM (\
+         SELECT id, version, create_time, update_time, data, {delete_time} AS end_time\
+         {compressed} FROM {table} WHERE id = "
+    ));
+    sql.push_value(id.to_sql())
+        .push(" AND update_time <= ")
+        .push_value(SqlValue::Timestamp(timestamp))
+        .push(&format!(
+            " UNION ALL \
+             SELECT id, version, create_time, update_time, data, \
+             CASE WHEN version < (SELECT MAX(version) FROM {history} WHERE id = "
+        ))
+        .push_value(id.to_sql())
+        .push(&format!(") OR EXISTS (SELECT 1 FROM {table} WHERE id = "))
+        .push_value(id.to_sql())
+        .push(&format!(
+            ") THEN {delete_time} ELSE history_time END{compressed} FROM {history} WHERE id = "
+        ))
+        .push_value(id.to_sql())
+        .push(" AND update_time <= ")
+        .push_value(SqlValue::Timestamp(timestamp))
+        .push(") AS versions ORDER BY version DESC LIMIT 1");
+    sql
+}
+
+/// Returns an error if the type of `table` is not a [`DataType::HISTORY`] type.
+pub(crate) fn check_history<DATA: DataType>(table: &TableName) -> Result<(), C3p0Error> {
+    if !DATA::HISTORY {
+        return Err(C3p0Error::Other {
+            cause: format!(
+                "The DataType of table [{}] does not enable HISTORY",
+                table.declared()
+            ),
+        });
+    }
+    Ok(())
+}
+
+/// Returns ` WHERE delete_time IS NULL` for the [`DataType::SOFT_DELETE`] types, an empty
+/// string otherwise.
+pub(crate) fn where_not_deleted<DATA: DataType>() -> &'static str {
+    if DATA::SOFT_DELETE {
+        " WHERE delete_time IS NULL"
+    } else {
+        ""
+    }
+}
+
+/// Returns ` AND delete_time IS NULL` for the [`DataType::SOFT_DELETE`] types, an empty
+/// string otherwise.
+pub(crate) fn and_not_deleted<DATA: DataType>() -> &'static str {
+    if DATA::SOFT_DELETE {
+        " AND delete_time IS NULL"
+    } else {
+        ""
+    }
+}
+
+/// Returns an error if the type of `table` is not a [`DataType::SOFT_DELETE`] type.
+pub(crate) fn check_soft_delete<DATA: DataType>(table: &TableName) -> Result<(), C3p0Error> {
+    if !DATA::SOFT_DELETE {
+        return Err(C3p0Error::Other {
+            cause: format!(
+                "The DataType of table [{}] does not enable SOFT_DELETE",
+                table.declared()
+            ),
+        });
+    }
+    Ok(())
+}
+
+/// Returns the paths of the [`DataType::UNIQUE_KEY`], or an error if it is empty.
+pub(crate) fn unique_key<DATA: DataType>() -> Result<Vec<JsonPath>, C3p0Error> {
+    if DATA::UNIQUE_KEY.is_empty() {
+        return Err(C3p0Error::Other {
+            cause: format!(
+                "The DataType of table [{}] does not declare a UNIQUE_KEY",
+                DATA::TABLE_NAME
+            ),
+        });
+    }
+    Ok(DATA::UNIQUE_KEY
+        .iter()
+        .map(|path| JsonPath::from(*path))
+        .collect())
+}
+
+/// Returns the id to insert for a new entry: `None` for the ids generated by the
+/// database, the provided or the generated id otherwise.
+pub(crate) fn new_id<DATA: DataType>(
+    table: &TableName,
+    id: Option<DATA::ID>,
+) -> Result<Option<DATA::ID>, C3p0Error> {
+    match (DATA::ID::AUTO_INCREMENT, id) {
+        (true, None) => Ok(None),
+        (true, Some(_)) => Err(C3p0Error::Other {
+            cause: format!(
+                "The ids of table [{}] are generated by the database and cannot be provided",
+                table.declared()
+            ),
+        }),
+        (false, Some(id)) => Ok(Some(id)),
+        (false, None) => DATA::ID::generate()
+            .map(Some)
+            .ok_or_else(|| C3p0Error::Other {
+                cause: format!(
+                    "The ids of table [{}] are not generated and must be provided",
+                    table.declared()
+                ),
+            }),
+    }
+}
+
+/// The `(id, create_time)` of an inserted entry.
+pub(crate) type CreatedRow<DATA> = (<DATA as DataId>::ID, DateTime<Utc>);
+
+/// Returns the `(id, create_time)` rows returned by a multi-row insert of the `records`
+/// in the order of the records.
+///
+/// The ids generated by the database are drawn in the order of the inserted rows, while
+/// the order of the returned rows is not guaranteed.
+pub(crate) fn returned_in_order<DATA: DataType, T>(
+    records: &[(Option<DATA::ID>, T)],
+    mut rows: Vec<CreatedRow<DATA>>,
+) -> Result<Vec<CreatedRow<DATA>>, C3p0Error> {
+    if DATA::ID::AUTO_INCREMENT {
+        rows.sort_by(|(first, _), (second, _)| first.cmp(second));
+        return Ok(rows);
+    }
+    let create_times: HashMap<DATA::ID, DateTime<Utc>> = rows.into_iter().collect();
+    records
+        .iter()
+        .filter_map(|(id, _)| id.as_ref())
+        .map(|id| {
+            create_times
+                .get(id)
+                .map(|create_time| (id.clone(), *create_time))
+                .ok_or_else(|| sqlx::Error::RowNotFound.into())
+        })
+        .collect()
+}
+
+/// Returns the filter matching the entry that has the same [`DataType::UNIQUE_KEY`] as
+/// the encoded `data`, or `None` if one of the paths is missing or `null`, in which case
+/// no entry can conflict with it.
+pub(crate) fn unique_key_filter<DATA: DataType>(
+    data: &CodecOf<DATA>,
+) -> Result<Option<Filter>, C3p0Error> {
+    let data = serde_json::to_value(data).map_err(|err| C3p0Error::Other {
+        cause: format!("Cannot encode data of table [{}]: {err}", DATA::TABLE_NAME),
+    })?;
+    let mut filters = Vec::new();
+    for path in unique_key::<DATA>()? {
+        let value = path
+            .keys()
+            .iter()
+            .try_fold(&data, |value, key| value.get(key));
+        let value = match value {
+            None | Some(Value::Null) => return Ok(None),
+            Some(Value::Bool(value)) => SqlValue::Bool(*value),
+            Some(Value::Number(value)) => match value.as_i64() {
+                Some(value) => SqlValue::Int(value),
+                None => SqlValue::Float(value.as_f64().unwrap_or_default()),
+            },
+            Some(Value::String(value)) => SqlValue::Text(value.clone()),
+            Some(value) => SqlValue::Text(value.to_string()),
+        };
+        filters.push(Filter::eq(Field::Json(path), value));
+    }
+    Ok(Some(Filter::and(filters)))
 }
 
 /// Type-level helper that lets a single generic method accept a [`DataType`] *or*
@@ -32,7 +329,11 @@ impl<DATA: DataType> WithData for Record<DATA> {
     type DATA = DATA;
 }
 
-impl<DATA: DataType> WithData for NewRecord<DATA> {
+impl<DATA: NewData> WithData for NewRecord<DATA> {
+    type DATA = DATA::DATA;
+}
+
+impl<DATA: DataType> WithData for ProvidedId<DATA> {
     type DATA = DATA;
 }
 
@@ -41,7 +342,7 @@ impl<DATA: DataType> WithData for NewRecord<DATA> {
 #[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
 pub struct Record<DATA: DataType> {
     /// The unique identifier of the model.
-    pub id: i64,
+    pub id: DATA::ID,
     /// The version of the model used for optimistic locking.
     pub version: i64,
     /// UTC timestamp when the model was created (DB-side clock).
@@ -52,6 +353,16 @@ pub struct Record<DATA: DataType> {
     pub data: DATA,
 }
 
+/// A record of a [`DataType::SOFT_DELETE`] table, along with its deletion time.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct SoftDeleteRecord<DATA: DataType> {
+    /// The record.
+    pub record: Record<DATA>,
+    /// UTC timestamp when the record was soft deleted (DB-side clock); `None` if it is
+    /// not deleted.
+    pub delete_time: Option<DateTime<Utc>>,
+}
+
 /// A new model for a database table.
 /// This is used to create a new entry in a database table.
 #[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
@@ -65,6 +376,15 @@ impl<DATA: DataType> NewRecord<DATA> {
     pub fn new(data: DATA) -> Self {
         NewRecord { data }
     }
+
+    /// Creates a new `NewRecord` instance with the given id, which is required by the
+    /// id types that are not generated, like `String`. The ids generated by the database
+    /// cannot be provided.
+    pub fn with_id(id: DATA::ID, data: DATA) -> NewRecord<ProvidedId<DATA>> {
+        NewRecord {
+            data: ProvidedId { id, data },
+        }
+    }
 }
 
 impl<DATA: DataType + Default> Default for NewRecord<DATA> {
@@ -82,18 +402,174 @@ where
     }
 }
 
+/// The data of a new entry along with its id, see [`NewRecord::with_id`].
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct ProvidedId<DATA: DataType> {
+    /// The id of the new entry.
+    pub id: DATA::ID,
+    /// The data of the new entry.
+    pub data: DATA,
+}
+
+/// The content of a [`NewRecord`]: a [`DataType`], whose id is generated by the database
+/// or by the [`IdType::generate`] of its id type, or a [`ProvidedId`].
+pub trait NewData: WithData {
+    /// Returns the provided id, if any, and the data of the new entry.
+    fn into_parts(self) -> (Option<<Self::DATA as DataId>::ID>, Self::DATA);
+}
+
+impl<DATA: DataType> NewData for DATA {
+    fn into_parts(self) -> (Option<DATA::ID>, DATA) {
+        (None, self)
+    }
+}
+
+impl<DATA: DataType> NewData for ProvidedId<DATA> {
+    fn into_parts(self) -> (Option<DATA::ID>, DATA) {
+        (Some(self.id), self.data)
+    }
+}
+
+/// The result of a batch update performed with [`Tx::update_all`](crate::Tx::update_all).
+#[derive(Clone, Debug, PartialEq)]
+pub struct UpdateAllResult<DATA: DataType> {
+    /// The updated records, with their new `version` and `update_time`.
+    pub updated: Vec<Record<DATA>>,
+    /// The records that were not updated because their `version` no longer matches the
+    /// one in the database (or because their id was already present earlier in the
+    /// batch). They are returned unchanged so that they can be re-fetched and retried.
+    pub conflicts: Vec<Record<DATA>>,
+}
+
+impl<DATA: DataType> UpdateAllResult<DATA> {
+    /// Splits the `records` of a batch update between the updated and the conflicting
+    /// ones. `updated` maps the ids of the updated entries to their new `update_time`;
+    /// only the first record with a given id can be considered updated.
+    pub(crate) fn new(
+        records: Vec<EncodedRecord<DATA>>,
+        mut updated: HashMap<DATA::ID, DateTime<Utc>>,
+    ) -> Self {
+        let mut result = UpdateAllResult {
+            updated: Vec::new(),
+            conflicts: Vec::new(),
+        };
+        for record in records {
+            match updated.remove(&record.id) {
+                Some(update_time) => {
+                    let mut record = record.decode();
+                    record.version += 1;
+                    record.update_time = update_time;
+                    result.updated.push(record);
+                }
+                None => result.conflicts.push(record.decode()),
+            }
+        }
+        result
+    }
+}
+
+/// A [`Record`] whose data has been encoded with the [`DataType::CODEC`], ready to be
+/// bound to a query.
+pub(crate) struct EncodedRecord<DATA: DataType> {
+    pub(crate) id: DATA::ID,
+    pub(crate) version: i64,
+    pub(crate) create_time: DateTime<Utc>,
+    pub(crate) update_time: DateTime<Utc>,
+    pub(crate) data: CodecOf<DATA>,
+}
+
+impl<DATA: DataType> EncodedRecord<DATA> {
+    pub(crate) fn encode(record: Record<DATA>) -> Self {
+        EncodedRecord {
+            id: record.id,
+            version: record.version,
+            create_time: record.create_time,
+            update_time: record.update_time,
+            data: CodecOf::<DATA>::encode(record.data),
+        }
+    }
+
+    pub(crate) fn decode(self) -> Record<DATA> {
+        Record {
+            id: self.id,
+            version: self.version,
+            create_time: self.create_time,
+            update_time: self.update_time,
+            data: CodecOf::<DATA>::decode(self.data),
+        }
+    }
+}
+
+/// Returns the records of a batch update whose id was not seen earlier in the batch.
+pub(crate) fn unique_by_id<DATA: DataType>(
+    records: &[EncodedRecord<DATA>],
+) -> Vec<&EncodedRecord<DATA>> {
+    let mut ids = HashSet::new();
+    records
+        .iter()
+        .filter(|record| ids.insert(&record.id))
+        .collect()
+}
+
+/// Returns the query selecting all the columns of the entries of the `table`, see
+/// [`DbOps::select_query_base`].
+pub(crate) fn select_query<DATA: DataType>(table: &TableName) -> String {
+    select_columns_query::<DATA>(
+        table,
+        &format!(
+            "id, version, create_time, update_time, data{}",
+            compressed_column::<DATA>()
+        ),
+    )
+}
+
+/// Returns the query selecting the `columns` of the entries of `table`, as
+/// [`DbOps::select_query_base`] does.
+pub(crate) fn select_columns_query<DATA: DataType>(table: &TableName, columns: &str) -> String {
+    if DATA::SOFT_DELETE {
+        format!(
+            "SELECT {columns} FROM (SELECT * FROM {table} WHERE delete_time IS NULL) AS {} ",
+            table.unqualified_name()
+        )
+    } else {
+        format!("SELECT {columns} FROM {table} ")
+    }
+}
+
+/// Returns the identifiers of the [`DataType::TABLE_NAME`] table, whose syntax is checked
+/// when the caller is compiled.
+fn checked_table_name<DATA: DataType, D: SqlDialect>() -> TableName {
+    const {
+        if let Err(reason) = TableName::check(DATA::TABLE_NAME) {
+            panic!("{}", reason);
+        }
+    }
+    TableName::of::<DATA, D>().expect("the table name is checked at compile time")
+}
+
+/// The operations on the entries of the [`DataType::TABLE_NAME`] table, implemented for
+/// [`Record`] by each backend.
 pub trait DbOps<DB: Database, WITH: WithData> {
     /// Returns a SQL query string to select all columns from the database table. I.e.:
     ///
     /// ```sql
     /// SELECT id, version, create_time, update_time, data FROM table_name
     /// ```
-    fn select_query_base() -> String {
-        format!(
-            "SELECT id, version, create_time, update_time, data FROM {} ",
-            WITH::DATA::TABLE_NAME
-        )
-    }
+    ///
+    /// For the [`DataType::SOFT_DELETE`] types, the table is replaced by a subquery
+    /// returning only the entries that are not deleted, aliased with the table name:
+    ///
+    /// ```sql
+    /// SELECT id, version, create_time, update_time, data
+    /// FROM (SELECT * FROM table_name WHERE delete_time IS NULL) AS table_name
+    /// ```
+    ///
+    /// The `data_compressed` column follows the `data` column for the JSON types with a
+    /// [`DataType::COMPRESSION`], see the [`format`](crate::format) module.
+    ///
+    /// A [`DataType::TABLE_NAME`] that is not a valid table name, see
+    /// [`TableName::check`], fails the compilation of the calls.
+    fn select_query_base() -> String;
 
     /// Returns a [`QueryAs`] for `Record<DATA>` whose SQL is the standard select
     /// prefix produced by [`select_query_base`](Self::select_query_base) followed by a
@@ -115,6 +591,12 @@ pub trait DbOps<DB: Database, WITH: WithData> {
     ///
     /// Bind parameters with [`QueryAs::bind`] in the order they appear in `tail`.
     ///
+    /// The query can be executed with [`QueryAs::fetch`] to get a stream of records that
+    /// are decoded one at a time as they are received from the database.
+    ///
+    /// A [`DataType::TABLE_NAME`] that is not a valid table name, see
+    /// [`TableName::check`], fails the compilation of the calls.
+    ///
     /// # SQL injection
     ///
     /// `tail` is concatenated **verbatim** into the final SQL string with no escaping
@@ -151,13 +633,32 @@ pub trait DbOps<DB: Database, WITH: WithData> {
         tail: &str,
     ) -> QueryAs<'_, DB, Record<WITH::DATA>, <DB as Database>::Arguments>;
 
+    /// Creates the table if it does not exist, see
+    /// [`Tx::create_table_if_not_exists`](crate::Tx::create_table_if_not_exists).
+    fn create_table_if_not_exists(
+        tx: &mut DB::Connection,
+    ) -> impl Future<Output = Result<(), C3p0Error>>;
+
+    /// Drops the table if it exists, see
+    /// [`Tx::drop_table_if_exists`](crate::Tx::drop_table_if_exists).
+    fn drop_table_if_exists(
+        tx: &mut DB::Connection,
+        cascade: bool,
+    ) -> impl Future<Output = Result<(), C3p0Error>>;
+
+    /// Returns the differences between the table and the expected structure, see
+    /// [`Tx::verify_table`](crate::Tx::verify_table).
+    fn verify_table(
+        tx: &mut DB::Connection,
+    ) -> impl Future<Output = Result<TableReport, C3p0Error>>;
+
     /// Returns the number of rows in the table.
     fn count_all(tx: &mut DB::Connection) -> impl Future<Output = Result<u64, C3p0Error>>;
 
     /// Returns true if the entry with the given id exists.
     fn exists_by_id(
         tx: &mut DB::Connection,
-        id: i64,
+        id: <WITH::DATA as DataId>::ID,
     ) -> impl Future<Output = Result<bool, C3p0Error>>;
 
     /// Returns entries in the table ordered by `id` ASC, skipping the first `offset`
@@ -168,19 +669,104 @@ pub trait DbOps<DB: Database, WITH: WithData> {
         limit: Option<u64>,
     ) -> impl Future<Output = Result<Vec<Record<WITH::DATA>>, C3p0Error>>;
 
+    /// Returns entries in the table ordered by the `sort` keys, skipping the first `offset`
+    /// rows and returning at most `limit` rows. `limit = None` means no upper bound.
+    fn fetch_all_sorted(
+        tx: &mut DB::Connection,
+        sort: &[SortKey],
+        offset: u64,
+        limit: Option<u64>,
+    ) -> impl Future<Output = Result<Vec<Record<WITH::DATA>>, C3p0Error>>;
+
+    /// Returns a stream of all the entries in the table ordered by `id` ASC.
+    /// Each row is decoded only when it is polled from the stream.
+    fn fetch_all_stream<'a>(
+        tx: &'a mut DB::Connection,
+    ) -> impl Stream<Item = Result<Record<WITH::DATA>, C3p0Error>> + use<'a, DB, WITH, Self>
+    where
+        WITH::DATA: 'a;
+
+    /// Returns the entries matching the `filter` ordered by `id` ASC, skipping the first
+    /// `offset` rows and returning at most `limit` rows. `limit = None` means no upper bound.
+    fn fetch_where(
+        tx: &mut DB::Connection,
+        filter: &Filter,
+        offset: u64,
+        limit: Option<u64>,
+    ) -> impl Future<Output = Result<Vec<Record<WITH::DATA>>, C3p0Error>>;
+
+    /// Same as [`fetch_where`](Self::fetch_where), with an optional `filter`, but the
+    /// entries whose `data` cannot be decoded are returned as a [`DecodeError`](crate::DecodeError) instead of
+    /// failing the whole query.
+    fn fetch_lenient(
+        tx: &mut DB::Connection,
+        filter: Option<&Filter>,
+        offset: u64,
+        limit: Option<u64>,
+    ) -> impl Future<Output = Result<Vec<LenientRecord<WITH::DATA>>, C3p0Error>>;
+
+    /// Same as [`fetch_where_sorted`](Self::fetch_where_sorted), but only the fields of
+    /// the `projection` are returned, deserialized as `P`.
+    fn fetch_projected<P: DeserializeOwned + Send + Unpin>(
+        tx: &mut DB::Connection,
+        projection: &Projection,
+        filter: &Filter,
+        sort: &[SortKey],
+        offset: u64,
+        limit: Option<u64>,
+    ) -> impl Future<Output = Result<Vec<Projected<WITH::DATA, P>>, C3p0Error>>;
+
+    /// Returns the entries matching the `filter` ordered by the `sort` keys, skipping the
+    /// first `offset` rows and returning at most `limit` rows. `limit = None` means no upper bound.
+    fn fetch_where_sorted(
+        tx: &mut DB::Connection,
+        filter: &Filter,
+        sort: &[SortKey],
+        offset: u64,
+        limit: Option<u64>,
+    ) -> impl Future<Output = Result<Vec<Record<WITH::DATA>>, C3p0Error>>;
+
+    /// Returns a stream of the entries matching the `filter` ordered by `id` ASC.
+    /// Each row is decoded only when it is polled from the stream.
+    fn fetch_where_stream<'a>(
+        tx: &'a mut DB::Connection,
+        filter: &Filter,
+    ) -> impl Stream<Item = Result<Record<WITH::DATA>, C3p0Error>> + use<'a, DB, WITH, Self>
+    where
+        WITH::DATA: 'a;
+
+    /// Returns the number of entries matching the `filter`.
+    fn count_where(
+        tx: &mut DB::Connection,
+        filter: &Filter,
+    ) -> impl Future<Output = Result<u64, C3p0Error>>;
+
+    /// Returns the page of at most `limit` entries matching the optional `filter` that
+    /// follows the `cursor` in the given `order`. A `None` cursor returns the first page.
+    fn fetch_page(
+        tx: &mut DB::Connection,
+        filter: Option<&Filter>,
+        order: PageOrder,
+        cursor: Option<&Cursor>,
+        limit: u64,
+    ) -> impl Future<Output = Result<Page<WITH::DATA>, C3p0Error>>;
+
     /// Returns the entry with the given id. Returns None if the entry does not exist.
     fn fetch_one_optional_by_id(
         tx: &mut DB::Connection,
-        id: i64,
+        id: <WITH::DATA as DataId>::ID,
     ) -> impl Future<Output = Result<Option<Record<WITH::DATA>>, C3p0Error>>;
 
     /// Returns the entry with the given id. Returns an error if the entry does not exist.
     fn fetch_one_by_id(
         tx: &mut DB::Connection,
-        id: i64,
+        id: <WITH::DATA as DataId>::ID,
     ) -> impl Future<Output = Result<Record<WITH::DATA>, C3p0Error>>;
 
     /// Deletes the entry with the given id.
+    ///
+    /// For the [`DataType::SOFT_DELETE`] types the entry is marked as deleted instead, and
+    /// the returned record has the incremented `version` and the new `update_time`.
     fn delete(
         self,
         tx: &mut DB::Connection,
@@ -192,7 +778,37 @@ pub trait DbOps<DB: Database, WITH: WithData> {
     /// Deletes the entry with the given id.
     fn delete_by_id(
         tx: &mut DB::Connection,
-        id: i64,
+        id: <WITH::DATA as DataId>::ID,
+    ) -> impl Future<Output = Result<u64, C3p0Error>>;
+
+    /// Deletes the entries matching the `filter` and returns the number of deleted rows.
+    fn delete_where(
+        tx: &mut DB::Connection,
+        filter: &Filter,
+    ) -> impl Future<Output = Result<u64, C3p0Error>>;
+
+    /// Returns all the entries, including the soft deleted ones, ordered by `id` ASC.
+    /// Returns an error if the type is not a [`DataType::SOFT_DELETE`] type.
+    fn fetch_including_deleted(
+        tx: &mut DB::Connection,
+        offset: u64,
+        limit: Option<u64>,
+    ) -> impl Future<Output = Result<Vec<SoftDeleteRecord<WITH::DATA>>, C3p0Error>>;
+
+    /// Restores the soft deleted entry with the given id, incrementing its `version`.
+    /// Returns [`sqlx::Error::RowNotFound`] if there is no deleted entry with that id, or
+    /// an error if the type is not a [`DataType::SOFT_DELETE`] type.
+    fn restore(
+        tx: &mut DB::Connection,
+        id: <WITH::DATA as DataId>::ID,
+    ) -> impl Future<Output = Result<Record<WITH::DATA>, C3p0Error>>;
+
+    /// Physically removes the soft deleted entries, optionally only those deleted before
+    /// `deleted_before`, and returns their number. Returns an error if the type is not
+    /// a [`DataType::SOFT_DELETE`] type.
+    fn purge(
+        tx: &mut DB::Connection,
+        deleted_before: Option<DateTime<Utc>>,
     ) -> impl Future<Output = Result<u64, C3p0Error>>;
 
     /// Updates the entry with the given id. Returns an error if the entry does not exist.
@@ -202,6 +818,52 @@ pub trait DbOps<DB: Database, WITH: WithData> {
         self,
         tx: &mut DB::Connection,
     ) -> impl Future<Output = Result<Record<WITH::DATA>, C3p0Error>>;
+
+    /// Applies the `patch` to the `data` of the entry with the given id, incrementing its
+    /// `version` and setting its `update_time`, and returns the patched record.
+    ///
+    /// When `version` is given, the patch is only applied if it matches the version of
+    /// the entry, otherwise an [`OptimisticLockError`](C3p0Error::OptimisticLockError) is
+    /// returned. Without `version`, a missing entry results in a
+    /// [`sqlx::Error::RowNotFound`].
+    fn patch(
+        tx: &mut DB::Connection,
+        id: <WITH::DATA as DataId>::ID,
+        version: Option<i64>,
+        patch: &JsonPatch,
+    ) -> impl Future<Output = Result<Record<WITH::DATA>, C3p0Error>>;
+
+    /// Updates the entries in as few statements as possible, using the same optimistic
+    /// locking as [`update`](Self::update). Instead of failing, the records whose version
+    /// does not match are returned in [`UpdateAllResult::conflicts`].
+    fn update_all(
+        records: Vec<Record<WITH::DATA>>,
+        tx: &mut DB::Connection,
+    ) -> impl Future<Output = Result<UpdateAllResult<WITH::DATA>, C3p0Error>>;
+
+    /// Returns the prior versions of the entry with the given id ordered by `version` ASC.
+    /// Returns an error if the type is not a [`DataType::HISTORY`] type.
+    fn fetch_history(
+        tx: &mut DB::Connection,
+        id: <WITH::DATA as DataId>::ID,
+    ) -> impl Future<Output = Result<Vec<Record<WITH::DATA>>, C3p0Error>>;
+
+    /// Returns the given version of the entry with the given id, be it the current or a
+    /// prior one. Returns an error if the type is not a [`DataType::HISTORY`] type.
+    fn fetch_version(
+        tx: &mut DB::Connection,
+        id: <WITH::DATA as DataId>::ID,
+        version: i64,
+    ) -> impl Future<Output = Result<Option<Record<WITH::DATA>>, C3p0Error>>;
+
+    /// Returns the version of the entry with the given id that was current at `timestamp`,
+    /// or `None` if it did not exist yet or was already deleted. Returns an error if the
+    /// type is not a [`DataType::HISTORY`] type.
+    fn fetch_as_of(
+        tx: &mut DB::Connection,
+        id: <WITH::DATA as DataId>::ID,
+        timestamp: DateTime<Utc>,
+    ) -> impl Future<Output = Result<Option<Record<WITH::DATA>>, C3p0Error>>;
 }
 
 pub trait DbSave<DB: Database, WITH: WithData> {
@@ -210,4 +872,321 @@ pub trait DbSave<DB: Database, WITH: WithData> {
         self,
         tx: &mut DB::Connection,
     ) -> impl Future<Output = Result<Record<WITH::DATA>, C3p0Error>>;
+
+    /// Creates a new entry or, if an entry with the same [`DataType::UNIQUE_KEY`] exists,
+    /// replaces its data, incrementing its `version` and preserving its `create_time`.
+    fn upsert_by_key(
+        self,
+        tx: &mut DB::Connection,
+    ) -> impl Future<Output = Result<Record<WITH::DATA>, C3p0Error>>;
+
+    /// Creates new entries with multi-row inserts, split in as many statements as
+    /// needed to respect the bind parameters limit of the database (and, on MySQL, the
+    /// packet size).
+    ///
+    /// The returned records are in the same order as `records`.
+    fn save_all(
+        records: Vec<Self>,
+        tx: &mut DB::Connection,
+    ) -> impl Future<Output = Result<Vec<Record<WITH::DATA>>, C3p0Error>>
+    where
+        Self: Sized;
+}
+
+// The operations run on the `TABLE_NAME` table, a `Table` runs the `TableOps` on its own
+impl<DB, WITH, T> DbOps<DB, WITH> for T
+where
+    DB: Database + SqlDialect,
+    WITH: WithData,
+    T: TableOps<DB, WITH>,
+{
+    fn select_query_base() -> String {
+        select_query::<WITH::DATA>(&checked_table_name::<WITH::DATA, DB>())
+    }
+
+    fn query_with_tail(
+        tail: &str,
+    ) -> QueryAs<'_, DB, Record<WITH::DATA>, <DB as Database>::Arguments> {
+        T::query_with_tail(&checked_table_name::<WITH::DATA, DB>(), tail)
+    }
+
+    async fn create_table_if_not_exists(tx: &mut DB::Connection) -> Result<(), C3p0Error> {
+        T::create_table_if_not_exists(tx, &TableName::of::<WITH::DATA, DB>()?).await
+    }
+
+    async fn drop_table_if_exists(tx: &mut DB::Connection, cascade: bool) -> Result<(), C3p0Error> {
+        T::drop_table_if_exists(tx, &TableName::of::<WITH::DATA, DB>()?, cascade).await
+    }
+
+    async fn verify_table(tx: &mut DB::Connection) -> Result<TableReport, C3p0Error> {
+        T::verify_table(tx, &TableName::of::<WITH::DATA, DB>()?).await
+    }
+
+    async fn count_all(tx: &mut DB::Connection) -> Result<u64, C3p0Error> {
+        T::count_all(tx, &TableName::of::<WITH::DATA, DB>()?).await
+    }
+
+    async fn exists_by_id(
+        tx: &mut DB::Connection,
+        id: <WITH::DATA as DataId>::ID,
+    ) -> Result<bool, C3p0Error> {
+        T::exists_by_id(tx, &TableName::of::<WITH::DATA, DB>()?, id).await
+    }
+
+    async fn fetch_all(
+        tx: &mut DB::Connection,
+        offset: u64,
+        limit: Option<u64>,
+    ) -> Result<Vec<Record<WITH::DATA>>, C3p0Error> {
+        T::fetch_all(tx, &TableName::of::<WITH::DATA, DB>()?, offset, limit).await
+    }
+
+    async fn fetch_all_sorted(
+        tx: &mut DB::Connection,
+        sort: &[SortKey],
+        offset: u64,
+        limit: Option<u64>,
+    ) -> Result<Vec<Record<WITH::DATA>>, C3p0Error> {
+        T::fetch_all_sorted(tx, &TableName::of::<WITH::DATA, DB>()?, sort, offset, limit).await
+    }
+
+    fn fetch_all_stream<'a>(
+        tx: &'a mut DB::Connection,
+    ) -> impl Stream<Item = Result<Record<WITH::DATA>, C3p0Error>> + use<'a, DB, WITH, T>
+    where
+        WITH::DATA: 'a,
+    {
+        match TableName::of::<WITH::DATA, DB>() {
+            Ok(table) => T::fetch_all_stream(tx, &table).left_stream(),
+            Err(err) => stream::once(future::ready(Err(err))).right_stream(),
+        }
+    }
+
+    async fn fetch_where(
+        tx: &mut DB::Connection,
+        filter: &Filter,
+        offset: u64,
+        limit: Option<u64>,
+    ) -> Result<Vec<Record<WITH::DATA>>, C3p0Error> {
+        T::fetch_where(
+            tx,
+            &TableName::of::<WITH::DATA, DB>()?,
+            filter,
+            offset,
+            limit,
+        )
+        .await
+    }
+
+    async fn fetch_lenient(
+        tx: &mut DB::Connection,
+        filter: Option<&Filter>,
+        offset: u64,
+        limit: Option<u64>,
+    ) -> Result<Vec<LenientRecord<WITH::DATA>>, C3p0Error> {
+        T::fetch_lenient(
+            tx,
+            &TableName::of::<WITH::DATA, DB>()?,
+            filter,
+            offset,
+            limit,
+        )
+        .await
+    }
+
+    async fn fetch_projected<P: DeserializeOwned + Send + Unpin>(
+        tx: &mut DB::Connection,
+        projection: &Projection,
+        filter: &Filter,
+        sort: &[SortKey],
+        offset: u64,
+        limit: Option<u64>,
+    ) -> Result<Vec<Projected<WITH::DATA, P>>, C3p0Error> {
+        T::fetch_projected(
+            tx,
+            &TableName::of::<WITH::DATA, DB>()?,
+            projection,
+            filter,
+            sort,
+            offset,
+            limit,
+        )
+        .await
+    }
+
+    async fn fetch_where_sorted(
+        tx: &mut DB::Connection,
+        filter: &Filter,
+        sort: &[SortKey],
+        offset: u64,
+        limit: Option<u64>,
+    ) -> Result<Vec<Record<WITH::DATA>>, C3p0Error> {
+        T::fetch_where_sorted(
+            tx,
+            &TableName::of::<WITH::DATA, DB>()?,
+            filter,
+            sort,
+            offset,
+            limit,
+        )
+        .await
+    }
+
+    fn fetch_where_stream<'a>(
+        tx: &'a mut DB::Connection,
+        filter: &Filter,
+    ) -> impl Stream<Item = Result<Record<WITH::DATA>, C3p0Error>> + use<'a, DB, WITH, T>
+    where
+        WITH::DATA: 'a,
+    {
+        match TableName::of::<WITH::DATA, DB>() {
+            Ok(table) => T::fetch_where_stream(tx, &table, filter).left_stream(),
+            Err(err) => stream::once(future::ready(Err(err))).right_stream(),
+        }
+    }
+
+    async fn count_where(tx: &mut DB::Connection, filter: &Filter) -> Result<u64, C3p0Error> {
+        T::count_where(tx, &TableName::of::<WITH::DATA, DB>()?, filter).await
+    }
+
+    async fn fetch_page(
+        tx: &mut DB::Connection,
+        filter: Option<&Filter>,
+        order: PageOrder,
+        cursor: Option<&Cursor>,
+        limit: u64,
+    ) -> Result<Page<WITH::DATA>, C3p0Error> {
+        T::fetch_page(
+            tx,
+            &TableName::of::<WITH::DATA, DB>()?,
+            filter,
+            order,
+            cursor,
+            limit,
+        )
+        .await
+    }
+
+    async fn fetch_one_optional_by_id(
+        tx: &mut DB::Connection,
+        id: <WITH::DATA as DataId>::ID,
+    ) -> Result<Option<Record<WITH::DATA>>, C3p0Error> {
+        T::fetch_one_optional_by_id(tx, &TableName::of::<WITH::DATA, DB>()?, id).await
+    }
+
+    async fn fetch_one_by_id(
+        tx: &mut DB::Connection,
+        id: <WITH::DATA as DataId>::ID,
+    ) -> Result<Record<WITH::DATA>, C3p0Error> {
+        T::fetch_one_by_id(tx, &TableName::of::<WITH::DATA, DB>()?, id).await
+    }
+
+    async fn delete(self, tx: &mut DB::Connection) -> Result<Record<WITH::DATA>, C3p0Error> {
+        T::delete(self, tx, &TableName::of::<WITH::DATA, DB>()?).await
+    }
+
+    async fn delete_all(tx: &mut DB::Connection) -> Result<u64, C3p0Error> {
+        T::delete_all(tx, &TableName::of::<WITH::DATA, DB>()?).await
+    }
+
+    async fn delete_by_id(
+        tx: &mut DB::Connection,
+        id: <WITH::DATA as DataId>::ID,
+    ) -> Result<u64, C3p0Error> {
+        T::delete_by_id(tx, &TableName::of::<WITH::DATA, DB>()?, id).await
+    }
+
+    async fn delete_where(tx: &mut DB::Connection, filter: &Filter) -> Result<u64, C3p0Error> {
+        T::delete_where(tx, &TableName::of::<WITH::DATA, DB>()?, filter).await
+    }
+
+    async fn fetch_including_deleted(
+        tx: &mut DB::Connection,
+        offset: u64,
+        limit: Option<u64>,
+    ) -> Result<Vec<SoftDeleteRecord<WITH::DATA>>, C3p0Error> {
+        T::fetch_including_deleted(tx, &TableName::of::<WITH::DATA, DB>()?, offset, limit).await
+    }
+
+    async fn restore(
+        tx: &mut DB::Connection,
+        id: <WITH::DATA as DataId>::ID,
+    ) -> Result<Record<WITH::DATA>, C3p0Error> {
+        T::restore(tx, &TableName::of::<WITH::DATA, DB>()?, id).await
+    }
+
+    async fn purge(
+        tx: &mut DB::Connection,
+        deleted_before: Option<DateTime<Utc>>,
+    ) -> Result<u64, C3p0Error> {
+        T::purge(tx, &TableName::of::<WITH::DATA, DB>()?, deleted_before).await
+    }
+
+    async fn update(self, tx: &mut DB::Connection) -> Result<Record<WITH::DATA>, C3p0Error> {
+        T::update(self, tx, &TableName::of::<WITH::DATA, DB>()?).await
+    }
+
+    async fn patch(
+        tx: &mut DB::Connection,
+        id: <WITH::DATA as DataId>::ID,
+        version: Option<i64>,
+        patch: &JsonPatch,
+    ) -> Result<Record<WITH::DATA>, C3p0Error> {
+        T::patch(tx, &TableName::of::<WITH::DATA, DB>()?, id, version, patch).await
+    }
+
+    async fn update_all(
+        records: Vec<Record<WITH::DATA>>,
+        tx: &mut DB::Connection,
+    ) -> Result<UpdateAllResult<WITH::DATA>, C3p0Error> {
+        T::update_all(records, tx, &TableName::of::<WITH::DATA, DB>()?).await
+    }
+
+    async fn fetch_history(
+        tx: &mut DB::Connection,
+        id: <WITH::DATA as DataId>::ID,
+    ) -> Result<Vec<Record<WITH::DATA>>, C3p0Error> {
+        T::fetch_history(tx, &TableName::of::<WITH::DATA, DB>()?, id).await
+    }
+
+    async fn fetch_version(
+        tx: &mut DB::Connection,
+        id: <WITH::DATA as DataId>::ID,
+        version: i64,
+    ) -> Result<Option<Record<WITH::DATA>>, C3p0Error> {
+        T::fetch_version(tx, &TableName::of::<WITH::DATA, DB>()?, id, version).await
+    }
+
+    async fn fetch_as_of(
+        tx: &mut DB::Connection,
+        id: <WITH::DATA as DataId>::ID,
+        timestamp: DateTime<Utc>,
+    ) -> Result<Option<Record<WITH::DATA>>, C3p0Error> {
+        T::fetch_as_of(tx, &TableName::of::<WITH::DATA, DB>()?, id, timestamp).await
+    }
+}
+
+impl<DB, WITH, T> DbSave<DB, WITH> for T
+where
+    DB: Database + SqlDialect,
+    WITH: WithData,
+    T: TableSave<DB, WITH>,
+{
+    async fn save(self, tx: &mut DB::Connection) -> Result<Record<WITH::DATA>, C3p0Error> {
+        T::save(self, tx, &TableName::of::<WITH::DATA, DB>()?).await
+    }
+
+    async fn upsert_by_key(self, tx: &mut DB::Connection) -> Result<Record<WITH::DATA>, C3p0Error> {
+        T::upsert_by_key(self, tx, &TableName::of::<WITH::DATA, DB>()?).await
+    }
+
+    async fn save_all(
+        records: Vec<Self>,
+        tx: &mut DB::Connection,
+    ) -> Result<Vec<Record<WITH::DATA>>, C3p0Error>
+    where
+        Self: Sized,
+    {
+        T::save_all(records, tx, &TableName::of::<WITH::DATA, DB>()?).await
+    }
 }
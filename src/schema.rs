@@ -0,0 +1,602 @@
+//! The structure of the tables, as declared by the [`DataType`]s.
+//!
+//! A [`TableSchema`] holds the declarations of a [`DataType`] that determine the structure
+//! of its table. It is rendered by [`Tx::create_table_if_not_exists`](crate::Tx::create_table_if_not_exists)
+//! and, with the `migrate` feature, by the generator of migrations of the `migrate` module.
+//!
+//! A [`TableReport`] lists the differences between an existing table and the structure
+//! expected by c3p0, as returned by [`Tx::verify_table`](crate::Tx::verify_table).
+
+use std::fmt::{Display, Formatter};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    DataType, IdType,
+    error::C3p0Error,
+    format::{Format, has_compressed_column},
+    index::{IndexKind, JsonIndex},
+    sql::{JsonType, SqlDialect},
+    table::TableName,
+};
+
+/// The name of the index over the [`DataType::UNIQUE_KEY`].
+pub(crate) const UNIQUE_KEY_INDEX: &str = "unique_key";
+
+/// The structure of the table of a [`DataType`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TableSchema {
+    /// The [`DataType::TABLE_NAME`].
+    pub table_name: String,
+    /// Whether the ids are generated by the database, see [`IdType::AUTO_INCREMENT`].
+    pub auto_increment: bool,
+    /// The [`DataType::SOFT_DELETE`].
+    pub soft_delete: bool,
+    /// The [`DataType::HISTORY`].
+    pub history: bool,
+    /// The [`DataType::FORMAT`].
+    #[serde(default)]
+    pub format: Format,
+    /// Whether the table has the `data_compressed` column of the JSON types with a
+    /// [`DataType::COMPRESSION`], see the [`format`](crate::format) module.
+    #[serde(default)]
+    pub compressed: bool,
+    /// The indexes over the `data` column: the one named `unique_key` over the
+    /// [`DataType::UNIQUE_KEY`], if any, followed by the [`DataType::INDEXES`].
+    pub indexes: Vec<IndexSchema>,
+}
+
+/// An index over the `data` column of a [`TableSchema`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum IndexSchema {
+    /// An index over the values at the JSON paths, extracted as the given types.
+    Values {
+        name: String,
+        paths: Vec<(String, JsonType)>,
+        unique: bool,
+    },
+    /// A containment index, see [`JsonIndex::containment`].
+    Containment { name: String },
+}
+
+impl IndexSchema {
+    /// Returns the name of the index, without the table name.
+    pub fn name(&self) -> &str {
+        match self {
+            IndexSchema::Values { name, .. } | IndexSchema::Containment { name } => name,
+        }
+    }
+}
+
+impl From<&JsonIndex> for IndexSchema {
+    fn from(index: &JsonIndex) -> Self {
+        match index.kind() {
+            IndexKind::Values { paths, unique } => IndexSchema::Values {
+                name: index.name().to_owned(),
+                paths: paths
+                    .iter()
+                    .map(|(path, json_type)| ((*path).to_owned(), *json_type))
+                    .collect(),
+                unique,
+            },
+            IndexKind::Containment => IndexSchema::Containment {
+                name: index.name().to_owned(),
+            },
+        }
+    }
+}
+
+impl TableSchema {
+    /// Returns the structure of the table of `DATA`, or an error if its declarations are
+    /// not valid.
+    pub fn of<DATA: DataType>() -> Result<Self, C3p0Error> {
+        let mut indexes = Vec::with_capacity(DATA::INDEXES.len() + 1);
+        if !DATA::UNIQUE_KEY.is_empty() && DATA::COMPRESSION.is_some() {
+            // The values of the compressed documents would not be indexed
+            return Err(C3p0Error::Other {
+                cause: format!(
+                    "Invalid UNIQUE_KEY of table [{}]: it is not supported with the COMPRESSION",
+                    DATA::TABLE_NAME
+                ),
+            });
+        }
+        if !DATA::UNIQUE_KEY.is_empty() {
+            indexes.push(IndexSchema::Values {
+                name: UNIQUE_KEY_INDEX.to_owned(),
+                paths: DATA::UNIQUE_KEY
+                    .iter()
+                    .map(|path| ((*path).to_owned(), JsonType::Text))
+                    .collect(),
+                unique: true,
+            });
+        }
+        for index in DATA::INDEXES {
+            if index.name() == UNIQUE_KEY_INDEX {
+                return Err(C3p0Error::Other {
+                    cause: format!(
+                        "Invalid index [{UNIQUE_KEY_INDEX}] of table [{}]: the name is \
+                         reserved for the index of the UNIQUE_KEY",
+                        DATA::TABLE_NAME
+                    ),
+                });
+            }
+            if index.is_unique() && DATA::COMPRESSION.is_some() {
+                // The compressed documents would never conflict with each other
+                return Err(C3p0Error::Other {
+                    cause: format!(
+                        "Invalid index [{}] of table [{}]: a unique index is not supported with \
+                         the COMPRESSION",
+                        index.name(),
+                        DATA::TABLE_NAME
+                    ),
+                });
+            }
+            indexes.push(index.into());
+        }
+        let schema = TableSchema {
+            table_name: DATA::TABLE_NAME.to_owned(),
+            auto_increment: DATA::ID::AUTO_INCREMENT,
+            soft_delete: DATA::SOFT_DELETE,
+            history: DATA::HISTORY,
+            format: DATA::FORMAT,
+            compressed: has_compressed_column::<DATA>(),
+            indexes,
+        };
+        schema.check()?;
+        Ok(schema)
+    }
+
+    /// Checks that the indexes have distinct valid names and index at least one path, and
+    /// that there are no indexes and no `data_compressed` column if the
+    /// [`format`](Self::format) is binary.
+    pub fn check(&self) -> Result<(), C3p0Error> {
+        if self.format.is_binary() && self.compressed {
+            return Err(C3p0Error::Other {
+                cause: format!(
+                    "Invalid table [{}]: the {:?} format stores the compressed data in the \
+                     data column",
+                    self.table_name, self.format
+                ),
+            });
+        }
+        if self.format.is_binary() && !self.indexes.is_empty() {
+            return Err(C3p0Error::Other {
+                cause: format!(
+                    "Invalid indexes of table [{}]: the {:?} format does not support JSON \
+                     indexes or a UNIQUE_KEY",
+                    self.table_name, self.format
+                ),
+            });
+        }
+        for (position, index) in self.indexes.iter().enumerate() {
+            let invalid = |reason: &str| C3p0Error::Other {
+                cause: format!(
+                    "Invalid index [{}] of table [{}]: {reason}",
+                    index.name(),
+                    self.table_name
+                ),
+            };
+            let name = index.name();
+            let valid_name = name
+                .chars()
+                .next()
+                .is_some_and(|first| first.is_ascii_alphabetic() || first == '_')
+                && name
+                    .chars()
+                    .all(|char| char.is_ascii_alphanumeric() || char == '_');
+            if !valid_name {
+                return Err(invalid(
+                    "the name must be made of ASCII letters, digits and underscores and start \
+                     with a letter or an underscore",
+                ));
+            }
+            if self.indexes[..position]
+                .iter()
+                .any(|other| other.name() == name)
+            {
+                return Err(invalid("the name is used by another index"));
+            }
+            if let IndexSchema::Values { paths, .. } = index
+                && paths.is_empty()
+            {
+                return Err(invalid("it must index at least one path"));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The differences between a table and the structure expected by c3p0, see
+/// [`Tx::verify_table`](crate::Tx::verify_table).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TableReport {
+    /// The [`DataType::TABLE_NAME`].
+    pub table_name: String,
+    /// The differences found, empty if the table has the expected structure.
+    pub issues: Vec<TableIssue>,
+}
+
+impl TableReport {
+    /// Returns true if the table has the expected structure.
+    pub fn is_valid(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+impl Display for TableReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.is_valid() {
+            return write!(f, "The table [{}] is valid", self.table_name);
+        }
+        write!(f, "The table [{}] is not valid:", self.table_name)?;
+        for issue in &self.issues {
+            write!(f, "\n- {issue}")?;
+        }
+        Ok(())
+    }
+}
+
+/// A difference between a table and the structure expected by c3p0. The `table` is the
+/// quoted identifier of the table, or of its history table.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TableIssue {
+    /// The table does not exist.
+    MissingTable { table: String },
+    /// A mandatory column does not exist.
+    MissingColumn { table: String, column: String },
+    /// A column has a type that c3p0 cannot read or write; `expected` is the type
+    /// created by c3p0.
+    WrongType {
+        table: String,
+        column: String,
+        expected: String,
+        actual: String,
+    },
+    /// The primary key is missing or is not over the expected columns.
+    WrongPrimaryKey {
+        table: String,
+        expected: Vec<String>,
+        actual: Vec<String>,
+    },
+    /// The `data` column is stored as text without a check that it contains valid JSON.
+    MissingJsonCheck { table: String },
+}
+
+impl Display for TableIssue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TableIssue::MissingTable { table } => write!(f, "the table {table} does not exist"),
+            TableIssue::MissingColumn { table, column } => {
+                write!(f, "the column {column} of {table} does not exist")
+            }
+            TableIssue::WrongType {
+                table,
+                column,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "the column {column} of {table} has type [{actual}] instead of [{expected}]"
+            ),
+            TableIssue::WrongPrimaryKey {
+                table,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "the primary key of {table} is ({}) instead of ({})",
+                actual.join(", "),
+                expected.join(", ")
+            ),
+            TableIssue::MissingJsonCheck { table } => {
+                write!(
+                    f,
+                    "the data column of {table} is not checked to be valid JSON"
+                )
+            }
+        }
+    }
+}
+
+/// The kinds of the values of the columns of the c3p0 tables.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum ColumnKind {
+    Id,
+    Version,
+    Time,
+    Data,
+    /// The `data` column of a binary [`Format`].
+    Bytes,
+}
+
+/// A column of an existing table.
+pub(crate) struct ColumnInfo {
+    pub(crate) name: String,
+    /// The type of the column, as reported by the database.
+    pub(crate) data_type: String,
+    pub(crate) primary_key: bool,
+}
+
+/// The structure of an existing table.
+pub(crate) struct TableInfo {
+    pub(crate) columns: Vec<ColumnInfo>,
+    /// Whether the table has a check that the `data` column contains valid JSON.
+    pub(crate) json_check: bool,
+}
+
+/// The DDL rendering rules of a database backend.
+pub(crate) trait Ddl: SqlDialect {
+    /// The type of the nullable `delete_time` column of the [`DataType::SOFT_DELETE`] types.
+    #[cfg_attr(not(feature = "migrate"), allow(dead_code))]
+    const DELETE_TIME_TYPE: &'static str;
+
+    /// The type of the nullable `data_compressed` column of the compressed JSON types.
+    const COMPRESSED_TYPE: &'static str;
+
+    /// Returns the statements creating the table and, if declared, its history table and
+    /// its indexes, unless they exist.
+    fn create_table(schema: &TableSchema, table: &TableName) -> Vec<String>;
+
+    /// Returns the statement creating the history table unless it exists.
+    fn create_history_table(schema: &TableSchema, table: &TableName) -> String;
+
+    /// Returns the statements adding the index to the table.
+    fn create_index(index: &IndexSchema, table: &TableName) -> Vec<String>;
+
+    /// Returns the statements removing the index from the table.
+    #[cfg_attr(not(feature = "migrate"), allow(dead_code))]
+    fn drop_index(index: &IndexSchema, table: &TableName) -> Vec<String>;
+
+    /// Returns the types of the columns of the kind that c3p0 can read and write, lower
+    /// case and without the length and precision; the first one is the created type.
+    fn column_types(kind: ColumnKind, auto_increment: bool) -> &'static [&'static str];
+
+    /// Returns true if the `data` column of the type must have a check that it contains
+    /// valid JSON.
+    fn requires_json_check(data_type: &str) -> bool;
+
+    /// Returns the statements dropping the table and its history table, if they exist.
+    fn drop_table(schema: &TableSchema, table: &TableName, cascade: bool) -> Vec<String> {
+        let cascade = if cascade { " CASCADE" } else { "" };
+        let mut statements = Vec::with_capacity(2);
+        if schema.history {
+            statements.push(format!("DROP TABLE IF EXISTS {}{cascade}", table.history()));
+        }
+        statements.push(format!("DROP TABLE IF EXISTS {table}{cascade}"));
+        statements
+    }
+}
+
+/// Returns the statements changing the structure of the table of `previous` into the one
+/// of `current`.
+#[cfg_attr(not(feature = "migrate"), allow(dead_code))]
+pub(crate) fn alter_table<D: Ddl>(
+    previous: &TableSchema,
+    current: &TableSchema,
+) -> Result<Vec<String>, C3p0Error> {
+    current.check()?;
+    let table = TableName::parse::<D>(&current.table_name)?;
+    if previous.auto_increment != current.auto_increment {
+        return Err(C3p0Error::Other {
+            cause: format!(
+                "The type of the ids of table [{}] cannot be changed",
+                current.table_name
+            ),
+        });
+    }
+    if previous.format != current.format {
+        return Err(C3p0Error::Other {
+            cause: format!(
+                "The format of the data of table [{}] cannot be changed",
+                current.table_name
+            ),
+        });
+    }
+
+    if previous.compressed && !current.compressed {
+        // The compressed documents would be lost by removing the column
+        return Err(C3p0Error::Other {
+            cause: format!(
+                "The compression of the data of table [{}] cannot be removed",
+                current.table_name
+            ),
+        });
+    }
+
+    let mut statements = Vec::new();
+    for index in &previous.indexes {
+        if !current.indexes.contains(index) {
+            statements.extend(D::drop_index(index, &table));
+        }
+    }
+
+    if previous.history && !current.history {
+        statements.push(format!("DROP TABLE IF EXISTS {}", table.history()));
+    }
+    let mut tables = vec![table.name()];
+    if previous.history && current.history {
+        tables.push(table.history());
+    }
+    match (previous.soft_delete, current.soft_delete) {
+        (false, true) => {
+            for name in &tables {
+                statements.push(format!(
+                    "ALTER TABLE {name} ADD COLUMN delete_time {}",
+                    D::DELETE_TIME_TYPE
+                ));
+            }
+        }
+        (true, false) => {
+            // The soft deleted entries would be restored by removing the column
+            statements.push(format!("DELETE FROM {table} WHERE delete_time IS NOT NULL"));
+            for name in &tables {
+                statements.push(format!("ALTER TABLE {name} DROP COLUMN delete_time"));
+            }
+        }
+        _ => {}
+    }
+    if !previous.compressed && current.compressed {
+        for name in &tables {
+            statements.push(format!(
+                "ALTER TABLE {name} ADD COLUMN data_compressed {}",
+                D::COMPRESSED_TYPE
+            ));
+        }
+    }
+    if !previous.history && current.history {
+        statements.push(D::create_history_table(current, &table));
+    }
+
+    for index in &current.indexes {
+        if !previous.indexes.contains(index) {
+            statements.extend(D::create_index(index, &table));
+        }
+    }
+    Ok(statements)
+}
+
+/// Returns the changes of [`alter_table`] that lose data.
+#[cfg_attr(not(feature = "migrate"), allow(dead_code))]
+pub(crate) fn destructive_changes(previous: &TableSchema, current: &TableSchema) -> Vec<String> {
+    let mut changes = Vec::new();
+    if previous.history && !current.history {
+        changes.push(format!(
+            "drop the history table of [{}]",
+            current.table_name
+        ));
+    }
+    if previous.soft_delete && !current.soft_delete {
+        changes.push(format!(
+            "delete the soft deleted entries of [{}]",
+            current.table_name
+        ));
+    }
+    changes
+}
+
+/// Returns the differences between the existing tables of `schema` and the structure
+/// expected by c3p0. `main` and `history` are the structures of the table and of its
+/// history table, `None` if they do not exist.
+pub(crate) fn verify_table<D: Ddl>(
+    schema: &TableSchema,
+    table: &TableName,
+    main: Option<TableInfo>,
+    history: Option<TableInfo>,
+) -> TableReport {
+    let mut issues = Vec::new();
+    verify_columns::<D>(schema, table.name(), main, &["id"], &mut issues);
+    if schema.history {
+        verify_columns::<D>(
+            schema,
+            table.history(),
+            history,
+            &["id", "version"],
+            &mut issues,
+        );
+    }
+    TableReport {
+        table_name: schema.table_name.clone(),
+        issues,
+    }
+}
+
+fn verify_columns<D: Ddl>(
+    schema: &TableSchema,
+    table: &str,
+    info: Option<TableInfo>,
+    primary_key: &[&str],
+    issues: &mut Vec<TableIssue>,
+) {
+    let Some(info) = info else {
+        issues.push(TableIssue::MissingTable {
+            table: table.to_owned(),
+        });
+        return;
+    };
+
+    let mut columns = vec![
+        ("id", ColumnKind::Id),
+        ("version", ColumnKind::Version),
+        ("create_time", ColumnKind::Time),
+        ("update_time", ColumnKind::Time),
+        (
+            "data",
+            if schema.format.is_binary() {
+                ColumnKind::Bytes
+            } else {
+                ColumnKind::Data
+            },
+        ),
+    ];
+    if schema.compressed {
+        columns.push(("data_compressed", ColumnKind::Bytes));
+    }
+    if schema.soft_delete {
+        columns.push(("delete_time", ColumnKind::Time));
+    }
+    if primary_key.len() > 1 {
+        columns.push(("history_time", ColumnKind::Time));
+    }
+    for (name, kind) in columns {
+        let Some(column) = info
+            .columns
+            .iter()
+            .find(|column| column.name.eq_ignore_ascii_case(name))
+        else {
+            issues.push(TableIssue::MissingColumn {
+                table: table.to_owned(),
+                column: name.to_owned(),
+            });
+            continue;
+        };
+        let data_type = normalize_type(&column.data_type);
+        let types = D::column_types(kind, schema.auto_increment);
+        if !types.contains(&data_type.as_str()) {
+            issues.push(TableIssue::WrongType {
+                table: table.to_owned(),
+                column: name.to_owned(),
+                expected: types[0].to_owned(),
+                actual: column.data_type.clone(),
+            });
+        } else if kind == ColumnKind::Data && D::requires_json_check(&data_type) && !info.json_check
+        {
+            issues.push(TableIssue::MissingJsonCheck {
+                table: table.to_owned(),
+            });
+        }
+    }
+
+    let mut actual: Vec<String> = info
+        .columns
+        .iter()
+        .filter(|column| column.primary_key)
+        .map(|column| column.name.to_lowercase())
+        .collect();
+    actual.sort();
+    let mut expected: Vec<String> = primary_key.iter().map(|name| (*name).to_owned()).collect();
+    expected.sort();
+    if actual != expected {
+        issues.push(TableIssue::WrongPrimaryKey {
+            table: table.to_owned(),
+            expected,
+            actual,
+        });
+    }
+}
+
+/// Returns the type in lower case, without the length and precision, e.g. `timestamp`
+/// for `TIMESTAMP(3)`.
+fn normalize_type(data_type: &str) -> String {
+    let mut normalized = String::with_capacity(data_type.len());
+    let mut depth = 0;
+    for char in data_type.chars() {
+        match char {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            _ if depth == 0 => normalized.push(char.to_ascii_lowercase()),
+            _ => {}
+        }
+    }
+    normalized.split_whitespace().collect::<Vec<_>>().join(" ")
+}
@@ -1,5 +1,10 @@
 use std::fmt::{Debug, Display};
 
+use chrono::{DateTime, Utc};
+use sqlx::{Arguments, Database, Encode, Type};
+
+use crate::{error::C3p0Error, patch::JsonPatch};
+
 /// An enum to represent the order by of a query.
 #[derive(Clone, Debug, PartialEq)]
 pub enum OrderBy {
@@ -17,3 +22,314 @@ impl Display for OrderBy {
         }
     }
 }
+
+/// A key used to sort the entries returned by a query.
+///
+/// Entries whose JSON path is missing or `null` are always sorted last, whatever the
+/// direction.
+#[derive(Clone, Debug, PartialEq)]
+pub enum SortKey {
+    /// Sorts by the `id` column.
+    Id(OrderBy),
+    /// Sorts by the `create_time` column.
+    CreateTime(OrderBy),
+    /// Sorts by the `update_time` column.
+    UpdateTime(OrderBy),
+    /// Sorts by the value at a path of the `data` column, compared as the given type.
+    Json(JsonPath, JsonType, OrderBy),
+}
+
+impl SortKey {
+    /// Returns a key sorting by the value at `path` of the `data` column, compared as `json_type`.
+    pub fn json(path: impl Into<JsonPath>, json_type: JsonType, order: OrderBy) -> Self {
+        SortKey::Json(path.into(), json_type, order)
+    }
+}
+
+/// A value bound as a query parameter by the SQL generated by c3p0.
+///
+/// When compared against a JSON path, the variant also decides how the JSON value is
+/// extracted: `Int` and `Float` compare numerically, `Bool` as a boolean and `Text` as a
+/// string. `Timestamp` values compare against JSON paths as RFC 3339 strings, which is
+/// the format `chrono` uses when serialising a `DateTime<Utc>`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum SqlValue {
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Text(String),
+    Timestamp(DateTime<Utc>),
+}
+
+impl SqlValue {
+    /// Returns the JSON type a JSON path must be extracted as to be compared with this value.
+    pub(crate) fn json_type(&self) -> JsonType {
+        match self {
+            SqlValue::Bool(_) => JsonType::Bool,
+            SqlValue::Int(_) | SqlValue::Float(_) => JsonType::Number,
+            SqlValue::Text(_) | SqlValue::Timestamp(_) => JsonType::Text,
+        }
+    }
+}
+
+impl From<bool> for SqlValue {
+    fn from(value: bool) -> Self {
+        SqlValue::Bool(value)
+    }
+}
+
+impl From<i32> for SqlValue {
+    fn from(value: i32) -> Self {
+        SqlValue::Int(value.into())
+    }
+}
+
+impl From<i64> for SqlValue {
+    fn from(value: i64) -> Self {
+        SqlValue::Int(value)
+    }
+}
+
+impl From<u32> for SqlValue {
+    fn from(value: u32) -> Self {
+        SqlValue::Int(value.into())
+    }
+}
+
+impl From<f64> for SqlValue {
+    fn from(value: f64) -> Self {
+        SqlValue::Float(value)
+    }
+}
+
+impl From<String> for SqlValue {
+    fn from(value: String) -> Self {
+        SqlValue::Text(value)
+    }
+}
+
+impl From<&str> for SqlValue {
+    fn from(value: &str) -> Self {
+        SqlValue::Text(value.to_owned())
+    }
+}
+
+impl From<DateTime<Utc>> for SqlValue {
+    fn from(value: DateTime<Utc>) -> Self {
+        SqlValue::Timestamp(value)
+    }
+}
+
+/// The type a value stored at a [`JsonPath`] is extracted as.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JsonType {
+    /// The value is compared and sorted as a string.
+    Text,
+    /// The value is compared and sorted as a number.
+    Number,
+    /// The value is compared as a boolean.
+    Bool,
+}
+
+/// A path to a value nested inside the `data` JSON column.
+///
+/// Each element of the path is an object key; `JsonPath::from("address.city")` addresses
+/// the `city` key of the `address` object. Use [`JsonPath::new`] when a key itself
+/// contains a dot.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct JsonPath {
+    keys: Vec<String>,
+}
+
+impl JsonPath {
+    /// Creates a path from its individual keys.
+    pub fn new<K: Into<String>>(keys: impl IntoIterator<Item = K>) -> Self {
+        JsonPath {
+            keys: keys.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    /// Returns the keys of the path.
+    pub fn keys(&self) -> &[String] {
+        &self.keys
+    }
+
+    /// Renders the path using the `$.key1.key2` syntax accepted by MySQL and SQLite.
+    /// Keys that are not plain identifiers are double-quoted.
+    #[cfg_attr(not(any(feature = "mysql", feature = "sqlite")), allow(dead_code))]
+    pub(crate) fn to_dollar_path(&self) -> String {
+        let mut path = String::from("$");
+        for key in &self.keys {
+            path.push('.');
+            let is_identifier = !key.is_empty()
+                && !key.starts_with(|c: char| c.is_ascii_digit())
+                && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+            if is_identifier {
+                path.push_str(key);
+            } else {
+                path.push('"');
+                path.push_str(&key.replace('\\', "\\\\").replace('"', "\\\""));
+                path.push('"');
+            }
+        }
+        path
+    }
+}
+
+impl From<&str> for JsonPath {
+    fn from(path: &str) -> Self {
+        JsonPath::new(path.split('.'))
+    }
+}
+
+impl From<String> for JsonPath {
+    fn from(path: String) -> Self {
+        JsonPath::from(path.as_str())
+    }
+}
+
+/// The SQL rendering rules of a database backend.
+pub(crate) trait SqlDialect {
+    /// The clause to use in place of `LIMIT` when an `OFFSET` is given without a limit.
+    const NO_LIMIT: &'static str;
+
+    /// The maximum number of bind parameters accepted by a single statement.
+    const MAX_BIND_PARAMS: usize;
+
+    /// Returns the placeholder of the bind parameter at the given 1-based `index`.
+    fn placeholder(index: usize) -> String;
+
+    /// Returns `value` as a quoted SQL string literal.
+    fn string_literal(value: &str) -> String {
+        format!("'{}'", value.replace('\'', "''"))
+    }
+
+    /// Returns `identifier` as a quoted SQL identifier.
+    fn quote_identifier(identifier: &str) -> String {
+        format!("\"{}\"", identifier.replace('"', "\"\""))
+    }
+
+    /// Returns the identifier an unquoted `identifier` refers to once it is quoted.
+    fn fold_identifier(identifier: &str) -> String {
+        identifier.to_owned()
+    }
+
+    /// Returns an expression extracting the value at `path` from the `data` column as
+    /// `json_type`. Missing keys and JSON `null` values must evaluate to SQL `NULL`.
+    fn json_field(path: &JsonPath, json_type: JsonType) -> String;
+
+    /// Returns the `ORDER BY` term sorting the nullable `expr`, placing `NULL`s last.
+    fn order_by_nulls_last(expr: &str, order: &OrderBy) -> String {
+        format!("{expr} {order} NULLS LAST")
+    }
+
+    /// Appends a condition matching when the `data` column contains the JSON `value`,
+    /// see [`Filter::contains`](crate::filter::Filter::contains).
+    fn json_contains(sql: &mut SqlBuilder<Self>, value: &serde_json::Value)
+    where
+        Self: Sized;
+
+    /// Returns an expression building a JSON object with the given keys and the JSON values
+    /// at the paths of the `data` column. Missing values must evaluate to JSON `null`.
+    fn json_object(fields: &[(String, JsonPath)]) -> String;
+
+    /// Returns an expression applying the `patch` to the `data` column. The values to bind
+    /// are appended to `values`, in placeholder order.
+    fn json_patch(patch: &JsonPatch, values: &mut Vec<serde_json::Value>) -> String;
+
+    /// Converts a value to the representation the backend expects when it is bound.
+    fn bind_value(value: SqlValue) -> SqlValue {
+        value
+    }
+}
+
+/// Accumulates an SQL string and the parameters bound to its placeholders.
+pub(crate) struct SqlBuilder<D: SqlDialect> {
+    sql: String,
+    values: Vec<SqlValue>,
+    _dialect: std::marker::PhantomData<D>,
+}
+
+impl<D: SqlDialect> SqlBuilder<D> {
+    pub(crate) fn new(sql: impl Into<String>) -> Self {
+        SqlBuilder {
+            sql: sql.into(),
+            values: Vec::new(),
+            _dialect: std::marker::PhantomData,
+        }
+    }
+
+    /// Appends a raw SQL fragment.
+    pub(crate) fn push(&mut self, sql: &str) -> &mut Self {
+        self.sql.push_str(sql);
+        self
+    }
+
+    /// Appends the placeholder of a new bind parameter holding `value`.
+    pub(crate) fn push_value(&mut self, value: SqlValue) -> &mut Self {
+        self.values.push(D::bind_value(value));
+        self.sql.push_str(&D::placeholder(self.values.len()));
+        self
+    }
+
+    /// Appends an `ORDER BY` clause for the sort keys. The `id` is always used as the last
+    /// key, so that the order is deterministic.
+    pub(crate) fn push_order_by(&mut self, sort: &[SortKey]) -> &mut Self {
+        let mut terms = Vec::with_capacity(sort.len() + 1);
+        for key in sort {
+            terms.push(match key {
+                SortKey::Id(order) => format!("id {order}"),
+                SortKey::CreateTime(order) => format!("create_time {order}"),
+                SortKey::UpdateTime(order) => format!("update_time {order}"),
+                SortKey::Json(path, json_type, order) => {
+                    D::order_by_nulls_last(&D::json_field(path, *json_type), order)
+                }
+            });
+        }
+        if !sort.iter().any(|key| matches!(key, SortKey::Id(_))) {
+            terms.push("id asc".to_owned());
+        }
+        self.push(" ORDER BY ").push(&terms.join(", "))
+    }
+
+    /// Appends the `LIMIT` and `OFFSET` clauses. `limit = None` means no upper bound.
+    pub(crate) fn push_limit_offset(&mut self, offset: u64, limit: Option<u64>) -> &mut Self {
+        match limit {
+            Some(limit) => self.push(" LIMIT ").push_value(SqlValue::Int(limit as i64)),
+            None => self.push(" ").push(D::NO_LIMIT),
+        };
+        self.push(" OFFSET ")
+            .push_value(SqlValue::Int(offset as i64))
+    }
+
+    /// Returns the SQL string and the values to bind, in placeholder order.
+    pub(crate) fn build(self) -> (String, Vec<SqlValue>) {
+        (self.sql, self.values)
+    }
+}
+
+/// Converts a list of [`SqlValue`]s into the backend specific query arguments.
+pub(crate) fn to_arguments<DB: Database>(
+    values: Vec<SqlValue>,
+) -> Result<<DB as Database>::Arguments, C3p0Error>
+where
+    for<'q> bool: Encode<'q, DB> + Type<DB>,
+    for<'q> i64: Encode<'q, DB> + Type<DB>,
+    for<'q> f64: Encode<'q, DB> + Type<DB>,
+    for<'q> String: Encode<'q, DB> + Type<DB>,
+    for<'q> DateTime<Utc>: Encode<'q, DB> + Type<DB>,
+{
+    let mut arguments = <DB as Database>::Arguments::default();
+    for value in values {
+        match value {
+            SqlValue::Bool(value) => arguments.add(value),
+            SqlValue::Int(value) => arguments.add(value),
+            SqlValue::Float(value) => arguments.add(value),
+            SqlValue::Text(value) => arguments.add(value),
+            SqlValue::Timestamp(value) => arguments.add(value),
+        }
+        .map_err(sqlx::Error::Encode)?;
+    }
+    Ok(arguments)
+}
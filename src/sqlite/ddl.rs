@@ -0,0 +1,196 @@
+use sqlx::{Row, Sqlite, SqliteConnection};
+
+use crate::{
+    error::C3p0Error,
+    schema::{ColumnInfo, ColumnKind, Ddl, IndexSchema, TableInfo, TableSchema},
+    sql::{JsonPath, SqlDialect},
+    table::TableName,
+};
+
+/// Returns the type and the constraints of the `data` column, followed by the
+/// `data_compressed` column of the compressed JSON types.
+fn data_column(schema: &TableSchema) -> String {
+    if schema.format.is_binary() {
+        "BLOB NOT NULL".to_owned()
+    } else if schema.compressed {
+        format!(
+            "JSON NOT NULL CHECK (json_valid(data)), data_compressed {}",
+            <Sqlite as Ddl>::COMPRESSED_TYPE
+        )
+    } else {
+        "JSON NOT NULL CHECK (json_valid(data))".to_owned()
+    }
+}
+
+impl Ddl for Sqlite {
+    const DELETE_TIME_TYPE: &'static str = "TEXT";
+    const COMPRESSED_TYPE: &'static str = "BLOB";
+
+    fn create_table(schema: &TableSchema, table: &TableName) -> Vec<String> {
+        let data = data_column(schema);
+        let mut statements = vec![format!(
+            r#"
+                CREATE TABLE IF NOT EXISTS {} (
+                    id {},
+                    version integer not null,
+                    create_time TEXT NOT NULL,
+                    update_time TEXT NOT NULL,
+                    data {data}{}
+                )
+                "#,
+            table,
+            if schema.auto_increment {
+                "integer primary key autoincrement"
+            } else {
+                "TEXT not null primary key"
+            },
+            if schema.soft_delete {
+                ", delete_time TEXT"
+            } else {
+                ""
+            },
+        )];
+        if schema.history {
+            statements.push(Self::create_history_table(schema, table));
+        }
+        for index in &schema.indexes {
+            statements.extend(Self::create_index(index, table));
+        }
+        statements
+    }
+
+    fn create_history_table(schema: &TableSchema, table: &TableName) -> String {
+        let data = data_column(schema);
+        format!(
+            r#"
+                CREATE TABLE IF NOT EXISTS {} (
+                    id {} not null,
+                    version integer not null,
+                    create_time TEXT NOT NULL,
+                    update_time TEXT NOT NULL,
+                    data {data},{}
+                    history_time TEXT NOT NULL,
+                    primary key (id, version)
+                )
+                "#,
+            table.history(),
+            if schema.auto_increment {
+                "integer"
+            } else {
+                "TEXT"
+            },
+            if schema.soft_delete {
+                "\n                    delete_time TEXT,"
+            } else {
+                ""
+            },
+        )
+    }
+
+    /// The containment queries are specific to PostgreSQL, so the containment indexes
+    /// are ignored.
+    fn create_index(index: &IndexSchema, table: &TableName) -> Vec<String> {
+        let IndexSchema::Values { paths, unique, .. } = index else {
+            return vec![];
+        };
+        let values: Vec<String> = paths
+            .iter()
+            .map(|(path, json_type)| Self::json_field(&JsonPath::from(path.as_str()), *json_type))
+            .collect();
+        // SQLite qualifies the index with the schema, not the table
+        vec![format!(
+            "CREATE {}INDEX IF NOT EXISTS {} ON {} ({})",
+            if *unique { "UNIQUE " } else { "" },
+            index_name(index, table),
+            table.unqualified_name(),
+            values.join(", "),
+        )]
+    }
+
+    fn drop_index(index: &IndexSchema, table: &TableName) -> Vec<String> {
+        match index {
+            IndexSchema::Values { .. } => {
+                vec![format!("DROP INDEX IF EXISTS {}", index_name(index, table))]
+            }
+            IndexSchema::Containment { .. } => vec![],
+        }
+    }
+
+    /// The declared types, which also set the affinity of the columns.
+    fn column_types(kind: ColumnKind, auto_increment: bool) -> &'static [&'static str] {
+        match kind {
+            // Only an `integer primary key` is an alias of the rowid
+            ColumnKind::Id if auto_increment => &["integer"],
+            ColumnKind::Id => &["text", "varchar"],
+            ColumnKind::Version => &["integer", "bigint", "int"],
+            ColumnKind::Time => &["text", "datetime"],
+            ColumnKind::Data => &["json", "text"],
+            ColumnKind::Bytes => &["blob"],
+        }
+    }
+
+    /// SQLite stores JSON as text.
+    fn requires_json_check(_data_type: &str) -> bool {
+        true
+    }
+
+    /// SQLite does not support `CASCADE`.
+    fn drop_table(schema: &TableSchema, table: &TableName, _cascade: bool) -> Vec<String> {
+        let mut statements = Vec::with_capacity(2);
+        if schema.history {
+            statements.push(format!("DROP TABLE IF EXISTS {}", table.history()));
+        }
+        statements.push(format!("DROP TABLE IF EXISTS {table}"));
+        statements
+    }
+}
+
+/// Returns the name of the index qualified by the schema of the table, if any.
+fn index_name(index: &IndexSchema, table: &TableName) -> String {
+    let name = table.index(index.name());
+    match table.schema() {
+        Some(schema) => format!("{schema}.{name}"),
+        None => name,
+    }
+}
+
+/// Returns the structure of the table, or of its history table, or `None` if it does
+/// not exist.
+pub(super) async fn table_info(
+    tx: &mut SqliteConnection,
+    table: &TableName,
+    history: bool,
+) -> Result<Option<TableInfo>, C3p0Error> {
+    let name = table.unquoted_name(history);
+    let master = match table.schema() {
+        Some(schema) => format!("{schema}.sqlite_master"),
+        None => "sqlite_master".to_owned(),
+    };
+    let sql: Option<String> = sqlx::query_scalar(sqlx::AssertSqlSafe(format!(
+        "SELECT sql FROM {master} WHERE type = 'table' AND name = ? COLLATE NOCASE"
+    )))
+    .bind(&name)
+    .fetch_optional(&mut *tx)
+    .await?;
+    let Some(sql) = sql else {
+        return Ok(None);
+    };
+    let columns = sqlx::query("SELECT name, type, pk FROM pragma_table_info(?, ?)")
+        .bind(&name)
+        .bind(table.unquoted_schema())
+        .fetch_all(&mut *tx)
+        .await?
+        .iter()
+        .map(|row| {
+            Ok(ColumnInfo {
+                name: row.try_get(0)?,
+                data_type: row.try_get(1)?,
+                primary_key: row.try_get::<i64, _>(2)? > 0,
+            })
+        })
+        .collect::<Result<_, sqlx::Error>>()?;
+    Ok(Some(TableInfo {
+        columns,
+        json_check: sql.to_lowercase().contains("json_valid("),
+    }))
+}
@@ -0,0 +1,149 @@
+use sqlx::Sqlite;
+
+use serde_json::Value;
+
+use crate::{
+    patch::{JsonPatch, PatchOp},
+    sql::{JsonPath, JsonType, SqlBuilder, SqlDialect, SqlValue},
+};
+
+impl SqlDialect for Sqlite {
+    /// SQLite treats a negative LIMIT as "no upper bound" (per its docs).
+    const NO_LIMIT: &'static str = "LIMIT -1";
+
+    /// The default `SQLITE_MAX_VARIABLE_NUMBER` since SQLite 3.32.
+    const MAX_BIND_PARAMS: usize = 32766;
+
+    fn placeholder(_index: usize) -> String {
+        "?".to_owned()
+    }
+
+    /// Renders `data ->> '$.key1.key2'`, which already returns the SQL value matching the
+    /// JSON type (`true` and `false` become `1` and `0`).
+    fn json_field(path: &JsonPath, _json_type: JsonType) -> String {
+        format!(
+            "(data ->> {})",
+            Self::string_literal(&path.to_dollar_path())
+        )
+    }
+
+    /// SQLite has no containment operator, so the condition compares each value of the
+    /// JSON `value` with `json_type` and `json_extract`, see [`push_contains`].
+    fn json_contains(sql: &mut SqlBuilder<Self>, value: &Value) {
+        push_contains(sql, "'$'", value, 0);
+    }
+
+    /// Renders `json_object('name', data -> '$.key1.key2', ...)`; the `->` operator returns
+    /// the JSON values, while `->>` and `json_extract` return the booleans as integers.
+    fn json_object(fields: &[(String, JsonPath)]) -> String {
+        let arguments: Vec<String> = fields
+            .iter()
+            .map(|(name, path)| {
+                format!(
+                    "{}, data -> {}",
+                    Self::string_literal(name),
+                    Self::string_literal(&path.to_dollar_path())
+                )
+            })
+            .collect();
+        format!("json_object({})", arguments.join(", "))
+    }
+
+    /// Timestamps are stored as text with the same format produced by `NOW_EXPR`, so they
+    /// must be bound with that format to compare correctly.
+    fn bind_value(value: SqlValue) -> SqlValue {
+        match value {
+            SqlValue::Timestamp(value) => {
+                SqlValue::Text(value.format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string())
+            }
+            value => value,
+        }
+    }
+
+    /// Renders nested `json_patch`, `json_set` and `json_remove` calls. `json_set` does
+    /// not replace the parents that are not objects, so they are first replaced with a
+    /// merge patch.
+    fn json_patch(patch: &JsonPatch, values: &mut Vec<Value>) -> String {
+        match patch {
+            JsonPatch::Merge(patch) => {
+                values.push(patch.clone());
+                "json_patch(data, ?)".to_owned()
+            }
+            JsonPatch::Ops(ops) => ops.iter().fold("data".to_owned(), |data, op| match op {
+                PatchOp::Set(path, value) => {
+                    let data = match PatchOp::parents_patch(path) {
+                        Some(parents) => {
+                            values.push(parents);
+                            format!("json_patch({data}, ?)")
+                        }
+                        None => data,
+                    };
+                    values.push(value.clone());
+                    format!(
+                        "json_set({data}, {}, json(?))",
+                        Self::string_literal(&path.to_dollar_path())
+                    )
+                }
+                PatchOp::Remove(path) => format!(
+                    "json_remove({data}, {})",
+                    Self::string_literal(&path.to_dollar_path())
+                ),
+            }),
+        }
+    }
+}
+
+/// Appends the condition matching when the JSON value at `path`, an SQL expression
+/// evaluating to a path of the `data` column, contains `value`. The elements of the arrays
+/// are searched with a `json_each` subquery, whose alias is numbered by `depth`.
+fn push_contains(sql: &mut SqlBuilder<Sqlite>, path: &str, value: &Value, depth: usize) {
+    let json_type = format!("json_type(data, {path})");
+    let json_extract = format!("json_extract(data, {path})");
+    match value {
+        Value::Null => {
+            sql.push(&format!("({json_type} = 'null')"));
+        }
+        Value::Bool(value) => {
+            sql.push(&format!("({json_type} = '{value}')"));
+        }
+        Value::Number(number) => {
+            let number = match number.as_i64() {
+                Some(number) => SqlValue::Int(number),
+                None => SqlValue::Float(number.as_f64().unwrap_or(f64::NAN)),
+            };
+            sql.push(&format!(
+                "({json_type} IN ('integer', 'real') AND {json_extract} = "
+            ))
+            .push_value(number)
+            .push(")");
+        }
+        Value::String(value) => {
+            sql.push(&format!("({json_type} = 'text' AND {json_extract} = "))
+                .push_value(SqlValue::Text(value.clone()))
+                .push(")");
+        }
+        Value::Array(values) => {
+            let element = format!("c3p0_element_{depth}");
+            sql.push(&format!("({json_type} = 'array'"));
+            for value in values {
+                sql.push(&format!(
+                    " AND EXISTS (SELECT 1 FROM json_each(data, {path}) AS {element} WHERE "
+                ));
+                push_contains(sql, &format!("{element}.fullkey"), value, depth + 1);
+                sql.push(")");
+            }
+            sql.push(")");
+        }
+        Value::Object(entries) => {
+            sql.push(&format!("({json_type} = 'object'"));
+            for (key, value) in entries {
+                // The path of the key without the leading `$`
+                let key_path = JsonPath::new([key]).to_dollar_path();
+                let key_path = Sqlite::string_literal(&key_path[1..]);
+                sql.push(" AND ");
+                push_contains(sql, &format!("({path} || {key_path})"), value, depth);
+            }
+            sql.push(")");
+        }
+    }
+}
@@ -0,0 +1,920 @@
+//! Tables whose name is chosen at runtime.
+//!
+//! The [`Tx`] methods run on the table of [`DataType::TABLE_NAME`], which is fixed at
+//! compile time. A [`Table`] binds a [`DataType`] to a table name known only at runtime,
+//! e.g. one table per tenant or a name read from the configuration, and exposes the same
+//! operations:
+//!
+//! ```rust
+//! #[cfg(feature = "postgres")]
+//! pub mod with_postgres {
+//!
+//!     use c3p0::{C3p0Error, DataType, Record, Table};
+//!
+//!     #[derive(Clone, serde::Serialize, serde::Deserialize, Debug, PartialEq)]
+//!     pub struct Order {
+//!         pub amount: i64,
+//!     }
+//!
+//!     impl DataType for Order {
+//!         const TABLE_NAME: &'static str = "ORDERS";
+//!         type CODEC = Self;
+//!     }
+//!
+//!     pub async fn save_order(
+//!         conn: &mut sqlx::PgConnection,
+//!         tenant_id: u64,
+//!         order: Order,
+//!     ) -> Result<Record<Order>, C3p0Error> {
+//!         let orders = Table::<Order>::new(format!("tenant_{tenant_id}_orders"))?;
+//!         orders.save(conn, order.into()).await
+//!     }
+//! }
+//! ```
+//!
+//! Both [`DataType::TABLE_NAME`] and the name of a [`Table`] are validated and quoted
+//! with the same rules: the unquoted parts keep the meaning they have in plain SQL, e.g.
+//! on PostgreSQL `Table::new("ORDERS")` refers to the `orders` table, while
+//! `Table::new("\"ORDERS\"")` refers to `"ORDERS"`.
+
+use std::fmt::{Display, Formatter};
+use std::marker::PhantomData;
+
+use chrono::{DateTime, Utc};
+use futures_core::Stream;
+use futures_util::{StreamExt, future, stream};
+use serde::de::DeserializeOwned;
+
+use crate::{
+    C3p0Error, DataType, NewData, NewRecord, Record, SoftDeleteRecord, Tx, UpdateAllResult,
+    decode::{DecodeError, LenientRecord, scan_for_decode_errors},
+    filter::Filter,
+    ops::{TableOps, TableSave},
+    page::{Cursor, Page, PageOrder},
+    patch::JsonPatch,
+    projection::{Projected, Projection},
+    schema::TableReport,
+    sql::{SortKey, SqlDialect},
+    upgrade::{UpgradeProgress, upgrade_all, upgrade_on_read},
+};
+
+/// The SQL identifiers of a table and of the objects derived from it, as they are
+/// interpolated in the statements.
+#[derive(Clone, Debug)]
+pub struct TableName {
+    declared: String,
+    name: String,
+    history: String,
+    schema: Option<String>,
+    /// The unquoted name of the schema of the table, if it is qualified.
+    #[cfg_attr(not(any(feature = "mysql", feature = "sqlite")), allow(dead_code))]
+    unquoted_schema: Option<String>,
+    unqualified_name: String,
+    /// The unquoted name of the table, followed by a suffix in the derived names.
+    base: String,
+    quote: fn(&str) -> String,
+}
+
+impl TableName {
+    /// Returns the identifiers of the [`DataType::TABLE_NAME`] table quoted for the
+    /// dialect `D`, or an error if the name is not valid.
+    pub(crate) fn of<DATA: DataType, D: SqlDialect>() -> Result<Self, C3p0Error> {
+        TableName::parse::<D>(DATA::TABLE_NAME)
+    }
+
+    /// Returns the identifiers of the table `table_name`, with the syntax of
+    /// [`DataType::TABLE_NAME`], quoted for the dialect `D`.
+    pub(crate) fn parse<D: SqlDialect>(table_name: &str) -> Result<Self, C3p0Error> {
+        let mut parts = TableName::identifiers(table_name)?;
+        let quote = |part: &Identifier| -> fn(&str) -> String {
+            if part.quoted {
+                D::quote_identifier
+            } else {
+                quote_folded::<D>
+            }
+        };
+        let table = parts.pop().expect("at least one identifier is parsed");
+        let schema = parts
+            .pop()
+            .map(|schema| (quote(&schema)(&schema.name), schema.name));
+        Ok(TableName::new(
+            table_name,
+            schema,
+            table.name.clone(),
+            quote(&table),
+        ))
+    }
+
+    /// Splits the table name `table_name` in its schema, if any, and table identifiers.
+    fn identifiers(table_name: &str) -> Result<Vec<Identifier>, C3p0Error> {
+        TableName::check(table_name).map_err(|reason| C3p0Error::Other {
+            cause: format!("Invalid table name [{table_name}]: {reason}"),
+        })?;
+        Ok(parse_identifiers(table_name))
+    }
+
+    /// Checks the syntax of a table name, returning the reason why it is not valid: a
+    /// table name, optionally qualified by a schema, separated by a dot, each of them
+    /// either made of ASCII letters, digits and underscores and starting with a letter or
+    /// an underscore, or enclosed in double quotes or backticks. A quote is escaped inside
+    /// a quoted identifier by doubling it.
+    ///
+    /// The [`DataType`](derive@crate::DataType) derive calls it at compile time on the
+    /// declared `table`.
+    pub const fn check(table_name: &str) -> Result<(), &'static str> {
+        let bytes = table_name.as_bytes();
+        let mut position = 0;
+        let mut parts = 0;
+        loop {
+            parts += 1;
+            if parts > 2 {
+                return Err("it must be a table name, optionally qualified by a schema");
+            }
+            let start = position;
+            if position < bytes.len() && (bytes[position] == b'"' || bytes[position] == b'`') {
+                let quote = bytes[position];
+                position += 1;
+                loop {
+                    if position == bytes.len() {
+                        return Err("a quoted identifier is not closed");
+                    }
+                    let byte = bytes[position];
+                    position += 1;
+                    if byte == 0 {
+                        return Err("it contains a NUL character");
+                    }
+                    if byte == quote {
+                        if position < bytes.len() && bytes[position] == quote {
+                            position += 1;
+                        } else {
+                            break;
+                        }
+                    }
+                }
+                if position == start + 2 {
+                    return Err("it contains an empty identifier");
+                }
+            } else {
+                while position < bytes.len() && bytes[position] != b'.' {
+                    let byte = bytes[position];
+                    if !(byte.is_ascii_alphabetic()
+                        || byte == b'_'
+                        || (position > start && byte.is_ascii_digit()))
+                    {
+                        return Err(
+                            "the unquoted identifiers must be made of ASCII letters, digits and \
+                             underscores and start with a letter or an underscore",
+                        );
+                    }
+                    position += 1;
+                }
+                if position == start {
+                    return Err("it contains an empty identifier");
+                }
+            }
+            if position == bytes.len() {
+                return Ok(());
+            }
+            if bytes[position] != b'.' {
+                return Err("a quoted identifier must be followed by a dot");
+            }
+            position += 1;
+        }
+    }
+
+    /// Returns an error if `table_name` is not a valid table name.
+    pub(crate) fn validate(table_name: &str) -> Result<(), C3p0Error> {
+        TableName::identifiers(table_name).map(|_| ())
+    }
+
+    /// Creates the identifiers of the table `base`, qualified by the quoted and unquoted
+    /// names of its `schema`, if any.
+    fn new(
+        declared: &str,
+        schema: Option<(String, String)>,
+        base: String,
+        quote: fn(&str) -> String,
+    ) -> Self {
+        let (schema, unquoted_schema) = schema.unzip();
+        let qualify = |name: String| match &schema {
+            Some(schema) => format!("{schema}.{name}"),
+            None => name,
+        };
+        TableName {
+            declared: declared.to_owned(),
+            name: qualify(quote(&base)),
+            history: qualify(quote(&format!("{base}_HISTORY"))),
+            unqualified_name: quote(&base),
+            schema,
+            unquoted_schema,
+            base,
+            quote,
+        }
+    }
+
+    /// Returns the name of the table as it is declared, for the messages.
+    pub fn declared(&self) -> &str {
+        &self.declared
+    }
+
+    /// Returns the identifier of the table, qualified by its schema if any.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the identifier of the history table of the [`DataType::HISTORY`] types,
+    /// qualified by the schema of the table if any.
+    pub fn history(&self) -> &str {
+        &self.history
+    }
+
+    /// Returns the identifier of the index `<table>_<name>` of the table, not qualified;
+    /// `unique_key` is the name of the index over the [`DataType::UNIQUE_KEY`].
+    pub fn index(&self, name: &str) -> String {
+        (self.quote)(&format!("{}_{name}", self.base))
+    }
+
+    /// Returns the identifier of the schema of the table, if it is qualified.
+    pub fn schema(&self) -> Option<&str> {
+        self.schema.as_deref()
+    }
+
+    /// Returns the identifier of the table, not qualified.
+    pub fn unqualified_name(&self) -> &str {
+        &self.unqualified_name
+    }
+
+    /// Returns the unquoted name of the table, or of its history table, as it is stored
+    /// in the catalog of the databases that do not fold the identifiers.
+    #[cfg_attr(not(any(feature = "mysql", feature = "sqlite")), allow(dead_code))]
+    pub(crate) fn unquoted_name(&self, history: bool) -> String {
+        if history {
+            format!("{}_HISTORY", self.base)
+        } else {
+            self.base.clone()
+        }
+    }
+
+    /// Returns the unquoted name of the schema of the table, if it is qualified.
+    #[cfg_attr(not(any(feature = "mysql", feature = "sqlite")), allow(dead_code))]
+    pub(crate) fn unquoted_schema(&self) -> Option<&str> {
+        self.unquoted_schema.as_deref()
+    }
+}
+
+/// Quotes an unquoted identifier, keeping the meaning it has in plain SQL.
+fn quote_folded<D: SqlDialect>(identifier: &str) -> String {
+    D::quote_identifier(&D::fold_identifier(identifier))
+}
+
+/// An identifier of a [`DataType::TABLE_NAME`], without the quotes.
+struct Identifier {
+    name: String,
+    quoted: bool,
+}
+
+/// Splits the dot separated identifiers of the valid table name `table_name`, see
+/// [`TableName::check`], unescaping the quoted ones.
+fn parse_identifiers(table_name: &str) -> Vec<Identifier> {
+    let mut parsed = Vec::new();
+    let mut chars = table_name.chars().peekable();
+    loop {
+        let identifier = match chars.next_if(|char| *char == '"' || *char == '`') {
+            Some(quote) => {
+                let mut name = String::new();
+                while let Some(char) = chars.next() {
+                    if char == quote && chars.next_if_eq(&quote).is_none() {
+                        break;
+                    }
+                    name.push(char);
+                }
+                // The dot following the identifier, if any
+                chars.next();
+                Identifier { name, quoted: true }
+            }
+            None => Identifier {
+                name: chars.by_ref().take_while(|char| *char != '.').collect(),
+                quoted: false,
+            },
+        };
+        parsed.push(identifier);
+        if chars.peek().is_none() {
+            return parsed;
+        }
+    }
+}
+
+impl Display for TableName {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.name)
+    }
+}
+
+/// A [`DataType`] bound to a table whose name is chosen at runtime.
+///
+/// The table has the same structure as the one of [`DataType::TABLE_NAME`], and each
+/// method runs the operation of the same name of [`Tx`] on it.
+pub struct Table<DATA: DataType> {
+    name: String,
+    data: PhantomData<fn() -> DATA>,
+}
+
+impl<DATA: DataType> Clone for Table<DATA> {
+    fn clone(&self) -> Self {
+        Table {
+            name: self.name.clone(),
+            data: PhantomData,
+        }
+    }
+}
+
+impl<DATA: DataType> std::fmt::Debug for Table<DATA> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Table").field("name", &self.name).finish()
+    }
+}
+
+impl<DATA: DataType> Table<DATA> {
+    /// Returns the table with the given name, or an error if the name is not valid. The
+    /// name has the syntax of [`DataType::TABLE_NAME`] and is quoted with the same rules.
+    pub fn new(name: impl Into<String>) -> Result<Self, C3p0Error> {
+        let name = name.into();
+        TableName::validate(&name)?;
+        Ok(Table {
+            name,
+            data: PhantomData,
+        })
+    }
+
+    /// Returns the name of the table, as it was given.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the identifiers of the table quoted for the backend of `C`.
+    pub fn table_name<C: Tx>(&self) -> Result<TableName, C3p0Error> {
+        C::parse_table_name(&self.name)
+    }
+
+    /// See [`Tx::create_table_if_not_exists`].
+    pub async fn create_table_if_not_exists<C: Tx>(&self, tx: &mut C) -> Result<(), C3p0Error>
+    where
+        Record<DATA>: TableOps<C::DB, DATA>,
+    {
+        <Record<DATA> as TableOps<C::DB, DATA>>::create_table_if_not_exists(
+            tx,
+            &self.table_name::<C>()?,
+        )
+        .await
+    }
+
+    /// See [`Tx::verify_table`].
+    pub async fn verify_table<C: Tx>(&self, tx: &mut C) -> Result<TableReport, C3p0Error>
+    where
+        Record<DATA>: TableOps<C::DB, DATA>,
+    {
+        <Record<DATA> as TableOps<C::DB, DATA>>::verify_table(tx, &self.table_name::<C>()?).await
+    }
+
+    /// See [`Tx::drop_table_if_exists`].
+    pub async fn drop_table_if_exists<C: Tx>(
+        &self,
+        tx: &mut C,
+        cascade: bool,
+    ) -> Result<(), C3p0Error>
+    where
+        Record<DATA>: TableOps<C::DB, DATA>,
+    {
+        <Record<DATA> as TableOps<C::DB, DATA>>::drop_table_if_exists(
+            tx,
+            &self.table_name::<C>()?,
+            cascade,
+        )
+        .await
+    }
+
+    /// See [`Tx::count_all`].
+    pub async fn count_all<C: Tx>(&self, tx: &mut C) -> Result<u64, C3p0Error>
+    where
+        Record<DATA>: TableOps<C::DB, DATA>,
+    {
+        <Record<DATA> as TableOps<C::DB, DATA>>::count_all(tx, &self.table_name::<C>()?).await
+    }
+
+    /// See [`Tx::exists_by_id`].
+    pub async fn exists_by_id<C: Tx>(&self, tx: &mut C, id: DATA::ID) -> Result<bool, C3p0Error>
+    where
+        Record<DATA>: TableOps<C::DB, DATA>,
+    {
+        <Record<DATA> as TableOps<C::DB, DATA>>::exists_by_id(tx, &self.table_name::<C>()?, id)
+            .await
+    }
+
+    /// See [`Tx::fetch_all`].
+    pub async fn fetch_all<C: Tx>(
+        &self,
+        tx: &mut C,
+        offset: u64,
+        limit: Option<u64>,
+    ) -> Result<Vec<Record<DATA>>, C3p0Error>
+    where
+        Record<DATA>: TableOps<C::DB, DATA>,
+    {
+        let table = self.table_name::<C>()?;
+        let records =
+            <Record<DATA> as TableOps<C::DB, DATA>>::fetch_all(tx, &table, offset, limit).await?;
+        upgrade_on_read::<C::DB, DATA>(tx, &table, records).await
+    }
+
+    /// See [`Tx::fetch_all_sorted`].
+    pub async fn fetch_all_sorted<C: Tx>(
+        &self,
+        tx: &mut C,
+        sort: &[SortKey],
+        offset: u64,
+        limit: Option<u64>,
+    ) -> Result<Vec<Record<DATA>>, C3p0Error>
+    where
+        Record<DATA>: TableOps<C::DB, DATA>,
+    {
+        let table = self.table_name::<C>()?;
+        let records = <Record<DATA> as TableOps<C::DB, DATA>>::fetch_all_sorted(
+            tx, &table, sort, offset, limit,
+        )
+        .await?;
+        upgrade_on_read::<C::DB, DATA>(tx, &table, records).await
+    }
+
+    /// See [`Tx::fetch_all_stream`].
+    pub fn fetch_all_stream<'a, C: Tx>(
+        &self,
+        tx: &'a mut C,
+    ) -> impl Stream<Item = Result<Record<DATA>, C3p0Error>> + use<'a, C, DATA>
+    where
+        Record<DATA>: TableOps<C::DB, DATA>,
+        DATA: 'a,
+    {
+        match self.table_name::<C>() {
+            Ok(table) => {
+                <Record<DATA> as TableOps<C::DB, DATA>>::fetch_all_stream(tx, &table).left_stream()
+            }
+            Err(err) => stream::once(future::ready(Err(err))).right_stream(),
+        }
+    }
+
+    /// See [`Tx::fetch_where`].
+    pub async fn fetch_where<C: Tx>(
+        &self,
+        tx: &mut C,
+        filter: &Filter,
+        offset: u64,
+        limit: Option<u64>,
+    ) -> Result<Vec<Record<DATA>>, C3p0Error>
+    where
+        Record<DATA>: TableOps<C::DB, DATA>,
+    {
+        let table = self.table_name::<C>()?;
+        let records =
+            <Record<DATA> as TableOps<C::DB, DATA>>::fetch_where(tx, &table, filter, offset, limit)
+                .await?;
+        upgrade_on_read::<C::DB, DATA>(tx, &table, records).await
+    }
+
+    /// See [`Tx::fetch_where_sorted`].
+    pub async fn fetch_where_sorted<C: Tx>(
+        &self,
+        tx: &mut C,
+        filter: &Filter,
+        sort: &[SortKey],
+        offset: u64,
+        limit: Option<u64>,
+    ) -> Result<Vec<Record<DATA>>, C3p0Error>
+    where
+        Record<DATA>: TableOps<C::DB, DATA>,
+    {
+        let table = self.table_name::<C>()?;
+        let records = <Record<DATA> as TableOps<C::DB, DATA>>::fetch_where_sorted(
+            tx, &table, filter, sort, offset, limit,
+        )
+        .await?;
+        upgrade_on_read::<C::DB, DATA>(tx, &table, records).await
+    }
+
+    /// See [`Tx::fetch_projected`].
+    pub async fn fetch_projected<C: Tx, P: DeserializeOwned + Send + Unpin>(
+        &self,
+        tx: &mut C,
+        projection: &Projection,
+        filter: &Filter,
+        sort: &[SortKey],
+        offset: u64,
+        limit: Option<u64>,
+    ) -> Result<Vec<Projected<DATA, P>>, C3p0Error>
+    where
+        Record<DATA>: TableOps<C::DB, DATA>,
+    {
+        <Record<DATA> as TableOps<C::DB, DATA>>::fetch_projected(
+            tx,
+            &self.table_name::<C>()?,
+            projection,
+            filter,
+            sort,
+            offset,
+            limit,
+        )
+        .await
+    }
+
+    /// See [`Tx::fetch_all_lenient`].
+    pub async fn fetch_all_lenient<C: Tx>(
+        &self,
+        tx: &mut C,
+        offset: u64,
+        limit: Option<u64>,
+    ) -> Result<Vec<LenientRecord<DATA>>, C3p0Error>
+    where
+        Record<DATA>: TableOps<C::DB, DATA>,
+    {
+        <Record<DATA> as TableOps<C::DB, DATA>>::fetch_lenient(
+            tx,
+            &self.table_name::<C>()?,
+            None,
+            offset,
+            limit,
+        )
+        .await
+    }
+
+    /// See [`Tx::fetch_where_stream`].
+    pub fn fetch_where_stream<'a, C: Tx>(
+        &self,
+        tx: &'a mut C,
+        filter: &Filter,
+    ) -> impl Stream<Item = Result<Record<DATA>, C3p0Error>> + use<'a, C, DATA>
+    where
+        Record<DATA>: TableOps<C::DB, DATA>,
+        DATA: 'a,
+    {
+        match self.table_name::<C>() {
+            Ok(table) => {
+                <Record<DATA> as TableOps<C::DB, DATA>>::fetch_where_stream(tx, &table, filter)
+                    .left_stream()
+            }
+            Err(err) => stream::once(future::ready(Err(err))).right_stream(),
+        }
+    }
+
+    /// See [`Tx::count_where`].
+    pub async fn count_where<C: Tx>(&self, tx: &mut C, filter: &Filter) -> Result<u64, C3p0Error>
+    where
+        Record<DATA>: TableOps<C::DB, DATA>,
+    {
+        <Record<DATA> as TableOps<C::DB, DATA>>::count_where(tx, &self.table_name::<C>()?, filter)
+            .await
+    }
+
+    /// See [`Tx::fetch_page`].
+    pub async fn fetch_page<C: Tx>(
+        &self,
+        tx: &mut C,
+        order: PageOrder,
+        cursor: Option<&Cursor>,
+        limit: u64,
+    ) -> Result<Page<DATA>, C3p0Error>
+    where
+        Record<DATA>: TableOps<C::DB, DATA>,
+    {
+        let table = self.table_name::<C>()?;
+        let mut page = <Record<DATA> as TableOps<C::DB, DATA>>::fetch_page(
+            tx, &table, None, order, cursor, limit,
+        )
+        .await?;
+        page.records = upgrade_on_read::<C::DB, DATA>(tx, &table, page.records).await?;
+        Ok(page)
+    }
+
+    /// See [`Tx::fetch_page_where`].
+    pub async fn fetch_page_where<C: Tx>(
+        &self,
+        tx: &mut C,
+        filter: &Filter,
+        order: PageOrder,
+        cursor: Option<&Cursor>,
+        limit: u64,
+    ) -> Result<Page<DATA>, C3p0Error>
+    where
+        Record<DATA>: TableOps<C::DB, DATA>,
+    {
+        let table = self.table_name::<C>()?;
+        let mut page = <Record<DATA> as TableOps<C::DB, DATA>>::fetch_page(
+            tx,
+            &table,
+            Some(filter),
+            order,
+            cursor,
+            limit,
+        )
+        .await?;
+        page.records = upgrade_on_read::<C::DB, DATA>(tx, &table, page.records).await?;
+        Ok(page)
+    }
+
+    /// See [`Tx::fetch_one_optional_by_id`].
+    pub async fn fetch_one_optional_by_id<C: Tx>(
+        &self,
+        tx: &mut C,
+        id: DATA::ID,
+    ) -> Result<Option<Record<DATA>>, C3p0Error>
+    where
+        Record<DATA>: TableOps<C::DB, DATA>,
+    {
+        let table = self.table_name::<C>()?;
+        let record =
+            <Record<DATA> as TableOps<C::DB, DATA>>::fetch_one_optional_by_id(tx, &table, id)
+                .await?;
+        Ok(
+            upgrade_on_read::<C::DB, DATA>(tx, &table, record.into_iter().collect())
+                .await?
+                .pop(),
+        )
+    }
+
+    /// See [`Tx::fetch_one_by_id`].
+    pub async fn fetch_one_by_id<C: Tx>(
+        &self,
+        tx: &mut C,
+        id: DATA::ID,
+    ) -> Result<Record<DATA>, C3p0Error>
+    where
+        Record<DATA>: TableOps<C::DB, DATA>,
+    {
+        let table = self.table_name::<C>()?;
+        let record =
+            <Record<DATA> as TableOps<C::DB, DATA>>::fetch_one_by_id(tx, &table, id).await?;
+        Ok(upgrade_on_read::<C::DB, DATA>(tx, &table, vec![record])
+            .await?
+            .remove(0))
+    }
+
+    /// See [`Tx::delete`].
+    pub async fn delete<C: Tx>(
+        &self,
+        tx: &mut C,
+        record: Record<DATA>,
+    ) -> Result<Record<DATA>, C3p0Error>
+    where
+        Record<DATA>: TableOps<C::DB, DATA>,
+    {
+        <Record<DATA> as TableOps<C::DB, DATA>>::delete(record, tx, &self.table_name::<C>()?).await
+    }
+
+    /// See [`Tx::delete_all`].
+    pub async fn delete_all<C: Tx>(&self, tx: &mut C) -> Result<u64, C3p0Error>
+    where
+        Record<DATA>: TableOps<C::DB, DATA>,
+    {
+        <Record<DATA> as TableOps<C::DB, DATA>>::delete_all(tx, &self.table_name::<C>()?).await
+    }
+
+    /// See [`Tx::delete_by_id`].
+    pub async fn delete_by_id<C: Tx>(&self, tx: &mut C, id: DATA::ID) -> Result<u64, C3p0Error>
+    where
+        Record<DATA>: TableOps<C::DB, DATA>,
+    {
+        <Record<DATA> as TableOps<C::DB, DATA>>::delete_by_id(tx, &self.table_name::<C>()?, id)
+            .await
+    }
+
+    /// See [`Tx::delete_where`].
+    pub async fn delete_where<C: Tx>(&self, tx: &mut C, filter: &Filter) -> Result<u64, C3p0Error>
+    where
+        Record<DATA>: TableOps<C::DB, DATA>,
+    {
+        <Record<DATA> as TableOps<C::DB, DATA>>::delete_where(tx, &self.table_name::<C>()?, filter)
+            .await
+    }
+
+    /// See [`Tx::fetch_including_deleted`].
+    pub async fn fetch_including_deleted<C: Tx>(
+        &self,
+        tx: &mut C,
+        offset: u64,
+        limit: Option<u64>,
+    ) -> Result<Vec<SoftDeleteRecord<DATA>>, C3p0Error>
+    where
+        Record<DATA>: TableOps<C::DB, DATA>,
+    {
+        <Record<DATA> as TableOps<C::DB, DATA>>::fetch_including_deleted(
+            tx,
+            &self.table_name::<C>()?,
+            offset,
+            limit,
+        )
+        .await
+    }
+
+    /// See [`Tx::restore`].
+    pub async fn restore<C: Tx>(&self, tx: &mut C, id: DATA::ID) -> Result<Record<DATA>, C3p0Error>
+    where
+        Record<DATA>: TableOps<C::DB, DATA>,
+    {
+        <Record<DATA> as TableOps<C::DB, DATA>>::restore(tx, &self.table_name::<C>()?, id).await
+    }
+
+    /// See [`Tx::purge`].
+    pub async fn purge<C: Tx>(
+        &self,
+        tx: &mut C,
+        deleted_before: Option<DateTime<Utc>>,
+    ) -> Result<u64, C3p0Error>
+    where
+        Record<DATA>: TableOps<C::DB, DATA>,
+    {
+        <Record<DATA> as TableOps<C::DB, DATA>>::purge(tx, &self.table_name::<C>()?, deleted_before)
+            .await
+    }
+
+    /// See [`Tx::update`].
+    pub async fn update<C: Tx>(
+        &self,
+        tx: &mut C,
+        record: Record<DATA>,
+    ) -> Result<Record<DATA>, C3p0Error>
+    where
+        Record<DATA>: TableOps<C::DB, DATA>,
+    {
+        <Record<DATA> as TableOps<C::DB, DATA>>::update(record, tx, &self.table_name::<C>()?).await
+    }
+
+    /// See [`Tx::patch`].
+    pub async fn patch<C: Tx>(
+        &self,
+        tx: &mut C,
+        id: DATA::ID,
+        version: Option<i64>,
+        patch: &JsonPatch,
+    ) -> Result<Record<DATA>, C3p0Error>
+    where
+        Record<DATA>: TableOps<C::DB, DATA>,
+    {
+        <Record<DATA> as TableOps<C::DB, DATA>>::patch(
+            tx,
+            &self.table_name::<C>()?,
+            id,
+            version,
+            patch,
+        )
+        .await
+    }
+
+    /// See [`Tx::update_all`].
+    pub async fn update_all<C: Tx>(
+        &self,
+        tx: &mut C,
+        records: Vec<Record<DATA>>,
+    ) -> Result<UpdateAllResult<DATA>, C3p0Error>
+    where
+        Record<DATA>: TableOps<C::DB, DATA>,
+    {
+        <Record<DATA> as TableOps<C::DB, DATA>>::update_all(records, tx, &self.table_name::<C>()?)
+            .await
+    }
+
+    /// See [`Tx::upgrade_all`].
+    pub async fn upgrade_all<C: Tx>(
+        &self,
+        tx: &mut C,
+        batch_size: u64,
+        progress: impl FnMut(&UpgradeProgress),
+    ) -> Result<UpgradeProgress, C3p0Error>
+    where
+        Record<DATA>: TableOps<C::DB, DATA>,
+    {
+        upgrade_all::<C::DB, DATA>(tx, &self.table_name::<C>()?, batch_size, progress).await
+    }
+
+    /// See [`Tx::reencrypt_all`].
+    #[cfg(feature = "encryption")]
+    pub async fn reencrypt_all<C: Tx>(
+        &self,
+        tx: &mut C,
+        batch_size: u64,
+        progress: impl FnMut(&UpgradeProgress),
+    ) -> Result<UpgradeProgress, C3p0Error>
+    where
+        Record<DATA>: TableOps<C::DB, DATA>,
+    {
+        crate::crypto::reencrypt_all::<C::DB, DATA>(
+            tx,
+            &self.table_name::<C>()?,
+            batch_size,
+            progress,
+        )
+        .await
+    }
+
+    /// See [`Tx::scan_for_decode_errors`].
+    pub async fn scan_for_decode_errors<C: Tx>(
+        &self,
+        tx: &mut C,
+        chunk_size: u64,
+    ) -> Result<Vec<DecodeError<DATA>>, C3p0Error>
+    where
+        Record<DATA>: TableOps<C::DB, DATA>,
+    {
+        scan_for_decode_errors::<C::DB, DATA>(tx, &self.table_name::<C>()?, chunk_size).await
+    }
+
+    /// See [`Tx::fetch_history`].
+    pub async fn fetch_history<C: Tx>(
+        &self,
+        tx: &mut C,
+        id: DATA::ID,
+    ) -> Result<Vec<Record<DATA>>, C3p0Error>
+    where
+        Record<DATA>: TableOps<C::DB, DATA>,
+    {
+        <Record<DATA> as TableOps<C::DB, DATA>>::fetch_history(tx, &self.table_name::<C>()?, id)
+            .await
+    }
+
+    /// See [`Tx::fetch_version`].
+    pub async fn fetch_version<C: Tx>(
+        &self,
+        tx: &mut C,
+        id: DATA::ID,
+        version: i64,
+    ) -> Result<Option<Record<DATA>>, C3p0Error>
+    where
+        Record<DATA>: TableOps<C::DB, DATA>,
+    {
+        <Record<DATA> as TableOps<C::DB, DATA>>::fetch_version(
+            tx,
+            &self.table_name::<C>()?,
+            id,
+            version,
+        )
+        .await
+    }
+
+    /// See [`Tx::fetch_as_of`].
+    pub async fn fetch_as_of<C: Tx>(
+        &self,
+        tx: &mut C,
+        id: DATA::ID,
+        timestamp: DateTime<Utc>,
+    ) -> Result<Option<Record<DATA>>, C3p0Error>
+    where
+        Record<DATA>: TableOps<C::DB, DATA>,
+    {
+        <Record<DATA> as TableOps<C::DB, DATA>>::fetch_as_of(
+            tx,
+            &self.table_name::<C>()?,
+            id,
+            timestamp,
+        )
+        .await
+    }
+
+    /// See [`Tx::save`].
+    pub async fn save<C: Tx, NEW: NewData<DATA = DATA>>(
+        &self,
+        tx: &mut C,
+        record: NewRecord<NEW>,
+    ) -> Result<Record<DATA>, C3p0Error>
+    where
+        NewRecord<NEW>: TableSave<C::DB, DATA>,
+    {
+        <NewRecord<NEW> as TableSave<C::DB, DATA>>::save(record, tx, &self.table_name::<C>()?).await
+    }
+
+    /// See [`Tx::upsert_by_key`].
+    pub async fn upsert_by_key<C: Tx, NEW: NewData<DATA = DATA>>(
+        &self,
+        tx: &mut C,
+        record: NewRecord<NEW>,
+    ) -> Result<Record<DATA>, C3p0Error>
+    where
+        NewRecord<NEW>: TableSave<C::DB, DATA>,
+    {
+        <NewRecord<NEW> as TableSave<C::DB, DATA>>::upsert_by_key(
+            record,
+            tx,
+            &self.table_name::<C>()?,
+        )
+        .await
+    }
+
+    /// See [`Tx::save_all`].
+    pub async fn save_all<C: Tx, NEW: NewData<DATA = DATA>>(
+        &self,
+        tx: &mut C,
+        records: Vec<NewRecord<NEW>>,
+    ) -> Result<Vec<Record<DATA>>, C3p0Error>
+    where
+        NewRecord<NEW>: TableSave<C::DB, DATA>,
+    {
+        <NewRecord<NEW> as TableSave<C::DB, DATA>>::save_all(records, tx, &self.table_name::<C>()?)
+            .await
+    }
+}
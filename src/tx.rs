@@ -1,19 +1,41 @@
+use chrono::{DateTime, Utc};
+use futures_core::Stream;
+use serde::de::DeserializeOwned;
 use sqlx::Database;
 
-use crate::{C3p0Error, DataType, NewRecord, Record, WithData};
+use crate::{
+    C3p0Error, DataId, DataType, NewData, NewRecord, Record, SoftDeleteRecord, UpdateAllResult,
+    WithData,
+    decode::{DecodeError, LenientRecord},
+    filter::Filter,
+    page::{Cursor, Page, PageOrder},
+    patch::JsonPatch,
+    projection::{Projected, Projection},
+    schema::TableReport,
+    sql::SortKey,
+    table::TableName,
+    upgrade::UpgradeProgress,
+};
 
 /// A trait for a transaction.
-pub trait Tx {
-    type DB: Database;
+pub trait Tx: Sized {
+    type DB: Database<Connection = Self>;
 
-    /// Creates the table if it does not exist.
+    /// Returns the identifiers of the table `table_name`, with the syntax of
+    /// [`DataType::TABLE_NAME`], quoted for the SQL dialect of the backend, see
+    /// [`Table`](crate::Table).
+    fn parse_table_name(table_name: &str) -> Result<TableName, C3p0Error>;
+
+    /// Creates the table if it does not exist, along with the `<TABLE_NAME>_HISTORY`
+    /// table of the [`DataType::HISTORY`] types.
     ///
     /// # This is mostly intended for development and tests only
     ///
     /// The generated `CREATE TABLE` statement contains the minimum set of columns required,
     /// it does **not** add:
     ///
-    /// - any **indexes** on `data`;
+    /// - any **indexes** on `data`, except the unique index over the
+    ///   [`DataType::UNIQUE_KEY`];
     /// - any **CHECK constraints**;
     /// - any **foreign keys**, generated columns, partitioning, table-level
     ///   storage parameters, comments, or grants.
@@ -26,7 +48,7 @@ pub trait Tx {
         &mut self,
     ) -> impl Future<Output = Result<(), C3p0Error>>;
 
-    /// Drops the table if it exists.
+    /// Drops the table if it exists, along with its history table.
     ///
     /// The `cascade` flag controls whether the drop should propagate to dependent
     /// objects. Note that on MySQL the `CASCADE` keyword is ignored.
@@ -35,13 +57,25 @@ pub trait Tx {
         cascade: bool,
     ) -> impl Future<Output = Result<(), C3p0Error>>;
 
+    /// Returns the differences between the table, and the history table of the
+    /// [`DataType::HISTORY`] types, and the structure expected by c3p0: missing tables and
+    /// columns, column types that c3p0 cannot read or write, a missing or wrong primary key
+    /// and, where JSON is stored as text, a missing check that `data` is valid JSON.
+    ///
+    /// It is meant to be called at startup, to report a hand-written migration that does
+    /// not match the declarations of the [`DataType`] before the first failing query. The
+    /// indexes and the additional columns are not verified.
+    fn verify_table<DATA: WithData>(
+        &mut self,
+    ) -> impl Future<Output = Result<TableReport, C3p0Error>>;
+
     /// Returns the number of rows in the table.
     fn count_all<DATA: WithData>(&mut self) -> impl Future<Output = Result<u64, C3p0Error>>;
 
     /// Returns true if the entry with the given id exists.
     fn exists_by_id<DATA: WithData>(
         &mut self,
-        id: i64,
+        id: <DATA::DATA as DataId>::ID,
     ) -> impl Future<Output = Result<bool, C3p0Error>>;
 
     /// Returns entries in the table ordered by `id` ASC, skipping the first `offset`
@@ -52,19 +86,139 @@ pub trait Tx {
         limit: Option<u64>,
     ) -> impl Future<Output = Result<Vec<Record<DATA::DATA>>, C3p0Error>>;
 
+    /// Returns entries in the table ordered by the `sort` keys, skipping the first `offset`
+    /// rows and returning at most `limit` rows. `limit = None` means no upper bound.
+    ///
+    /// The `id` is always appended as the last sort key, so that the order is deterministic.
+    /// Entries whose sorting JSON path is missing or `null` are returned last.
+    fn fetch_all_sorted<DATA: WithData>(
+        &mut self,
+        sort: &[SortKey],
+        offset: u64,
+        limit: Option<u64>,
+    ) -> impl Future<Output = Result<Vec<Record<DATA::DATA>>, C3p0Error>>;
+
+    /// Returns a stream of all the entries in the table ordered by `id` ASC.
+    ///
+    /// Rows are decoded one at a time as they are received from the database, so the
+    /// whole table can be processed in constant memory. The stream borrows the
+    /// transaction until it is dropped.
+    fn fetch_all_stream<'a, DATA: WithData>(
+        &'a mut self,
+    ) -> impl Stream<Item = Result<Record<DATA::DATA>, C3p0Error>> + 'a
+    where
+        DATA::DATA: 'a;
+
+    /// Returns the entries matching the `filter` ordered by `id` ASC, skipping the first
+    /// `offset` rows and returning at most `limit` rows. `limit = None` means no upper bound.
+    ///
+    /// The filter is rendered to the SQL dialect of the backend, see [`Filter`]. The
+    /// filters and the sort keys over JSON paths return an error for the binary types and
+    /// only match the entries stored as plain JSON of the compressed types, see
+    /// [`format`](crate::format).
+    fn fetch_where<DATA: WithData>(
+        &mut self,
+        filter: &Filter,
+        offset: u64,
+        limit: Option<u64>,
+    ) -> impl Future<Output = Result<Vec<Record<DATA::DATA>>, C3p0Error>>;
+
+    /// Returns the entries matching the `filter` ordered by the `sort` keys, skipping the
+    /// first `offset` rows and returning at most `limit` rows. `limit = None` means no upper bound.
+    ///
+    /// See [`fetch_all_sorted`](Self::fetch_all_sorted) for the sorting rules.
+    fn fetch_where_sorted<DATA: WithData>(
+        &mut self,
+        filter: &Filter,
+        sort: &[SortKey],
+        offset: u64,
+        limit: Option<u64>,
+    ) -> impl Future<Output = Result<Vec<Record<DATA::DATA>>, C3p0Error>>;
+
+    /// Returns only the fields of the `projection` of the entries matching the `filter`,
+    /// extracted by the database and deserialized as `P`, ordered by the `sort` keys,
+    /// skipping the first `offset` rows and returning at most `limit` rows. `limit = None`
+    /// means no upper bound.
+    ///
+    /// See the [`projection`](crate::projection) module.
+    fn fetch_projected<DATA: WithData, P: DeserializeOwned + Send + Unpin>(
+        &mut self,
+        projection: &Projection,
+        filter: &Filter,
+        sort: &[SortKey],
+        offset: u64,
+        limit: Option<u64>,
+    ) -> impl Future<Output = Result<Vec<Projected<DATA::DATA, P>>, C3p0Error>>;
+
+    /// Same as [`fetch_all`](Self::fetch_all), but an entry whose `data` cannot be decoded
+    /// is returned as a [`DecodeError`], with its id, raw JSON and serde error, instead of
+    /// failing the whole query.
+    ///
+    /// The entries are returned as stored, they are not upgraded on read.
+    fn fetch_all_lenient<DATA: WithData>(
+        &mut self,
+        offset: u64,
+        limit: Option<u64>,
+    ) -> impl Future<Output = Result<Vec<LenientRecord<DATA::DATA>>, C3p0Error>>;
+
+    /// Returns a stream of the entries matching the `filter` ordered by `id` ASC.
+    ///
+    /// See [`fetch_all_stream`](Self::fetch_all_stream).
+    fn fetch_where_stream<'a, DATA: WithData>(
+        &'a mut self,
+        filter: &Filter,
+    ) -> impl Stream<Item = Result<Record<DATA::DATA>, C3p0Error>> + 'a
+    where
+        DATA::DATA: 'a;
+
+    /// Returns the number of entries matching the `filter`.
+    fn count_where<DATA: WithData>(
+        &mut self,
+        filter: &Filter,
+    ) -> impl Future<Output = Result<u64, C3p0Error>>;
+
+    /// Returns the page of at most `limit` entries that follows the `cursor` in the given
+    /// `order`. A `None` cursor returns the first page.
+    ///
+    /// The returned [`Page::next`] cursor must be passed to fetch the following page and
+    /// is `None` when there are no more entries. A cursor can only be used with the same
+    /// `order` of the query that produced it, otherwise
+    /// [`C3p0Error::InvalidCursor`] is returned. The `limit` must be between 1 and
+    /// `i64::MAX - 1`.
+    fn fetch_page<DATA: WithData>(
+        &mut self,
+        order: PageOrder,
+        cursor: Option<&Cursor>,
+        limit: u64,
+    ) -> impl Future<Output = Result<Page<DATA::DATA>, C3p0Error>>;
+
+    /// Same as [`fetch_page`](Self::fetch_page) but only returns the entries matching the
+    /// `filter`. The same filter must be used for all the pages.
+    fn fetch_page_where<DATA: WithData>(
+        &mut self,
+        filter: &Filter,
+        order: PageOrder,
+        cursor: Option<&Cursor>,
+        limit: u64,
+    ) -> impl Future<Output = Result<Page<DATA::DATA>, C3p0Error>>;
+
     /// Returns the entry with the given id. Returns None if the entry does not exist.
     fn fetch_one_optional_by_id<DATA: WithData>(
         &mut self,
-        id: i64,
+        id: <DATA::DATA as DataId>::ID,
     ) -> impl Future<Output = Result<Option<Record<DATA::DATA>>, C3p0Error>>;
 
     /// Returns the entry with the given id. Returns an error if the entry does not exist.
     fn fetch_one_by_id<DATA: WithData>(
         &mut self,
-        id: i64,
+        id: <DATA::DATA as DataId>::ID,
     ) -> impl Future<Output = Result<Record<DATA::DATA>, C3p0Error>>;
 
     /// Deletes the entry with the given id.
+    ///
+    /// For the [`DataType::SOFT_DELETE`] types, this and the other `delete*` methods mark
+    /// the entries as deleted instead of removing them; a soft delete increments the
+    /// `version` and sets the `update_time` like an update.
     fn delete<DATA: DataType>(
         &mut self,
         record: Record<DATA>,
@@ -76,7 +230,41 @@ pub trait Tx {
     /// Deletes the entry with the given id.
     fn delete_by_id<DATA: WithData>(
         &mut self,
-        id: i64,
+        id: <DATA::DATA as DataId>::ID,
+    ) -> impl Future<Output = Result<u64, C3p0Error>>;
+
+    /// Deletes the entries matching the `filter` and returns the number of deleted rows.
+    fn delete_where<DATA: WithData>(
+        &mut self,
+        filter: &Filter,
+    ) -> impl Future<Output = Result<u64, C3p0Error>>;
+
+    /// Returns all the entries, including the soft deleted ones, ordered by `id` ASC,
+    /// skipping the first `offset` rows and returning at most `limit` rows.
+    ///
+    /// Returns an error if the type is not a [`DataType::SOFT_DELETE`] type.
+    fn fetch_including_deleted<DATA: WithData>(
+        &mut self,
+        offset: u64,
+        limit: Option<u64>,
+    ) -> impl Future<Output = Result<Vec<SoftDeleteRecord<DATA::DATA>>, C3p0Error>>;
+
+    /// Restores the soft deleted entry with the given id, incrementing its `version`.
+    ///
+    /// Returns [`sqlx::Error::RowNotFound`] if there is no deleted entry with that id, or
+    /// an error if the type is not a [`DataType::SOFT_DELETE`] type.
+    fn restore<DATA: WithData>(
+        &mut self,
+        id: <DATA::DATA as DataId>::ID,
+    ) -> impl Future<Output = Result<Record<DATA::DATA>, C3p0Error>>;
+
+    /// Physically removes the soft deleted entries, optionally only those deleted before
+    /// `deleted_before`, and returns the number of removed rows.
+    ///
+    /// Returns an error if the type is not a [`DataType::SOFT_DELETE`] type.
+    fn purge<DATA: WithData>(
+        &mut self,
+        deleted_before: Option<DateTime<Utc>>,
     ) -> impl Future<Output = Result<u64, C3p0Error>>;
 
     /// Updates the entry with the given id. Returns an error if the entry does not exist.
@@ -87,9 +275,134 @@ pub trait Tx {
         record: Record<DATA>,
     ) -> impl Future<Output = Result<Record<DATA>, C3p0Error>>;
 
+    /// Applies the `patch` to the `data` of the entry with the given id and returns the
+    /// patched record. The patch is applied by the database in a single statement, so
+    /// the fields changed concurrently by other writers that it does not touch are
+    /// preserved.
+    ///
+    /// The `version` is incremented and the `update_time` set as by
+    /// [`update`](Self::update). When `version` is given, the patch is only applied if it
+    /// matches the version of the entry, otherwise an
+    /// [`OptimisticLockError`](C3p0Error::OptimisticLockError) is returned.
+    fn patch<DATA: WithData>(
+        &mut self,
+        id: <DATA::DATA as DataId>::ID,
+        version: Option<i64>,
+        patch: &JsonPatch,
+    ) -> impl Future<Output = Result<Record<DATA::DATA>, C3p0Error>>;
+
+    /// Updates the entries in bulk, using the same optimistic locking as
+    /// [`update`](Self::update).
+    ///
+    /// A version mismatch does not abort the batch: the records whose version no longer
+    /// matches the database are returned in [`UpdateAllResult::conflicts`] while all the
+    /// others are updated, so that only the conflicting ones need to be retried.
+    fn update_all<DATA: DataType>(
+        &mut self,
+        records: Vec<Record<DATA>>,
+    ) -> impl Future<Output = Result<UpdateAllResult<DATA>, C3p0Error>>;
+
+    /// Rewrites in the latest shape of the [`DataType::CODEC`] the entries stored in a
+    /// previous one, as matched by [`Codec::outdated`](crate::Codec::outdated), and
+    /// returns the number of upgraded entries.
+    ///
+    /// The entries are updated as by [`update_all`](Self::update_all), in batches of
+    /// `batch_size` entries ordered by `id`, and `progress` is called after each batch.
+    /// The entries changed concurrently are skipped and counted in
+    /// [`UpgradeProgress::conflicts`]; running it again upgrades them. All the entries of
+    /// the binary [`DataType::FORMAT`]s are rewritten.
+    fn upgrade_all<DATA: WithData>(
+        &mut self,
+        batch_size: u64,
+        progress: impl FnMut(&UpgradeProgress),
+    ) -> impl Future<Output = Result<UpgradeProgress, C3p0Error>>;
+
+    /// Rewrites all the entries, so that their fields encrypted by [`crate::crypto`] are
+    /// encrypted with the current key of the [`KeyRing`](crate::crypto::KeyRing), and
+    /// returns the number of rewritten entries in [`UpgradeProgress::upgraded`].
+    ///
+    /// The entries are updated as by [`upgrade_all`](Self::upgrade_all). The soft deleted
+    /// entries and the history are not rewritten, so the previous keys are still needed to
+    /// read them.
+    #[cfg(feature = "encryption")]
+    fn reencrypt_all<DATA: WithData>(
+        &mut self,
+        batch_size: u64,
+        progress: impl FnMut(&UpgradeProgress),
+    ) -> impl Future<Output = Result<UpgradeProgress, C3p0Error>>;
+
+    /// Returns the entries of the table whose `data` cannot be decoded, e.g. after a
+    /// change of the [`Codec`](crate::Codec) that is not compatible with the stored JSON.
+    ///
+    /// The table is read in chunks of `chunk_size` entries ordered by `id`. Each
+    /// [`DecodeError`] reports the path of the JSON value that cannot be decoded.
+    fn scan_for_decode_errors<DATA: WithData>(
+        &mut self,
+        chunk_size: u64,
+    ) -> impl Future<Output = Result<Vec<DecodeError<DATA::DATA>>, C3p0Error>>;
+
+    /// Returns the prior versions of the entry with the given id, ordered by `version`
+    /// ASC. The current version is not included.
+    ///
+    /// Returns an error if the type is not a [`DataType::HISTORY`] type.
+    fn fetch_history<DATA: WithData>(
+        &mut self,
+        id: <DATA::DATA as DataId>::ID,
+    ) -> impl Future<Output = Result<Vec<Record<DATA::DATA>>, C3p0Error>>;
+
+    /// Returns the given version of the entry with the given id, be it the current or a
+    /// prior one, or `None` if it does not exist.
+    ///
+    /// Returns an error if the type is not a [`DataType::HISTORY`] type.
+    fn fetch_version<DATA: WithData>(
+        &mut self,
+        id: <DATA::DATA as DataId>::ID,
+        version: i64,
+    ) -> impl Future<Output = Result<Option<Record<DATA::DATA>>, C3p0Error>>;
+
+    /// Returns the version of the entry with the given id that was current at
+    /// `timestamp`, i.e. the latest one whose `update_time` is not after it. Returns
+    /// `None` if the entry was created after `timestamp`, or deleted at or before it.
+    ///
+    /// Returns an error if the type is not a [`DataType::HISTORY`] type.
+    fn fetch_as_of<DATA: WithData>(
+        &mut self,
+        id: <DATA::DATA as DataId>::ID,
+        timestamp: DateTime<Utc>,
+    ) -> impl Future<Output = Result<Option<Record<DATA::DATA>>, C3p0Error>>;
+
     /// Creates a new entry.
-    fn save<DATA: DataType>(
+    fn save<DATA: NewData>(
         &mut self,
         record: NewRecord<DATA>,
-    ) -> impl Future<Output = Result<Record<DATA>, C3p0Error>>;
+    ) -> impl Future<Output = Result<Record<DATA::DATA>, C3p0Error>>;
+
+    /// Creates a new entry or, if an entry with the same [`DataType::UNIQUE_KEY`] already
+    /// exists, replaces its data in a single atomic statement.
+    ///
+    /// On the update path the `version` is incremented, the `update_time` is set and the
+    /// `create_time` is preserved, so the returned record tells which path was taken.
+    /// Returns an error if the type does not declare a `UNIQUE_KEY`; the table must
+    /// have a unique index over it, as created by
+    /// [`create_table_if_not_exists`](Self::create_table_if_not_exists).
+    ///
+    /// On MySQL, where a conflict over any unique index triggers the update, the entries
+    /// with a provided id and the types declaring other unique
+    /// [`INDEXES`](DataType::INDEXES) are rejected, and an error is returned if the
+    /// statement conflicts over a unique index created by hand.
+    fn upsert_by_key<DATA: NewData>(
+        &mut self,
+        record: NewRecord<DATA>,
+    ) -> impl Future<Output = Result<Record<DATA::DATA>, C3p0Error>>;
+
+    /// Creates new entries in bulk, using as few statements as possible.
+    ///
+    /// The returned records are in the same order as `records`. As for [`save`](Self::save),
+    /// `create_time` and `update_time` are set by the database clock. On MySQL the ids
+    /// generated by the database for a multi-row insert are read back from
+    /// `LAST_INSERT_ID()`, and an error is returned if they are not consecutive.
+    fn save_all<DATA: NewData>(
+        &mut self,
+        records: Vec<NewRecord<DATA>>,
+    ) -> impl Future<Output = Result<Vec<Record<DATA::DATA>>, C3p0Error>>;
 }
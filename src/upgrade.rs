@@ -0,0 +1,150 @@
+//! The rewriting of the entries stored in a previous shape of their [`Codec`].
+
+use std::collections::HashMap;
+
+use sqlx::Database;
+
+use crate::{
+    C3p0Error, Codec, DataType, IdType, Record,
+    filter::{Field, Filter},
+    format::{has_compressed_column, json_queryable},
+    ops::TableOps,
+    record::CodecOf,
+    table::TableName,
+};
+
+/// The maximum number of ids checked by a single query when upgrading the entries read.
+const READ_BATCH_SIZE: usize = 1000;
+
+/// The progress of [`Tx::upgrade_all`](crate::Tx::upgrade_all), and of
+/// `Tx::reencrypt_all` with the `encryption` feature.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct UpgradeProgress {
+    /// The number of outdated entries when the upgrade started.
+    pub total: u64,
+    /// The number of entries rewritten in the latest shape so far.
+    pub upgraded: u64,
+    /// The number of entries skipped because they were changed concurrently; they are
+    /// upgraded by a following run.
+    pub conflicts: u64,
+}
+
+/// Rewrites in the latest shape the outdated `records` just read, for the
+/// [`DataType::UPGRADE_ON_READ`] types, and returns them with their new `version`.
+pub(crate) async fn upgrade_on_read<DB: Database, DATA: DataType>(
+    tx: &mut DB::Connection,
+    table: &TableName,
+    mut records: Vec<Record<DATA>>,
+) -> Result<Vec<Record<DATA>>, C3p0Error>
+where
+    Record<DATA>: TableOps<DB, DATA>,
+{
+    if !DATA::UPGRADE_ON_READ || !json_queryable::<DATA>() || records.is_empty() {
+        return Ok(records);
+    }
+    let Some(outdated) = CodecOf::<DATA>::outdated() else {
+        return Ok(records);
+    };
+
+    let mut upgraded = HashMap::new();
+    for chunk in records.chunks(READ_BATCH_SIZE) {
+        let filter = Filter::and([
+            outdated.clone(),
+            Filter::in_list(Field::Id, chunk.iter().map(|record| record.id.to_sql())),
+        ]);
+        let stale =
+            <Record<DATA> as TableOps<DB, DATA>>::fetch_where(tx, table, &filter, 0, None).await?;
+        if stale.is_empty() {
+            continue;
+        }
+        let result = <Record<DATA> as TableOps<DB, DATA>>::update_all(stale, tx, table).await?;
+        upgraded.extend(
+            result
+                .updated
+                .into_iter()
+                .map(|record| (record.id.clone(), record)),
+        );
+    }
+    if !upgraded.is_empty() {
+        for record in &mut records {
+            if let Some(upgraded) = upgraded.remove(&record.id) {
+                *record = upgraded;
+            }
+        }
+    }
+    Ok(records)
+}
+
+/// Rewrites in the latest shape all the outdated entries, in batches of `batch_size`
+/// entries ordered by `id`, calling `progress` after each batch.
+pub(crate) async fn upgrade_all<DB: Database, DATA: DataType>(
+    tx: &mut DB::Connection,
+    table: &TableName,
+    batch_size: u64,
+    progress: impl FnMut(&UpgradeProgress),
+) -> Result<UpgradeProgress, C3p0Error>
+where
+    Record<DATA>: TableOps<DB, DATA>,
+{
+    let Some(outdated) = CodecOf::<DATA>::outdated() else {
+        return Ok(UpgradeProgress::default());
+    };
+    // The filter cannot select the binary or compressed entries, so all of them are rewritten
+    let outdated = if !json_queryable::<DATA>() || has_compressed_column::<DATA>() {
+        Filter::and([])
+    } else {
+        outdated
+    };
+    rewrite_where::<DB, DATA>(tx, table, &outdated, batch_size, progress).await
+}
+
+/// Rewrites with the [`DataType::CODEC`] the entries matching the `filter`, in batches of
+/// `batch_size` entries ordered by `id`, calling `progress` after each batch.
+pub(crate) async fn rewrite_where<DB: Database, DATA: DataType>(
+    tx: &mut DB::Connection,
+    table: &TableName,
+    filter: &Filter,
+    batch_size: u64,
+    mut progress: impl FnMut(&UpgradeProgress),
+) -> Result<UpgradeProgress, C3p0Error>
+where
+    Record<DATA>: TableOps<DB, DATA>,
+{
+    let batch_size = batch_size.max(1);
+    let mut result = UpgradeProgress {
+        total: <Record<DATA> as TableOps<DB, DATA>>::count_where(tx, table, filter).await?,
+        ..UpgradeProgress::default()
+    };
+
+    // The batches are selected after the last id, so that an entry that still matches the
+    // filter after its rewrite is not selected again
+    let mut last_id: Option<DATA::ID> = None;
+    loop {
+        let batch_filter = match &last_id {
+            Some(id) => Filter::and([filter.clone(), Filter::gt(Field::Id, id.to_sql())]),
+            None => filter.clone(),
+        };
+        let batch = <Record<DATA> as TableOps<DB, DATA>>::fetch_where(
+            tx,
+            table,
+            &batch_filter,
+            0,
+            Some(batch_size),
+        )
+        .await?;
+        let Some(last) = batch.last() else {
+            break;
+        };
+        last_id = Some(last.id.clone());
+        let complete = (batch.len() as u64) < batch_size;
+
+        let updated = <Record<DATA> as TableOps<DB, DATA>>::update_all(batch, tx, table).await?;
+        result.upgraded += updated.updated.len() as u64;
+        result.conflicts += updated.conflicts.len() as u64;
+        progress(&result);
+        if complete {
+            break;
+        }
+    }
+    Ok(result)
+}
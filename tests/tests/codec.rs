@@ -122,3 +122,213 @@ impl Codec<UserVersion2> for Versioning2 {
         }
     }
 }
+
+const UPGRADE_TABLE: &str =
+    const_format::concatcp!("CODEC_UPGRADE_TEST_TABLE", const_random::const_random!(u64));
+
+/// Writes the entries in the first shape.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+struct LegacyUser {
+    pub username: String,
+    pub email: String,
+}
+
+impl c3p0::DataType for LegacyUser {
+    const TABLE_NAME: &'static str = UPGRADE_TABLE;
+    type CODEC = LegacyVersioning;
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "@json_tag")]
+enum LegacyVersioning {
+    V1(UserVersion1),
+}
+
+impl Codec<LegacyUser> for LegacyVersioning {
+    fn encode(data: LegacyUser) -> Self {
+        LegacyVersioning::V1(UserVersion1 {
+            username: data.username,
+            email: data.email,
+        })
+    }
+
+    fn decode(data: Self) -> LegacyUser {
+        match data {
+            LegacyVersioning::V1(user) => LegacyUser {
+                username: user.username,
+                email: user.email,
+            },
+        }
+    }
+}
+
+/// Reads the entries in the latest shape and rewrites the outdated ones.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+struct UpgradedUser {
+    pub username: String,
+    pub email: String,
+    pub age: u32,
+}
+
+impl c3p0::DataType for UpgradedUser {
+    const TABLE_NAME: &'static str = UPGRADE_TABLE;
+    type CODEC = UpgradedVersioning;
+    const UPGRADE_ON_READ: bool = true;
+}
+
+impl Upgrade for UserVersion1 {
+    type Next = UpgradedUser;
+
+    fn upgrade(self) -> UpgradedUser {
+        UpgradedUser {
+            username: self.username,
+            email: self.email,
+            age: 18,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "@json_tag")]
+enum UpgradedVersioning {
+    V1(UserVersion1),
+    V2(UpgradedUser),
+}
+
+impl Codec<UpgradedUser> for UpgradedVersioning {
+    fn encode(data: UpgradedUser) -> Self {
+        UpgradedVersioning::V2(data)
+    }
+
+    fn decode(data: Self) -> UpgradedUser {
+        match data {
+            UpgradedVersioning::V1(user) => user.upgrade(),
+            UpgradedVersioning::V2(user) => user,
+        }
+    }
+
+    fn outdated() -> Option<Filter> {
+        Some(Filter::ne(Field::json("@json_tag"), "V2"))
+    }
+}
+
+/// Reads the entries in the latest shape without rewriting them.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+struct LazyUser {
+    pub username: String,
+    pub email: String,
+    pub age: u32,
+}
+
+impl c3p0::DataType for LazyUser {
+    const TABLE_NAME: &'static str = UPGRADE_TABLE;
+    type CODEC = LazyVersioning;
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "@json_tag")]
+enum LazyVersioning {
+    V1(UserVersion1),
+    V2(LazyUser),
+}
+
+impl Codec<LazyUser> for LazyVersioning {
+    fn encode(data: LazyUser) -> Self {
+        LazyVersioning::V2(data)
+    }
+
+    fn decode(data: Self) -> LazyUser {
+        match data {
+            LazyVersioning::V1(user) => LazyUser {
+                username: user.username,
+                email: user.email,
+                age: 18,
+            },
+            LazyVersioning::V2(user) => user,
+        }
+    }
+
+    fn outdated() -> Option<Filter> {
+        Some(Filter::ne(Field::json("@json_tag"), "V2"))
+    }
+}
+
+fn legacy_user(username: &str) -> NewRecord<LegacyUser> {
+    LegacyUser {
+        username: username.to_owned(),
+        email: format!("{username}@test.com"),
+    }
+    .into()
+}
+
+#[test]
+fn should_write_back_the_upgraded_entries() -> Result<(), C3p0Error> {
+    run_test(async {
+        let data = data(false).await;
+        let pool = &data.0;
+
+        pool.transaction::<_, C3p0Error, _>(async |conn| {
+            conn.drop_table_if_exists::<LegacyUser>(false).await?;
+            conn.create_table_if_not_exists::<LegacyUser>().await?;
+            let saved = conn
+                .save_all(vec![
+                    legacy_user("alice"),
+                    legacy_user("bob"),
+                    legacy_user("carol"),
+                ])
+                .await?;
+            let outdated = <UpgradedVersioning as Codec<UpgradedUser>>::outdated().unwrap();
+            assert_eq!(3, conn.count_where::<UpgradedUser>(&outdated).await?);
+
+            // Without UPGRADE_ON_READ the entries are only upgraded in memory
+            let lazy = conn.fetch_all::<LazyUser>(0, None).await?;
+            assert!(
+                lazy.iter()
+                    .all(|user| user.version == 0 && user.data.age == 18)
+            );
+            assert_eq!(3, conn.count_where::<LazyUser>(&outdated).await?);
+
+            // With UPGRADE_ON_READ the entries read are rewritten
+            let upgraded = conn.fetch_one_by_id::<UpgradedUser>(saved[0].id).await?;
+            assert_eq!(1, upgraded.version);
+            assert_eq!(18, upgraded.data.age);
+            assert_eq!(2, conn.count_where::<UpgradedUser>(&outdated).await?);
+            let again = conn.fetch_one_by_id::<UpgradedUser>(saved[0].id).await?;
+            assert_eq!(upgraded, again);
+
+            // The explicit batch job rewrites the remaining entries
+            let mut reports = vec![];
+            let progress = conn
+                .upgrade_all::<LazyUser>(1, |progress| reports.push(*progress))
+                .await?;
+            assert_eq!(
+                UpgradeProgress {
+                    total: 2,
+                    upgraded: 2,
+                    conflicts: 0
+                },
+                progress
+            );
+            assert_eq!(
+                vec![1, 2],
+                reports
+                    .iter()
+                    .map(|report| report.upgraded)
+                    .collect::<Vec<_>>()
+            );
+            assert_eq!(0, conn.count_where::<LazyUser>(&outdated).await?);
+
+            let users = conn.fetch_all::<LazyUser>(0, None).await?;
+            assert_eq!(
+                vec![1, 1, 1],
+                users.iter().map(|user| user.version).collect::<Vec<_>>()
+            );
+            assert_eq!(
+                UpgradeProgress::default(),
+                conn.upgrade_all::<LazyUser>(10, |_| {}).await?
+            );
+            Ok(())
+        })
+        .await
+    })
+}
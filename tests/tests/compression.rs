@@ -0,0 +1,237 @@
+use crate::utils::*;
+use crate::*;
+
+/// Declares a type whose large documents are compressed with `$compression` and the test
+/// of their storage.
+macro_rules! compression_test {
+    ($module:ident, $compression:expr) => {
+        pub mod $module {
+            use super::*;
+            use c3p0::patch::JsonPatch;
+            use c3p0::projection::Projection;
+            use serde::{Deserialize, Serialize};
+            use serde_json::{Value, json};
+
+            #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+            pub struct Document {
+                pub title: String,
+                pub body: String,
+            }
+
+            impl DataType for Document {
+                const TABLE_NAME: &'static str =
+                    const_format::concatcp!("TEST_TABLE_", const_random::const_random!(u64));
+                type CODEC = Self;
+                const HISTORY: bool = true;
+                const COMPRESSION: Option<Compression> = Some($compression);
+            }
+
+            #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+            pub struct KeyedDocument {
+                pub title: String,
+            }
+
+            impl DataType for KeyedDocument {
+                const TABLE_NAME: &'static str =
+                    const_format::concatcp!("TEST_TABLE_", const_random::const_random!(u64));
+                type CODEC = Self;
+                const UNIQUE_KEY: &'static [&'static str] = &["title"];
+                const COMPRESSION: Option<Compression> = Some($compression);
+            }
+
+            #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+            pub struct IndexedDocument {
+                pub title: String,
+            }
+
+            impl DataType for IndexedDocument {
+                const TABLE_NAME: &'static str =
+                    const_format::concatcp!("TEST_TABLE_", const_random::const_random!(u64));
+                type CODEC = Self;
+                const INDEXES: &'static [JsonIndex] =
+                    &[JsonIndex::new("title", &[("title", JsonType::Text)]).unique()];
+                const COMPRESSION: Option<Compression> = Some($compression);
+            }
+
+            #[test]
+            fn should_compress_the_large_documents() -> Result<(), C3p0Error> {
+                run_test(async {
+                    let data = data(false).await;
+                    let pool = &data.0;
+                    let raw = DynamicTable::new(Document::TABLE_NAME)?;
+
+                    let (small, large) = pool
+                        .transaction::<_, C3p0Error, _>(async |conn| {
+                            conn.drop_table_if_exists::<Document>(false).await?;
+                            conn.create_table_if_not_exists::<Document>().await?;
+
+                            let small = conn
+                                .save(NewRecord::new(Document {
+                                    title: "small".to_owned(),
+                                    body: "short".to_owned(),
+                                }))
+                                .await?;
+                            let large = conn
+                                .save(NewRecord::new(Document {
+                                    title: "large".to_owned(),
+                                    body: "long ".repeat(1000),
+                                }))
+                                .await?;
+
+                            // The small documents stay plain JSON, the large ones are
+                            // stored in the data_compressed column
+                            assert_eq!(
+                                json!({"title": "small", "body": "short"}),
+                                raw.fetch_one_by_id(conn, small.id).await?.data
+                            );
+                            assert_eq!(Value::Null, raw.fetch_one_by_id(conn, large.id).await?.data);
+                            assert!(conn.verify_table::<Document>().await?.is_valid());
+
+                            // The compressed documents are decompressed on read
+                            let mut record = conn.fetch_one_by_id::<Document>(large.id).await?;
+                            assert_eq!(large, record);
+                            assert_eq!(2, conn.fetch_all::<Document>(0, None).await?.len());
+
+                            // The JSON queries only match the plain documents
+                            let titled = |title: &str| Filter::eq(Field::json("title"), title);
+                            assert_eq!(
+                                vec![small.clone()],
+                                conn.fetch_where::<Document>(&titled("small"), 0, None).await?
+                            );
+                            assert!(
+                                conn.fetch_where::<Document>(&titled("large"), 0, None)
+                                    .await?
+                                    .is_empty()
+                            );
+                            assert_eq!(1, conn.count_where::<Document>(&!titled("other")).await?);
+                            let untitled = Filter::is_null(Field::json("title"));
+                            assert_eq!(0, conn.count_where::<Document>(&untitled).await?);
+                            let page = conn
+                                .fetch_page_where::<Document>(&titled("small"), PageOrder::IdAsc, None, 10)
+                                .await?;
+                            assert_eq!(vec![small.clone()], page.records);
+                            let by_title = [SortKey::json("title", JsonType::Text, OrderBy::Asc)];
+                            assert_eq!(
+                                vec![small.clone()],
+                                conn.fetch_where_sorted::<Document>(&Filter::and([]), &by_title, 0, None)
+                                    .await?
+                            );
+                            let titles = conn
+                                .fetch_projected::<Document, Value>(
+                                    &Projection::new().field("title", "title"),
+                                    &Filter::and([]),
+                                    &[],
+                                    0,
+                                    None,
+                                )
+                                .await?;
+                            assert_eq!(
+                                vec![json!({"title": "small"})],
+                                titles.into_iter().map(|projected| projected.data).collect::<Vec<_>>()
+                            );
+                            let by_id = Filter::eq(Field::Id, large.id);
+                            assert_eq!(
+                                vec![large.clone()],
+                                conn.fetch_where::<Document>(&by_id, 0, None).await?
+                            );
+                            assert_eq!(1, conn.count_where::<Document>(&by_id).await?);
+
+                            // The documents updated below the threshold are stored as plain JSON again
+                            record.data.body = "short".to_owned();
+                            let updated = conn.update(record).await?;
+                            assert_eq!(
+                                updated.data,
+                                serde_json::from_value::<Document>(
+                                    raw.fetch_one_by_id(conn, large.id).await?.data
+                                )
+                                .unwrap()
+                            );
+                            let history = conn.fetch_history::<Document>(large.id).await?;
+                            assert_eq!(large.data, history[0].data);
+
+                            assert!(conn.scan_for_decode_errors::<Document>(1).await?.is_empty());
+                            Ok((small, large))
+                        })
+                        .await?;
+
+                    // The patches apply to the plain documents and fail on the compressed ones
+                    pool.transaction::<_, C3p0Error, _>(async |conn| {
+                        let patched = conn
+                            .patch::<Document>(
+                                small.id,
+                                None,
+                                &JsonPatch::Merge(json!({"title": "patched"})),
+                            )
+                            .await?;
+                        assert_eq!("patched", patched.data.title);
+
+                        let compressed = conn
+                            .save(NewRecord::new(Document {
+                                title: "compressed".to_owned(),
+                                body: "long ".repeat(1000),
+                            }))
+                            .await?;
+                        assert!(
+                            conn.patch::<Document>(
+                                compressed.id,
+                                None,
+                                &JsonPatch::Merge(json!({"title": "patched"}))
+                            )
+                            .await
+                            .is_err()
+                        );
+                        assert_eq!(
+                            compressed,
+                            conn.fetch_one_by_id::<Document>(compressed.id).await?
+                        );
+                        assert_eq!(
+                            large.version + 1,
+                            conn.fetch_one_by_id::<Document>(large.id).await?.version
+                        );
+
+                        // A DynamicTable decompresses the documents and writes them uncompressed
+                        let dynamic = DynamicTable::of::<Document>()?;
+                        let record = dynamic.fetch_one_by_id(conn, compressed.id).await?;
+                        assert_eq!(serde_json::to_value(&compressed.data).unwrap(), record.data);
+                        let titled = Filter::eq(Field::json("title"), "compressed");
+                        assert_eq!(0, dynamic.count_where(conn, &titled).await?);
+                        let updated = dynamic.update(conn, record.clone()).await?;
+                        assert_eq!(record.data, updated.data);
+                        assert_eq!(record.data, raw.fetch_one_by_id(conn, compressed.id).await?.data);
+                        assert_eq!(1, dynamic.count_where(conn, &titled).await?);
+                        assert_eq!(
+                            compressed.data,
+                            conn.fetch_one_by_id::<Document>(compressed.id).await?.data
+                        );
+
+                        // They are compressed again when updated through the DataType
+                        let record = conn.fetch_one_by_id::<Document>(compressed.id).await?;
+                        conn.update(record).await?;
+                        assert_eq!(Value::Null, raw.fetch_one_by_id(conn, compressed.id).await?.data);
+                        Ok(())
+                    })
+                    .await?;
+
+                    // The UNIQUE_KEY and the unique indexes are not supported
+                    pool.transaction::<_, C3p0Error, _>(async |conn| {
+                        assert!(TableSchema::of::<KeyedDocument>().is_err());
+                        assert!(TableSchema::of::<IndexedDocument>().is_err());
+                        assert!(
+                            conn.create_table_if_not_exists::<KeyedDocument>()
+                                .await
+                                .is_err()
+                        );
+                        Ok(())
+                    })
+                    .await
+                })
+            }
+        }
+    };
+}
+
+#[cfg(feature = "zstd")]
+compression_test!(zstd, Compression::zstd(256));
+
+#[cfg(feature = "gzip")]
+compression_test!(gzip, Compression::gzip(256));
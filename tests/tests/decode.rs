@@ -0,0 +1,112 @@
+use crate::utils::*;
+use crate::*;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+
+const DECODE_TABLE: &str = const_format::concatcp!("TEST_TABLE_", const_random::const_random!(u64));
+
+/// The data written without constraints on its shape.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct LooseData {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<Value>,
+    pub tags: Vec<Value>,
+}
+
+impl c3p0::DataType for LooseData {
+    const TABLE_NAME: &'static str = DECODE_TABLE;
+    type CODEC = Self;
+}
+
+/// The same data read with a stricter shape.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct StrictData {
+    pub name: String,
+    pub tags: Vec<u32>,
+}
+
+impl c3p0::DataType for StrictData {
+    const TABLE_NAME: &'static str = DECODE_TABLE;
+    type CODEC = Self;
+}
+
+#[test]
+fn should_read_the_entries_that_cannot_be_decoded() -> Result<(), C3p0Error> {
+    run_test(async {
+        let data = data(false).await;
+        let pool = &data.0;
+
+        pool.transaction::<_, C3p0Error, _>(async |conn| {
+            conn.drop_table_if_exists::<LooseData>(false).await?;
+            conn.create_table_if_not_exists::<LooseData>().await?;
+
+            let valid = conn
+                .save(NewRecord::new(LooseData {
+                    name: Some(json!("valid")),
+                    tags: vec![json!(1), json!(2)],
+                }))
+                .await?;
+            let wrong_tag = conn
+                .save(NewRecord::new(LooseData {
+                    name: Some(json!("wrong_tag")),
+                    tags: vec![json!(1), json!("two")],
+                }))
+                .await?;
+            let wrong_name = conn
+                .save(NewRecord::new(LooseData {
+                    name: Some(json!(5)),
+                    tags: vec![],
+                }))
+                .await?;
+            let missing_name = conn
+                .save(NewRecord::new(LooseData {
+                    name: None,
+                    tags: vec![],
+                }))
+                .await?;
+
+            let entries = conn.fetch_all_lenient::<StrictData>(0, None).await?;
+            assert_eq!(4, entries.len());
+            let record = entries[0].as_ref().expect("the entry should be decoded");
+            assert_eq!(valid.id, record.id);
+            assert_eq!(vec![1, 2], record.data.tags);
+
+            let error = entries[1]
+                .as_ref()
+                .expect_err("the entry should not be decoded");
+            assert_eq!(wrong_tag.id, error.id);
+            assert_eq!("tags[1]", error.path);
+            assert_eq!(json!([1, "two"]), error.data["tags"]);
+            assert!(error.error.contains("invalid type"), "{}", error.error);
+
+            // The paging of fetch_all applies
+            let entries = conn.fetch_all_lenient::<StrictData>(1, Some(1)).await?;
+            assert_eq!(1, entries.len());
+            assert_eq!(wrong_tag.id, entries[0].as_ref().unwrap_err().id);
+
+            // The scan reads the table in chunks smaller than the table
+            let errors = conn.scan_for_decode_errors::<StrictData>(2).await?;
+            let reported: Vec<(i64, &str)> = errors
+                .iter()
+                .map(|error| (error.id, error.path.as_str()))
+                .collect();
+            assert_eq!(
+                vec![
+                    (wrong_tag.id, "tags[1]"),
+                    (wrong_name.id, "name"),
+                    (missing_name.id, "")
+                ],
+                reported
+            );
+            assert!(errors[2].error.contains("missing field `name`"));
+
+            assert!(
+                conn.scan_for_decode_errors::<LooseData>(2)
+                    .await?
+                    .is_empty()
+            );
+            Ok(())
+        })
+        .await
+    })
+}
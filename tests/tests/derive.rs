@@ -0,0 +1,221 @@
+use crate::utils::*;
+use crate::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Customer {
+    pub id: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, c3p0::DataType)]
+#[c3p0(
+    table = "TEST_TABLE_DERIVED_INVOICES",
+    unique("number"),
+    indexes(
+        customer("customer.id": Number, "date": Text),
+        paid("paid": Bool, unique),
+        data(containment),
+    ),
+    soft_delete,
+    history
+)]
+pub struct Invoice {
+    pub number: String,
+    pub customer: Customer,
+    pub date: String,
+    pub paid: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, c3p0::DataType)]
+#[c3p0(table = "\"notes\".\"Derived Notes\"", codec = NoteCodec, id = String)]
+#[c3p0(upgrade_on_read)]
+pub struct Note {
+    pub text: String,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "version")]
+pub enum NoteCodec {
+    V1(Note),
+}
+
+impl Codec<Note> for NoteCodec {
+    fn encode(data: Note) -> Self {
+        NoteCodec::V1(data)
+    }
+
+    fn decode(data: Self) -> Note {
+        match data {
+            NoteCodec::V1(note) => note,
+        }
+    }
+}
+
+/// Writes the entries in the first shape.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, c3p0::DataType, c3p0::Codec)]
+#[c3p0(table = "TEST_TABLE_DERIVED_USERS", codec = UserV1Codec, tag = "version")]
+pub struct UserV1 {
+    pub username: String,
+}
+
+impl Upgrade for UserV1 {
+    type Next = UserV2;
+
+    fn upgrade(self) -> UserV2 {
+        UserV2 {
+            username: self.username,
+            age: 18,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct UserV2 {
+    pub username: String,
+    pub age: u32,
+}
+
+impl Upgrade for UserV2 {
+    type Next = User;
+
+    fn upgrade(self) -> User {
+        User {
+            username: self.username,
+            age: self.age,
+            email: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, c3p0::DataType, c3p0::Codec)]
+#[c3p0(table = "TEST_TABLE_DERIVED_USERS", codec = UserCodec, versions(UserV1, UserV2), tag = "version")]
+pub struct User {
+    pub username: String,
+    pub age: u32,
+    pub email: Option<String>,
+}
+
+#[cfg(feature = "zstd")]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, c3p0::DataType)]
+#[c3p0(table = "TEST_TABLE_DERIVED_REPORTS", compression = zstd(16 * 1024))]
+pub struct Report {
+    pub body: String,
+}
+
+#[test]
+fn should_derive_the_declarations() {
+    assert_eq!("TEST_TABLE_DERIVED_INVOICES", Invoice::TABLE_NAME);
+    assert_eq!(&["number"], Invoice::UNIQUE_KEY);
+    assert_eq!(
+        &[
+            JsonIndex::new(
+                "customer",
+                &[("customer.id", JsonType::Number), ("date", JsonType::Text)]
+            ),
+            JsonIndex::new("paid", &[("paid", JsonType::Bool)]).unique(),
+            JsonIndex::containment("data"),
+        ],
+        Invoice::INDEXES
+    );
+    static_assertions::const_assert!(Invoice::SOFT_DELETE);
+    static_assertions::const_assert!(Invoice::HISTORY);
+    static_assertions::const_assert!(!Invoice::UPGRADE_ON_READ);
+    assert!(TableSchema::of::<Invoice>().is_ok());
+
+    assert_eq!("\"notes\".\"Derived Notes\"", Note::TABLE_NAME);
+    assert!(Note::INDEXES.is_empty());
+    static_assertions::const_assert!(Note::UPGRADE_ON_READ);
+    static_assertions::assert_type_eq_all!(<Note as DataType>::CODEC, c3p0::WithId<NoteCodec, String>);
+    static_assertions::assert_type_eq_all!(<Note as DataId>::ID, String);
+
+    #[cfg(feature = "zstd")]
+    assert_eq!(Some(Compression::zstd(16 * 1024)), Report::COMPRESSION);
+}
+
+#[test]
+fn should_check_the_derived_table_names_as_at_runtime() {
+    // The derive rejects at compile time the names rejected by TableName::check
+    static_assertions::const_assert!(TableName::check(Invoice::TABLE_NAME).is_ok());
+    static_assertions::const_assert!(TableName::check(Note::TABLE_NAME).is_ok());
+    static_assertions::const_assert!(TableName::check("billing.invoices").is_ok());
+    static_assertions::const_assert!(TableName::check("a.b.c").is_err());
+    static_assertions::const_assert!(TableName::check("1invoices").is_err());
+    static_assertions::const_assert!(TableName::check("\"invoices").is_err());
+    assert!(DynamicTable::new("a.b.c").is_err());
+}
+
+#[test]
+fn should_store_a_derived_type() -> Result<(), C3p0Error> {
+    run_test(async {
+        let data = data(false).await;
+        let pool = &data.0;
+
+        pool.transaction::<_, C3p0Error, _>(async |conn| {
+            conn.drop_table_if_exists::<Invoice>(false).await?;
+            conn.create_table_if_not_exists::<Invoice>().await?;
+            let report = conn.verify_table::<Invoice>().await?;
+            assert!(report.is_valid(), "{report}");
+
+            let invoice = conn
+                .save(NewRecord::new(Invoice {
+                    number: "2025/1".to_owned(),
+                    customer: Customer { id: 7 },
+                    date: "2025-01-31".to_owned(),
+                    paid: false,
+                }))
+                .await?;
+            assert_eq!(invoice, conn.fetch_one_by_id::<Invoice>(invoice.id).await?);
+            conn.drop_table_if_exists::<Invoice>(false).await
+        })
+        .await
+    })
+}
+
+#[test]
+fn should_upgrade_with_a_derived_codec() -> Result<(), C3p0Error> {
+    run_test(async {
+        let data = data(false).await;
+        let pool = &data.0;
+
+        pool.transaction::<_, C3p0Error, _>(async |conn| {
+            conn.drop_table_if_exists::<User>(false).await?;
+            conn.create_table_if_not_exists::<User>().await?;
+
+            let old = conn
+                .save(NewRecord::new(UserV1 {
+                    username: "old".to_owned(),
+                }))
+                .await?;
+            let new = conn
+                .save(NewRecord::new(User {
+                    username: "new".to_owned(),
+                    age: 40,
+                    email: Some("new@c3p0.io".to_owned()),
+                }))
+                .await?;
+            assert!(matches!(
+                UserCodec::encode(new.data.clone()),
+                UserCodec::V3(_)
+            ));
+
+            // The previous versions are upgraded through the chain
+            let upgraded = conn.fetch_one_by_id::<User>(old.id).await?;
+            assert_eq!(
+                User {
+                    username: "old".to_owned(),
+                    age: 18,
+                    email: None,
+                },
+                upgraded.data
+            );
+            assert_eq!(new, conn.fetch_one_by_id::<User>(new.id).await?);
+
+            let progress = conn.upgrade_all::<User>(10, |_| {}).await?;
+            assert_eq!(1, progress.total);
+            assert_eq!(1, progress.upgraded);
+            assert!(conn.fetch_one_by_id::<UserV1>(old.id).await.is_err());
+            conn.drop_table_if_exists::<User>(false).await
+        })
+        .await
+    })
+}
@@ -0,0 +1,300 @@
+use crate::utils::*;
+use crate::*;
+use c3p0::{DynamicId, IdKind, WithId};
+use futures_util::TryStreamExt;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Account {
+    pub username: String,
+    pub age: i64,
+}
+
+impl c3p0::DataType for Account {
+    const TABLE_NAME: &'static str =
+        const_format::concatcp!("TEST_TABLE_", const_random::const_random!(u64));
+    type CODEC = Self;
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Member {
+    pub username: String,
+}
+
+impl c3p0::DataType for Member {
+    const TABLE_NAME: &'static str =
+        const_format::concatcp!("TEST_TABLE_", const_random::const_random!(u64));
+    type CODEC = WithId<Self, String>;
+    const SOFT_DELETE: bool = true;
+    const HISTORY: bool = true;
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Player {
+    pub username: String,
+    pub score: i64,
+}
+
+impl c3p0::DataType for Player {
+    const TABLE_NAME: &'static str =
+        const_format::concatcp!("TEST_TABLE_", const_random::const_random!(u64));
+    type CODEC = WithId<Self, String>;
+    const SOFT_DELETE: bool = true;
+    const HISTORY: bool = true;
+}
+
+#[test]
+fn should_access_a_table_as_raw_json() -> Result<(), C3p0Error> {
+    run_test(async {
+        let data = data(false).await;
+        let pool = &data.0;
+        let table = DynamicTable::new(Account::TABLE_NAME)?;
+
+        pool.transaction::<_, C3p0Error, _>(async |conn| {
+            conn.drop_table_if_exists::<Account>(false).await?;
+            conn.create_table_if_not_exists::<Account>().await?;
+
+            let saved = conn
+                .save(NewRecord::new(Account {
+                    username: "ada".to_owned(),
+                    age: 36,
+                }))
+                .await?;
+
+            let record = table.fetch_one_by_id(conn, saved.id).await?;
+            assert_eq!(DynamicId::Int(saved.id), record.id);
+            assert_eq!(saved.version, record.version);
+            assert_eq!(json!({"username": "ada", "age": 36}), record.data);
+
+            // The updates keep the optimistic locking
+            let mut changed = record.clone();
+            changed.data["age"] = json!(37);
+            let updated = table.update(conn, changed).await?;
+            assert_eq!(record.version + 1, updated.version);
+            assert_eq!(
+                37,
+                conn.fetch_one_by_id::<Account>(saved.id).await?.data.age
+            );
+
+            assert!(matches!(
+                table.update(conn, record).await,
+                Err(C3p0Error::OptimisticLockError { .. })
+            ));
+
+            // The ids must be of the kind of the table
+            assert!(table.fetch_one_by_id(conn, "ada").await.is_err());
+            Ok(())
+        })
+        .await?;
+
+        pool.transaction::<_, C3p0Error, _>(async |conn| {
+            let saved = table
+                .save(conn, json!({"username": "grace", "age": 45}))
+                .await?;
+            let DynamicId::Int(id) = saved.id else {
+                panic!("The ids of the table are integers");
+            };
+            let account = conn.fetch_one_by_id::<Account>(id).await?;
+            assert_eq!("grace", account.data.username);
+
+            let found = table
+                .fetch_where(conn, &Filter::gt(Field::json("age"), 40), 0, None)
+                .await?;
+            assert_eq!(
+                vec![saved.id.clone()],
+                found.into_iter().map(|r| r.id).collect::<Vec<_>>()
+            );
+
+            table.delete(conn, saved).await?;
+            assert_eq!(1, table.count_all(conn).await?);
+            assert_eq!(1, conn.count_all::<Account>().await?);
+            Ok(())
+        })
+        .await
+    })
+}
+
+#[test]
+fn should_respect_the_options_of_the_table() -> Result<(), C3p0Error> {
+    run_test(async {
+        let data = data(false).await;
+        let pool = &data.0;
+        let table = DynamicTable::of::<Member>()?;
+        assert_eq!(
+            DynamicTable::new(Member::TABLE_NAME)?
+                .with_ids(IdKind::Text)
+                .with_soft_delete(true)
+                .with_history(true),
+            table
+        );
+
+        pool.transaction::<_, C3p0Error, _>(async |conn| {
+            conn.drop_table_if_exists::<Member>(false).await?;
+            conn.create_table_if_not_exists::<Member>().await?;
+
+            // The text ids are provided
+            assert!(table.save(conn, json!({"username": "ada"})).await.is_err());
+            let saved = table
+                .save_with_id(conn, "ada", json!({"username": "ada"}))
+                .await?;
+            assert_eq!(DynamicId::from("ada"), saved.id);
+
+            let mut changed = saved.clone();
+            changed.data["username"] = json!("Ada");
+            let updated = table.update(conn, changed).await?;
+            assert_eq!(
+                vec![saved.version],
+                conn.fetch_history::<Member>("ada".to_owned())
+                    .await?
+                    .iter()
+                    .map(|record| record.version)
+                    .collect::<Vec<_>>()
+            );
+
+            // The entries are soft deleted
+            table.delete(conn, updated).await?;
+            assert_eq!(0, table.count_all(conn).await?);
+            assert!(table.fetch_all(conn, 0, None).await?.is_empty());
+            assert!(table.fetch_one_optional_by_id(conn, "ada").await?.is_none());
+            let deleted = conn.fetch_including_deleted::<Member>(0, None).await?;
+            assert_eq!(1, deleted.len());
+            assert!(deleted[0].delete_time.is_some());
+
+            conn.drop_table_if_exists::<Member>(false).await
+        })
+        .await
+    })
+}
+
+#[test]
+fn should_run_the_operations_of_the_typed_tables() -> Result<(), C3p0Error> {
+    run_test(async {
+        let data = data(false).await;
+        let pool = &data.0;
+        let table = DynamicTable::of::<Player>()?;
+
+        pool.transaction::<_, C3p0Error, _>(async |conn| {
+            conn.drop_table_if_exists::<Player>(false).await?;
+            conn.create_table_if_not_exists::<Player>().await?;
+
+            for (username, score) in [("ada", 3), ("bob", 1), ("eve", 2)] {
+                table
+                    .save_with_id(
+                        conn,
+                        username,
+                        json!({"username": username, "score": score}),
+                    )
+                    .await?;
+            }
+            assert!(table.exists_by_id(conn, "ada").await?);
+            assert!(!table.exists_by_id(conn, "joe").await?);
+
+            let by_score = table
+                .fetch_all_sorted(
+                    conn,
+                    &[SortKey::json("score", JsonType::Number, OrderBy::Desc)],
+                    0,
+                    None,
+                )
+                .await?;
+            assert_eq!(
+                vec!["ada", "eve", "bob"],
+                by_score
+                    .iter()
+                    .map(|record| record.data["username"].as_str().unwrap())
+                    .collect::<Vec<_>>()
+            );
+
+            let first = table.fetch_page(conn, PageOrder::IdAsc, None, 2).await?;
+            assert_eq!(2, first.records.len());
+            let second = table
+                .fetch_page(conn, PageOrder::IdAsc, first.next.as_ref(), 2)
+                .await?;
+            assert_eq!(vec![DynamicId::from("eve")], ids(&second.records));
+            assert!(second.next.is_none());
+
+            let streamed: Vec<DynamicRecord> = table
+                .fetch_where_stream(conn, &Filter::gt(Field::json("score"), 1))
+                .try_collect()
+                .await?;
+            assert_eq!(
+                vec![DynamicId::from("ada"), DynamicId::from("eve")],
+                ids(&streamed)
+            );
+
+            // The patches and the batch updates keep the history
+            let patched = table
+                .patch(conn, "ada", Some(0), &JsonPatch::Merge(json!({"score": 4})))
+                .await?;
+            assert_eq!(1, patched.version);
+            assert_eq!(json!(4), patched.data["score"]);
+            let mut stale = patched.clone();
+            stale.version = 0;
+            let mut changed = patched.clone();
+            changed.data["score"] = json!(5);
+            let result = table.update_all(conn, vec![changed, stale]).await?;
+            assert_eq!(1, result.updated.len());
+            assert_eq!(1, result.conflicts.len());
+            assert_eq!(
+                vec![0, 1],
+                table
+                    .fetch_history(conn, "ada")
+                    .await?
+                    .iter()
+                    .map(|record| record.version)
+                    .collect::<Vec<_>>()
+            );
+            assert_eq!(
+                Some(json!(4)),
+                table
+                    .fetch_version(conn, "ada", 1)
+                    .await?
+                    .map(|record| record.data["score"].clone())
+            );
+
+            // The deleted entries are soft deleted and can be restored
+            assert_eq!(1, table.delete_by_id(conn, "bob").await?);
+            assert_eq!(
+                1,
+                table
+                    .delete_where(conn, &Filter::eq(Field::json("username"), "eve"))
+                    .await?
+            );
+            assert_eq!(1, table.count_all(conn).await?);
+            let deleted = table.fetch_including_deleted(conn, 0, None).await?;
+            assert_eq!(3, deleted.len());
+            assert_eq!(
+                2,
+                deleted
+                    .iter()
+                    .filter(|record| record.delete_time.is_some())
+                    .count()
+            );
+            table.restore(conn, "bob").await?;
+            assert_eq!(2, table.count_all(conn).await?);
+            assert_eq!(1, table.purge(conn, None).await?);
+            assert_eq!(2, table.delete_all(conn).await?);
+            assert_eq!(0, table.count_all(conn).await?);
+
+            // The errors name the table of the handle
+            let untracked = DynamicTable::new(Player::TABLE_NAME)?.with_ids(IdKind::Text);
+            let err = untracked.fetch_history(conn, "ada").await.unwrap_err();
+            assert!(err.to_string().contains(Player::TABLE_NAME));
+            let err = untracked.restore(conn, "ada").await.unwrap_err();
+            assert!(err.to_string().contains(Player::TABLE_NAME));
+            let err = table
+                .save(conn, json!({"username": "joe", "score": 0}))
+                .await
+                .unwrap_err();
+            assert!(err.to_string().contains(Player::TABLE_NAME));
+
+            conn.drop_table_if_exists::<Player>(false).await
+        })
+        .await
+    })
+}
+
+fn ids(records: &[DynamicRecord]) -> Vec<DynamicId> {
+    records.iter().map(|record| record.id.clone()).collect()
+}
@@ -0,0 +1,199 @@
+use crate::utils::*;
+use crate::*;
+use c3p0::crypto::KeyRing;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Customer {
+    pub name: String,
+    #[serde(with = "c3p0::crypto::encrypted")]
+    pub tax_code: String,
+    #[serde(with = "c3p0::crypto::encrypted")]
+    pub phone: Option<String>,
+}
+
+impl c3p0::DataType for Customer {
+    const TABLE_NAME: &'static str =
+        const_format::concatcp!("TEST_TABLE_", const_random::const_random!(u64));
+    type CODEC = Self;
+}
+
+#[test]
+fn should_encrypt_the_annotated_fields() -> Result<(), C3p0Error> {
+    run_test(async {
+        let data = data(false).await;
+        let pool = &data.0;
+        let k1 = pool.clone().with_key_ring(KeyRing::new("k1", [1; 32]));
+        // The values encrypted with the previous keys are still decrypted
+        let k2_k1 = pool
+            .clone()
+            .with_key_ring(KeyRing::new("k2", [2; 32]).with_key("k1", [1; 32]));
+        let k2 = pool.clone().with_key_ring(KeyRing::new("k2", [2; 32]));
+        let k3 = pool.clone().with_key_ring(KeyRing::new("k3", [3; 32]));
+        let raw = DynamicTable::new(Customer::TABLE_NAME)?;
+
+        let customer = Customer {
+            name: "ada".to_owned(),
+            tax_code: "LVLDAA15D50H501X".to_owned(),
+            phone: None,
+        };
+        let saved = k1
+            .transaction::<_, C3p0Error, _>(async |conn| {
+                conn.drop_table_if_exists::<Customer>(false).await?;
+                conn.create_table_if_not_exists::<Customer>().await?;
+
+                let saved = conn.save(NewRecord::new(customer.clone())).await?;
+
+                let stored = raw.fetch_one_by_id(conn, saved.id).await?.data;
+                assert_eq!(json!("ada"), stored["name"]);
+                assert_eq!(json!("k1"), stored["tax_code"]["key_id"]);
+                assert_eq!(json!("k1"), stored["phone"]["key_id"]);
+                assert!(!stored.to_string().contains("LVLDAA15D50H501X"));
+                assert_eq!(
+                    customer,
+                    conn.fetch_one_by_id::<Customer>(saved.id).await?.data
+                );
+                Ok(saved)
+            })
+            .await?;
+
+        // The key ring belongs to the pool it was supplied to, the values are neither read
+        // nor stored in plaintext without it
+        pool.transaction::<_, C3p0Error, _>(async |conn| {
+            assert!(conn.fetch_one_by_id::<Customer>(saved.id).await.is_err());
+            assert!(conn.save(NewRecord::new(customer.clone())).await.is_err());
+            assert_eq!(1, conn.count_all::<Customer>().await?);
+            Ok(())
+        })
+        .await?;
+
+        k2_k1
+            .transaction::<_, C3p0Error, _>(async |conn| {
+                assert_eq!(
+                    customer,
+                    conn.fetch_one_by_id::<Customer>(saved.id).await?.data
+                );
+
+                let progress = conn.reencrypt_all::<Customer>(10, |_| {}).await?;
+                assert_eq!(1, progress.total);
+                assert_eq!(1, progress.upgraded);
+                let stored = raw.fetch_one_by_id(conn, saved.id).await?.data;
+                assert_eq!(json!("k2"), stored["tax_code"]["key_id"]);
+                Ok(())
+            })
+            .await?;
+
+        // The previous key is no longer needed
+        k2.transaction::<_, C3p0Error, _>(async |conn| {
+            assert_eq!(
+                customer,
+                conn.fetch_one_by_id::<Customer>(saved.id).await?.data
+            );
+            Ok(())
+        })
+        .await?;
+
+        // The values cannot be decrypted without their key
+        k3.transaction::<_, C3p0Error, _>(async |conn| {
+            assert!(conn.fetch_one_by_id::<Customer>(saved.id).await.is_err());
+            let errors = conn.scan_for_decode_errors::<Customer>(10).await?;
+            assert_eq!(1, errors.len());
+            // The first encrypted field in the stored order, which depends on the backend
+            assert!(["tax_code", "phone"].contains(&errors[0].path.as_str()));
+
+            conn.drop_table_if_exists::<Customer>(false).await
+        })
+        .await
+    })
+}
+
+#[test]
+fn should_not_decrypt_the_values_moved_to_another_field() -> Result<(), C3p0Error> {
+    run_test(async {
+        let data = data(false).await;
+        let pool = data.0.clone().with_key_ring(KeyRing::new("k1", [1; 32]));
+        let raw = DynamicTable::new(Customer::TABLE_NAME)?;
+
+        pool.transaction::<_, C3p0Error, _>(async |conn| {
+            conn.drop_table_if_exists::<Customer>(false).await?;
+            conn.create_table_if_not_exists::<Customer>().await?;
+
+            let saved = conn
+                .save(NewRecord::new(Customer {
+                    name: "ada".to_owned(),
+                    tax_code: "LVLDAA15D50H501X".to_owned(),
+                    phone: Some("555-0100".to_owned()),
+                }))
+                .await?;
+
+            let mut stored = raw.fetch_one_by_id(conn, saved.id).await?;
+            let tax_code = stored.data["tax_code"].take();
+            stored.data["tax_code"] = stored.data["phone"].take();
+            stored.data["phone"] = tax_code;
+            raw.update(conn, stored).await?;
+
+            assert!(conn.fetch_one_by_id::<Customer>(saved.id).await.is_err());
+            let errors = conn.scan_for_decode_errors::<Customer>(10).await?;
+            assert_eq!(1, errors.len());
+
+            // The values are only encrypted and decrypted by the c3p0 operations
+            let plaintext = serde_json::to_value(&saved.data).unwrap();
+            assert_eq!(json!("LVLDAA15D50H501X"), plaintext["tax_code"]);
+            assert_eq!(json!("555-0100"), plaintext["phone"]);
+            assert_eq!(saved.data, serde_json::from_value(plaintext).unwrap());
+
+            conn.drop_table_if_exists::<Customer>(false).await
+        })
+        .await
+    })
+}
+
+#[test]
+fn should_not_patch_the_encrypted_values() -> Result<(), C3p0Error> {
+    run_test(async {
+        let data = data(false).await;
+        let pool = data.0.clone().with_key_ring(KeyRing::new("k1", [1; 32]));
+        let raw = DynamicTable::new(Customer::TABLE_NAME)?;
+
+        pool.transaction::<_, C3p0Error, _>(async |conn| {
+            conn.drop_table_if_exists::<Customer>(false).await?;
+            conn.create_table_if_not_exists::<Customer>().await?;
+
+            let saved = conn
+                .save(NewRecord::new(Customer {
+                    name: "ada".to_owned(),
+                    tax_code: "LVLDAA15D50H501X".to_owned(),
+                    phone: Some("555-0100".to_owned()),
+                }))
+                .await?;
+
+            // The patched values would be stored in plaintext
+            for patch in [
+                JsonPatch::Ops(vec![PatchOp::set("tax_code", "BNCGNN70A01H501Z")]),
+                JsonPatch::Ops(vec![PatchOp::set("phone.data", "555-0199")]),
+                JsonPatch::Merge(json!({"name": "grace", "phone": "555-0199"})),
+                JsonPatch::Merge(json!(["not", "an", "object"])),
+            ] {
+                assert!(
+                    conn.patch::<Customer>(saved.id, None, &patch)
+                        .await
+                        .is_err()
+                );
+            }
+            let stored = raw.fetch_one_by_id(conn, saved.id).await?.data;
+            assert_eq!(json!("k1"), stored["tax_code"]["key_id"]);
+            assert_eq!(json!("k1"), stored["phone"]["key_id"]);
+
+            // The other fields can be patched
+            let patched = conn
+                .patch::<Customer>(saved.id, None, &JsonPatch::Merge(json!({"name": "grace"})))
+                .await?;
+            assert_eq!("grace", patched.data.name);
+            assert_eq!("LVLDAA15D50H501X", patched.data.tax_code);
+
+            conn.drop_table_if_exists::<Customer>(false).await
+        })
+        .await
+    })
+}
@@ -0,0 +1,294 @@
+use crate::utils::*;
+use crate::*;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Address {
+    pub city: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TestData {
+    pub name: String,
+    pub age: Option<i64>,
+    pub score: f64,
+    pub active: bool,
+    pub address: Address,
+}
+
+impl c3p0::DataType for TestData {
+    const TABLE_NAME: &'static str =
+        const_format::concatcp!("TEST_TABLE_", const_random::const_random!(u64));
+    type CODEC = Self;
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TaggedData {
+    pub name: String,
+    pub tags: Vec<String>,
+    pub sizes: Vec<Vec<i64>>,
+    pub address: Address,
+    pub active: bool,
+    pub note: Option<String>,
+}
+
+impl c3p0::DataType for TaggedData {
+    const TABLE_NAME: &'static str =
+        const_format::concatcp!("TEST_TABLE_", const_random::const_random!(u64));
+    type CODEC = Self;
+}
+
+fn tagged(name: &str, tags: &[&str], sizes: Vec<Vec<i64>>, city: &str) -> NewRecord<TaggedData> {
+    TaggedData {
+        name: name.to_owned(),
+        tags: tags.iter().map(|tag| (*tag).to_owned()).collect(),
+        sizes,
+        address: Address {
+            city: city.to_owned(),
+        },
+        active: tags.len() > 1,
+        note: None,
+    }
+    .into()
+}
+
+fn new_data(name: &str, age: Option<i64>, score: f64, active: bool, city: &str) -> TestData {
+    TestData {
+        name: name.to_owned(),
+        age,
+        score,
+        active,
+        address: Address {
+            city: city.to_owned(),
+        },
+    }
+}
+
+async fn names<C: Tx>(conn: &mut C, filter: &Filter) -> Result<Vec<String>, C3p0Error> {
+    Ok(conn
+        .fetch_where::<TestData>(filter, 0, None)
+        .await?
+        .into_iter()
+        .map(|record| record.data.name)
+        .collect())
+}
+
+async fn tagged_names<C: Tx>(conn: &mut C, filter: &Filter) -> Result<Vec<String>, C3p0Error> {
+    Ok(conn
+        .fetch_where::<TaggedData>(filter, 0, None)
+        .await?
+        .into_iter()
+        .map(|record| record.data.name)
+        .collect())
+}
+
+#[test]
+fn should_fetch_count_and_delete_where() -> Result<(), C3p0Error> {
+    run_test(async {
+        let data = data(false).await;
+        let pool = &data.0;
+
+        pool.transaction::<_, C3p0Error, _>(async |conn| {
+            conn.create_table_if_not_exists::<TestData>().await?;
+            conn.delete_all::<TestData>().await?;
+
+            let alice = conn
+                .save(new_data("alice", Some(30), 1.5, true, "Rome").into())
+                .await?;
+            let bob = conn
+                .save(new_data("bob", Some(10), 2.5, false, "Paris").into())
+                .await?;
+            conn.save(new_data("carol", None, 3.5, true, "Rome").into())
+                .await?;
+            conn.save(new_data("o'neil", Some(20), -1.0, false, "Berlin").into())
+                .await?;
+
+            assert_eq!(
+                vec!["alice", "carol"],
+                names(conn, &Filter::eq(Field::json("address.city"), "Rome")).await?
+            );
+            assert_eq!(
+                vec!["o'neil"],
+                names(conn, &Filter::eq(Field::json("name"), "o'neil")).await?
+            );
+            assert_eq!(
+                vec!["alice", "o'neil"],
+                names(conn, &Filter::gt(Field::json("age"), 15)).await?
+            );
+            assert_eq!(
+                vec!["bob", "o'neil"],
+                names(conn, &Filter::lte(Field::json("age"), 20)).await?
+            );
+            assert_eq!(
+                vec!["bob", "carol"],
+                names(conn, &Filter::gte(Field::json("score"), 2.0)).await?
+            );
+            assert_eq!(
+                vec!["alice", "carol"],
+                names(conn, &Filter::eq(Field::json("active"), true)).await?
+            );
+            assert_eq!(
+                vec!["carol"],
+                names(conn, &Filter::is_null(Field::json("age"))).await?
+            );
+            assert_eq!(
+                4,
+                names(conn, &Filter::is_null(Field::json("missing")))
+                    .await?
+                    .len()
+            );
+            assert_eq!(
+                vec!["bob", "carol", "o'neil"],
+                names(conn, &Filter::ne(Field::json("name"), "alice")).await?
+            );
+            assert_eq!(
+                vec!["bob", "o'neil"],
+                names(
+                    conn,
+                    &Filter::in_list(Field::json("address.city"), ["Paris", "Berlin"])
+                )
+                .await?
+            );
+            assert!(
+                names(conn, &Filter::in_list(Field::Id, Vec::<i64>::new()))
+                    .await?
+                    .is_empty()
+            );
+            assert_eq!(
+                vec!["alice", "bob"],
+                names(conn, &Filter::in_list(Field::Id, [alice.id, bob.id])).await?
+            );
+            assert_eq!(
+                vec!["carol", "o'neil"],
+                names(conn, &!Filter::in_list(Field::Id, [alice.id, bob.id])).await?
+            );
+            assert_eq!(
+                vec!["bob", "carol"],
+                names(
+                    conn,
+                    &Filter::or([
+                        Filter::and([
+                            Filter::eq(Field::json("address.city"), "Rome"),
+                            Filter::is_null(Field::json("age")),
+                        ]),
+                        Filter::eq(Field::json("name"), "bob"),
+                    ])
+                )
+                .await?
+            );
+            assert_eq!(4, names(conn, &Filter::and([])).await?.len());
+            assert!(names(conn, &Filter::or([])).await?.is_empty());
+            assert_eq!(
+                4,
+                names(conn, &Filter::gte(Field::CreateTime, alice.create_time))
+                    .await?
+                    .len()
+            );
+            assert_eq!(
+                vec!["alice"],
+                names(
+                    conn,
+                    &Filter::and([Filter::eq(Field::Version, 0), Filter::lt(Field::Id, bob.id)])
+                )
+                .await?
+            );
+
+            let paged = conn
+                .fetch_where::<TestData>(&Filter::eq(Field::json("active"), false), 1, Some(1))
+                .await?;
+            assert_eq!(1, paged.len());
+            assert_eq!("o'neil", paged[0].data.name);
+
+            let rome = Filter::eq(Field::json("address.city"), "Rome");
+            assert_eq!(2, conn.count_where::<TestData>(&rome).await?);
+            assert_eq!(2, conn.delete_where::<TestData>(&rome).await?);
+            assert_eq!(0, conn.count_where::<TestData>(&rome).await?);
+            assert_eq!(2, conn.count_all::<TestData>().await?);
+
+            Ok(())
+        })
+        .await
+    })
+}
+
+#[test]
+fn should_fetch_the_entries_containing_a_json_value() -> Result<(), C3p0Error> {
+    run_test(async {
+        let data = data(false).await;
+        let pool = &data.0;
+
+        pool.transaction::<_, C3p0Error, _>(async |conn| {
+            conn.create_table_if_not_exists::<TaggedData>().await?;
+            conn.delete_all::<TaggedData>().await?;
+
+            conn.save(tagged("alice", &["red", "blue"], vec![vec![1, 2]], "Rome"))
+                .await?;
+            conn.save(tagged("bob", &["blue"], vec![vec![3], vec![4, 5]], "Paris"))
+                .await?;
+            conn.save(tagged("o'neil", &[], vec![], "Rome")).await?;
+
+            assert_eq!(
+                3,
+                tagged_names(conn, &Filter::contains(json!({})))
+                    .await?
+                    .len()
+            );
+            assert_eq!(
+                vec!["alice", "o'neil"],
+                tagged_names(
+                    conn,
+                    &Filter::contains(json!({ "address": { "city": "Rome" } }))
+                )
+                .await?
+            );
+            assert_eq!(
+                vec!["alice", "bob"],
+                tagged_names(conn, &Filter::contains(json!({ "tags": ["blue"] }))).await?
+            );
+            assert_eq!(
+                vec!["alice"],
+                tagged_names(
+                    conn,
+                    &Filter::contains(json!({ "tags": ["blue", "red"], "active": true }))
+                )
+                .await?
+            );
+            assert_eq!(
+                vec!["bob"],
+                tagged_names(
+                    conn,
+                    &Filter::contains(json!({ "sizes": [[5]], "name": "bob" }))
+                )
+                .await?
+            );
+            assert_eq!(
+                vec!["o'neil"],
+                tagged_names(
+                    conn,
+                    &Filter::contains(json!({ "name": "o'neil", "note": null }))
+                )
+                .await?
+            );
+            // JSON_CONTAINS also finds the scalars in the arrays
+            if matches!(db_specific::db_type(), DbType::Pg | DbType::Sqlite) {
+                assert!(
+                    tagged_names(conn, &Filter::contains(json!({ "tags": "blue" })))
+                        .await?
+                        .is_empty()
+                );
+            }
+            assert!(
+                tagged_names(conn, &Filter::contains(json!({ "sizes": [[1, 3]] })))
+                    .await?
+                    .is_empty()
+            );
+            assert_eq!(
+                vec!["bob", "o'neil"],
+                tagged_names(conn, &!Filter::contains(json!({ "tags": ["red"] }))).await?
+            );
+            Ok(())
+        })
+        .await
+    })
+}
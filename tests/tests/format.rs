@@ -0,0 +1,181 @@
+use crate::utils::*;
+use crate::*;
+
+/// Declares the types stored in `$format` and the test of their storage.
+macro_rules! format_test {
+    ($module:ident, $format:expr) => {
+        pub mod $module {
+            use super::*;
+            use serde::{Deserialize, Serialize};
+
+            const TABLE: &str =
+                const_format::concatcp!("TEST_TABLE_", const_random::const_random!(u64));
+
+            #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+            pub struct SampleV1 {
+                pub sensor: String,
+                pub values: Vec<f64>,
+            }
+
+            #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+            pub struct Sample {
+                pub sensor: String,
+                pub values: Vec<f64>,
+                pub unit: String,
+            }
+
+            impl Upgrade for SampleV1 {
+                type Next = Sample;
+
+                fn upgrade(self) -> Sample {
+                    Sample {
+                        sensor: self.sensor,
+                        values: self.values,
+                        unit: "celsius".to_owned(),
+                    }
+                }
+            }
+
+            #[derive(Serialize, Deserialize)]
+            #[serde(tag = "version")]
+            pub enum SampleV1Codec {
+                V1(SampleV1),
+            }
+
+            impl Codec<SampleV1> for SampleV1Codec {
+                fn encode(data: SampleV1) -> Self {
+                    SampleV1Codec::V1(data)
+                }
+
+                fn decode(data: Self) -> SampleV1 {
+                    match data {
+                        SampleV1Codec::V1(sample) => sample,
+                    }
+                }
+            }
+
+            impl DataType for SampleV1 {
+                const TABLE_NAME: &'static str = TABLE;
+                type CODEC = SampleV1Codec;
+                const HISTORY: bool = true;
+                const FORMAT: Format = $format;
+            }
+
+            #[derive(Serialize, Deserialize)]
+            #[serde(tag = "version")]
+            pub enum SampleCodec {
+                V1(SampleV1),
+                V2(Sample),
+            }
+
+            impl Codec<Sample> for SampleCodec {
+                fn encode(data: Sample) -> Self {
+                    SampleCodec::V2(data)
+                }
+
+                fn decode(data: Self) -> Sample {
+                    match data {
+                        SampleCodec::V1(sample) => sample.upgrade(),
+                        SampleCodec::V2(sample) => sample,
+                    }
+                }
+
+                fn outdated() -> Option<Filter> {
+                    Some(Filter::ne(Field::json("version"), "V2"))
+                }
+            }
+
+            impl DataType for Sample {
+                const TABLE_NAME: &'static str = TABLE;
+                type CODEC = SampleCodec;
+                const HISTORY: bool = true;
+                const FORMAT: Format = $format;
+            }
+
+            #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+            pub struct KeyedSample {
+                pub sensor: String,
+            }
+
+            impl DataType for KeyedSample {
+                const TABLE_NAME: &'static str =
+                    const_format::concatcp!("TEST_TABLE_", const_random::const_random!(u64));
+                type CODEC = Self;
+                const UNIQUE_KEY: &'static [&'static str] = &["sensor"];
+                const FORMAT: Format = $format;
+            }
+
+            #[test]
+            fn should_store_the_data_in_the_format() -> Result<(), C3p0Error> {
+                run_test(async {
+                    let data = data(false).await;
+                    let pool = &data.0;
+
+                    pool.transaction::<_, C3p0Error, _>(async |conn| {
+                        conn.drop_table_if_exists::<Sample>(false).await?;
+                        conn.create_table_if_not_exists::<Sample>().await?;
+                        let report = conn.verify_table::<Sample>().await?;
+                        assert!(report.is_valid(), "{report}");
+
+                        let old = conn
+                            .save(NewRecord::new(SampleV1 {
+                                sensor: "old".to_owned(),
+                                values: vec![1.5, 2.5],
+                            }))
+                            .await?;
+                        let new = conn
+                            .save(NewRecord::new(Sample {
+                                sensor: "new".to_owned(),
+                                values: vec![],
+                                unit: "kelvin".to_owned(),
+                            }))
+                            .await?;
+
+                        // The previous shapes are decoded by the codec
+                        let mut record = conn.fetch_one_by_id::<Sample>(old.id).await?;
+                        assert_eq!(vec![1.5, 2.5], record.data.values);
+                        assert_eq!("celsius", record.data.unit);
+
+                        record.data.values.push(3.5);
+                        let updated = conn.update(record).await?;
+                        assert_eq!(updated, conn.fetch_one_by_id::<Sample>(old.id).await?);
+                        let history = conn.fetch_history::<Sample>(old.id).await?;
+                        assert_eq!(vec![1.5, 2.5], history[0].data.values);
+
+                        // All the entries are rewritten, as the outdated ones cannot be selected
+                        let progress = conn.upgrade_all::<Sample>(10, |_| {}).await?;
+                        assert_eq!(2, progress.total);
+                        assert_eq!(2, progress.upgraded);
+                        assert_eq!(
+                            "kelvin",
+                            conn.fetch_one_by_id::<Sample>(new.id).await?.data.unit
+                        );
+                        assert!(conn.fetch_one_by_id::<SampleV1>(old.id).await.is_err());
+
+                        assert!(conn.scan_for_decode_errors::<Sample>(1).await?.is_empty());
+                        Ok(())
+                    })
+                    .await?;
+
+                    // The JSON indexes are not supported
+                    pool.transaction::<_, C3p0Error, _>(async |conn| {
+                        assert!(TableSchema::of::<KeyedSample>().is_err());
+                        assert!(
+                            conn.create_table_if_not_exists::<KeyedSample>()
+                                .await
+                                .is_err()
+                        );
+                        Ok(())
+                    })
+                    .await
+                })
+            }
+        }
+    };
+}
+
+#[cfg(feature = "msgpack")]
+format_test!(msgpack, Format::MessagePack);
+
+#[cfg(feature = "cbor")]
+format_test!(cbor, Format::Cbor);
@@ -0,0 +1,291 @@
+use crate::utils::*;
+use crate::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TestData {
+    pub value: i64,
+}
+
+impl c3p0::DataType for TestData {
+    const TABLE_NAME: &'static str =
+        const_format::concatcp!("TEST_TABLE_", const_random::const_random!(u64));
+    type CODEC = Self;
+    const HISTORY: bool = true;
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct KeyedData {
+    pub key: String,
+    pub value: i64,
+}
+
+impl c3p0::DataType for KeyedData {
+    const TABLE_NAME: &'static str =
+        const_format::concatcp!("TEST_TABLE_", const_random::const_random!(u64));
+    type CODEC = Self;
+    const UNIQUE_KEY: &'static [&'static str] = &["key"];
+    const SOFT_DELETE: bool = true;
+    const HISTORY: bool = true;
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct NoHistoryData {
+    pub value: i64,
+}
+
+impl c3p0::DataType for NoHistoryData {
+    const TABLE_NAME: &'static str =
+        const_format::concatcp!("TEST_TABLE_", const_random::const_random!(u64));
+    type CODEC = Self;
+}
+
+fn versions<DATA: DataType>(records: &[Record<DATA>]) -> Vec<i64> {
+    records.iter().map(|record| record.version).collect()
+}
+
+#[test]
+fn should_keep_prior_versions() -> Result<(), C3p0Error> {
+    run_test(async {
+        let data = data(false).await;
+        let pool = &data.0;
+
+        pool.transaction::<_, C3p0Error, _>(async |conn| {
+            conn.drop_table_if_exists::<TestData>(false).await?;
+            conn.create_table_if_not_exists::<TestData>().await?;
+
+            let saved = conn.save(TestData { value: 0 }.into()).await?;
+            let other = conn.save(TestData { value: 100 }.into()).await?;
+            assert!(conn.fetch_history::<TestData>(saved.id).await?.is_empty());
+
+            let mut to_update = saved.clone();
+            to_update.data.value = 1;
+            let updated = conn.update(to_update).await?;
+
+            // A failed update does not change the history
+            assert!(matches!(
+                conn.update(saved.clone()).await,
+                Err(C3p0Error::OptimisticLockError { .. })
+            ));
+
+            let patched = conn
+                .patch::<TestData>(
+                    saved.id,
+                    Some(updated.version),
+                    &JsonPatch::Ops(vec![PatchOp::set("value", 2)]),
+                )
+                .await?;
+
+            let mut to_update = patched.clone();
+            to_update.data.value = 3;
+            let result = conn.update_all(vec![to_update]).await?;
+            let current = result.updated[0].clone();
+
+            let history = conn.fetch_history::<TestData>(saved.id).await?;
+            assert_eq!(vec![0, 1, 2], versions(&history));
+            assert_eq!(saved, history[0]);
+            assert_eq!(updated, history[1]);
+            assert_eq!(patched, history[2]);
+
+            // fetch_version returns the prior and the current versions
+            assert_eq!(
+                Some(&updated),
+                conn.fetch_version::<TestData>(saved.id, 1).await?.as_ref()
+            );
+            assert_eq!(
+                Some(&current),
+                conn.fetch_version::<TestData>(saved.id, 3).await?.as_ref()
+            );
+            assert!(conn.fetch_version::<TestData>(saved.id, 4).await?.is_none());
+
+            // The history of each entry is separate
+            assert!(conn.fetch_history::<TestData>(other.id).await?.is_empty());
+
+            // Deleting an entry keeps its last version
+            assert_eq!(1, conn.delete_by_id::<TestData>(saved.id).await?);
+            assert_eq!(
+                vec![0, 1, 2, 3],
+                versions(&conn.fetch_history::<TestData>(saved.id).await?)
+            );
+            assert_eq!(
+                Some(&current),
+                conn.fetch_version::<TestData>(saved.id, 3).await?.as_ref()
+            );
+
+            assert_eq!(1, conn.delete_all::<TestData>().await?);
+            let history = conn.fetch_history::<TestData>(other.id).await?;
+            assert_eq!(vec![other], history);
+
+            Ok(())
+        })
+        .await
+    })
+}
+
+#[test]
+fn should_fetch_the_version_current_at_a_timestamp() -> Result<(), C3p0Error> {
+    run_test(async {
+        let data = data(false).await;
+        let pool = &data.0;
+
+        let saved = pool
+            .transaction::<_, C3p0Error, _>(async |conn| {
+                conn.drop_table_if_exists::<TestData>(false).await?;
+                conn.create_table_if_not_exists::<TestData>().await?;
+                conn.save(TestData { value: 0 }.into()).await
+            })
+            .await?;
+
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+
+        let updated = pool
+            .transaction::<_, C3p0Error, _>(async |conn| {
+                let mut to_update = saved.clone();
+                to_update.data.value = 1;
+                conn.update(to_update).await
+            })
+            .await?;
+        assert!(updated.update_time > saved.update_time);
+
+        let millisecond = chrono::Duration::milliseconds(1);
+        let future = updated.update_time + chrono::Duration::hours(1);
+
+        pool.transaction::<_, C3p0Error, _>(async |conn| {
+            assert!(
+                conn.fetch_as_of::<TestData>(saved.id, saved.create_time - millisecond)
+                    .await?
+                    .is_none()
+            );
+            assert_eq!(
+                Some(&saved),
+                conn.fetch_as_of::<TestData>(saved.id, saved.create_time)
+                    .await?
+                    .as_ref()
+            );
+            assert_eq!(
+                Some(&saved),
+                conn.fetch_as_of::<TestData>(saved.id, updated.update_time - millisecond)
+                    .await?
+                    .as_ref()
+            );
+            assert_eq!(
+                Some(&updated),
+                conn.fetch_as_of::<TestData>(saved.id, updated.update_time)
+                    .await?
+                    .as_ref()
+            );
+            assert_eq!(
+                Some(&updated),
+                conn.fetch_as_of::<TestData>(saved.id, future)
+                    .await?
+                    .as_ref()
+            );
+            Ok(())
+        })
+        .await?;
+
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+
+        pool.transaction::<_, C3p0Error, _>(async |conn| {
+            conn.delete(updated.clone()).await?;
+
+            // The entry no longer exists after the deletion
+            assert!(
+                conn.fetch_as_of::<TestData>(saved.id, future)
+                    .await?
+                    .is_none()
+            );
+            assert_eq!(
+                Some(&updated),
+                conn.fetch_as_of::<TestData>(saved.id, updated.update_time)
+                    .await?
+                    .as_ref()
+            );
+            Ok(())
+        })
+        .await
+    })
+}
+
+#[test]
+fn should_keep_history_of_soft_deletes_and_upserts() -> Result<(), C3p0Error> {
+    run_test(async {
+        let data = data(false).await;
+        let pool = &data.0;
+
+        pool.transaction::<_, C3p0Error, _>(async |conn| {
+            conn.drop_table_if_exists::<KeyedData>(false).await?;
+            conn.create_table_if_not_exists::<KeyedData>().await?;
+
+            let new_data = |value| KeyedData {
+                key: "key".to_owned(),
+                value,
+            };
+
+            let saved = conn.upsert_by_key(new_data(0).into()).await?;
+            let upserted = conn.upsert_by_key(new_data(1).into()).await?;
+            assert_eq!(
+                vec![saved.clone()],
+                conn.fetch_history::<KeyedData>(saved.id).await?
+            );
+
+            let future = upserted.update_time + chrono::Duration::hours(1);
+
+            let deleted = conn.delete(upserted.clone()).await?;
+            assert!(
+                conn.fetch_as_of::<KeyedData>(saved.id, future)
+                    .await?
+                    .is_none()
+            );
+
+            let restored = conn.restore::<KeyedData>(saved.id).await?;
+            assert_eq!(
+                Some(&restored),
+                conn.fetch_as_of::<KeyedData>(saved.id, future)
+                    .await?
+                    .as_ref()
+            );
+
+            let history = conn.fetch_history::<KeyedData>(saved.id).await?;
+            assert_eq!(vec![0, 1, 2], versions(&history));
+            assert_eq!(upserted, history[1]);
+            assert_eq!(deleted, history[2]);
+
+            Ok(())
+        })
+        .await
+    })
+}
+
+#[test]
+fn should_fail_without_history() -> Result<(), C3p0Error> {
+    run_test(async {
+        let data = data(false).await;
+        let pool = &data.0;
+
+        pool.transaction::<_, C3p0Error, _>(async |conn| {
+            conn.drop_table_if_exists::<NoHistoryData>(false).await?;
+            conn.create_table_if_not_exists::<NoHistoryData>().await?;
+
+            let saved = conn.save(NoHistoryData { value: 0 }.into()).await?;
+            conn.update(saved.clone()).await?;
+
+            assert!(matches!(
+                conn.fetch_history::<NoHistoryData>(saved.id).await,
+                Err(C3p0Error::Other { .. })
+            ));
+            assert!(matches!(
+                conn.fetch_version::<NoHistoryData>(saved.id, 0).await,
+                Err(C3p0Error::Other { .. })
+            ));
+            assert!(matches!(
+                conn.fetch_as_of::<NoHistoryData>(saved.id, saved.create_time)
+                    .await,
+                Err(C3p0Error::Other { .. })
+            ));
+
+            Ok(())
+        })
+        .await
+    })
+}
@@ -0,0 +1,290 @@
+use crate::utils::*;
+use crate::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TestData {
+    pub value: i64,
+}
+
+impl c3p0::DataType for TestData {
+    const TABLE_NAME: &'static str =
+        const_format::concatcp!("TEST_TABLE_", const_random::const_random!(u64));
+    type CODEC = Self;
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct NamedData {
+    pub key: String,
+    pub value: i64,
+}
+
+impl c3p0::DataType for NamedData {
+    const TABLE_NAME: &'static str =
+        const_format::concatcp!("TEST_TABLE_", const_random::const_random!(u64));
+    type CODEC = WithId<Self, String>;
+    const UNIQUE_KEY: &'static [&'static str] = &["key"];
+    const HISTORY: bool = true;
+}
+
+fn named(id: &str, key: &str, value: i64) -> NewRecord<ProvidedId<NamedData>> {
+    NewRecord::with_id(
+        id.to_owned(),
+        NamedData {
+            key: key.to_owned(),
+            value,
+        },
+    )
+}
+
+#[test]
+fn should_use_caller_supplied_ids() -> Result<(), C3p0Error> {
+    run_test(async {
+        let data = data(false).await;
+        let pool = &data.0;
+
+        pool.transaction::<_, C3p0Error, _>(async |conn| {
+            conn.drop_table_if_exists::<NamedData>(false).await?;
+            conn.create_table_if_not_exists::<NamedData>().await?;
+
+            let saved = conn.save(named("first", "a", 0)).await?;
+            assert_eq!("first", saved.id);
+            assert_eq!(
+                saved,
+                conn.fetch_one_by_id::<NamedData>("first".to_owned())
+                    .await?
+            );
+            assert!(conn.exists_by_id::<NamedData>("first".to_owned()).await?);
+            assert!(!conn.exists_by_id::<NamedData>("other".to_owned()).await?);
+
+            let saved_all = conn
+                .save_all(vec![named("third", "c", 2), named("second", "b", 1)])
+                .await?;
+            assert_eq!(
+                vec!["third", "second"],
+                saved_all
+                    .iter()
+                    .map(|record| record.id.as_str())
+                    .collect::<Vec<_>>()
+            );
+            for record in &saved_all {
+                assert_eq!(
+                    record,
+                    &conn.fetch_one_by_id::<NamedData>(record.id.clone()).await?
+                );
+            }
+
+            let mut to_update = saved.clone();
+            to_update.data.value = 10;
+            let updated = conn.update(to_update).await?;
+            assert_eq!("first", updated.id);
+            assert_eq!(
+                vec![saved.clone()],
+                conn.fetch_history::<NamedData>(saved.id.clone()).await?
+            );
+
+            if matches!(
+                db_specific::db_type(),
+                DbType::MySql | DbType::MariaDB | DbType::TiDB
+            ) {
+                // A provided id could conflict on the primary key instead of the key
+                assert!(conn.upsert_by_key(named("fourth", "b", 3)).await.is_err());
+                conn.save(named("fourth", "d", 4)).await?;
+            } else {
+                // The conflicting entry keeps its own id
+                let upserted = conn.upsert_by_key(named("fourth", "b", 3)).await?;
+                assert_eq!("second", upserted.id);
+                assert_eq!(3, upserted.data.value);
+                let upserted = conn.upsert_by_key(named("fourth", "d", 4)).await?;
+                assert_eq!("fourth", upserted.id);
+            }
+
+            // The pages follow the order of the ids
+            let page = conn
+                .fetch_page::<NamedData>(PageOrder::IdAsc, None, 2)
+                .await?;
+            let next = Cursor::from_token(&page.next.unwrap().to_token())?;
+            let page = conn
+                .fetch_page::<NamedData>(PageOrder::IdAsc, Some(&next), 2)
+                .await?;
+            assert_eq!(
+                vec!["second", "third"],
+                page.records
+                    .iter()
+                    .map(|record| record.id.as_str())
+                    .collect::<Vec<_>>()
+            );
+
+            assert_eq!(1, conn.delete_by_id::<NamedData>("first".to_owned()).await?);
+            assert!(
+                conn.fetch_one_optional_by_id::<NamedData>("first".to_owned())
+                    .await?
+                    .is_none()
+            );
+
+            Ok(())
+        })
+        .await?;
+
+        // The id is the primary key
+        pool.transaction::<_, C3p0Error, _>(async |conn| {
+            assert!(conn.save(named("second", "e", 0)).await.is_err());
+            Ok(())
+        })
+        .await
+    })
+}
+
+#[test]
+fn should_check_the_provided_ids() -> Result<(), C3p0Error> {
+    run_test(async {
+        let data = data(false).await;
+        let pool = &data.0;
+
+        pool.transaction::<_, C3p0Error, _>(async |conn| {
+            conn.drop_table_if_exists::<TestData>(false).await?;
+            conn.create_table_if_not_exists::<TestData>().await?;
+            conn.drop_table_if_exists::<NamedData>(false).await?;
+            conn.create_table_if_not_exists::<NamedData>().await?;
+
+            // The ids generated by the database cannot be provided
+            assert!(matches!(
+                conn.save(NewRecord::with_id(1, TestData { value: 0 }))
+                    .await,
+                Err(C3p0Error::Other { .. })
+            ));
+
+            // The ids that are not generated must be provided
+            let new_data = NamedData {
+                key: "a".to_owned(),
+                value: 0,
+            };
+            assert!(matches!(
+                conn.save(new_data.clone().into()).await,
+                Err(C3p0Error::Other { .. })
+            ));
+            assert!(matches!(
+                conn.save_all(vec![new_data.into()]).await,
+                Err(C3p0Error::Other { .. })
+            ));
+            assert_eq!(0, conn.count_all::<NamedData>().await?);
+
+            Ok(())
+        })
+        .await
+    })
+}
+
+#[cfg(feature = "uuid")]
+mod uuid_ids {
+    use super::*;
+    use c3p0::id::UuidV7;
+
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+    pub struct V4Data {
+        pub value: i64,
+    }
+
+    impl c3p0::DataType for V4Data {
+        const TABLE_NAME: &'static str =
+            const_format::concatcp!("TEST_TABLE_", const_random::const_random!(u64));
+        type CODEC = WithId<Self, uuid::Uuid>;
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+    pub struct V7Data {
+        pub value: i64,
+    }
+
+    impl c3p0::DataType for V7Data {
+        const TABLE_NAME: &'static str =
+            const_format::concatcp!("TEST_TABLE_", const_random::const_random!(u64));
+        type CODEC = WithId<Self, UuidV7>;
+        const SOFT_DELETE: bool = true;
+    }
+
+    #[test]
+    fn should_generate_uuid_ids() -> Result<(), C3p0Error> {
+        run_test(async {
+            let data = data(false).await;
+            let pool = &data.0;
+
+            pool.transaction::<_, C3p0Error, _>(async |conn| {
+                conn.drop_table_if_exists::<V4Data>(false).await?;
+                conn.create_table_if_not_exists::<V4Data>().await?;
+
+                let saved = conn.save(V4Data { value: 0 }.into()).await?;
+                assert_eq!(Some(uuid::Version::Random), saved.id.get_version());
+                assert_eq!(saved, conn.fetch_one_by_id::<V4Data>(saved.id).await?);
+
+                let id = uuid::Uuid::new_v4();
+                let saved = conn
+                    .save(NewRecord::with_id(id, V4Data { value: 1 }))
+                    .await?;
+                assert_eq!(id, saved.id);
+
+                conn.drop_table_if_exists::<V7Data>(false).await?;
+                conn.create_table_if_not_exists::<V7Data>().await?;
+
+                let saved = conn
+                    .save_all((0..5).map(|value| V7Data { value }.into()).collect())
+                    .await?;
+                // The UUIDs v7 follow the order of creation
+                assert_eq!(
+                    saved,
+                    conn.fetch_all_sorted::<V7Data>(&[SortKey::Id(OrderBy::Asc)], 0, None)
+                        .await?
+                );
+
+                let deleted = conn.delete(saved[0].clone()).await?;
+                assert!(!conn.exists_by_id::<V7Data>(deleted.id).await?);
+                conn.restore::<V7Data>(deleted.id).await?;
+                assert!(conn.exists_by_id::<V7Data>(deleted.id).await?);
+
+                Ok(())
+            })
+            .await
+        })
+    }
+}
+
+#[cfg(feature = "ulid")]
+mod ulid_ids {
+    use super::*;
+
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+    pub struct UlidData {
+        pub value: i64,
+    }
+
+    impl c3p0::DataType for UlidData {
+        const TABLE_NAME: &'static str =
+            const_format::concatcp!("TEST_TABLE_", const_random::const_random!(u64));
+        type CODEC = WithId<Self, ulid::Ulid>;
+    }
+
+    #[test]
+    fn should_generate_ulid_ids() -> Result<(), C3p0Error> {
+        run_test(async {
+            let data = data(false).await;
+            let pool = &data.0;
+
+            pool.transaction::<_, C3p0Error, _>(async |conn| {
+                conn.drop_table_if_exists::<UlidData>(false).await?;
+                conn.create_table_if_not_exists::<UlidData>().await?;
+
+                let saved = conn
+                    .save_all((0..3).map(|value| UlidData { value }.into()).collect())
+                    .await?;
+                for record in &saved {
+                    assert_eq!(record, &conn.fetch_one_by_id::<UlidData>(record.id).await?);
+                }
+                assert_eq!(1, conn.delete_by_id::<UlidData>(saved[0].id).await?);
+                assert_eq!(2, conn.count_all::<UlidData>().await?);
+
+                Ok(())
+            })
+            .await
+        })
+    }
+}
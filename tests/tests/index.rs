@@ -0,0 +1,242 @@
+use crate::utils::*;
+use crate::*;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct UserData {
+    pub username: String,
+    pub tenant: String,
+    pub age: i64,
+    pub active: bool,
+}
+
+impl c3p0::DataType for UserData {
+    const TABLE_NAME: &'static str =
+        const_format::concatcp!("TEST_TABLE_", const_random::const_random!(u64));
+    type CODEC = Self;
+    const INDEXES: &'static [JsonIndex] = &[
+        JsonIndex::new("username", &[("username", JsonType::Text)]).unique(),
+        JsonIndex::new(
+            "tenant_age",
+            &[("tenant", JsonType::Text), ("age", JsonType::Number)],
+        ),
+        JsonIndex::new("active", &[("active", JsonType::Bool)]),
+        JsonIndex::containment("data"),
+    ];
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct InvalidData {
+    pub value: i64,
+}
+
+impl c3p0::DataType for InvalidData {
+    const TABLE_NAME: &'static str =
+        const_format::concatcp!("TEST_TABLE_", const_random::const_random!(u64));
+    type CODEC = Self;
+    const INDEXES: &'static [JsonIndex] = &[
+        JsonIndex::new("value", &[("value", JsonType::Number)]),
+        JsonIndex::new("value", &[("value", JsonType::Text)]),
+    ];
+}
+
+/// A type adding an index to the table of [`PlainData`], created without it.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct IndexedData {
+    pub value: String,
+}
+
+impl c3p0::DataType for IndexedData {
+    const TABLE_NAME: &'static str =
+        const_format::concatcp!("TEST_TABLE_", const_random::const_random!(u64));
+    type CODEC = Self;
+    const INDEXES: &'static [JsonIndex] = &[JsonIndex::new("value", &[("value", JsonType::Text)])];
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PlainData {
+    pub value: String,
+}
+
+impl c3p0::DataType for PlainData {
+    const TABLE_NAME: &'static str = IndexedData::TABLE_NAME;
+    type CODEC = Self;
+}
+
+fn user(username: &str, tenant: &str, age: i64) -> NewRecord<UserData> {
+    UserData {
+        username: username.to_owned(),
+        tenant: tenant.to_owned(),
+        age,
+        active: age > 18,
+    }
+    .into()
+}
+
+/// Returns the lower case names of the indexes of `table_name`.
+async fn index_names(pool: &C3p0Impl, table_name: &str) -> Result<Vec<String>, C3p0Error> {
+    let table_name = table_name.to_lowercase();
+    let query = match db_specific::db_type() {
+        DbType::Pg => format!(
+            "SELECT LOWER(indexname::text) FROM pg_indexes WHERE tablename = '{table_name}'"
+        ),
+        DbType::Sqlite => format!(
+            "SELECT LOWER(name) FROM sqlite_master WHERE type = 'index' \
+             AND LOWER(tbl_name) = '{table_name}'"
+        ),
+        _ => format!(
+            "SELECT DISTINCT LOWER(index_name) FROM information_schema.statistics \
+             WHERE LOWER(table_name) = '{table_name}' AND table_schema = DATABASE()"
+        ),
+    };
+    let mut names: Vec<String> = sqlx::query_scalar(sqlx::AssertSqlSafe(query))
+        .fetch_all(pool.pool())
+        .await?;
+    names.sort();
+    Ok(names)
+}
+
+#[test]
+fn should_create_the_declared_indexes() -> Result<(), C3p0Error> {
+    run_test(async {
+        let data = data(false).await;
+        let pool = &data.0;
+
+        pool.transaction::<_, C3p0Error, _>(async |conn| {
+            conn.drop_table_if_exists::<UserData>(false).await?;
+            conn.create_table_if_not_exists::<UserData>().await?;
+            // The indexes are created idempotently
+            conn.create_table_if_not_exists::<UserData>().await?;
+            Ok(())
+        })
+        .await?;
+
+        let table_name = UserData::TABLE_NAME.to_lowercase();
+        let mut expected = vec![
+            format!("{table_name}_active"),
+            format!("{table_name}_tenant_age"),
+            format!("{table_name}_username"),
+        ];
+        if db_specific::db_type() == DbType::Pg {
+            expected.push(format!("{table_name}_data"));
+        }
+        expected.sort();
+        let names = index_names(pool, UserData::TABLE_NAME).await?;
+        for name in &expected {
+            assert!(names.contains(name), "{name} not found in {names:?}");
+        }
+        if db_specific::db_type() != DbType::Pg {
+            assert!(!names.contains(&format!("{table_name}_data")));
+        }
+
+        pool.transaction::<_, C3p0Error, _>(async |conn| {
+            conn.save_all(vec![
+                user("alice", "a", 30),
+                user("bob", "a", 12),
+                user("carol", "b", 40),
+            ])
+            .await?;
+
+            // The filters render the indexed expressions
+            let filter = Filter::and([
+                Filter::eq(Field::json("tenant"), "a"),
+                Filter::gt(Field::json("age"), 18),
+            ]);
+            let adults = conn.fetch_where::<UserData>(&filter, 0, None).await?;
+            assert_eq!(1, adults.len());
+            assert_eq!("alice", adults[0].data.username);
+            let tenant_b = conn
+                .fetch_where::<UserData>(&Filter::contains(json!({ "tenant": "b" })), 0, None)
+                .await?;
+            assert_eq!(1, tenant_b.len());
+            assert_eq!("carol", tenant_b[0].data.username);
+
+            // The indexed text values are not limited in length
+            let long_name = "x".repeat(1000);
+            conn.save(user(&long_name, "c", 12)).await?;
+            let long = conn
+                .fetch_where::<UserData>(&Filter::eq(Field::json("username"), &*long_name), 0, None)
+                .await?;
+            assert_eq!(1, long.len());
+            assert_eq!(long_name, long[0].data.username);
+            assert_eq!(
+                2,
+                conn.count_where::<UserData>(&Filter::eq(Field::json("active"), true))
+                    .await?
+            );
+            Ok(())
+        })
+        .await?;
+
+        // The unique indexes reject the duplicated values
+        pool.transaction::<_, C3p0Error, _>(async |conn| {
+            assert!(conn.save(user("alice", "c", 50)).await.is_err());
+            Ok(())
+        })
+        .await
+    })
+}
+
+#[test]
+fn should_reject_the_invalid_indexes() -> Result<(), C3p0Error> {
+    run_test(async {
+        let data = data(false).await;
+        let pool = &data.0;
+
+        pool.transaction::<_, C3p0Error, _>(async |conn| {
+            assert!(matches!(
+                conn.create_table_if_not_exists::<InvalidData>().await,
+                Err(C3p0Error::Other { .. })
+            ));
+            Ok(())
+        })
+        .await
+    })
+}
+
+#[test]
+fn should_add_the_indexes_to_the_existing_tables() -> Result<(), C3p0Error> {
+    run_test(async {
+        let data = data(false).await;
+        let pool = &data.0;
+
+        let index_name = format!("{}_value", IndexedData::TABLE_NAME.to_lowercase());
+        pool.transaction::<_, C3p0Error, _>(async |conn| {
+            conn.drop_table_if_exists::<PlainData>(false).await?;
+            conn.create_table_if_not_exists::<PlainData>().await?;
+            conn.save(
+                PlainData {
+                    value: "one".to_owned(),
+                }
+                .into(),
+            )
+            .await?;
+            Ok(())
+        })
+        .await?;
+        let names = index_names(pool, IndexedData::TABLE_NAME).await?;
+        assert!(!names.contains(&index_name));
+
+        pool.transaction::<_, C3p0Error, _>(async |conn| {
+            conn.create_table_if_not_exists::<IndexedData>().await?;
+            // The indexes are added once
+            conn.create_table_if_not_exists::<IndexedData>().await?;
+            Ok(())
+        })
+        .await?;
+        let names = index_names(pool, IndexedData::TABLE_NAME).await?;
+        assert!(
+            names.contains(&index_name),
+            "{index_name} not found in {names:?}"
+        );
+
+        pool.transaction::<_, C3p0Error, _>(async |conn| {
+            let filter = Filter::eq(Field::json("value"), "one");
+            assert_eq!(1, conn.count_where::<IndexedData>(&filter).await?);
+            conn.drop_table_if_exists::<IndexedData>(false).await?;
+            Ok(())
+        })
+        .await
+    })
+}
@@ -0,0 +1,297 @@
+use crate::utils::*;
+use crate::*;
+use c3p0::migrate::{Backend, MigrationGenerator, SNAPSHOT_FILE};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct UserData {
+    pub username: String,
+    pub age: i64,
+}
+
+impl c3p0::DataType for UserData {
+    const TABLE_NAME: &'static str =
+        const_format::concatcp!("TEST_TABLE_", const_random::const_random!(u64));
+    type CODEC = Self;
+    const UNIQUE_KEY: &'static [&'static str] = &["username"];
+}
+
+/// The next declaration of the table of `UserData`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct UserDataV2 {
+    pub username: String,
+    pub age: i64,
+}
+
+impl c3p0::DataType for UserDataV2 {
+    const TABLE_NAME: &'static str = UserData::TABLE_NAME;
+    type CODEC = Self;
+    const UNIQUE_KEY: &'static [&'static str] = &["username"];
+    const INDEXES: &'static [JsonIndex] = &[JsonIndex::new("age", &[("age", JsonType::Number)])];
+    const SOFT_DELETE: bool = true;
+    const HISTORY: bool = true;
+}
+
+/// A document whose table is later migrated to the compression of its data.
+#[cfg(feature = "zstd")]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Note {
+    pub body: String,
+}
+
+#[cfg(feature = "zstd")]
+impl c3p0::DataType for Note {
+    const TABLE_NAME: &'static str =
+        const_format::concatcp!("TEST_TABLE_", const_random::const_random!(u64));
+    type CODEC = Self;
+}
+
+/// The declaration of the table of `Note` with the compression of its data.
+#[cfg(feature = "zstd")]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CompressedNote {
+    pub body: String,
+}
+
+#[cfg(feature = "zstd")]
+impl c3p0::DataType for CompressedNote {
+    const TABLE_NAME: &'static str = Note::TABLE_NAME;
+    type CODEC = Self;
+    const COMPRESSION: Option<Compression> = Some(Compression::zstd(256));
+}
+
+fn backend() -> Backend {
+    match db_specific::db_type() {
+        DbType::Pg => "postgres",
+        DbType::Sqlite | DbType::InMemory => "sqlite",
+        _ => "mysql",
+    }
+    .parse()
+    .unwrap()
+}
+
+/// Applies the migrations of `dir`, tracked in a table specific to the `table` of the test.
+async fn apply(pool: &C3p0Impl, dir: &Path, table: &str) -> Result<(), C3p0Error> {
+    let mut migrator = c3p0::sqlx::migrate::Migrator::new(dir)
+        .await
+        .map_err(c3p0::sqlx::Error::from)?;
+    migrator.dangerous_set_table_name(format!("{table}_migrations"));
+    migrator
+        .run(pool.pool())
+        .await
+        .map_err(c3p0::sqlx::Error::from)?;
+    Ok(())
+}
+
+#[test]
+fn should_generate_the_migrations_of_the_declarations() -> Result<(), C3p0Error> {
+    run_test(async {
+        let data = data(false).await;
+        let pool = &data.0;
+
+        let dir = std::env::temp_dir().join(format!("c3p0_{}", UserData::TABLE_NAME));
+        let _ = std::fs::remove_dir_all(&dir);
+        // The migrations applied by a previous run are not in the new directory
+        sqlx::query(sqlx::AssertSqlSafe(format!(
+            "DROP TABLE IF EXISTS {}_migrations",
+            UserData::TABLE_NAME
+        )))
+        .execute(pool.pool())
+        .await?;
+        let generator = MigrationGenerator::new(backend(), &dir);
+
+        // The first migration creates the table
+        let first = generator
+            .generate("create users", &[TableSchema::of::<UserData>()?])?
+            .unwrap();
+        assert!(
+            first
+                .file_name()
+                .unwrap()
+                .to_string_lossy()
+                .ends_with("_create_users.sql")
+        );
+        assert!(dir.join(SNAPSHOT_FILE).exists());
+        apply(pool, &dir, UserData::TABLE_NAME).await?;
+
+        let saved = pool
+            .transaction::<_, C3p0Error, _>(async |conn| {
+                let saved = conn
+                    .save(
+                        UserData {
+                            username: "alice".to_owned(),
+                            age: 30,
+                        }
+                        .into(),
+                    )
+                    .await?;
+                Ok(saved)
+            })
+            .await?;
+
+        // The unique index of the key is created
+        pool.transaction::<_, C3p0Error, _>(async |conn| {
+            let duplicated = UserData {
+                username: "alice".to_owned(),
+                age: 40,
+            };
+            assert!(conn.save(duplicated.into()).await.is_err());
+            Ok(())
+        })
+        .await?;
+
+        // Nothing is generated if the declarations did not change
+        assert!(
+            generator
+                .generate("unchanged", &[TableSchema::of::<UserData>()?])?
+                .is_none()
+        );
+
+        // The following migration only contains the changes
+        let second = generator
+            .generate("history", &[TableSchema::of::<UserDataV2>()?])?
+            .unwrap();
+        assert!(second > first);
+        let sql = std::fs::read_to_string(&second).unwrap();
+        // Only the history table is created
+        assert_eq!(1, sql.matches("CREATE TABLE").count());
+        assert!(sql.to_lowercase().contains("delete_time"));
+        apply(pool, &dir, UserData::TABLE_NAME).await?;
+
+        pool.transaction::<_, C3p0Error, _>(async |conn| {
+            let record = conn.fetch_one_by_id::<UserDataV2>(saved.id).await?;
+            assert_eq!("alice", record.data.username);
+
+            let mut updated = record.clone();
+            updated.data.age = 31;
+            let updated = conn.update(updated).await?;
+            assert_eq!(
+                vec![record],
+                conn.fetch_history::<UserDataV2>(saved.id).await?
+            );
+
+            conn.delete(updated).await?;
+            assert_eq!(0, conn.count_all::<UserDataV2>().await?);
+            assert_eq!(
+                1,
+                conn.fetch_including_deleted::<UserDataV2>(0, None)
+                    .await?
+                    .len()
+            );
+            Ok(())
+        })
+        .await?;
+
+        // The changes that lose data must be allowed
+        let err = generator
+            .generate("no history", &[TableSchema::of::<UserData>()?])
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("history table"));
+        assert!(err.contains("soft deleted entries"));
+        let err = generator.generate("drop users", &[]).unwrap_err();
+        assert!(err.to_string().contains(UserData::TABLE_NAME));
+        generator
+            .clone()
+            .allow_destructive(true)
+            .generate("drop users", &[])?
+            .unwrap();
+        apply(pool, &dir, UserData::TABLE_NAME).await?;
+        pool.transaction::<_, C3p0Error, _>(async |conn| {
+            assert!(conn.count_all::<UserDataV2>().await.is_err());
+            Ok(())
+        })
+        .await?;
+
+        let _ = std::fs::remove_dir_all(&dir);
+        Ok(())
+    })
+}
+
+#[cfg(feature = "zstd")]
+#[test]
+fn should_migrate_a_table_to_the_compression_of_its_data() -> Result<(), C3p0Error> {
+    run_test(async {
+        let data = data(false).await;
+        let pool = &data.0;
+
+        let dir = std::env::temp_dir().join(format!("c3p0_{}", Note::TABLE_NAME));
+        let _ = std::fs::remove_dir_all(&dir);
+        sqlx::query(sqlx::AssertSqlSafe(format!(
+            "DROP TABLE IF EXISTS {}_migrations",
+            Note::TABLE_NAME
+        )))
+        .execute(pool.pool())
+        .await?;
+        let generator = MigrationGenerator::new(backend(), &dir);
+
+        generator
+            .generate("create notes", &[TableSchema::of::<Note>()?])?
+            .unwrap();
+        apply(pool, &dir, Note::TABLE_NAME).await?;
+
+        let plain = pool
+            .transaction::<_, C3p0Error, _>(async |conn| {
+                // The declaration with the compression misses the column of the compressed data
+                assert!(!conn.verify_table::<CompressedNote>().await?.is_valid());
+                conn.save(
+                    Note {
+                        body: "short".to_owned(),
+                    }
+                    .into(),
+                )
+                .await
+            })
+            .await?;
+
+        // The column of the compressed data is added to the existing table
+        let compressed = generator
+            .generate("compress notes", &[TableSchema::of::<CompressedNote>()?])?
+            .unwrap();
+        let sql = std::fs::read_to_string(&compressed).unwrap();
+        assert!(sql.contains("data_compressed"));
+        assert!(!sql.contains("CREATE TABLE"));
+        apply(pool, &dir, Note::TABLE_NAME).await?;
+
+        pool.transaction::<_, C3p0Error, _>(async |conn| {
+            assert!(conn.verify_table::<CompressedNote>().await?.is_valid());
+            assert_eq!(
+                plain.data.body,
+                conn.fetch_one_by_id::<CompressedNote>(plain.id)
+                    .await?
+                    .data
+                    .body
+            );
+            let large = conn
+                .save(
+                    CompressedNote {
+                        body: "compressed ".repeat(100),
+                    }
+                    .into(),
+                )
+                .await?;
+            assert_eq!(
+                large,
+                conn.fetch_one_by_id::<CompressedNote>(large.id).await?
+            );
+            Ok(())
+        })
+        .await?;
+
+        // The compressed documents would be lost by removing the compression
+        let err = generator
+            .generate("uncompress notes", &[TableSchema::of::<Note>()?])
+            .unwrap_err();
+        assert!(err.to_string().contains("compression"));
+
+        generator
+            .clone()
+            .allow_destructive(true)
+            .generate("drop notes", &[])?
+            .unwrap();
+        apply(pool, &dir, Note::TABLE_NAME).await?;
+        let _ = std::fs::remove_dir_all(&dir);
+        Ok(())
+    })
+}
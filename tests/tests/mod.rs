@@ -1,3 +1,30 @@
 pub mod codec;
+#[cfg(any(feature = "zstd", feature = "gzip"))]
+pub mod compression;
+pub mod decode;
+#[cfg(feature = "derive")]
+pub mod derive;
+pub mod dynamic;
+#[cfg(feature = "encryption")]
+pub mod encryption;
+pub mod filter;
+#[cfg(any(feature = "msgpack", feature = "cbor"))]
+pub mod format;
+pub mod history;
+pub mod id;
+pub mod index;
 pub mod json;
 pub mod json_transaction;
+#[cfg(feature = "migrate")]
+pub mod migrate;
+pub mod page;
+pub mod patch;
+pub mod projection;
+pub mod save_all;
+pub mod soft_delete;
+pub mod sort;
+pub mod stream;
+pub mod table;
+pub mod update_all;
+pub mod upsert;
+pub mod verify;
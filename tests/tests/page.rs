@@ -0,0 +1,159 @@
+use crate::utils::*;
+use crate::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TestData {
+    pub value: i64,
+}
+
+impl c3p0::DataType for TestData {
+    const TABLE_NAME: &'static str =
+        const_format::concatcp!("TEST_TABLE_", const_random::const_random!(u64));
+    type CODEC = Self;
+}
+
+async fn fetch_all_pages<C: Tx>(
+    conn: &mut C,
+    filter: Option<&Filter>,
+    order: PageOrder,
+    limit: u64,
+) -> Result<Vec<Vec<i64>>, C3p0Error> {
+    let mut pages = vec![];
+    let mut token: Option<String> = None;
+    loop {
+        // The cursor goes through its string representation as it would with an HTTP client
+        let cursor = token.as_deref().map(Cursor::from_token).transpose()?;
+        let page = match filter {
+            Some(filter) => {
+                conn.fetch_page_where::<TestData>(filter, order, cursor.as_ref(), limit)
+                    .await?
+            }
+            None => {
+                conn.fetch_page::<TestData>(order, cursor.as_ref(), limit)
+                    .await?
+            }
+        };
+        pages.push(
+            page.records
+                .iter()
+                .map(|record| record.data.value)
+                .collect(),
+        );
+        match page.next {
+            Some(next) => token = Some(next.to_token()),
+            None => return Ok(pages),
+        }
+    }
+}
+
+#[test]
+fn should_paginate_with_cursors() -> Result<(), C3p0Error> {
+    run_test(async {
+        let data = data(false).await;
+        let pool = &data.0;
+
+        let first = pool
+            .transaction::<_, C3p0Error, _>(async |conn| {
+                conn.create_table_if_not_exists::<TestData>().await?;
+                conn.delete_all::<TestData>().await?;
+
+                let first = conn.save(TestData { value: 0 }.into()).await?;
+                for value in 1..5 {
+                    conn.save(TestData { value }.into()).await?;
+                }
+                Ok(first)
+            })
+            .await?;
+
+        // Updating the first record in a later transaction moves it at the end of the
+        // update_time order
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        pool.transaction::<_, C3p0Error, _>(async |conn| conn.update(first).await)
+            .await?;
+
+        pool.transaction::<_, C3p0Error, _>(async |conn| {
+            assert_eq!(
+                vec![vec![0, 1], vec![2, 3], vec![4]],
+                fetch_all_pages(conn, None, PageOrder::IdAsc, 2).await?
+            );
+            assert_eq!(
+                vec![vec![4, 3, 2], vec![1, 0]],
+                fetch_all_pages(conn, None, PageOrder::IdDesc, 3).await?
+            );
+            assert_eq!(
+                vec![vec![0, 1, 2, 3, 4]],
+                fetch_all_pages(conn, None, PageOrder::IdAsc, 5).await?
+            );
+
+            let update_time_asc = fetch_all_pages(conn, None, PageOrder::UpdateTimeAsc, 2).await?;
+            assert_eq!(3, update_time_asc.len());
+            assert_eq!(
+                Some(&0),
+                update_time_asc.concat().last(),
+                "the updated record must be the last one"
+            );
+
+            let mut update_time_desc = fetch_all_pages(conn, None, PageOrder::UpdateTimeDesc, 2)
+                .await?
+                .concat();
+            update_time_desc.reverse();
+            assert_eq!(update_time_asc.concat(), update_time_desc);
+
+            let odd = Filter::in_list(Field::json("value"), [1, 3, 5]);
+            assert_eq!(
+                vec![vec![1], vec![3]],
+                fetch_all_pages(conn, Some(&odd), PageOrder::IdAsc, 1).await?
+            );
+
+            Ok(())
+        })
+        .await
+    })
+}
+
+#[test]
+fn should_reject_invalid_cursors() -> Result<(), C3p0Error> {
+    run_test(async {
+        let data = data(false).await;
+        let pool = &data.0;
+
+        assert!(matches!(
+            Cursor::from_token("not a token"),
+            Err(C3p0Error::InvalidCursor { .. })
+        ));
+        assert!(matches!(
+            Cursor::from_token("e30"),
+            Err(C3p0Error::InvalidCursor { .. })
+        ));
+
+        pool.transaction::<_, C3p0Error, _>(async |conn| {
+            conn.create_table_if_not_exists::<TestData>().await?;
+            conn.delete_all::<TestData>().await?;
+            for value in 0..3 {
+                conn.save(TestData { value }.into()).await?;
+            }
+
+            let page = conn
+                .fetch_page::<TestData>(PageOrder::IdAsc, None, 1)
+                .await?;
+            let cursor = page.next.expect("there should be a next page");
+            assert_eq!(PageOrder::IdAsc, cursor.order());
+
+            let result = conn
+                .fetch_page::<TestData>(PageOrder::UpdateTimeAsc, Some(&cursor), 1)
+                .await;
+            assert!(matches!(result, Err(C3p0Error::InvalidCursor { .. })));
+
+            for limit in [0, u64::MAX] {
+                assert!(
+                    conn.fetch_page::<TestData>(PageOrder::IdAsc, None, limit)
+                        .await
+                        .is_err()
+                );
+            }
+            Ok(())
+        })
+        .await
+    })
+}
@@ -0,0 +1,201 @@
+use crate::utils::*;
+use crate::*;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TestData {
+    pub name: String,
+    pub count: i64,
+    pub extra: Value,
+}
+
+impl c3p0::DataType for TestData {
+    const TABLE_NAME: &'static str =
+        const_format::concatcp!("TEST_TABLE_", const_random::const_random!(u64));
+    type CODEC = Self;
+}
+
+#[test]
+fn should_apply_merge_patch() -> Result<(), C3p0Error> {
+    run_test(async {
+        let data = data(false).await;
+        let pool = &data.0;
+
+        pool.transaction::<_, C3p0Error, _>(async |conn| {
+            conn.create_table_if_not_exists::<TestData>().await?;
+            conn.delete_all::<TestData>().await?;
+
+            let saved = conn
+                .save(
+                    TestData {
+                        name: "name".to_owned(),
+                        count: 1,
+                        extra: json!({
+                            "keep": "me",
+                            "drop": 1,
+                            "scalar": 2,
+                            "nested": { "a": 1, "b": 2, "list": [1, 2] }
+                        }),
+                    }
+                    .into(),
+                )
+                .await?;
+
+            let patched = conn
+                .patch::<TestData>(
+                    saved.id,
+                    None,
+                    &JsonPatch::Merge(json!({
+                        "count": 2,
+                        "extra": {
+                            "drop": null,
+                            "scalar": { "now": "an object", "without": null },
+                            "nested": { "a": null, "c": 3, "list": [3] },
+                            "new": { "deep": { "x": true } },
+                            "o'key": "quoted"
+                        }
+                    })),
+                )
+                .await?;
+
+            assert_eq!(saved.id, patched.id);
+            assert_eq!(1, patched.version);
+            assert_eq!(saved.create_time, patched.create_time);
+            assert_eq!("name", patched.data.name);
+            assert_eq!(2, patched.data.count);
+            assert_eq!(
+                json!({
+                    "keep": "me",
+                    "scalar": { "now": "an object" },
+                    "nested": { "b": 2, "c": 3, "list": [3] },
+                    "new": { "deep": { "x": true } },
+                    "o'key": "quoted"
+                }),
+                patched.data.extra
+            );
+            assert_eq!(patched, conn.fetch_one_by_id::<TestData>(saved.id).await?);
+
+            Ok(())
+        })
+        .await
+    })
+}
+
+#[test]
+fn should_apply_patch_operations() -> Result<(), C3p0Error> {
+    run_test(async {
+        let data = data(false).await;
+        let pool = &data.0;
+
+        pool.transaction::<_, C3p0Error, _>(async |conn| {
+            conn.create_table_if_not_exists::<TestData>().await?;
+            conn.delete_all::<TestData>().await?;
+
+            let saved = conn
+                .save(
+                    TestData {
+                        name: "name".to_owned(),
+                        count: 1,
+                        extra: json!({ "scalar": 1, "nested": { "a": 1, "b": 2 } }),
+                    }
+                    .into(),
+                )
+                .await?;
+
+            let patched = conn
+                .patch::<TestData>(
+                    saved.id,
+                    Some(saved.version),
+                    &JsonPatch::Ops(vec![
+                        PatchOp::set("name", "new name"),
+                        PatchOp::set("extra.nested.a", json!({ "replaced": [1, 2] })),
+                        PatchOp::remove("extra.nested.b"),
+                        PatchOp::remove("extra.missing.key"),
+                        PatchOp::set("extra.created.parent", "value"),
+                        PatchOp::set("extra.scalar.child", json!(null)),
+                        PatchOp::set(JsonPath::new(["extra", "dotted.key"]), 1.5),
+                        PatchOp::set("count", 3),
+                        PatchOp::set("count", 4),
+                    ]),
+                )
+                .await?;
+
+            assert_eq!(1, patched.version);
+            assert_eq!("new name", patched.data.name);
+            assert_eq!(4, patched.data.count);
+            assert_eq!(
+                json!({
+                    "scalar": { "child": null },
+                    "nested": { "a": { "replaced": [1, 2] } },
+                    "created": { "parent": "value" },
+                    "dotted.key": 1.5
+                }),
+                patched.data.extra
+            );
+            assert_eq!(patched, conn.fetch_one_by_id::<TestData>(saved.id).await?);
+
+            Ok(())
+        })
+        .await
+    })
+}
+
+#[test]
+fn should_check_the_version_when_patching() -> Result<(), C3p0Error> {
+    run_test(async {
+        let data = data(false).await;
+        let pool = &data.0;
+
+        pool.transaction::<_, C3p0Error, _>(async |conn| {
+            conn.create_table_if_not_exists::<TestData>().await?;
+            conn.delete_all::<TestData>().await?;
+
+            let saved = conn
+                .save(
+                    TestData {
+                        name: "name".to_owned(),
+                        count: 1,
+                        extra: json!({}),
+                    }
+                    .into(),
+                )
+                .await?;
+            let patch = JsonPatch::Ops(vec![PatchOp::set("count", 10)]);
+
+            let patched = conn.patch::<TestData>(saved.id, Some(0), &patch).await?;
+            assert_eq!(1, patched.version);
+
+            assert!(matches!(
+                conn.patch::<TestData>(saved.id, Some(0), &patch).await,
+                Err(C3p0Error::OptimisticLockError { .. })
+            ));
+            assert!(matches!(
+                conn.patch::<TestData>(saved.id + 1000, None, &patch).await,
+                Err(C3p0Error::SqlxError(c3p0::sqlx::Error::RowNotFound))
+            ));
+            assert!(matches!(
+                conn.patch::<TestData>(
+                    saved.id,
+                    None,
+                    &JsonPatch::Ops(vec![PatchOp::remove(JsonPath::new(Vec::<String>::new()))])
+                )
+                .await,
+                Err(C3p0Error::Other { .. })
+            ));
+
+            // An update with a stale version fails after a patch
+            assert!(matches!(
+                conn.update(saved).await,
+                Err(C3p0Error::OptimisticLockError { .. })
+            ));
+            assert_eq!(
+                1,
+                conn.fetch_one_by_id::<TestData>(patched.id).await?.version
+            );
+
+            Ok(())
+        })
+        .await
+    })
+}
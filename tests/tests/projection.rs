@@ -0,0 +1,163 @@
+use crate::utils::*;
+use crate::*;
+use c3p0::projection::{Projected, Projection};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Customer {
+    pub name: String,
+    pub vip: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Order {
+    pub number: String,
+    pub total: i64,
+    pub customer: Customer,
+    pub notes: Option<String>,
+    pub lines: Vec<String>,
+}
+
+impl c3p0::DataType for Order {
+    const TABLE_NAME: &'static str =
+        const_format::concatcp!("TEST_TABLE_", const_random::const_random!(u64));
+    type CODEC = Self;
+    const SOFT_DELETE: bool = true;
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+pub struct OrderRow {
+    pub number: String,
+    pub total: i64,
+    pub customer: String,
+    pub vip: bool,
+    pub discount: Option<i64>,
+}
+
+fn new_order(number: &str, total: i64, customer: &str, vip: bool) -> Order {
+    Order {
+        number: number.to_owned(),
+        total,
+        customer: Customer {
+            name: customer.to_owned(),
+            vip,
+        },
+        notes: None,
+        lines: vec!["a line".to_owned()],
+    }
+}
+
+#[test]
+fn should_fetch_the_projected_fields() -> Result<(), C3p0Error> {
+    run_test(async {
+        let data = data(false).await;
+        let pool = &data.0;
+
+        pool.transaction::<_, C3p0Error, _>(async |conn| {
+            conn.drop_table_if_exists::<Order>(false).await?;
+            conn.create_table_if_not_exists::<Order>().await?;
+
+            let first = conn
+                .save(new_order("2025/1", 100, "ada", true).into())
+                .await?;
+            let second = conn
+                .save(new_order("2025/2", 250, "bob", false).into())
+                .await?;
+            let third = conn
+                .save(new_order("2025/3", 50, "ada", true).into())
+                .await?;
+            conn.delete(third).await?;
+
+            let projection = Projection::new()
+                .field("number", "number")
+                .field("total", "total")
+                .field("customer", "customer.name")
+                .field("vip", "customer.vip")
+                .field("discount", "discount");
+
+            let rows = conn
+                .fetch_projected::<Order, OrderRow>(
+                    &projection,
+                    &Filter::and([]),
+                    &[SortKey::Id(OrderBy::Desc)],
+                    0,
+                    None,
+                )
+                .await?;
+            // The soft deleted entries are excluded
+            assert_eq!(
+                vec![
+                    Projected {
+                        id: second.id,
+                        version: second.version,
+                        data: OrderRow {
+                            number: "2025/2".to_owned(),
+                            total: 250,
+                            customer: "bob".to_owned(),
+                            vip: false,
+                            discount: None,
+                        },
+                    },
+                    Projected {
+                        id: first.id,
+                        version: first.version,
+                        data: OrderRow {
+                            number: "2025/1".to_owned(),
+                            total: 100,
+                            customer: "ada".to_owned(),
+                            vip: true,
+                            discount: None,
+                        },
+                    },
+                ],
+                rows
+            );
+
+            let rows = conn
+                .fetch_projected::<Order, serde_json::Value>(
+                    &Projection::new().field("number", "number"),
+                    &Filter::eq(Field::json("customer.name"), "ada"),
+                    &[SortKey::json("total", JsonType::Number, OrderBy::Asc)],
+                    0,
+                    Some(1),
+                )
+                .await?;
+            assert_eq!(1, rows.len());
+            assert_eq!(first.id, rows[0].id);
+            assert_eq!(serde_json::json!({ "number": "2025/1" }), rows[0].data);
+
+            // The table helper returns the same rows
+            let table = Table::<Order>::new(Order::TABLE_NAME)?;
+            assert_eq!(
+                rows,
+                table
+                    .fetch_projected::<_, serde_json::Value>(
+                        conn,
+                        &Projection::new().field("number", "number"),
+                        &Filter::eq(Field::json("customer.name"), "ada"),
+                        &[SortKey::json("total", JsonType::Number, OrderBy::Asc)],
+                        0,
+                        Some(1),
+                    )
+                    .await?
+            );
+
+            assert!(matches!(
+                conn.fetch_projected::<Order, serde_json::Value>(
+                    &Projection::new()
+                        .field("number", "number")
+                        .field("number", "customer.name"),
+                    &Filter::and([]),
+                    &[],
+                    0,
+                    None,
+                )
+                .await,
+                Err(C3p0Error::Other { .. })
+            ));
+
+            conn.drop_table_if_exists::<Order>(false).await
+        })
+        .await
+    })
+}
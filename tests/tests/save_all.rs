@@ -0,0 +1,64 @@
+use crate::utils::*;
+use crate::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TestData {
+    pub value: i64,
+}
+
+impl c3p0::DataType for TestData {
+    const TABLE_NAME: &'static str =
+        const_format::concatcp!("TEST_TABLE_", const_random::const_random!(u64));
+    type CODEC = Self;
+}
+
+#[test]
+fn should_save_all_in_input_order() -> Result<(), C3p0Error> {
+    run_test(async {
+        let data = data(false).await;
+        let pool = &data.0;
+
+        pool.transaction::<_, C3p0Error, _>(async |conn| {
+            conn.create_table_if_not_exists::<TestData>().await?;
+            conn.delete_all::<TestData>().await?;
+
+            assert!(conn.save_all::<TestData>(vec![]).await?.is_empty());
+
+            let single = conn.save(TestData { value: -1 }.into()).await?;
+
+            let values: Vec<i64> = (0..1000).map(|value| (value * 7919) % 1000).collect();
+            let saved = conn
+                .save_all(
+                    values
+                        .iter()
+                        .map(|value| TestData { value: *value }.into())
+                        .collect(),
+                )
+                .await?;
+
+            assert_eq!(values.len(), saved.len());
+            assert_eq!(
+                values,
+                saved
+                    .iter()
+                    .map(|record| record.data.value)
+                    .collect::<Vec<_>>()
+            );
+            for (previous, record) in std::iter::once(&single).chain(&saved).zip(&saved) {
+                assert!(record.id > previous.id);
+            }
+            for record in &saved {
+                assert_eq!(0, record.version);
+                assert_eq!(record.create_time, record.update_time);
+            }
+
+            let mut expected = vec![single];
+            expected.extend(saved);
+            assert_eq!(expected, conn.fetch_all::<TestData>(0, None).await?);
+
+            Ok(())
+        })
+        .await
+    })
+}
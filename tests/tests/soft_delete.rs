@@ -0,0 +1,256 @@
+use crate::utils::*;
+use crate::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TestData {
+    pub value: i64,
+}
+
+impl c3p0::DataType for TestData {
+    const TABLE_NAME: &'static str =
+        const_format::concatcp!("TEST_TABLE_", const_random::const_random!(u64));
+    type CODEC = Self;
+    const SOFT_DELETE: bool = true;
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct KeyedData {
+    pub key: String,
+    pub value: i64,
+}
+
+impl c3p0::DataType for KeyedData {
+    const TABLE_NAME: &'static str =
+        const_format::concatcp!("TEST_TABLE_", const_random::const_random!(u64));
+    type CODEC = Self;
+    const UNIQUE_KEY: &'static [&'static str] = &["key"];
+    const SOFT_DELETE: bool = true;
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct HardData {
+    pub value: i64,
+}
+
+impl c3p0::DataType for HardData {
+    const TABLE_NAME: &'static str =
+        const_format::concatcp!("TEST_TABLE_", const_random::const_random!(u64));
+    type CODEC = Self;
+}
+
+fn values(records: &[Record<TestData>]) -> Vec<i64> {
+    records.iter().map(|record| record.data.value).collect()
+}
+
+#[test]
+fn should_soft_delete() -> Result<(), C3p0Error> {
+    run_test(async {
+        let data = data(false).await;
+        let pool = &data.0;
+
+        pool.transaction::<_, C3p0Error, _>(async |conn| {
+            conn.drop_table_if_exists::<TestData>(false).await?;
+            conn.create_table_if_not_exists::<TestData>().await?;
+
+            let saved = conn
+                .save_all((0..6).map(|value| TestData { value }.into()).collect())
+                .await?;
+
+            // delete
+            let deleted = conn.delete(saved[0].clone()).await?;
+            assert_eq!(saved[0].version + 1, deleted.version);
+            assert!(matches!(
+                conn.delete(saved[0].clone()).await,
+                Err(C3p0Error::OptimisticLockError { .. })
+            ));
+            assert!(matches!(
+                conn.delete(deleted.clone()).await,
+                Err(C3p0Error::OptimisticLockError { .. })
+            ));
+
+            // delete_by_id and delete_where
+            assert_eq!(1, conn.delete_by_id::<TestData>(saved[1].id).await?);
+            assert_eq!(0, conn.delete_by_id::<TestData>(saved[1].id).await?);
+            assert_eq!(
+                1,
+                conn.delete_where::<TestData>(&Filter::eq(Field::json("value"), 2))
+                    .await?
+            );
+
+            // The deleted entries are not visible anymore
+            assert_eq!(3, conn.count_all::<TestData>().await?);
+            assert!(!conn.exists_by_id::<TestData>(saved[0].id).await?);
+            assert!(conn.exists_by_id::<TestData>(saved[3].id).await?);
+            assert!(
+                conn.fetch_one_optional_by_id::<TestData>(saved[0].id)
+                    .await?
+                    .is_none()
+            );
+            assert_eq!(
+                vec![3, 4, 5],
+                values(&conn.fetch_all::<TestData>(0, None).await?)
+            );
+            assert_eq!(
+                vec![3, 4],
+                values(
+                    &conn
+                        .fetch_where::<TestData>(&Filter::lt(Field::json("value"), 5), 0, None)
+                        .await?
+                )
+            );
+            assert_eq!(
+                0,
+                conn.count_where::<TestData>(&Filter::lt(Field::json("value"), 3))
+                    .await?
+            );
+            let with_tail = Record::<TestData>::query_with_tail("ORDER BY id DESC")
+                .fetch_all(&mut *conn)
+                .await?;
+            assert_eq!(vec![5, 4, 3], values(&with_tail));
+
+            // Deleted entries cannot be updated or patched
+            assert!(matches!(
+                conn.update(deleted.clone()).await,
+                Err(C3p0Error::OptimisticLockError { .. })
+            ));
+            assert!(
+                conn.patch::<TestData>(
+                    deleted.id,
+                    None,
+                    &JsonPatch::Ops(vec![PatchOp::set("value", 100)])
+                )
+                .await
+                .is_err()
+            );
+            let result = conn.update_all(vec![deleted.clone()]).await?;
+            assert!(result.updated.is_empty());
+            assert_eq!(1, result.conflicts.len());
+
+            // fetch_including_deleted
+            let all = conn.fetch_including_deleted::<TestData>(0, None).await?;
+            assert_eq!(6, all.len());
+            assert_eq!(
+                vec![true, true, true, false, false, false],
+                all.iter()
+                    .map(|record| record.delete_time.is_some())
+                    .collect::<Vec<_>>()
+            );
+            assert_eq!(deleted, all[0].record);
+            let paged = conn.fetch_including_deleted::<TestData>(1, Some(2)).await?;
+            assert_eq!(
+                vec![1, 2],
+                paged
+                    .iter()
+                    .map(|record| record.record.data.value)
+                    .collect::<Vec<_>>()
+            );
+
+            // restore
+            let restored = conn.restore::<TestData>(deleted.id).await?;
+            assert_eq!(deleted.version + 1, restored.version);
+            assert_eq!(deleted.data, restored.data);
+            assert!(conn.exists_by_id::<TestData>(deleted.id).await?);
+            assert!(matches!(
+                conn.restore::<TestData>(deleted.id).await,
+                Err(C3p0Error::SqlxError(c3p0::sqlx::Error::RowNotFound))
+            ));
+
+            // delete_all only marks the entries that are not deleted yet
+            assert_eq!(4, conn.delete_all::<TestData>().await?);
+            assert_eq!(0, conn.count_all::<TestData>().await?);
+
+            // purge
+            let deleted_before = conn.fetch_including_deleted::<TestData>(0, None).await?[0]
+                .delete_time
+                .expect("the entry should be deleted");
+            assert_eq!(
+                0,
+                conn.purge::<TestData>(Some(deleted_before - chrono::Duration::seconds(1)))
+                    .await?
+            );
+            assert_eq!(6, conn.purge::<TestData>(None).await?);
+            assert!(
+                conn.fetch_including_deleted::<TestData>(0, None)
+                    .await?
+                    .is_empty()
+            );
+
+            Ok(())
+        })
+        .await
+    })
+}
+
+#[test]
+fn should_restore_soft_deleted_entries_on_upsert() -> Result<(), C3p0Error> {
+    run_test(async {
+        let data = data(false).await;
+        let pool = &data.0;
+
+        pool.transaction::<_, C3p0Error, _>(async |conn| {
+            conn.drop_table_if_exists::<KeyedData>(false).await?;
+            conn.create_table_if_not_exists::<KeyedData>().await?;
+
+            let saved = conn
+                .save(
+                    KeyedData {
+                        key: "key".to_owned(),
+                        value: 1,
+                    }
+                    .into(),
+                )
+                .await?;
+            conn.delete(saved.clone()).await?;
+            assert_eq!(0, conn.count_all::<KeyedData>().await?);
+
+            let upserted = conn
+                .upsert_by_key(
+                    KeyedData {
+                        key: "key".to_owned(),
+                        value: 2,
+                    }
+                    .into(),
+                )
+                .await?;
+            assert_eq!(saved.id, upserted.id);
+            assert_eq!(2, upserted.version);
+            assert_eq!(upserted, conn.fetch_one_by_id::<KeyedData>(saved.id).await?);
+
+            Ok(())
+        })
+        .await
+    })
+}
+
+#[test]
+fn should_reject_soft_delete_operations_on_hard_delete_types() -> Result<(), C3p0Error> {
+    run_test(async {
+        let data = data(false).await;
+        let pool = &data.0;
+
+        pool.transaction::<_, C3p0Error, _>(async |conn| {
+            conn.create_table_if_not_exists::<HardData>().await?;
+
+            let saved = conn.save(HardData { value: 1 }.into()).await?;
+            conn.delete(saved.clone()).await?;
+            assert!(!conn.exists_by_id::<HardData>(saved.id).await?);
+
+            assert!(matches!(
+                conn.fetch_including_deleted::<HardData>(0, None).await,
+                Err(C3p0Error::Other { .. })
+            ));
+            assert!(matches!(
+                conn.restore::<HardData>(saved.id).await,
+                Err(C3p0Error::Other { .. })
+            ));
+            assert!(matches!(
+                conn.purge::<HardData>(None).await,
+                Err(C3p0Error::Other { .. })
+            ));
+
+            Ok(())
+        })
+        .await
+    })
+}
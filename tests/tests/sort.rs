@@ -0,0 +1,129 @@
+use crate::utils::*;
+use crate::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TestData {
+    pub name: String,
+    pub rank: Option<i64>,
+    pub team: String,
+}
+
+impl c3p0::DataType for TestData {
+    const TABLE_NAME: &'static str =
+        const_format::concatcp!("TEST_TABLE_", const_random::const_random!(u64));
+    type CODEC = Self;
+}
+
+fn names(records: Vec<Record<TestData>>) -> Vec<String> {
+    records.into_iter().map(|record| record.data.name).collect()
+}
+
+#[test]
+fn should_fetch_sorted_by_columns_and_json_paths() -> Result<(), C3p0Error> {
+    run_test(async {
+        let data = data(false).await;
+        let pool = &data.0;
+
+        pool.transaction::<_, C3p0Error, _>(async |conn| {
+            conn.create_table_if_not_exists::<TestData>().await?;
+            conn.delete_all::<TestData>().await?;
+
+            for (name, rank, team) in [
+                ("alice", Some(30), "red"),
+                ("bob", Some(100), "blue"),
+                ("carol", None, "red"),
+                ("dave", Some(9), "blue"),
+            ] {
+                conn.save(
+                    TestData {
+                        name: name.to_owned(),
+                        rank,
+                        team: team.to_owned(),
+                    }
+                    .into(),
+                )
+                .await?;
+            }
+
+            // Numbers are compared numerically and missing values always come last
+            let by_rank_asc = conn
+                .fetch_all_sorted::<TestData>(
+                    &[SortKey::json("rank", JsonType::Number, OrderBy::Asc)],
+                    0,
+                    None,
+                )
+                .await?;
+            assert_eq!(vec!["dave", "alice", "bob", "carol"], names(by_rank_asc));
+
+            let by_rank_desc = conn
+                .fetch_all_sorted::<TestData>(
+                    &[SortKey::json("rank", JsonType::Number, OrderBy::Desc)],
+                    0,
+                    None,
+                )
+                .await?;
+            assert_eq!(vec!["bob", "alice", "dave", "carol"], names(by_rank_desc));
+
+            // Text comparison
+            let by_name_desc = conn
+                .fetch_all_sorted::<TestData>(
+                    &[SortKey::json("name", JsonType::Text, OrderBy::Desc)],
+                    0,
+                    None,
+                )
+                .await?;
+            assert_eq!(vec!["dave", "carol", "bob", "alice"], names(by_name_desc));
+
+            // Multiple keys; the id is the implicit last key
+            let by_team = conn
+                .fetch_all_sorted::<TestData>(
+                    &[SortKey::json("team", JsonType::Text, OrderBy::Asc)],
+                    0,
+                    None,
+                )
+                .await?;
+            assert_eq!(vec!["bob", "dave", "alice", "carol"], names(by_team));
+
+            let by_team_and_id_desc = conn
+                .fetch_all_sorted::<TestData>(
+                    &[
+                        SortKey::json("team", JsonType::Text, OrderBy::Asc),
+                        SortKey::Id(OrderBy::Desc),
+                    ],
+                    0,
+                    None,
+                )
+                .await?;
+            assert_eq!(
+                vec!["dave", "bob", "carol", "alice"],
+                names(by_team_and_id_desc)
+            );
+
+            let by_create_time = conn
+                .fetch_all_sorted::<TestData>(
+                    &[
+                        SortKey::CreateTime(OrderBy::Desc),
+                        SortKey::Id(OrderBy::Desc),
+                    ],
+                    1,
+                    Some(2),
+                )
+                .await?;
+            assert_eq!(vec!["carol", "bob"], names(by_create_time));
+
+            let red_by_rank = conn
+                .fetch_where_sorted::<TestData>(
+                    &Filter::eq(Field::json("team"), "red"),
+                    &[SortKey::json("rank", JsonType::Number, OrderBy::Default)],
+                    0,
+                    Some(10),
+                )
+                .await?;
+            assert_eq!(vec!["alice", "carol"], names(red_by_rank));
+
+            Ok(())
+        })
+        .await
+    })
+}
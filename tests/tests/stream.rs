@@ -0,0 +1,70 @@
+use crate::utils::*;
+use crate::*;
+use futures_util::{StreamExt, TryStreamExt};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TestData {
+    pub value: i64,
+}
+
+impl c3p0::DataType for TestData {
+    const TABLE_NAME: &'static str =
+        const_format::concatcp!("TEST_TABLE_", const_random::const_random!(u64));
+    type CODEC = Self;
+}
+
+#[test]
+fn should_stream_records() -> Result<(), C3p0Error> {
+    run_test(async {
+        let data = data(false).await;
+        let pool = &data.0;
+
+        pool.transaction::<_, C3p0Error, _>(async |conn| {
+            conn.create_table_if_not_exists::<TestData>().await?;
+            conn.delete_all::<TestData>().await?;
+
+            for value in 0..10 {
+                conn.save(TestData { value }.into()).await?;
+            }
+
+            let all: Vec<i64> = conn
+                .fetch_all_stream::<TestData>()
+                .map_ok(|record| record.data.value)
+                .try_collect()
+                .await?;
+            assert_eq!((0..10).collect::<Vec<_>>(), all);
+
+            let even: Vec<i64> = conn
+                .fetch_where_stream::<TestData>(&Filter::in_list(
+                    Field::json("value"),
+                    [0, 2, 4, 6, 8],
+                ))
+                .map_ok(|record| record.data.value)
+                .try_collect()
+                .await?;
+            assert_eq!(vec![0, 2, 4, 6, 8], even);
+
+            // The stream can be consumed partially
+            let first_three: Vec<i64> = conn
+                .fetch_all_stream::<TestData>()
+                .take(3)
+                .map_ok(|record| record.data.value)
+                .try_collect()
+                .await?;
+            assert_eq!(vec![0, 1, 2], first_three);
+
+            let total: i64 = Record::<TestData>::query_with_tail("ORDER BY id DESC")
+                .fetch(&mut *conn)
+                .try_fold(
+                    0,
+                    |total, record| async move { Ok(total + record.data.value) },
+                )
+                .await?;
+            assert_eq!(45, total);
+
+            Ok(())
+        })
+        .await
+    })
+}
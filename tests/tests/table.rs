@@ -0,0 +1,339 @@
+use crate::utils::*;
+use crate::*;
+use futures_util::StreamExt;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Order {
+    pub code: String,
+    pub amount: i64,
+}
+
+impl c3p0::DataType for Order {
+    const TABLE_NAME: &'static str =
+        const_format::concatcp!("TEST_TABLE_", const_random::const_random!(u64));
+    type CODEC = Self;
+    const UNIQUE_KEY: &'static [&'static str] = &["code"];
+    const HISTORY: bool = true;
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Invoice {
+    pub number: String,
+}
+
+impl c3p0::DataType for Invoice {
+    const TABLE_NAME: &'static str =
+        const_format::concatcp!("c3p0_schema.TEST_TABLE_", const_random::const_random!(u64));
+    type CODEC = Self;
+    const UNIQUE_KEY: &'static [&'static str] = &["number"];
+    const SOFT_DELETE: bool = true;
+    const HISTORY: bool = true;
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Quoted {
+    pub value: i64,
+}
+
+impl c3p0::DataType for Quoted {
+    const TABLE_NAME: &'static str =
+        const_format::concatcp!("\"Test_Table_", const_random::const_random!(u64), "\"");
+    type CODEC = Self;
+    const HISTORY: bool = true;
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Invalid {
+    pub value: i64,
+}
+
+impl c3p0::DataType for Invalid {
+    const TABLE_NAME: &'static str = "TEST_TABLE; DROP TABLE TEST_TABLE";
+    type CODEC = Self;
+}
+
+const TENANT_A: &str = const_format::concatcp!("tenant_a_", const_random::const_random!(u32));
+const TENANT_B: &str = const_format::concatcp!("tenant_b_", const_random::const_random!(u32));
+const TENANT_C: &str = const_format::concatcp!("tenant_c_", const_random::const_random!(u32));
+
+fn order(code: &str, amount: i64) -> NewRecord<Order> {
+    Order {
+        code: code.to_owned(),
+        amount,
+    }
+    .into()
+}
+
+#[test]
+fn should_validate_the_table_names() {
+    assert_eq!(
+        "tenant_1_orders",
+        Table::<Order>::new("tenant_1_orders").unwrap().name()
+    );
+    assert!(Table::<Order>::new("_Orders").is_ok());
+    assert!(Table::<Order>::new("schema.orders").is_ok());
+    assert!(Table::<Order>::new("\"Schema\".\"Orders; DROP\"").is_ok());
+    assert!(Table::<Order>::new("a".repeat(100)).is_ok());
+
+    for name in [
+        "",
+        "1_orders",
+        "orders; DROP TABLE users",
+        "orders\"",
+        "a.schema.orders",
+        "ördërs",
+    ] {
+        assert!(matches!(
+            Table::<Order>::new(name),
+            Err(C3p0Error::Other { .. })
+        ));
+    }
+}
+
+#[test]
+fn should_run_the_operations_on_runtime_tables() -> Result<(), C3p0Error> {
+    run_test(async {
+        let data = data(false).await;
+        let pool = &data.0;
+
+        let tenant_a = Table::<Order>::new(TENANT_A)?;
+        let tenant_b = Table::<Order>::new(TENANT_B)?;
+
+        pool.transaction::<_, C3p0Error, _>(async |conn| {
+            conn.drop_table_if_exists::<Order>(false).await?;
+            conn.create_table_if_not_exists::<Order>().await?;
+            for table in [&tenant_a, &tenant_b] {
+                table.drop_table_if_exists(conn, false).await?;
+                table.create_table_if_not_exists(conn).await?;
+            }
+
+            let saved = tenant_a.save(conn, order("first", 1)).await?;
+            tenant_a
+                .save_all(conn, vec![order("second", 2), order("third", 3)])
+                .await?;
+            tenant_b.save(conn, order("first", 10)).await?;
+
+            // Each table holds its own entries
+            assert_eq!(3, tenant_a.count_all(conn).await?);
+            assert_eq!(1, tenant_b.count_all(conn).await?);
+            assert_eq!(0, conn.count_all::<Order>().await?);
+            assert_eq!(saved, tenant_a.fetch_one_by_id(conn, saved.id).await?);
+            assert_eq!(10, tenant_b.fetch_all(conn, 0, None).await?[0].data.amount);
+
+            let filter = Filter::gt(Field::json("amount"), 1);
+            assert_eq!(2, tenant_a.count_where(conn, &filter).await?);
+            assert_eq!(
+                vec!["third", "second"],
+                tenant_a
+                    .fetch_where_sorted(conn, &filter, &[SortKey::Id(OrderBy::Desc)], 0, None)
+                    .await?
+                    .iter()
+                    .map(|record| record.data.code.as_str())
+                    .collect::<Vec<_>>()
+            );
+
+            // The updates are recorded in the history table of the runtime table
+            let mut to_update = saved.clone();
+            to_update.data.amount = 100;
+            let updated = tenant_a.update(conn, to_update).await?;
+            assert_eq!(
+                vec![saved.clone()],
+                tenant_a.fetch_history(conn, saved.id).await?
+            );
+            assert_eq!(
+                Some(saved.clone()),
+                tenant_a
+                    .fetch_version(conn, saved.id, saved.version)
+                    .await?
+            );
+
+            // The upsert uses the unique index of the runtime table
+            let upserted = tenant_a.upsert_by_key(conn, order("first", 1000)).await?;
+            assert_eq!(updated.id, upserted.id);
+            assert_eq!(1000, upserted.data.amount);
+            assert_eq!(3, tenant_a.count_all(conn).await?);
+            assert_eq!(10, tenant_b.fetch_all(conn, 0, None).await?[0].data.amount);
+
+            assert_eq!(
+                1,
+                tenant_a
+                    .delete_where(conn, &Filter::eq(Field::json("code"), "second"))
+                    .await?
+            );
+            assert_eq!(2, tenant_a.count_all(conn).await?);
+            assert_eq!(1, tenant_b.delete_all(conn).await?);
+            assert_eq!(0, tenant_b.count_all(conn).await?);
+
+            Ok(())
+        })
+        .await?;
+
+        // The unique index is created on the runtime table
+        pool.transaction::<_, C3p0Error, _>(async |conn| {
+            assert!(tenant_a.save(conn, order("third", 0)).await.is_err());
+            Ok(())
+        })
+        .await
+    })
+}
+
+#[test]
+fn should_quote_the_runtime_table_names() -> Result<(), C3p0Error> {
+    run_test(async {
+        let data = data(false).await;
+        let pool = &data.0;
+
+        let upper = Table::<Order>::new(TENANT_C.to_uppercase())?;
+        let lower = Table::<Order>::new(TENANT_C)?;
+        let quoted = Table::<Order>::new(format!("\"{}\"", TENANT_C.to_uppercase()))?;
+
+        pool.transaction::<_, C3p0Error, _>(async |conn| {
+            for table in [&upper, &lower, &quoted] {
+                table.drop_table_if_exists(conn, false).await?;
+            }
+            upper.create_table_if_not_exists(conn).await?;
+            upper.save(conn, order("first", 1)).await?;
+            assert_eq!(1, upper.count_all(conn).await?);
+
+            // The unquoted names keep the meaning they have in plain SQL, like the ones of
+            // TABLE_NAME, while the quoted names are case sensitive on PostgreSQL. SQLite
+            // ignores the case of all the identifiers.
+            match db_specific::db_type() {
+                DbType::Pg => {
+                    assert_eq!(1, lower.count_all(conn).await?);
+                    quoted.create_table_if_not_exists(conn).await?;
+                    assert_eq!(0, quoted.count_all(conn).await?);
+                    quoted.drop_table_if_exists(conn, false).await?;
+                }
+                DbType::Sqlite | DbType::InMemory => {
+                    assert_eq!(1, lower.count_all(conn).await?);
+                    assert_eq!(1, quoted.count_all(conn).await?);
+                }
+                _ => {}
+            }
+            upper.drop_table_if_exists(conn, false).await?;
+
+            Ok(())
+        })
+        .await
+    })
+}
+
+#[test]
+fn should_qualify_the_table_names_with_the_schema() -> Result<(), C3p0Error> {
+    run_test(async {
+        let data = data(false).await;
+        let pool = &data.0;
+
+        let create_schema = match db_specific::db_type() {
+            DbType::Sqlite => "ATTACH DATABASE ':memory:' AS c3p0_schema",
+            DbType::Pg => "CREATE SCHEMA IF NOT EXISTS c3p0_schema",
+            _ => "CREATE DATABASE IF NOT EXISTS c3p0_schema",
+        };
+        sqlx::query(create_schema).execute(pool.pool()).await?;
+
+        pool.transaction::<_, C3p0Error, _>(async |conn| {
+            conn.drop_table_if_exists::<Invoice>(false).await?;
+            conn.create_table_if_not_exists::<Invoice>().await?;
+
+            let saved = conn
+                .save(
+                    Invoice {
+                        number: "first".to_owned(),
+                    }
+                    .into(),
+                )
+                .await?;
+            let mut to_update = saved.clone();
+            to_update.data.number = "second".to_owned();
+            let updated = conn.update(to_update).await?;
+            assert_eq!(
+                vec![saved.clone()],
+                conn.fetch_history::<Invoice>(saved.id).await?
+            );
+
+            let upserted = conn
+                .upsert_by_key(
+                    Invoice {
+                        number: "second".to_owned(),
+                    }
+                    .into(),
+                )
+                .await?;
+            assert_eq!(updated.id, upserted.id);
+
+            conn.delete(upserted).await?;
+            assert_eq!(0, conn.count_all::<Invoice>().await?);
+            assert_eq!(
+                1,
+                conn.fetch_including_deleted::<Invoice>(0, None)
+                    .await?
+                    .len()
+            );
+
+            Ok(())
+        })
+        .await?;
+
+        // The unquoted names refer to the same table in plain SQL
+        let count: i64 = sqlx::query_scalar(sqlx::AssertSqlSafe(format!(
+            "SELECT COUNT(*) FROM {}",
+            Invoice::TABLE_NAME
+        )))
+        .fetch_one(pool.pool())
+        .await?;
+        assert_eq!(1, count);
+
+        Ok(())
+    })
+}
+
+#[test]
+fn should_quote_the_table_names() -> Result<(), C3p0Error> {
+    run_test(async {
+        let data = data(false).await;
+        let pool = &data.0;
+
+        pool.transaction::<_, C3p0Error, _>(async |conn| {
+            conn.drop_table_if_exists::<Quoted>(false).await?;
+            conn.create_table_if_not_exists::<Quoted>().await?;
+
+            let saved = conn.save(Quoted { value: 1 }.into()).await?;
+            let mut to_update = saved.clone();
+            to_update.data.value = 2;
+            conn.update(to_update).await?;
+            assert_eq!(vec![saved], conn.fetch_history::<Quoted>(1).await?);
+            assert_eq!(1, conn.count_all::<Quoted>().await?);
+
+            Ok(())
+        })
+        .await
+    })
+}
+
+#[test]
+fn should_reject_the_invalid_table_names() -> Result<(), C3p0Error> {
+    run_test(async {
+        let data = data(false).await;
+        let pool = &data.0;
+
+        pool.transaction::<_, C3p0Error, _>(async |conn| {
+            assert!(matches!(
+                conn.create_table_if_not_exists::<Invalid>().await,
+                Err(C3p0Error::Other { .. })
+            ));
+            assert!(matches!(
+                conn.count_all::<Invalid>().await,
+                Err(C3p0Error::Other { .. })
+            ));
+            assert!(matches!(
+                conn.fetch_all_stream::<Invalid>().next().await,
+                Some(Err(C3p0Error::Other { .. }))
+            ));
+
+            Ok(())
+        })
+        .await
+    })
+}
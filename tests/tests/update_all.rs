@@ -0,0 +1,93 @@
+use crate::utils::*;
+use crate::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TestData {
+    pub value: i64,
+}
+
+impl c3p0::DataType for TestData {
+    const TABLE_NAME: &'static str =
+        const_format::concatcp!("TEST_TABLE_", const_random::const_random!(u64));
+    type CODEC = Self;
+}
+
+fn values(records: &[Record<TestData>]) -> Vec<i64> {
+    records.iter().map(|record| record.data.value).collect()
+}
+
+#[test]
+fn should_update_all_and_report_conflicts() -> Result<(), C3p0Error> {
+    run_test(async {
+        let data = data(false).await;
+        let pool = &data.0;
+
+        let saved = pool
+            .transaction::<_, C3p0Error, _>(async |conn| {
+                conn.create_table_if_not_exists::<TestData>().await?;
+                conn.delete_all::<TestData>().await?;
+                conn.save_all((0..5).map(|value| TestData { value }.into()).collect())
+                    .await
+            })
+            .await?;
+
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+
+        pool.transaction::<_, C3p0Error, _>(async |conn| {
+            let result = conn.update_all::<TestData>(vec![]).await?;
+            assert!(result.updated.is_empty());
+            assert!(result.conflicts.is_empty());
+
+            // A concurrent writer updates the record with value 1
+            let mut concurrent = saved[1].clone();
+            concurrent.data.value = 100;
+            conn.update(concurrent).await?;
+
+            let mut batch: Vec<Record<TestData>> = saved.clone();
+            for record in &mut batch {
+                record.data.value += 10;
+            }
+            // A record that does not exist anymore
+            let mut deleted = saved[3].clone();
+            conn.delete(deleted.clone()).await?;
+            deleted.data.value = 33;
+            batch[3] = deleted;
+            // The same id twice in the same batch
+            let mut duplicate = saved[0].clone();
+            duplicate.data.value = 1000;
+            batch.push(duplicate);
+
+            let result = conn.update_all(batch).await?;
+
+            assert_eq!(vec![10, 12, 14], values(&result.updated));
+            for record in &result.updated {
+                assert_eq!(1, record.version);
+                assert!(record.update_time > record.create_time);
+            }
+            assert_eq!(vec![11, 33, 1000], values(&result.conflicts));
+            for record in &result.conflicts {
+                assert_eq!(0, record.version);
+            }
+
+            assert_eq!(
+                vec![10, 100, 12, 14],
+                values(&conn.fetch_all::<TestData>(0, None).await?)
+            );
+            for record in &result.updated {
+                assert_eq!(record, &conn.fetch_one_by_id::<TestData>(record.id).await?);
+            }
+
+            // The conflicting entries can be retried after being re-fetched
+            let mut retry = conn.fetch_one_by_id::<TestData>(saved[1].id).await?;
+            retry.data.value += 10;
+            let result = conn.update_all(vec![retry]).await?;
+            assert_eq!(vec![110], values(&result.updated));
+            assert_eq!(2, result.updated[0].version);
+            assert!(result.conflicts.is_empty());
+
+            Ok(())
+        })
+        .await
+    })
+}
@@ -0,0 +1,158 @@
+use crate::utils::*;
+use crate::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct User {
+    pub email: String,
+    pub name: String,
+}
+
+impl c3p0::DataType for User {
+    const TABLE_NAME: &'static str =
+        const_format::concatcp!("TEST_TABLE_", const_random::const_random!(u64));
+    type CODEC = Self;
+    const UNIQUE_KEY: &'static [&'static str] = &["email"];
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Address {
+    pub city: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Member {
+    pub tenant: String,
+    pub address: Address,
+    pub value: i64,
+}
+
+impl c3p0::DataType for Member {
+    const TABLE_NAME: &'static str =
+        const_format::concatcp!("TEST_TABLE_", const_random::const_random!(u64));
+    type CODEC = Self;
+    const UNIQUE_KEY: &'static [&'static str] = &["tenant", "address.city"];
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct NoKey {
+    pub value: i64,
+}
+
+impl c3p0::DataType for NoKey {
+    const TABLE_NAME: &'static str =
+        const_format::concatcp!("TEST_TABLE_", const_random::const_random!(u64));
+    type CODEC = Self;
+}
+
+fn user(email: &str, name: &str) -> NewRecord<User> {
+    User {
+        email: email.to_owned(),
+        name: name.to_owned(),
+    }
+    .into()
+}
+
+fn member(tenant: &str, city: &str, value: i64) -> NewRecord<Member> {
+    Member {
+        tenant: tenant.to_owned(),
+        address: Address {
+            city: city.to_owned(),
+        },
+        value,
+    }
+    .into()
+}
+
+#[test]
+fn should_upsert_by_key() -> Result<(), C3p0Error> {
+    run_test(async {
+        let data = data(false).await;
+        let pool = &data.0;
+
+        let inserted = pool
+            .transaction::<_, C3p0Error, _>(async |conn| {
+                conn.drop_table_if_exists::<User>(false).await?;
+                conn.create_table_if_not_exists::<User>().await?;
+                // Creating the table again must not fail on the existing index
+                conn.create_table_if_not_exists::<User>().await?;
+
+                let inserted = conn.upsert_by_key(user("a@c3p0.rs", "first")).await?;
+                assert_eq!(0, inserted.version);
+                assert_eq!("first", inserted.data.name);
+                assert_eq!(inserted.create_time, inserted.update_time);
+
+                let other = conn.upsert_by_key(user("b@c3p0.rs", "other")).await?;
+                assert_ne!(inserted.id, other.id);
+                Ok(inserted)
+            })
+            .await?;
+
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+
+        pool.transaction::<_, C3p0Error, _>(async |conn| {
+            let updated = conn.upsert_by_key(user("a@c3p0.rs", "second")).await?;
+            assert_eq!(inserted.id, updated.id);
+            assert_eq!(1, updated.version);
+            assert_eq!("second", updated.data.name);
+            assert_eq!(inserted.create_time, updated.create_time);
+            assert!(updated.update_time > inserted.update_time);
+            assert_eq!(updated, conn.fetch_one_by_id::<User>(updated.id).await?);
+
+            let updated = conn.upsert_by_key(user("a@c3p0.rs", "third")).await?;
+            assert_eq!(2, updated.version);
+            assert_eq!(2, conn.count_all::<User>().await?);
+
+            // The unique index also protects plain saves
+            assert!(conn.save(user("a@c3p0.rs", "duplicate")).await.is_err());
+            Ok(())
+        })
+        .await
+    })
+}
+
+#[test]
+fn should_upsert_by_composite_key() -> Result<(), C3p0Error> {
+    run_test(async {
+        let data = data(false).await;
+        let pool = &data.0;
+
+        pool.transaction::<_, C3p0Error, _>(async |conn| {
+            conn.drop_table_if_exists::<Member>(false).await?;
+            conn.create_table_if_not_exists::<Member>().await?;
+
+            let first = conn.upsert_by_key(member("t1", "Rome", 1)).await?;
+            let second = conn.upsert_by_key(member("t1", "Paris", 2)).await?;
+            let third = conn.upsert_by_key(member("t2", "Rome", 3)).await?;
+            assert_ne!(first.id, second.id);
+            assert_ne!(first.id, third.id);
+
+            let updated = conn.upsert_by_key(member("t1", "Rome", 4)).await?;
+            assert_eq!(first.id, updated.id);
+            assert_eq!(1, updated.version);
+            assert_eq!(4, updated.data.value);
+            assert_eq!(3, conn.count_all::<Member>().await?);
+
+            Ok(())
+        })
+        .await
+    })
+}
+
+#[test]
+fn should_fail_to_upsert_without_key() -> Result<(), C3p0Error> {
+    run_test(async {
+        let data = data(false).await;
+        let pool = &data.0;
+
+        pool.transaction::<_, C3p0Error, _>(async |conn| {
+            conn.create_table_if_not_exists::<NoKey>().await?;
+            assert!(matches!(
+                conn.upsert_by_key(NoKey { value: 1 }.into()).await,
+                Err(C3p0Error::Other { .. })
+            ));
+            Ok(())
+        })
+        .await
+    })
+}
@@ -0,0 +1,133 @@
+use crate::utils::*;
+use crate::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TestData {
+    pub value: i64,
+}
+
+impl c3p0::DataType for TestData {
+    const TABLE_NAME: &'static str =
+        const_format::concatcp!("TEST_TABLE_", const_random::const_random!(u64));
+    type CODEC = Self;
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct KeyedData {
+    pub key: String,
+}
+
+impl c3p0::DataType for KeyedData {
+    const TABLE_NAME: &'static str =
+        const_format::concatcp!("TEST_TABLE_", const_random::const_random!(u64));
+    type CODEC = WithId<Self, String>;
+    const UNIQUE_KEY: &'static [&'static str] = &["key"];
+    const SOFT_DELETE: bool = true;
+    const HISTORY: bool = true;
+}
+
+#[test]
+fn should_verify_the_created_tables() -> Result<(), C3p0Error> {
+    run_test(async {
+        let data = data(false).await;
+        let pool = &data.0;
+
+        pool.transaction::<_, C3p0Error, _>(async |conn| {
+            conn.drop_table_if_exists::<TestData>(false).await?;
+            conn.create_table_if_not_exists::<TestData>().await?;
+            let report = conn.verify_table::<TestData>().await?;
+            assert!(report.is_valid(), "{report}");
+            assert_eq!(TestData::TABLE_NAME, report.table_name);
+
+            conn.drop_table_if_exists::<KeyedData>(false).await?;
+            conn.create_table_if_not_exists::<KeyedData>().await?;
+            let report = conn.verify_table::<KeyedData>().await?;
+            assert!(report.is_valid(), "{report}");
+
+            // The history table is verified as well
+            conn.drop_table_if_exists::<KeyedData>(false).await?;
+            conn.create_table_if_not_exists::<TestData>().await?;
+            let report = conn.verify_table::<KeyedData>().await?;
+            assert_eq!(2, report.issues.len());
+            assert!(
+                report
+                    .issues
+                    .iter()
+                    .all(|issue| matches!(issue, TableIssue::MissingTable { .. }))
+            );
+            Ok(())
+        })
+        .await
+    })
+}
+
+#[test]
+fn should_report_the_wrong_structure() -> Result<(), C3p0Error> {
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+    pub struct BrokenData {
+        pub value: i64,
+    }
+
+    impl c3p0::DataType for BrokenData {
+        const TABLE_NAME: &'static str =
+            const_format::concatcp!("TEST_TABLE_", const_random::const_random!(u64));
+        type CODEC = Self;
+    }
+
+    run_test(async {
+        let data = data(false).await;
+        let pool = &data.0;
+
+        let (id_type, time_type) = match db_specific::db_type() {
+            DbType::Pg => ("BIGINT", "TIMESTAMPTZ"),
+            DbType::Sqlite | DbType::InMemory => ("INTEGER", "TEXT"),
+            _ => ("BIGINT", "TIMESTAMP(3)"),
+        };
+        // A hand written table without primary key, update_time and JSON data
+        sqlx::query(sqlx::AssertSqlSafe(format!(
+            "CREATE TABLE {} (id {id_type} NOT NULL, version TEXT NOT NULL, \
+             create_time {time_type} NOT NULL, data TEXT NOT NULL)",
+            BrokenData::TABLE_NAME
+        )))
+        .execute(pool.pool())
+        .await?;
+
+        let report = pool
+            .transaction::<_, C3p0Error, _>(async |conn| conn.verify_table::<BrokenData>().await)
+            .await?;
+        assert!(!report.is_valid());
+        assert_eq!(4, report.issues.len(), "{report}");
+        assert!(report.issues.iter().any(|issue| matches!(
+            issue,
+            TableIssue::WrongType { column, .. } if column == "version"
+        )));
+        assert!(report.issues.iter().any(|issue| matches!(
+            issue,
+            TableIssue::MissingColumn { column, .. } if column == "update_time"
+        )));
+        assert!(report.issues.iter().any(|issue| matches!(
+            issue,
+            TableIssue::WrongPrimaryKey { expected, actual, .. }
+                if expected == &["id"] && actual.is_empty()
+        )));
+        if db_specific::db_type() == DbType::Sqlite {
+            assert!(
+                report
+                    .issues
+                    .iter()
+                    .any(|issue| matches!(issue, TableIssue::MissingJsonCheck { .. }))
+            );
+        } else {
+            assert!(report.issues.iter().any(|issue| matches!(
+                issue,
+                TableIssue::WrongType { column, .. } if column == "data"
+            )));
+        }
+
+        pool.transaction::<_, C3p0Error, _>(async |conn| {
+            conn.drop_table_if_exists::<BrokenData>(false).await
+        })
+        .await
+    })
+}